@@ -250,13 +250,14 @@ def run_cmd(command,
         run_ext("before_cmd")
 
         # The generate command handles patterns and flows.
-        # Future: Add options to generate patterns concurrently, or send them off to LSF.
-        # For now, just looping over the patterns.
+        # When run with --jobs or --lsf, the CLI dispatches each file to a separate Origen process
+        # which ends up here, reporting its results back to the dispatcher on completion.
         if command == "generate":
             origen.producer.generate(*[f for f in _origen.file_handler()])
 
             # Alway print a summary when initiated from the CLI
             origen.producer.summarize()
+            origen.tester._write_reports()
 
         elif command == "compile":
             _origen.set_operation("compile")
@@ -276,53 +277,6 @@ def run_cmd(command,
             interactive.interact(banner=f"Origen {origen.version}",
                                 context=origen.__interactive_context__())
 
-        elif command == "web:build":
-            _origen.set_operation("web")
-            from origen.web import run_cmd
-            return run_cmd("build", args)
-
-        elif command == "web:view":
-            _origen.set_operation("web")
-            from origen.web import run_cmd
-            return run_cmd("view", args)
-
-        elif command == "web:clean":
-            _origen.set_operation("web")
-            from origen.web import run_cmd
-            return run_cmd("clean", args)
-
-        elif command == "app:publish":
-            _origen.set_operation("app")
-            origen.app.__publish__(**args).summarize_and_exit()
-
-        elif command == "app:package":
-            _origen.set_operation("app")
-            origen.app.build_package(args)
-
-        elif command == "app:run_publish_checks":
-            _origen.set_operation("app")
-            origen.app.__run_publish_checks__(args).summarize_and_exit()
-
-        elif command == "app:init":
-            _origen.set_operation("app")
-            r = origen.app.__rc_init__()
-            r.summarize_and_exit()
-
-        elif command == "app:status":
-            _origen.set_operation("app")
-            r = origen.app.__rc_status__()
-            r.summarize()
-
-        elif command == "app:checkin":
-            _origen.set_operation("app")
-            checkin_all = args.pop("all", False)
-            args["dry_run"] = args.pop("dry-run", False)
-            if 'pathspecs' in args and not checkin_all:
-                r = origen.app.__rc_checkin__(**args)
-            else:
-                r = origen.app.__rc_checkin__(pathspecs=None, **args)
-            r.gist()
-
         # TODO need to remove generic result
         elif command == "mailer:test":
             if origen.mailer is None:
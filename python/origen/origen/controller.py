@@ -132,6 +132,11 @@ class Base:
             self._load_regs()
             return origen.dut.db.memory_maps(self.model_id)
 
+        elif name == "memories":
+            self._load_regs()
+            m = self.model()
+            return {n: m.memory(n) for n in m.memories}
+
         elif name in timesets.Proxy.api():
             from origen.timesets import Proxy
             proxy = timesets.Proxy(self)
@@ -225,6 +230,60 @@ class Base:
     def model(self):
         return origen.dut.db.model(self.model_id)
 
+    def to_uvm_ral(self, package=None):
+        ''' Returns the register model of this block and all of its sub-blocks as a SystemVerilog
+            package of UVM register classes (uvm_reg/uvm_reg_block)'''
+        self._load_all_regs()
+        return origen.dut.db.uvm_ral(self.model_id, package)
+
+    def validate_regs(self):
+        ''' Checks the register model of this block and all of its sub-blocks for consistency
+            problems like overlapping registers or fields, returning a list of (is_error, message)
+            tuples. An empty list means that no problems were found.'''
+        self._load_all_regs()
+        return origen.dut.db.validate_regs(self.model_id)
+
+    def reset_domain(self, name):
+        ''' Resets the given reset domain within this block and its sub-blocks, applying the
+            domain's reset type to all of its registers and cascading to any child domains.
+            The reset is recorded in the current pattern.'''
+        self._load_all_regs()
+        self.model().apply_reset_domain(name)
+
+    def is_in_reset_state_for_domain(self, name):
+        ''' Returns True if all registers within the given reset domain hold their reset values '''
+        self._load_all_regs()
+        return self.model().is_in_reset_state_for_domain(name)
+
+    def is_modified_since_reset_for_domain(self, name):
+        ''' Returns True if any register within the given reset domain has been modified since
+            it was last reset '''
+        self._load_all_regs()
+        return self.model().is_modified_since_reset_for_domain(name)
+
+    def add_memory(self, *args, **kwargs):
+        ''' Adds a memory (array) to this block, this would normally be done via Memory() within
+            the block's registers.py '''
+        self._load_regs()
+        return RegLoader(self).Memory(*args, **kwargs)
+
+    def memory(self, name):
+        ''' Returns the memory of the given name '''
+        self._load_regs()
+        return self.model().memory(name)
+
+    def to_register_rst(self, title=None):
+        ''' Returns a reStructuredText page documenting the registers of this block and all of
+            its sub-blocks'''
+        self._load_all_regs()
+        return origen.dut.db.register_rst(self.model_id, title)
+
+    def _load_all_regs(self):
+        self._load_regs()
+        self._load_sub_blocks()
+        for sb in self.sub_blocks.values():
+            sb._load_all_regs()
+
     @contextmanager
     def add_reg(self, *args, **kwargs):
         self._load_regs()
@@ -0,0 +1,25 @@
+import _origen, origen
+from . import is_subcmd, unsupported_subcmd
+
+def run(args):
+    _origen.set_operation("regs")
+    if is_subcmd("check"):
+        origen.target.load()
+        issues = origen.dut.validate_regs()
+        strict = args.get("strict", False)
+        errors = 0
+        for (is_error, msg) in issues:
+            if is_error or strict:
+                errors += 1
+                origen.logger.error(msg)
+            else:
+                origen.logger.warning(msg)
+        if len(issues) == 0:
+            origen.logger.success("No problems found in the register model")
+        elif errors > 0:
+            origen.logger.error(f"Found {len(issues)} problem(s) in the register model")
+            exit(1)
+        else:
+            origen.logger.warning(f"Found {len(issues)} warning(s) in the register model")
+    else:
+        unsupported_subcmd()
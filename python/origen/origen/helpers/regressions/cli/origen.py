@@ -285,6 +285,7 @@ class InAppCommands(CoreCommands):
         # new = _CommonNames.new
         pl = _CommonNames.pl
         pls = _CommonNames.pls
+        regs = "regs"
         save_ref = "save_ref"
         target = "target"
         # web = "web"
@@ -384,6 +385,26 @@ class InAppCommands(CoreCommands):
     # new = Cmd(names.new)
     pl = _CommonNames.pl_cmd()
     pls = _CommonNames.pls_cmd()
+    regs = Cmd(
+        names.regs,
+        help="Work with the register model of the current target",
+        subcmds=[
+            Cmd(
+                "check",
+                help="Check the register model for consistency problems like overlapping registers or fields",
+                opts=in_app_opts.all() + [
+                    CmdOpt(
+                        "strict",
+                        help="Treat warnings (e.g. duplicate register names) as errors",
+                        ln="strict",
+                        sn="s",
+                        takes_value=False,
+                        required=False,
+                    ),
+                ]
+            ),
+        ],
+    )
     save_ref = Cmd(names.save_ref)
     target = Cmd(
         names.target,
@@ -436,7 +457,7 @@ class InAppCommands(CoreCommands):
 
     commands = [
         # app, aux_cmds, build, compile, creds, env, eval, exec, fmt, generate, i, mailer, mode, new, pl, pls, save_ref, target, web
-        app, aux_cmds, creds, env, eval, exec, generate, i, pl, pls, save_ref, target
+        app, aux_cmds, creds, env, eval, exec, generate, i, pl, pls, regs, save_ref, target
     ]
     cmds = commands
 
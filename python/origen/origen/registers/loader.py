@@ -132,13 +132,15 @@ class Loader:
             )
 
     @contextmanager
-    def MemoryMap(self, name):
+    def MemoryMap(self, name, range=None):
+        ''' The range is the number of addressable units in the memory map, address blocks which
+        extend beyond it are reported by the register model validation '''
         if self.memory_map is not None:
             raise RuntimeError(
                 f"Attempted to open memory map '{name}' when memory map '{self.memory_map.name}' is already open"
             )
         self.memory_map = origen.dut.db.get_or_create_memory_map(
-            self.controller.model_id, name)
+            self.controller.model_id, name, range)
         yield self
         self.memory_map = None
 
@@ -153,6 +155,22 @@ class Loader:
         yield self
         self.address_block = None
 
+    def Memory(self,
+               name,
+               width,
+               depth,
+               base_address=None,
+               service=None,
+               image=None):
+        ''' Defines a memory (array) with the given word width (in bits) and depth (number of
+            words). The service is the name of the service to be used to access it by default and
+            an optional Intel HEX, S-record or binary image can be given to initialize its data.'''
+        m = self.controller.model().add_memory(name, width, depth,
+                                               base_address, service)
+        if image is not None:
+            m.load_image(str(image))
+        return m
+
     def clean_enums(self, enums):
         e = []
         if enums is not None:
@@ -198,4 +216,5 @@ class Loader:
             "SimpleReg": self.SimpleReg,
             "MemoryMap": self.MemoryMap,
             "AddressBlock": self.AddressBlock,
+            "Memory": self.Memory,
         }
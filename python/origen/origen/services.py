@@ -1,8 +1,12 @@
 import origen
 import _origen
 JTAG = _origen.services.JTAG
+IJTAG = _origen.services.IJTAG
 SWD = _origen.services.SWD
 Simple = _origen.services.Simple
+SPI = _origen.services.SPI
+I2C = _origen.services.I2C
+IEEE1500 = _origen.services.IEEE1500
 
 
 # This defines the methods for defining sub-blocks in Python and then handles serializing
@@ -100,6 +100,13 @@ interbuild_dir = source_dir.joinpath('interbuild')
   and can safely be deleted between runs.
 '''
 
+generated_pages_dir = interbuild_dir.joinpath('origen')
+'''
+  Pages generated by Origen from the app, i.e. its registers and the test program flows from the last
+  ``origen generate``. These can be linked to from the app's own pages as ``interbuild/origen/registers``
+  and ``interbuild/origen/flows``.
+'''
+
 output_build_dir = origen.app.website_output_dir.joinpath(SPHINX_OUTPUT)
 ''' Resolved location for the final output '''
 
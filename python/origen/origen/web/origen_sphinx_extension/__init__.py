@@ -87,9 +87,11 @@ def setup(sphinx):
     sphinx.add_config_value('origen_bypass_rustdoc', False, '')
     sphinx.add_config_value('origen_bypass_subprojects', False, '')
     sphinx.add_config_value('origen_releasing_build', False, '')
+    sphinx.add_config_value('origen_register_pages', True, '')
 
     sphinx.connect("config-inited", apply_origen_config)
     sphinx.connect("builder-inited", subprojects.build_subprojects)
+    sphinx.connect("builder-inited", write_register_pages)
     sphinx.config.html_theme_path += [sphinxbootstrap4theme.get_path()]
     sphinx.add_html_theme('origen', str(theme_dir))
 
@@ -301,6 +303,28 @@ def apply_origen_config(sphinx, config):
             }
 
 
+def write_register_pages(sphinx):
+    '''
+    Loads the app's default target(s) and writes a page documenting the DUT's registers to the
+    :data:`origen.web.generated_pages_dir`. This is skipped for ``--no-api`` builds.
+  '''
+    config = sphinx.config
+    if config.origen_no_api or not config.origen_register_pages:
+        return
+    try:
+        origen.target.load_unless_loaded()
+    except Exception as e:
+        logger.warning(f"Could not load the target to build the register pages: {e}")
+        return
+    if origen.dut is None:
+        logger.info("No DUT is defined by the current target, skipping register pages")
+        return
+    d = origen.web.generated_pages_dir
+    d.mkdir(parents=True, exist_ok=True)
+    logger.info(f"Writing register pages to {d}")
+    d.joinpath("registers.rst").write_text(origen.dut.to_register_rst())
+
+
 def clean(partial_config):
     logger.info("Cleaning origen_sphinx_extension...")
     if hasattr(partial_config, 'origen_subprojects'):
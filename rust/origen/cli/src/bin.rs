@@ -290,133 +290,6 @@ fn main() -> Result<()> {
     /******************** Global only commands ******************************************/
     /************************************************************************************/
     // if !STATUS.is_app_present {
-        //************************************************************************************/
-        // let proj_help = "Manage multi-repository project areas and workspaces";
-        // origen_commands.push(CommandHelp {
-        //     name: "proj".to_string(),
-        //     help: proj_help.to_string(),
-        //     shortcut: None,
-        // });
-
-        // app = app
-        //     .subcommand(
-        //         Command::new("proj")
-        //             .display_order(1)
-        //             .about(proj_help)
-        //             .arg_required_else_help(true)
-        //             .subcommand(Command::new("init")
-        //                 .display_order(5)
-        //                 .about("Initialize a new project directory (create an initial project BOM)")
-        //                 .arg(Arg::new("dir")
-        //                     .action(SetArg)
-        //                     .help("The path to the project directory to initialize (PWD will be used by default if not given)")
-        //                     .value_name("DIR")
-        //                 )
-        //             )
-        //             .subcommand(Command::new("packages")
-        //                 .display_order(7)
-        //                 .about("Displays the IDs of all packages and package groups defined by the BOM")
-        //             )
-        //             .subcommand(Command::new("create")
-        //                 .display_order(10)
-        //                 .about("Create a new project workspace from the project BOM")
-        //                 .arg(Arg::new("path")
-        //                     .help("The path to the new workspace directory")
-        //                     .action(SetArg)
-        //                     .value_name("PATH")
-        //                     .required(true)
-        //                 )
-        //             )
-        //             .subcommand(Command::new("update")
-        //                 .display_order(15)
-        //                 .about("Update an existing project workspace per its current BOM")
-        //                 .arg(Arg::new("force")
-        //                     .short('f')
-        //                     .long("force")
-        //                     .required(false)
-        //                     .action(SetArgTrue)
-        //                     .help("Force the update and potentially lose any local modifications")
-        //                 )
-        //                 .arg(Arg::new("links")
-        //                     .short('l')
-        //                     .long("links")
-        //                     .required(false)
-        //                     .action(SetArgTrue)
-        //                     .help("Update the workspace links")
-        //                 )
-        //                 .arg(Arg::new("packages")
-        //                     .value_name("PACKAGES")
-        //                     .action(AppendArgs)
-        //                     .multiple(true)
-        //                     .help("Packages and/or groups to be updated, run 'origen proj packages' to see a list of possible package IDs")
-        //                     .required_unless("links")
-        //                     .required(true)
-        //                 )
-        //             )
-        //             .subcommand(Command::new("mods")
-        //                 .display_order(20)
-        //                 .about("Display a list of modified files within the given package(s)")
-        //                 .arg(Arg::new("packages")
-        //                     .help("Package(s) to look for modifications in, use 'all' to see the modification to all packages")
-        //                     .action(AppendArgs)
-        //                     .multiple(true)
-        //                     .value_name("PACKAGES")
-        //                     .required(true)
-        //                 )
-        //             )
-        //             .subcommand(Command::new("clean")
-        //                 .display_order(20)
-        //                 .about("Revert all local modifications within the given package(s)")
-        //                 .arg(Arg::new("packages")
-        //                     .help("Package(s) to revert local modifications in, use 'all' to clean all packages")
-        //                     .action(AppendArgs)
-        //                     .multiple(true)
-        //                     .value_name("PACKAGES")
-        //                     .required(true)
-        //                 )
-        //             )
-        //             .subcommand(Command::new("tag")
-        //                 .display_order(20)
-        //                 .about("Apply the given tag to the current view of the given package(s)")
-        //                 .arg(Arg::new("name")
-        //                     .help("Name of the tag to be applied")
-        //                     .action(SetArg)
-        //                     .value_name("NAME")
-        //                     .required(true)
-        //                 )
-        //                 .arg(Arg::new("packages")
-        //                     .help("Package(s) to be tagged, use 'all' to tag all packages")
-        //                     .multiple(true)
-        //                     .action(AppendArgs)
-        //                     .value_name("PACKAGES")
-        //                     .required(true)
-        //                 )
-        //                 .arg(Arg::new("force")
-        //                     .short('f')
-        //                     .long("force")
-        //                     .required(false)
-        //                     .action(SetArgTrue)
-        //                     .help("Force the application of the tag even if there are local modifications")
-        //                 )
-        //                 .arg(Arg::new("message")
-        //                     .short('m')
-        //                     .long("message")
-        //                     .required(false)
-        //                     .action(SetArg)
-        //                     .help("A message to be applied with the tag")
-        //                 )
-        //             )
-        //             .subcommand(Command::new("bom")
-        //                 .display_order(25)
-        //                 .about("View the active BOM in the current or given directory")
-        //                 .arg(Arg::new("dir")
-        //                     .action(SetArg)
-        //                     .help("The path to a directory (PWD will be used by default if not given)")
-        //                     .value_name("DIR")
-        //                 )
-        //             )
-        //     );
-
         // //************************************************************************************/
         // let new_help = "Create a new Origen application";
         // origen_commands.push(CommandHelp {
@@ -447,6 +320,8 @@ fn main() -> Result<()> {
     commands::aux_cmds::add_helps(&mut helps, &aux_cmds);
     commands::eval::add_helps(&mut helps);
     commands::exec::add_helps(&mut helps);
+    commands::completions::add_helps(&mut helps);
+    commands::config::add_helps(&mut helps);
     commands::credentials::add_helps(&mut helps);
     commands::interactive::add_helps(&mut helps);
 
@@ -456,8 +331,12 @@ fn main() -> Result<()> {
         commands::generate::add_helps(&mut helps);
         commands::target::add_helps(&mut helps);
         commands::save_ref::add_helps(&mut helps);
+        commands::regs::add_helps(&mut helps);
+        commands::web::add_helps(&mut helps);
+        commands::new::new_resource::add_helps(&mut helps);
     } else {
         commands::new::add_helps(&mut helps);
+        commands::proj::add_helps(&mut helps);
     }
 
     if STATUS.is_origen_present {
@@ -471,6 +350,8 @@ fn main() -> Result<()> {
     /************************************************************************************/
 
     // app = mailer::add_commands(app, &mut origen_commands)?;
+    app = commands::completions::add_commands(app, &helps, &extensions)?;
+    app = commands::config::add_commands(app, &helps, &extensions)?;
     app = commands::credentials::add_commands(app, &helps, &extensions)?;
     app = commands::eval::add_commands(app, &helps, &extensions)?;
     app = commands::exec::add_commands(app, &helps, &extensions)?;
@@ -494,81 +375,9 @@ fn main() -> Result<()> {
         app = commands::env::add_commands(app, &helps, &extensions)?;
         app = commands::generate::add_commands(app, &helps, &extensions)?;
         app = commands::save_ref::add_commands(app, &helps, &extensions)?;
-
-//         /************************************************************************************/
-//         let new_help = "Generate a new block, flow, pattern, etc. for your application";
-//         origen_commands.push(CommandHelp {
-//             name: "new".to_string(),
-//             help: new_help.to_string(),
-//             shortcut: None,
-//         });
-//         app = app.subcommand(
-//             Command::new("new")
-//             .about(new_help)
-//             .arg_required_else_help(true)
-//             .subcommand(Command::new("dut")
-//                 .display_order(5)
-//                 .about("Create a new top-level (DUT) block, see 'origen new dut -h' for more info")
-//                 .long_about(
-// "This generator creates a top-level (DUT) block and all of the associated resources for it, e.g. a
-// reg file, controller, target, timesets, pins, etc.
-
-// The NAME of the DUT should be given in lower case, optionally prefixed by parent DUT name(s) separated
-// by a forward slash.
-
-// Any parent DUT(s) will be created if they don't exist, but they will not be modified if they do.
-
-// Examples:
-//   origen new dut                # Creates <app_name>/blocks/dut/...
-//   origen new dut falcon         # Creates <app_name>/blocks/dut/derivatives/falcon/...
-//   origen new dut dsp/falcon     # Creates <app_name>/blocks/dut/derivatives/dsp/derivatives/falcon/...")
-//                 .arg(Arg::new("name")
-//                     .action(SetArg)
-//                     .required(false)
-//                     .help("The name of the new DUT")
-//                     .value_name("NAME")
-//                 )
-//             )
-//             .subcommand(Command::new("block")
-//                 .display_order(5)
-//                 .about("Create a new block, see 'origen new block -h' for more info")
-//                 .long_about(
-// "This generator creates a block (e.g. to represent RAM, ATD, Flash, DAC, etc.) and all of the associated
-// resources for it, e.g. a reg file, controller, timesets, etc.
-
-// The NAME should be given in lower case (e.g. flash/flash2kb, adc/adc16), optionally with
-// additional parent sub-block names after the initial type.
-
-// Alternatively, a reference to an existing BLOCK can be added, in which case a nested block will be created
-// within that block's 'blocks/' directory, rather than a primary top-level block.
-
-// Any parent block(s) will be created if they don't exist, but they will not be modified if they do.
-
-// Examples:
-//   origen new block dac                  # Creates <app_name>/blocks/dac/...
-//   origen new block adc/adc8bit          # Creates <app_name>/blocks/adc/derivatives/adc8bit/...
-//   origen new block adc/adc16bit         # Creates <app_name>/blocks/adc/derivatives/adc16bit/...
-//   origen new block nvm/flash/flash2kb   # Creates <app_name>/blocks/nvm/derivatives/flash/derivatives/flash2kb/...
-
-//   # Example of creating a nested sub-block
-//   origen new block bist --parent nvm/flash   # Creates <app_name>/blocks/nvm/derivatives/flash/blocks/bist/...")
-//                 .arg(Arg::new("name")
-//                     .action(SetArg)
-//                     .required(true)
-//                     .help("The name of the new block, including its parents if applicable")
-//                     .value_name("NAME")
-//                 )
-//                 .arg(
-//                     Arg::new("parent")
-//                         .short('p')
-//                         .long("parent")
-//                         .help("Create the new block nested within this existing block")
-//                         .action(SetArg)
-//                         .required(false)
-//                         .value_name("PARENT")
-//                 )
-//             )
-//         );
+        app = commands::regs::add_commands(app, &helps, &extensions)?;
+        app = commands::web::add_commands(app, &helps, &extensions)?;
+        app = commands::new::new_resource::add_commands(app, &helps, &extensions)?;
 
 //         /************************************************************************************/
 //         let c_help = "Compile templates";
@@ -612,101 +421,6 @@ fn main() -> Result<()> {
 
         app = commands::target::add_commands(app, &helps, &extensions)?;
 
-//         /************************************************************************************/
-//         let t_help = "Create, Build, and View Web Documentation";
-//         origen_commands.push(CommandHelp {
-//             name: "web".to_string(),
-//             help: t_help.to_string(),
-//             shortcut: Some("w".to_string()),
-//         });
-//         app = app.subcommand(
-//             Command::new("web")
-//                 .about(t_help)
-//                 .arg_required_else_help(true)
-//                 .visible_alias("w")
-//                 .subcommand(
-//                     Command::new("build") // What I think this command should be called
-//                         .about("Builds the web documentation")
-//                         .visible_alias("b")
-//                         .visible_alias("compile") // If coming from O1
-//                         .visible_alias("html") // If coming from Sphinx and using quickstart's Makefile
-//                         .arg(
-//                             Arg::new("view")
-//                                 .long("view")
-//                                 .help("Launch your web browser after the build")
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("clean")
-//                                 .long("clean")
-//                                 .help(
-//                                     "Clean up directories from previous builds and force a rebuild",
-//                                 )
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("release")
-//                                 .long("release")
-//                                 .short('r')
-//                                 .help("Release (deploy) the resulting web pages")
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("archive")
-//                                 .long("archive")
-//                                 .short('a')
-//                                 .help("Archive the resulting web pages after building")
-//                                 .action(SetArg)
-//                                 .multiple(false)
-//                                 .min_values(0),
-//                         )
-//                         .arg(
-//                             Arg::new("as-release")
-//                                 .long("as-release")
-//                                 .help("Build webpages with release checks")
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("release-with-warnings")
-//                                 .long("release-with-warnings")
-//                                 .help("Release webpages even if warnings persists")
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("no-api")
-//                                 .long("no-api")
-//                                 .help("Skip building the API")
-//                                 .action(SetArgTrue),
-//                         )
-//                         .arg(
-//                             Arg::new("sphinx-args")
-//                                 .long("sphinx-args")
-//                                 .help(
-//                                     "Additional arguments to pass to the 'sphinx-build' command
-//   Argument will passed as a single string and appended to the build command
-//   E.g.: 'origen web build --sphinx-args \"-q -D my_config_define=1\"'
-//      -> 'sphinx-build <source_dir> <output_dir> -q -D my_config_define=1'",
-//                                 )
-//                                 .action(SetArg)
-//                                 .multiple(false)
-//                                 .allow_hyphen_values(true),
-//                         ), // .arg(Arg::new("pdf")
-//                            //     .long("pdf")
-//                            //     .help("Create a PDF of resulting web pages")
-//                            //     .action(SetArgTrue)
-//                            // )
-//                 )
-//                 .subcommand(
-//                     Command::new("view")
-//                         .about("Launches your web browser to view previously built documentation")
-//                         .visible_alias("v"),
-//                 )
-//                 .subcommand(
-//                     Command::new("clean")
-//                         .about("Cleans the output directory and all cached files"),
-//                 ),
-//         );
-
 //         /************************************************************************************/
 //         let mailer_help =
 //             "Command-line-interface to Origen's mailer for quick emailing or shell-scripting";
@@ -788,6 +502,7 @@ fn main() -> Result<()> {
 //         );
     } else {
         app = commands::new::add_commands(app, &helps, &extensions)?;
+        app = commands::proj::add_commands(app, &helps, &extensions)?;
     }
 
     let mut all_cmds_and_aliases = vec![];
@@ -1021,13 +736,14 @@ fn main() -> Result<()> {
     match matches.subcommand_name() {
         Some(commands::app::BASE_CMD) => commands::app::run(matches.subcommand_matches(commands::app::BASE_CMD).unwrap(), &app, &extensions, plugins.as_ref(), &app_cmds.as_ref().unwrap())?,
         Some(commands::new::BASE_CMD) => run_non_ext_cmd_match_case!(new),
-        // Some("proj") => commands::proj::run(matches.subcommand_matches("proj").unwrap()),
+        Some(commands::proj::BASE_CMD) => run_non_ext_cmd_match_case!(proj),
         Some(commands::env::BASE_CMD) => run_non_ext_cmd_match_case!(env),
         Some(commands::eval::BASE_CMD) => run_cmd_match_case!(eval),
         Some(commands::develop_origen::BASE_CMD) => run_non_ext_cmd_match_case!(develop_origen),
         Some(commands::interactive::BASE_CMD) => run_cmd_match_case!(interactive),
         Some(commands::aux_cmds::BASE_CMD) => commands::aux_cmds::run(matches.subcommand_matches(commands::aux_cmds::BASE_CMD).unwrap(), &app, &extensions, plugins.as_ref(), &aux_cmds)?,
         Some(commands::generate::BASE_CMD) => run_cmd_match_case!(generate),
+        Some(commands::regs::BASE_CMD) => run_cmd_match_case!(regs),
         // Some("compile") => {
         //     let m = matches.subcommand_matches("compile").unwrap();
         //     commands::launch(
@@ -1046,65 +762,7 @@ fn main() -> Result<()> {
         //     );
         // }
         Some(commands::target::BASE_CMD) => run_non_ext_cmd_match_case!(target),
-        // Some("web") => {
-        //     let cmd = matches.subcommand_matches("web").unwrap();
-        //     let subcmd = cmd.subcommand().unwrap();
-        //     let sub = subcmd.1;
-        //     match subcmd.0 {
-        //         "build" => {
-        //             let mut args = IndexMap::new();
-        //             if sub.contains_id("view") {
-        //                 args.insert("view", "True".to_string());
-        //             }
-        //             if sub.contains_id("clean") {
-        //                 args.insert("clean", "True".to_string());
-        //             }
-        //             if sub.contains_id("no-api") {
-        //                 args.insert("no-api", "True".to_string());
-        //             }
-        //             if sub.contains_id("as-release") {
-        //                 args.insert("as-release", "True".to_string());
-        //             }
-        //             if sub.contains_id("release-with-warnings") {
-        //                 args.insert("release-with-warnings", "True".to_string());
-        //             }
-        //             if sub.contains_id("release") {
-        //                 args.insert("release", "True".to_string());
-        //             }
-        //             if sub.contains_id("archive") {
-        //                 if let Some(archive) = sub.get_one::<&str>("archive") {
-        //                     args.insert("archive", format!("'{}'", archive));
-        //                 } else {
-        //                     args.insert("archive", "True".to_string());
-        //                 }
-        //             }
-        //             if let Some(s_args) = sub.get_one::<&str>("sphinx-args") {
-        //                 // Recall that this comes in as a single argument, potentially quoted to mimic multiple,
-        //                 // but a single argument from the perspective here nonetheless
-        //                 args.insert("sphinx-args", format!("'{}'", s_args));
-        //             }
-        //             commands::launch(
-        //                 "web:build",
-        //                 if let Some(targets) = cmd.get_many::<String>("target") {
-        //                     Some(targets.map(|t| t.as_str()).collect())
-        //                 } else {
-        //                     Option::None
-        //                 },
-        //                 &None,
-        //                 None,
-        //                 None,
-        //                 None,
-        //                 false,
-        //                 Some(args),
-        //             )
-        //         }
-        //         "view" => commands::launch("web:view", None, &None, None, None, None, false, None),
-        //         "clean" => {
-        //             commands::launch("web:clean", None, &None, None, None, None, false, None)
-        //         }
-        //         _ => {}
-        //     }
-        // }
+        Some(commands::web::BASE_CMD) => run_non_ext_cmd_match_case!(web),
         // Some("mailer") => {
         //     let cmd = matches.subcommand_matches("mailer").unwrap();
         //     let subcmd = cmd.subcommand().unwrap();
@@ -1162,6 +820,8 @@ fn main() -> Result<()> {
         //         _ => {}
         //     }
         // }
+        Some(commands::completions::BASE_CMD) => commands::completions::run(matches.subcommand_matches(commands::completions::BASE_CMD).unwrap(), &app)?,
+        Some(commands::config::BASE_CMD) => run_non_ext_cmd_match_case!(config),
         Some(commands::credentials::BASE_CMD) => run_cmd_match_case!(credentials),
         // Some("mode") => {
         //     let matches = matches.subcommand_matches("mode").unwrap();
@@ -4,6 +4,8 @@ use crate::framework::AppCmds;
 use crate::commands::_prelude::*;
 use crate::framework::app_cmds::add_commands as add_app_user_commands;
 use crate::framework::app_cmds::add_helps as add_app_cmd_helps;
+use std::path::PathBuf;
+use std::process::exit;
 
 pub const BASE_CMD: &'static str = "app";
 
@@ -14,178 +16,171 @@ pub (crate) fn add_helps(helps: &mut CmdHelps, app_cmds: &AppCmds) {
 
 pub (crate) fn add_commands<'a>(app: App<'a>, helps: &'a CmdHelps, app_cmds: &'a AppCmds, exts: &'a Extensions, ) -> Result<App<'a>> {
     let mut app_subc = helps.core_cmd(BASE_CMD)
-        .arg_required_else_help(true);
-        // .subcommand(
-        //     Command::new("init")
-        //         .about("Initialize the application's revision control")
-        // )
-        // .subcommand(
-        //     Command::new("status")
-        //         .about("Show any local changes")
-        //         .arg(Arg::new("modified")
-        //             .long("modified")
-        //             .action(SetArgTrue)
-        //             .help("Show tracked, modified files")
-        //         )
-        //         .arg(Arg::new("untracked")
-        //             .long("untracked")
-        //             .action(SetArgTrue)
-        //             .help("Show untracked files")
-        //         )
-        // )
-        // .subcommand(
-        //     Command::new("checkin")
-        //         .about("Check in the given pathspecs")
-        //         .arg(Arg::new("pathspecs")
-        //             .help("The paths to be checked in")
-        //             .action(AppendArgs)
-        //             .value_name("PATHSPECS")
-        //             .multiple(true)
-        //         )
-        //         .arg(Arg::new("all")
-        //             .long("all")
-        //             .short('a')
-        //             .action(SetArgTrue)
-        //             .conflicts_with("pathspecs")
-        //             .help("Check in all changes in the workspace")
-        //         )
-        //         .arg(Arg::new("dry-run")
-        //             .long("dry-run")
-        //             .action(SetArgTrue)
-        //             .conflicts_with("pathspecs")
-        //             .help("Perform a dry-run only")
-        //         )
-        //         .arg(Arg::new("message")
-        //             .long("message")
-        //             .short('m')
-        //             .action(SetArg)
-        //             .required(true)
-        //             .help("Message to provide with the check-in operation")
-        //         )
-        // )
-        // .subcommand(
-        //     Command::new("package")
-        //         .about("Build the app into publishable package (e.g., a 'python wheel')"),
-        // )
-        // .subcommand(Command::new("run_publish_checks")
-        //     .about("Run production-ready and publish-ready checks")
-        // )
-        // .subcommand(Command::new("publish")
-        //     .about("Publish (release) the app")
-        //     .arg(Arg::new("dry-run")
-        //         .long("dry-run")
-        //         .action(SetArgTrue)
-        //         .help("Runs through the entire process except the uploading and mailer steps")
-        //     )
-        //     .arg(Arg::new("version")
-        //         .long("version")
-        //         .action(SetArg)
-        //         .value_name("VERSION")
-        //         .help("Publish with the given version increment")
-        //     )
-        //     .arg(Arg::new("release-note")
-        //         .long("release-note")
-        //         .action(SetArg)
-        //         .value_name("NOTE")
-        //         .help("Publish with the given release note")
-        //     )
-        //     .arg(Arg::new("release-title")
-        //         .long("release-title")
-        //         .action(SetArg)
-        //         .value_name("TITLE")
-        //         .help("Publish with the given release title")
-        //     )
-        //     .arg(Arg::new("no-release-title")
-        //         .long("no-release-title")
-        //         .action(SetArgTrue)
-        //         .help("Indicate no release title will be provided")
-        //         .conflicts_with("release-title")
-        //     )
-        // );
+        .arg_required_else_help(true)
+        .subcommand(
+            Command::new("init")
+                .about("Initialize the application's revision control")
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Show any local changes")
+                .arg(Arg::new("modified")
+                    .long("modified")
+                    .action(SetArgTrue)
+                    .help("Show tracked, modified files")
+                )
+                .arg(Arg::new("untracked")
+                    .long("untracked")
+                    .action(SetArgTrue)
+                    .help("Show untracked (new) files")
+                )
+        )
+        .subcommand(
+            Command::new("checkin")
+                .about("Check in the given pathspecs")
+                .arg(Arg::new("pathspecs")
+                    .help("The paths to be checked in")
+                    .action(AppendArgs)
+                    .value_name("PATHSPECS")
+                    .multiple(true)
+                )
+                .arg(Arg::new("all")
+                    .long("all")
+                    .short('a')
+                    .action(SetArgTrue)
+                    .conflicts_with("pathspecs")
+                    .help("Check in all changes in the workspace")
+                )
+                .arg(Arg::new("dry-run")
+                    .long("dry-run")
+                    .action(SetArgTrue)
+                    .help("Perform a dry-run only")
+                )
+                .arg(Arg::new("message")
+                    .long("message")
+                    .short('m')
+                    .action(SetArg)
+                    .required(true)
+                    .help("Message to provide with the check-in operation")
+                )
+        )
+        .subcommand(
+            Command::new("package")
+                .about("Build the app into publishable package (e.g., a 'python wheel')"),
+        )
+        .subcommand(Command::new("run_publish_checks")
+            .about("Run production-ready and publish-ready checks")
+        )
+        .subcommand(Command::new("publish")
+            .about("Publish (release) the app")
+            .arg(Arg::new("dry-run")
+                .long("dry-run")
+                .action(SetArgTrue)
+                .help("Runs through the entire process except the uploading and mailer steps")
+            )
+            .arg(Arg::new("version")
+                .long("version")
+                .action(SetArg)
+                .value_name("VERSION")
+                .help("Publish with the given version, or increment the current version by the given release type (major, minor, patch, beta, alpha, dev)")
+            )
+            .arg(Arg::new("release-note")
+                .long("release-note")
+                .action(SetArg)
+                .value_name("NOTE")
+                .help("Publish with the given release note")
+            )
+            .arg(Arg::new("release-title")
+                .long("release-title")
+                .action(SetArg)
+                .value_name("TITLE")
+                .help("Publish with the given release title")
+            )
+            .arg(Arg::new("no-release-title")
+                .long("no-release-title")
+                .action(SetArgTrue)
+                .help("Indicate no release title will be provided")
+                .conflicts_with("release-title")
+            )
+        );
     app_subc = add_app_user_commands(app_subc, helps, app_cmds, exts)?;
     Ok(app.subcommand(app_subc))
 }
 
-// fn _run(cmd: &str, proc_cmd: &ArgMatches, args: Option<IndexMap<&str, String>>) -> Result<()>{
-//     super::launch(
-//         cmd,
-//         if let Some(targets) = proc_cmd.get_many::<String>("target") {
-//             Some(targets.map(|t| t.as_str()).collect())
-//         } else {
-//             Option::None
-//         },
-//         &None,
-//         None,
-//         None,
-//         None,
-//         false,
-//         args,
-//     );
-//     Ok(())
-// }
-
 pub(crate) fn run(invocation: &ArgMatches, mut app: &App, exts: &Extensions, plugins: Option<&Plugins>, app_cmds: &AppCmds) -> origen::Result<()> {
     let subcmd = invocation.subcommand().unwrap();
     let sub = subcmd.1;
     match subcmd.0 {
-        // "init" => {
-        //     _run("app:init", sub, None)
-        // }
-        // "status" => {
-        //     let mut args = IndexMap::new();
-        //     if sub.contains_id("modified") {
-        //         args.insert("modified", "True".to_string());
-        //     }
-        //     if sub.contains_id("untracked") {
-        //         args.insert("untracked", "True".to_string());
-        //     }
-        //     _run("app:status", sub, Some(args))
-        // }
-        // "checkin" => {
-        //     let mut args = IndexMap::new();
-        //     if sub.contains_id("all") {
-        //         args.insert("all", "True".to_string());
-        //     }
-        //     if sub.contains_id("dry-run") {
-        //         args.insert("dry-run", "True".to_string());
-        //     }
-        //     if let Some(pathspecs) = sub.get_many::<String>("pathspecs") {
-        //         let p = pathspecs
-        //             .map(|ps| format!("\"{}\"", ps))
-        //             .collect::<Vec<String>>();
-        //         args.insert("pathspecs", format!("[{}]", p.join(",")));
-        //     }
-        //     args.insert(
-        //         "msg",
-        //         format!("\"{}\"", sub.get_one::<String>("message").unwrap()),
-        //     );
-        //     _run("app:checkin", sub, Some(args))
-        // }
-        // "package" => {
-        //     _run("app:package", sub, None)
-        // }
-        // "publish" => {
-        //     let mut args = IndexMap::new();
-        //     if sub.contains_id("dry-run") {
-        //         args.insert("dry-run", "True".to_string());
-        //     }
-        //     if let Some(v) = sub.get_one::<&str>("version") {
-        //         args.insert("version", format!("\"{}\"", v.to_string()));
-        //     }
-        //     if let Some(r) = sub.get_one::<&str>("release-note") {
-        //         args.insert("release-note", format!("\"{}\"", r.to_string()));
-        //     }
-        //     if let Some(t) = sub.get_one::<&str>("release-title") {
-        //         args.insert("release-title", format!("\"{}\"", t.to_string()));
-        //     }
-        //     if sub.contains_id("no-release-title") {
-        //         args.insert("no-release-title", "True".to_string());
-        //     }
-        //     _run("app:publish", sub, Some(args))
-        // }
-        // "run_publish_checks" => {
-        //     _run("app:run_publish_checks", sub, None)
-        // }
+        "init" => {
+            origen::app().unwrap().rc_init()?.summarize_and_exit();
+            Ok(())
+        }
+        "status" => {
+            let mut status = origen::app().unwrap().rc_status()?;
+            let modified = *sub.get_one::<bool>("modified").unwrap();
+            let untracked = *sub.get_one::<bool>("untracked").unwrap();
+            // Everything is shown unless limited to either kind of change
+            if modified && !untracked {
+                status.added.clear();
+            } else if untracked && !modified {
+                status.removed.clear();
+                status.changed.clear();
+                status.conflicted.clear();
+            }
+            status.summarize();
+            Ok(())
+        }
+        "checkin" => {
+            let pathspecs = match sub.get_many::<String>("pathspecs") {
+                Some(ps) if !*sub.get_one::<bool>("all").unwrap() => {
+                    Some(ps.map(PathBuf::from).collect::<Vec<PathBuf>>())
+                }
+                _ => None,
+            };
+            origen::app().unwrap().rc_checkin(
+                pathspecs.as_ref().map(|ps| ps.iter().map(|p| p.as_path()).collect()),
+                sub.get_one::<String>("message").unwrap(),
+                *sub.get_one::<bool>("dry-run").unwrap(),
+            )?.gist();
+            Ok(())
+        }
+        "package" => {
+            let r = origen::app().unwrap().build_package()?;
+            if let Some(m) = r.msg() {
+                log_info!("{}", m);
+            }
+            if !r.succeeded() {
+                bail!("Failed to build package");
+            }
+            Ok(())
+        }
+        "run_publish_checks" => {
+            let stat = origen::app().unwrap().run_publish_checks(false)?;
+            stat.summarize();
+            if stat.failed() {
+                exit(1);
+            }
+            Ok(())
+        }
+        "publish" => {
+            let app = origen::app().unwrap();
+            let version = match sub.get_one::<String>("version") {
+                Some(v) => Some(app.next_version(Some(v))?),
+                None => None,
+            };
+            let release_title = if *sub.get_one::<bool>("no-release-title").unwrap() {
+                Some(None)
+            } else {
+                sub.get_one::<String>("release-title").map(|t| Some(t.as_str()))
+            };
+            app.publish(
+                version,
+                release_title,
+                sub.get_one::<String>("release-note").map(|n| n.as_str()),
+                *sub.get_one::<bool>("dry-run").unwrap(),
+            )?.summarize_and_exit();
+            Ok(())
+        }
         "commands" => {
             if sub.subcommand().is_some() { // invocation.subcommand() {
                 let mut overrides = IndexMap::new();
@@ -197,7 +192,7 @@ pub(crate) fn run(invocation: &ArgMatches, mut app: &App, exts: &Extensions, plu
                 app = app.find_subcommand("commands").unwrap();
                 while matches.subcommand_name().is_some() {
                     let n = matches.subcommand_name().unwrap();
-                    matches = matches.subcommand_matches(&n).unwrap();
+                    matches = matches.subcommand_matches(n).unwrap();
                     app = app.find_subcommand(n).unwrap();
                     path_pieces.push(n.to_string());
                 }
@@ -0,0 +1,611 @@
+use super::_prelude::*;
+use origen::core::application::target;
+use pathdiff::diff_paths;
+
+pub const BASE_CMD: &'static str = "completions";
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish"];
+
+gen_core_cmd_funcs__no_exts__no_app_opts!(
+    BASE_CMD,
+    "Generate a shell completion script for the origen command",
+    {
+        |cmd: App<'a>| {
+            cmd.arg(
+            Arg::new("shell")
+                .help("The shell to generate the completion script for")
+                .action(SetArg)
+                .value_name("SHELL")
+                .possible_values(SHELLS)
+                .required(true),
+        )
+        .after_help("The script covers all commands available in the current workspace, including app, plugin and aux
+commands, and the app's targets. Re-generate it when these change.
+
+E.g. to load it into the current shell:
+  bash: source <(origen completions bash)
+  zsh:  source <(origen completions zsh)
+  fish: origen completions fish | source")
+        }
+    }
+);
+
+/// How the value(s) of an option or positional argument should be completed
+#[derive(Debug, Clone, PartialEq)]
+enum Values {
+    None,
+    Files,
+    Targets,
+    Modes,
+    List(Vec<String>),
+}
+
+#[derive(Debug)]
+struct Opt {
+    longs: Vec<String>,
+    shorts: Vec<char>,
+    help: String,
+    takes_value: bool,
+    values: Values,
+}
+
+impl Opt {
+    fn flags(&self) -> Vec<String> {
+        let mut flags: Vec<String> = self.longs.iter().map(|l| format!("--{}", l)).collect();
+        flags.extend(self.shorts.iter().map(|s| format!("-{}", s)));
+        flags
+    }
+}
+
+/// A command or subcommand, flattened out of the clap tree and identified by its path, e.g.
+/// "origen__app__commands"
+#[derive(Debug)]
+struct Cmd {
+    path: String,
+    /// The names (including visible aliases) of each of the command's subcommands, along with
+    /// the subcommand's path and help
+    subcmds: Vec<(Vec<String>, String, String)>,
+    opts: Vec<Opt>,
+    /// How any positional arguments should be completed
+    positionals: Values,
+}
+
+struct Completions {
+    bin: String,
+    cmds: Vec<Cmd>,
+    targets: Vec<String>,
+    modes: Vec<String>,
+}
+
+fn help_str(help: Option<&str>) -> String {
+    help.unwrap_or("")
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_string()
+}
+
+fn values_for(arg: &clap::Arg) -> Values {
+    let id = arg.get_id();
+    if id == crate::framework::TARGET_OPT_NAME || id == "target" {
+        return Values::Targets;
+    }
+    if id == crate::framework::MODE_OPT_NAME {
+        return Values::Modes;
+    }
+    if let Some(pvs) = arg.get_possible_values() {
+        return Values::List(
+            pvs.iter()
+                .filter(|pv| !pv.is_hide_set())
+                .map(|pv| pv.get_name().to_string())
+                .collect(),
+        );
+    }
+    let names = arg.get_value_names().map_or(vec![], |n| n.to_vec());
+    if names.iter().chain([id].iter()).any(|n| {
+        let n = n.to_uppercase();
+        n.contains("FILE") || n.contains("PATH") || n.contains("DIR") || n.contains("SCRIPT")
+    }) {
+        Values::Files
+    } else {
+        Values::None
+    }
+}
+
+fn collect<'a>(
+    cmd: &clap::Command<'a>,
+    path: &str,
+    globals: &[&clap::Arg<'a>],
+    cmds: &mut Vec<Cmd>,
+) {
+    let mut opts: Vec<Opt> = vec![];
+    let mut positionals = Values::None;
+    let mut child_globals: Vec<&clap::Arg> = globals.to_vec();
+    let args = cmd.get_arguments().collect::<Vec<&clap::Arg>>();
+    for arg in globals.iter().chain(args.iter()) {
+        // Clap's own help and version flags are only complete once the command has been built,
+        // so these are added separately below
+        if arg.is_hide_set() || arg.get_id() == "help" || arg.get_id() == "version" {
+            continue;
+        }
+        if arg.is_positional() {
+            if positionals == Values::None {
+                positionals = values_for(arg);
+            }
+            continue;
+        }
+        let mut longs: Vec<String> = vec![];
+        if let Some(l) = arg.get_long() {
+            longs.push(l.to_string());
+        }
+        if let Some(aliases) = arg.get_visible_aliases() {
+            longs.extend(aliases.iter().map(|a| a.to_string()));
+        }
+        let mut shorts: Vec<char> = vec![];
+        if let Some(s) = arg.get_short() {
+            shorts.push(s);
+        }
+        if let Some(aliases) = arg.get_visible_short_aliases() {
+            shorts.extend(aliases);
+        }
+        if longs.is_empty() && shorts.is_empty() {
+            continue;
+        }
+        // Global args may have been propagated already if clap has built this command
+        if opts.iter().any(|o| {
+            o.longs.iter().any(|l| longs.contains(l)) || o.shorts.iter().any(|s| shorts.contains(s))
+        }) {
+            continue;
+        }
+        if arg.is_global_set() && !globals.iter().any(|g| g.get_id() == arg.get_id()) {
+            child_globals.push(arg);
+        }
+        opts.push(Opt {
+            longs,
+            shorts,
+            help: help_str(arg.get_help()),
+            takes_value: arg.is_takes_value_set(),
+            values: values_for(arg),
+        });
+    }
+    if cmd.get_version().is_some() && !cmd.is_disable_version_flag_set() {
+        opts.push(Opt {
+            longs: vec!["version".to_string()],
+            shorts: vec!['V'],
+            help: "Print version information".to_string(),
+            takes_value: false,
+            values: Values::None,
+        });
+    }
+    if !cmd.is_disable_help_flag_set() {
+        opts.push(Opt {
+            longs: vec!["help".to_string()],
+            shorts: vec!['h'],
+            help: "Print help information".to_string(),
+            takes_value: false,
+            values: Values::None,
+        });
+    }
+
+    let mut subcmds = vec![];
+    let mut children = vec![];
+    for sub in cmd.get_subcommands() {
+        if sub.is_hide_set() {
+            continue;
+        }
+        let sub_path = format!("{}__{}", path, sub.get_name());
+        let mut names = vec![sub.get_name().to_string()];
+        names.extend(sub.get_visible_aliases().map(|a| a.to_string()));
+        subcmds.push((names, sub_path.clone(), help_str(sub.get_about())));
+        children.push((sub, sub_path));
+    }
+    cmds.push(Cmd {
+        path: path.to_string(),
+        subcmds,
+        opts,
+        positionals,
+    });
+    for (sub, sub_path) in children {
+        collect(sub, &sub_path, &child_globals, cmds);
+    }
+}
+
+impl Completions {
+    fn new(app: &clap::Command, targets: Vec<String>, modes: Vec<String>) -> Self {
+        let bin = match app.get_bin_name().unwrap_or(app.get_name()) {
+            "" => "origen",
+            n => n,
+        }
+        .to_string();
+        let mut cmds = vec![];
+        collect(app, &bin, &[], &mut cmds);
+        Self {
+            bin,
+            cmds,
+            targets,
+            modes,
+        }
+    }
+
+    fn fn_name(&self) -> String {
+        format!("_{}", self.bin.replace(|c: char| !c.is_alphanumeric(), "_"))
+    }
+
+    fn words(&self, values: &Values) -> String {
+        match values {
+            Values::Targets => self.targets.join(" "),
+            Values::Modes => self.modes.join(" "),
+            Values::List(l) => l.join(" "),
+            _ => "".to_string(),
+        }
+    }
+
+    /// The case arms which walk the words given so far to find the path of the current command
+    fn path_arms(&self, indent: &str, fish: bool) -> Vec<String> {
+        let mut lines = vec![];
+        for cmd in &self.cmds {
+            for (names, sub_path, _) in &cmd.subcmds {
+                let patterns = names
+                    .iter()
+                    .map(|n| format!("'{}__{}'", cmd.path, n))
+                    .collect::<Vec<String>>();
+                if fish {
+                    lines.push(format!("{}case {}", indent, patterns.join(" ")));
+                    lines.push(format!("{}    set cmd_path {}", indent, sub_path));
+                } else {
+                    lines.push(format!("{}{})", indent, patterns.join("|")));
+                    lines.push(format!("{}    cmd_path=\"{}\"", indent, sub_path));
+                    lines.push(format!("{}    ;;", indent));
+                }
+            }
+        }
+        lines
+    }
+
+    fn bash(&self) -> String {
+        let f = self.fn_name();
+        let mut lines = vec![
+            format!("{}() {{", f),
+            "    local i cur prev cmd_path".to_string(),
+            "    COMPREPLY=()".to_string(),
+            "    cur=\"${COMP_WORDS[COMP_CWORD]}\"".to_string(),
+            "    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"".to_string(),
+            format!("    cmd_path=\"{}\"", self.bin),
+            "".to_string(),
+            "    for ((i=1; i<COMP_CWORD; i++)); do".to_string(),
+            "        case \"${cmd_path}__${COMP_WORDS[i]}\" in".to_string(),
+        ];
+        lines.extend(self.path_arms("            ", false));
+        lines.push("        esac".to_string());
+        lines.push("    done".to_string());
+        lines.push("".to_string());
+        lines.push("    case \"${cmd_path}\" in".to_string());
+        for cmd in &self.cmds {
+            lines.push(format!("        {})", cmd.path));
+            let value_opts: Vec<&Opt> = cmd.opts.iter().filter(|o| o.takes_value).collect();
+            if !value_opts.is_empty() {
+                lines.push("            case \"${prev}\" in".to_string());
+                for o in value_opts {
+                    lines.push(format!("                {})", o.flags().join("|")));
+                    if o.values == Values::Files {
+                        lines.push(
+                            "                    COMPREPLY=($(compgen -f -- \"${cur}\"))"
+                                .to_string(),
+                        );
+                    } else {
+                        lines.push(format!(
+                            "                    COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                            self.words(&o.values)
+                        ));
+                    }
+                    lines.push("                    return 0".to_string());
+                    lines.push("                    ;;".to_string());
+                }
+                lines.push("            esac".to_string());
+            }
+            let mut words: Vec<String> = cmd.opts.iter().flat_map(|o| o.flags()).collect();
+            words.extend(cmd.subcmds.iter().flat_map(|s| s.0.clone()));
+            let positional = self.words(&cmd.positionals);
+            if !positional.is_empty() {
+                words.push(positional);
+            }
+            lines.push(format!(
+                "            COMPREPLY=($(compgen -W \"{}\" -- \"${{cur}}\"))",
+                words.join(" ")
+            ));
+            if cmd.positionals == Values::Files {
+                lines.push("            [[ \"${cur}\" != -* ]] && COMPREPLY+=($(compgen -f -- \"${cur}\"))".to_string());
+            }
+            lines.push("            ;;".to_string());
+        }
+        lines.push("    esac".to_string());
+        lines.push("    return 0".to_string());
+        lines.push("}".to_string());
+        lines.push("".to_string());
+        lines.push(format!(
+            "complete -F {} -o bashdefault -o default {}",
+            f, self.bin
+        ));
+        lines.join("\n") + "\n"
+    }
+
+    fn zsh(&self) -> String {
+        let esc = |s: &str| {
+            s.replace('\\', "\\\\")
+                .replace(':', "\\:")
+                .replace('\'', "'\\''")
+        };
+        let f = self.fn_name();
+        let mut lines = vec![
+            format!("#compdef {}", self.bin),
+            "".to_string(),
+            format!("{}() {{", f),
+            "    local i cmd_path prev".to_string(),
+            "    local -a subcmds opts".to_string(),
+            format!("    cmd_path=\"{}\"", self.bin),
+            "    prev=\"${words[CURRENT-1]}\"".to_string(),
+            "".to_string(),
+            "    for ((i=2; i<CURRENT; i++)); do".to_string(),
+            "        case \"${cmd_path}__${words[i]}\" in".to_string(),
+        ];
+        lines.extend(self.path_arms("            ", false));
+        lines.push("        esac".to_string());
+        lines.push("    done".to_string());
+        lines.push("".to_string());
+        lines.push("    case \"${cmd_path}\" in".to_string());
+        for cmd in &self.cmds {
+            lines.push(format!("        {})", cmd.path));
+            let value_opts: Vec<&Opt> = cmd.opts.iter().filter(|o| o.takes_value).collect();
+            if !value_opts.is_empty() {
+                lines.push("            case \"${prev}\" in".to_string());
+                for o in value_opts {
+                    lines.push(format!("                {})", o.flags().join("|")));
+                    if o.values == Values::Files {
+                        lines.push("                    _files".to_string());
+                    } else {
+                        let words = self.words(&o.values);
+                        if !words.is_empty() {
+                            lines.push(format!("                    compadd -- {}", words));
+                        }
+                    }
+                    lines.push("                    return".to_string());
+                    lines.push("                    ;;".to_string());
+                }
+                lines.push("            esac".to_string());
+            }
+            let subcmds = cmd
+                .subcmds
+                .iter()
+                .flat_map(|(names, _, help)| {
+                    names
+                        .iter()
+                        .map(move |n| format!("'{}:{}'", esc(n), esc(help)))
+                })
+                .collect::<Vec<String>>();
+            let opts = cmd
+                .opts
+                .iter()
+                .flat_map(|o| {
+                    o.flags()
+                        .into_iter()
+                        .map(move |fl| format!("'{}:{}'", esc(&fl), esc(&o.help)))
+                })
+                .collect::<Vec<String>>();
+            lines.push(format!("            subcmds=({})", subcmds.join(" ")));
+            lines.push(format!("            opts=({})", opts.join(" ")));
+            lines.push("            if [[ \"${words[CURRENT]}\" == -* ]]; then".to_string());
+            lines.push("                _describe 'option' opts".to_string());
+            lines.push("            else".to_string());
+            lines.push("                _describe 'command' subcmds".to_string());
+            match &cmd.positionals {
+                Values::Files => lines.push("                _files".to_string()),
+                Values::None => {}
+                v => lines.push(format!("                compadd -- {}", self.words(v))),
+            }
+            lines.push("            fi".to_string());
+            lines.push("            ;;".to_string());
+        }
+        lines.push("    esac".to_string());
+        lines.push("}".to_string());
+        lines.push("".to_string());
+        lines.push(format!("compdef {} {}", f, self.bin));
+        lines.join("\n") + "\n"
+    }
+
+    fn fish(&self) -> String {
+        let esc = |s: &str| s.replace('\\', "\\\\").replace('\'', "\\'");
+        let f = format!("__fish{}", self.fn_name());
+        let mut lines = vec![
+            format!("function {}_path", f),
+            "    set -l words (commandline -opc)".to_string(),
+            "    set -e words[1]".to_string(),
+            format!("    set -l cmd_path {}", self.bin),
+            "    for w in $words".to_string(),
+            "        switch \"$cmd_path\"__\"$w\"".to_string(),
+        ];
+        lines.extend(self.path_arms("            ", true));
+        lines.push("        end".to_string());
+        lines.push("    end".to_string());
+        lines.push("    echo $cmd_path".to_string());
+        lines.push("end".to_string());
+        lines.push("".to_string());
+        lines.push(format!("function {}_using_path", f));
+        lines.push(format!("    test ({}_path) = $argv[1]", f));
+        lines.push("end".to_string());
+        lines.push("".to_string());
+        lines.push(format!("complete -c {} -f", self.bin));
+        for cmd in &self.cmds {
+            let cond = format!("-n '{}_using_path {}'", f, cmd.path);
+            for (names, _, help) in &cmd.subcmds {
+                for n in names {
+                    lines.push(format!(
+                        "complete -c {} {} -a '{}' -d '{}'",
+                        self.bin,
+                        cond,
+                        esc(n),
+                        esc(help)
+                    ));
+                }
+            }
+            for o in &cmd.opts {
+                let mut line = format!("complete -c {} {}", self.bin, cond);
+                for l in &o.longs {
+                    line += &format!(" -l '{}'", esc(l));
+                }
+                for s in &o.shorts {
+                    line += &format!(" -s '{}'", s);
+                }
+                if o.takes_value {
+                    line += " -r";
+                    match &o.values {
+                        Values::Files => line += " -F",
+                        v => {
+                            let words = self.words(v);
+                            if !words.is_empty() {
+                                line += &format!(" -a '{}'", esc(&words));
+                            }
+                        }
+                    }
+                }
+                line += &format!(" -d '{}'", esc(&o.help));
+                lines.push(line);
+            }
+            match &cmd.positionals {
+                Values::Files => lines.push(format!("complete -c {} {} -F", self.bin, cond)),
+                Values::None => {}
+                v => lines.push(format!(
+                    "complete -c {} {} -a '{}'",
+                    self.bin,
+                    cond,
+                    esc(&self.words(v))
+                )),
+            }
+        }
+        lines.join("\n") + "\n"
+    }
+
+    fn generate(&self, shell: &str) -> Result<String> {
+        Ok(match shell {
+            "bash" => self.bash(),
+            "zsh" => self.zsh(),
+            "fish" => self.fish(),
+            _ => bail!(
+                "Unsupported shell '{}', expected one of: {}",
+                shell,
+                SHELLS.join(", ")
+            ),
+        })
+    }
+}
+
+/// Returns the names of the app's targets, as they would be given to the --targets option
+fn target_names() -> Vec<String> {
+    match origen::app() {
+        Some(app) => {
+            let dir = app.root.join("targets");
+            target::all(&dir)
+                .iter()
+                .filter_map(|t| diff_paths(&t.with_extension(""), &dir))
+                .map(|t| t.display().to_string().replace('\\', "/"))
+                .collect()
+        }
+        None => vec![],
+    }
+}
+
+pub(crate) fn run(invocation: &clap::ArgMatches, app: &clap::Command) -> Result<()> {
+    let completions = Completions::new(
+        app,
+        target_names(),
+        origen::MODES.iter().map(|m| m.to_string()).collect(),
+    );
+    print!(
+        "{}",
+        completions.generate(invocation.get_one::<String>("shell").unwrap())?
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> clap::Command<'static> {
+        clap::Command::new("origen")
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .short('v')
+                    .global(true)
+                    .help("Verbosity"),
+            )
+            .subcommand(
+                clap::Command::new("generate")
+                    .visible_alias("g")
+                    .about("Generate patterns or test programs")
+                    .arg(Arg::new("files").value_name("FILES").multiple(true))
+                    .arg(
+                        Arg::new("targets")
+                            .long("targets")
+                            .short('t')
+                            .takes_value(true),
+                    )
+                    .arg(Arg::new("mode").long("mode").takes_value(true)),
+            )
+            .subcommand(
+                clap::Command::new("app").subcommand(
+                    clap::Command::new("commands").subcommand(
+                        clap::Command::new("my_cmd")
+                            .about("An app command: with a colon")
+                            .arg(Arg::new("ext_opt.app.flag").long("flag")),
+                    ),
+                ),
+            )
+    }
+
+    #[test]
+    fn completes_the_assembled_command_tree() {
+        let c = Completions::new(
+            &app(),
+            vec!["eagle".to_string(), "sub/falcon".to_string()],
+            vec!["production".to_string()],
+        );
+        let paths = c
+            .cmds
+            .iter()
+            .map(|c| c.path.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(
+            paths,
+            vec![
+                "origen",
+                "origen__generate",
+                "origen__app",
+                "origen__app__commands",
+                "origen__app__commands__my_cmd"
+            ]
+        );
+
+        let bash = c.generate("bash").unwrap();
+        assert!(bash.contains("            'origen__generate'|'origen__g')\n                cmd_path=\"origen__generate\""));
+        assert!(bash.contains("                --targets|-t)\n                    COMPREPLY=($(compgen -W \"eagle sub/falcon\" -- \"${cur}\"))"));
+        assert!(bash.contains(
+            "                --mode)\n                    COMPREPLY=($(compgen -W \"production\""
+        ));
+        // Global options are inherited and the extension option is included
+        assert!(bash
+            .contains("COMPREPLY=($(compgen -W \"--verbose -v --flag --help -h\" -- \"${cur}\"))"));
+        assert!(bash.contains("complete -F _origen -o bashdefault -o default origen"));
+
+        let zsh = c.generate("zsh").unwrap();
+        assert!(zsh.contains("subcmds=('my_cmd:An app command\\: with a colon')"));
+        assert!(zsh.contains("compdef _origen origen"));
+
+        let fish = c.generate("fish").unwrap();
+        assert!(fish.contains("complete -c origen -n '__fish_origen_using_path origen' -a 'g' -d 'Generate patterns or test programs'"));
+        assert!(fish.contains("complete -c origen -n '__fish_origen_using_path origen__generate' -l 'targets' -s 't' -r -a 'eagle sub/falcon'"));
+        assert!(
+            fish.contains("complete -c origen -n '__fish_origen_using_path origen__generate' -F")
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use super::_prelude::*;
+use origen::core::config::provenance::{self, Provenance};
+use std::path::PathBuf;
+
+pub const BASE_CMD: &'static str = "config";
+
+gen_core_cmd_funcs__no_exts__no_app_opts!(
+    BASE_CMD,
+    "Inspect and validate the Origen configuration",
+    { |cmd: App<'a>| { cmd.visible_alias("cfg").arg_required_else_help(true) }},
+    core_subcmd__no_exts__no_app_opts!("show", "Show the effective config values and where each one comes from", { |cmd: App| {
+        cmd.arg(
+            Arg::new("files")
+                .long("files")
+                .help("Only list the config files that are in use, highest precedence first")
+                .action(SetArgTrue),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("where", "Show every place that a config key is set, highest precedence first", { |cmd: App| {
+        cmd.arg(
+            Arg::new("key")
+                .help("The config key, e.g. 'python_cmd' or 'mailer.server'. Giving a table shows all keys within it.")
+                .action(SetArg)
+                .value_name("KEY")
+                .required(true),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("validate", "Check config files for unknown keys and values of the wrong type", { |cmd: App| {
+        cmd.arg(
+            Arg::new("files")
+                .help("The config files to check, defaults to all of the config files that are in use")
+                .action(AppendArgs)
+                .value_name("FILES")
+                .multiple(true),
+        )
+    }})
+);
+
+fn show(invocation: &clap::ArgMatches) -> Result<()> {
+    let p = Provenance::collect()?;
+    if p.layers.is_empty() {
+        displayln!("No config files or 'ORIGEN_' environment variables are in use, all config values are defaulted");
+        return Ok(());
+    }
+    if *invocation.get_one::<bool>("files").unwrap() {
+        for layer in &p.layers {
+            displayln!("{}", layer.source);
+        }
+        return Ok(());
+    }
+    for (k, (v, layers)) in p.effective().iter() {
+        displayln!(
+            "{} = {}  # {}",
+            k,
+            provenance::inline(v),
+            layers
+                .iter()
+                .map(|l| l.location(k))
+                .collect::<Vec<String>>()
+                .join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn find(invocation: &clap::ArgMatches) -> Result<()> {
+    let key = invocation.get_one::<String>("key").unwrap();
+    let p = Provenance::collect()?;
+    let origins = p.origins(key);
+    if origins.is_empty() {
+        let top = key.split('.').next().unwrap();
+        if provenance::is_known_key(top) {
+            displayln!("'{}' is not set by any config file or environment variable, its default value is used", key);
+        } else {
+            displayln!("'{}' is not set and is not a known config key", key);
+        }
+        return Ok(());
+    }
+    let effective = p.effective();
+    for (layer, k, v) in origins {
+        let in_effect = match effective.get(k) {
+            Some((_, layers)) => layers.iter().any(|l| std::ptr::eq(*l, layer)),
+            None => false,
+        };
+        displayln!(
+            "{} {} = {}  # {}",
+            if in_effect { "*" } else { " " },
+            k,
+            provenance::inline(v),
+            layer.location(k)
+        );
+    }
+    displayln!("");
+    displayln!("* The value in effect, or part of it for keys which are merged across files");
+    Ok(())
+}
+
+fn validate(invocation: &clap::ArgMatches) -> Result<()> {
+    let files: Vec<PathBuf> = match invocation.get_many::<String>("files") {
+        Some(files) => files.map(PathBuf::from).collect(),
+        None => origen::origen_config_metadata().files.clone(),
+    };
+    if files.is_empty() {
+        displayln!("No config files are in use");
+        return Ok(());
+    }
+    let mut count = 0;
+    for f in &files {
+        let issues = provenance::validate(f);
+        if issues.is_empty() {
+            display_greenln!("PASS: {}", f.display());
+        } else {
+            display_redln!("FAIL: {}", f.display());
+            for issue in &issues {
+                displayln!("  {}", issue);
+            }
+            count += issues.len();
+        }
+    }
+    if count > 0 {
+        bail!("Found {} problem(s) in the config files", count);
+    }
+    Ok(())
+}
+
+pub(crate) fn run(invocation: &clap::ArgMatches) -> Result<()> {
+    match invocation.subcommand() {
+        Some(("show", subcmd)) => show(subcmd),
+        Some(("where", subcmd)) => find(subcmd),
+        Some(("validate", subcmd)) => validate(subcmd),
+        Some((n, _)) => unreachable_invalid_subc!(n),
+        None => Ok(()),
+    }
+}
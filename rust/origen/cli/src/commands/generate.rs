@@ -1,4 +1,12 @@
 use crate::commands::_prelude::*;
+use origen::core::application::target;
+use origen::core::file_handler::FileHandler;
+use origen::core::lsf;
+use origen::core::producer::dispatcher::{Backend, Dispatcher};
+use origen::core::producer::report::Report;
+use origen::{LOGGER, STATUS};
+use origen_metal::framework::reference_files;
+use std::process::exit;
 
 pub const BASE_CMD: &'static str = "generate";
 
@@ -17,6 +25,36 @@ gen_core_cmd_funcs!(
             )
             .arg(output_dir_opt!())
             .arg(ref_dir_opt!())
+            .arg(
+                Arg::new("jobs")
+                    .long("jobs")
+                    .short('j')
+                    .help("Generate the files in parallel, running up to this many local jobs at once")
+                    .action(SetArg)
+                    .value_name("JOBS")
+                    .conflicts_with("lsf"),
+            )
+            .arg(
+                Arg::new("lsf")
+                    .long("lsf")
+                    .help("Generate each file as a separate job submitted to the LSF")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("target_matrix")
+                    .long("target-matrix")
+                    .help("Generate the files for every target/mode combination defined by the given target preset (see [target_presets] in config/application.toml), with each combination's output going to its own sub-directory")
+                    .action(SetArg)
+                    .value_name("PRESET"),
+            )
+            .arg(
+                Arg::new("lsf_queue")
+                    .long("lsf-queue")
+                    .help("The LSF queue to submit the jobs to")
+                    .action(SetArg)
+                    .value_name("QUEUE")
+                    .requires("lsf"),
+            )
             // TODO re-add debug opt
             // .arg(
             //     Arg::new("debug")
@@ -28,4 +66,139 @@ gen_core_cmd_funcs!(
     }}
 );
 
-gen_simple_run_func!();
+pub(crate) fn run(invocation: &clap::ArgMatches, cmd_def: &clap::App, exts: &crate::Extensions, plugins: Option<&crate::Plugins>) -> Result<()> {
+    let lsf = invocation.contains_id("lsf") && *invocation.get_one::<bool>("lsf").unwrap();
+    let jobs = match invocation.get_one::<String>("jobs") {
+        Some(j) => match j.parse::<usize>() {
+            Ok(j) if j > 0 => j,
+            _ => bail!("The number of jobs must be a positive integer, got '{}'", j),
+        },
+        None => 1,
+    };
+    let matrix = invocation.get_one::<String>("target_matrix");
+    if matrix.is_none() && !lsf && jobs == 1 {
+        crate::commands::launch_from_invocation(
+            invocation,
+            cmd_def.find_subcommand(BASE_CMD).unwrap(),
+            exts.get_core_ext(BASE_CMD),
+            plugins,
+        );
+        return Ok(());
+    }
+
+    let backend = if lsf {
+        Backend::Lsf(lsf::Config {
+            queue: invocation.get_one::<String>("lsf_queue").map(|q| q.to_string()),
+            ..Default::default()
+        })
+    } else {
+        Backend::Local { workers: jobs }
+    };
+    let output_dir = match invocation.get_one::<String>("output_dir") {
+        Some(d) => std::path::PathBuf::from(d),
+        None => STATUS.output_dir(),
+    };
+    let mut dispatcher = Dispatcher::new(&std::env::current_exe()?, backend, &output_dir.join(".dispatch"));
+
+    // Arguments common to every job, each job then generates one of the given files
+    let mut common: Vec<String> = vec![];
+    for _ in 0..LOGGER.verbosity() {
+        common.push("-v".to_string());
+    }
+    let keywords = LOGGER.data().keywords.clone();
+    if !keywords.is_empty() {
+        common.push("--vk".to_string());
+        common.push(keywords.join(","));
+    }
+
+    let files = invocation.get_many::<String>("files").unwrap().map(|f| f.to_string()).collect();
+    let mut handler = FileHandler::new();
+    handler.init(files)?;
+    let files: Vec<String> = handler.map(|f| f.display().to_string()).collect();
+
+    // The job IDs of each target combination when running a target matrix
+    let mut combinations: Vec<(String, Vec<usize>)> = vec![];
+    if let Some(preset) = matrix {
+        if invocation.try_get_many::<String>("targets").ok().flatten().is_some()
+            || invocation.try_get_one::<bool>("no_targets").ok().flatten() == Some(&true)
+            || invocation.try_get_one::<String>("mode").ok().flatten().is_some()
+        {
+            bail!("The --target-matrix option can not be combined with the --targets, --no_targets or --mode options");
+        }
+        let ref_dir = match invocation.get_one::<String>("reference_dir") {
+            Some(d) => Some(std::path::PathBuf::from(d)),
+            None => STATUS.reference_dir(),
+        };
+        for c in target::preset_combinations(preset)? {
+            let id = c.id();
+            let mut args = vec!["--targets".to_string(), c.targets.join(",")];
+            if let Some(m) = &c.mode {
+                args.push("--mode".to_string());
+                args.push(m.to_string());
+            }
+            args.push("--output-dir".to_string());
+            args.push(output_dir.join(&id).display().to_string());
+            if let Some(d) = &ref_dir {
+                args.push("--ref-dir".to_string());
+                args.push(d.join(&id).display().to_string());
+            }
+            args.extend(common.iter().cloned());
+            let mut ids = vec![];
+            for file in &files {
+                let mut job_args = vec![BASE_CMD.to_string(), file.to_string()];
+                job_args.extend(args.iter().cloned());
+                ids.push(dispatcher.add_job(&format!("{} [{}]", file, id), job_args).id);
+            }
+            combinations.push((id, ids));
+        }
+    } else {
+        if let Some(targets) = invocation.try_get_many::<String>("targets").ok().flatten() {
+            common.push("--targets".to_string());
+            common.push(targets.map(|t| t.to_string()).collect::<Vec<String>>().join(","));
+        } else if invocation.try_get_one::<bool>("no_targets").ok().flatten() == Some(&true) {
+            common.push("--no_targets".to_string());
+        }
+        if let Some(m) = invocation.try_get_one::<String>("mode").ok().flatten() {
+            common.push("--mode".to_string());
+            common.push(m.to_string());
+        }
+        if let Some(d) = invocation.get_one::<String>("output_dir") {
+            common.push("--output-dir".to_string());
+            common.push(d.to_string());
+        }
+        if let Some(d) = invocation.get_one::<String>("reference_dir") {
+            common.push("--ref-dir".to_string());
+            common.push(d.to_string());
+        }
+        for file in &files {
+            let mut args = vec![BASE_CMD.to_string(), file.to_string()];
+            args.extend(common.iter().cloned());
+            dispatcher.add_job(file, args);
+        }
+    }
+    if dispatcher.jobs.is_empty() {
+        bail!("No files found to generate");
+    }
+
+    // Normally done by each generate run, but the jobs must all add to the same save_ref records
+    reference_files::clear_save_refs()?;
+    let summary = dispatcher.run()?;
+    summary.display();
+    if !combinations.is_empty() {
+        displayln!("Target combinations:");
+        for (id, ids) in &combinations {
+            let failed = summary.outcomes.iter().filter(|o| ids.contains(&o.job.id) && !o.passed).count();
+            let line = format!("  {}: {} passed, {} failed ({})", id, ids.len() - failed, failed, output_dir.join(id).display());
+            if failed == 0 {
+                display_greenln!("{}", line);
+            } else {
+                display_redln!("{}", line);
+            }
+        }
+    }
+    summary.report().write(&Report::default_dir(&output_dir))?;
+    if !summary.failed().is_empty() {
+        exit(1);
+    }
+    Ok(())
+}
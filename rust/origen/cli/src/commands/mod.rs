@@ -5,10 +5,14 @@ pub mod exec;
 pub mod interactive;
 // pub mod mode;
 pub mod new;
-// pub mod proj;
+pub mod proj;
+pub mod regs;
 pub mod save_ref;
 pub mod target;
+pub mod web;
 // pub mod mailer;
+pub mod completions;
+pub mod config;
 pub mod credentials;
 pub mod develop_origen;
 pub mod generate;
@@ -131,6 +135,7 @@ pub fn launch(base_cmd: Option<&str>, subcmds: Option<&Vec<String>>, invocation:
     }
 
     let mut targets = None;
+    let mut mode = None;
 
     for arg in cmd_def.get_arguments() {
         let arg_n= arg.get_id();
@@ -148,8 +153,8 @@ pub fn launch(base_cmd: Option<&str>, subcmds: Option<&Vec<String>>, invocation:
                 }
                 continue;
             } else if arg_n == "mode" {
-                // O1_MODE_SUPPORT
-                todo!("Mode argument is not currently supported!");
+                mode = Some(origen::clean_mode(invocation.get_one::<String>(arg_n).unwrap()));
+                continue;
             }
 
             let arg_str: String;
@@ -302,6 +307,9 @@ pub fn launch(base_cmd: Option<&str>, subcmds: Option<&Vec<String>>, invocation:
             cmd += &format!(", {}", strs_to_cli_arr!("targets", targs));
         }
     }
+    if let Some(m) = mode {
+        cmd += &format!(", mode=r'{}'", m);
+    }
     cmd += &format!(", verbosity={}", LOGGER.verbosity());
     cmd += &format!(", {}", strs_to_cli_arr!("verbosity_keywords", origen::LOGGER.data().keywords.iter()));
     cmd += ");";
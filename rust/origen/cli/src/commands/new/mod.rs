@@ -1,3 +1,5 @@
+pub mod new_resource;
+
 use crate::commands::_prelude::*;
 use origen_metal::tera::{Context, Tera};
 use std::process::exit;
@@ -64,6 +66,10 @@ pub fn current_user_to_author() -> Result<String> {
 }
 
 pub fn run(invocation: &clap::ArgMatches) -> origen::Result<()> {
+    // Within an application the generators for new application resources are used instead
+    if origen::STATUS.is_app_present {
+        return new_resource::run(invocation);
+    }
     if let Some((n, subcmd)) = invocation.subcommand() {
         let mut context = Context::new();
         let name = subcmd.get_one::<String>("name").unwrap();
@@ -78,13 +84,13 @@ pub fn run(invocation: &clap::ArgMatches) -> origen::Result<()> {
             }
         } else {
             out_dir = env::current_dir()?;
-            out_dir.push(&name);
+            out_dir.push(name);
         }
 
         //  Check output dir but hold off until other checks have passed
         if out_dir.exists() {
             // Check directory is empty
-            if !out_dir.read_dir()?.next().is_none() {
+            if out_dir.read_dir()?.next().is_some() {
                 log_error!("Target directory {} is not empty!", &out_dir.display());
                 exit(1);
             }
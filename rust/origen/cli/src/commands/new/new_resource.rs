@@ -1,203 +1,545 @@
-use super::PY_BLOCK;
-use clap::ArgMatches;
+// The in-application 'origen new' generators. Each generator plans the files that it will create
+// from the templates in commands/new/templates/py_generators, these can be overridden on a per-app
+// basis by placing a template of the same name in one of the app's 'generator_template_dirs'.
+use super::{BASE_CMD, PY_GENERATORS};
+use crate::commands::_prelude::*;
+use origen::core::application::Application;
 use origen::utility::file_actions as fa;
+use origen_metal::tera::{Context, Tera};
 use regex::Regex;
-use std::path::Path;
-use tera::{Context, Tera};
-
-pub fn run(matches: &ArgMatches) {
-    match matches.subcommand_name() {
-        Some("dut") => {
-            let name;
-
-            if let Some(n) = matches.subcommand_matches("dut").unwrap().get_one::<&str>("name") {
-                // Add the leading 'dut' to the fully qualified new DUT name if missing
-                if !n.starts_with("dut/") {
-                    name = format!("dut/{}", n);
-                } else {
-                    name = n.to_string();
-                }
-            } else {
-                name = "dut".to_string();
+use std::path::{Path, PathBuf};
+
+pub const LIST_CMD: &'static str = "list";
+pub const DUT_CMD: &'static str = "dut";
+pub const BLOCK_CMD: &'static str = "block";
+pub const FLOW_CMD: &'static str = "flow";
+pub const PATTERN_CMD: &'static str = "pattern";
+pub const TESTER_CMD: &'static str = "tester";
+pub const SERVICE_CMD: &'static str = "service";
+
+const DUT_HELP: &'static str = "Create a new top-level (DUT) block and a target to load it";
+const BLOCK_HELP: &'static str = "Create a new block, e.g. to represent RAM, ATD, Flash, DAC, etc.";
+const FLOW_HELP: &'static str = "Create a new test program flow";
+const PATTERN_HELP: &'static str = "Create a new pattern source file";
+const TESTER_HELP: &'static str = "Create a new custom tester and a target to load it";
+const SERVICE_HELP: &'static str = "Create a new protocol service";
+
+const BLOCK_TEMPLATES: &[&str] = &[
+    "block/attributes.py",
+    "block/controller.py",
+    "block/levels.py",
+    "block/pins.py",
+    "block/registers.py",
+    "block/services.py",
+    "block/sub_blocks.py",
+    "block/timing.py",
+];
+
+/// The block templates plus a target to load the new DUT
+const DUT_TEMPLATES: &[&str] = &{
+    let mut templates = [""; BLOCK_TEMPLATES.len() + 1];
+    let mut i = 0;
+    while i < BLOCK_TEMPLATES.len() {
+        templates[i] = BLOCK_TEMPLATES[i];
+        i += 1;
+    }
+    templates[i] = "dut/target.py";
+    templates
+};
+
+/// The available generators, their help and the templates that they render
+const GENERATORS: &[(&str, &str, &[&str])] = &[
+    (DUT_CMD, DUT_HELP, DUT_TEMPLATES),
+    (BLOCK_CMD, BLOCK_HELP, BLOCK_TEMPLATES),
+    (FLOW_CMD, FLOW_HELP, &["flow/flow.py"]),
+    (PATTERN_CMD, PATTERN_HELP, &["pattern/pattern.py"]),
+    (
+        TESTER_CMD,
+        TESTER_HELP,
+        &["tester/tester.py", "tester/target.py"],
+    ),
+    (SERVICE_CMD, SERVICE_HELP, &["service/service.py"]),
+];
+
+macro_rules! dry_run_opt {
+    () => {{
+        Arg::new("dry_run")
+            .long("dry-run")
+            .action(SetArgTrue)
+            .help("Show the files that would be created, without writing anything")
+    }};
+}
+
+gen_core_cmd_funcs__no_exts__no_app_opts!(
+    BASE_CMD,
+    "Generate a new DUT, block, flow, pattern, etc. for your application",
+    { |cmd: App<'a>| { cmd.arg_required_else_help(true) }},
+    core_subcmd__no_exts__no_app_opts!(LIST_CMD, "List the available generators and the templates that they use", { |cmd: App| {
+        cmd.visible_alias("ls")
+    }}),
+    core_subcmd__no_exts__no_app_opts!(DUT_CMD, DUT_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates a top-level (DUT) block and all of the associated resources for it, e.g. a
+reg file, controller, target, timesets, pins, etc.
+
+The NAME of the DUT should be given in lower case, optionally prefixed by parent DUT name(s) separated
+by a forward slash.
+
+Any parent DUT(s) will be created if they don't exist, but they will not be modified if they do.
+
+Examples:
+  origen new dut                # Creates <app_name>/blocks/dut/...
+  origen new dut falcon         # Creates <app_name>/blocks/dut/derivatives/falcon/...
+  origen new dut dsp/falcon     # Creates <app_name>/blocks/dut/derivatives/dsp/derivatives/falcon/...")
+            .arg(
+                Arg::new("name")
+                    .action(SetArg)
+                    .help("The name of the new DUT")
+                    .value_name("NAME"),
+            )
+            .arg(dry_run_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!(BLOCK_CMD, BLOCK_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates a block (e.g. to represent RAM, ATD, Flash, DAC, etc.) and all of the associated
+resources for it, e.g. a reg file, controller, timesets, etc.
+
+The NAME should be given in lower case (e.g. flash/flash2kb, adc/adc16), optionally with
+additional parent sub-block names after the initial type.
+
+Alternatively, a reference to an existing BLOCK can be added, in which case a nested block will be created
+within that block's 'blocks/' directory, rather than a primary top-level block.
+
+Any parent block(s) will be created if they don't exist, but they will not be modified if they do.
+
+Examples:
+  origen new block dac                  # Creates <app_name>/blocks/dac/...
+  origen new block adc/adc8bit          # Creates <app_name>/blocks/adc/derivatives/adc8bit/...
+  origen new block nvm/flash/flash2kb   # Creates <app_name>/blocks/nvm/derivatives/flash/derivatives/flash2kb/...
+  # Example of creating a nested sub-block
+  origen new block bist --parent nvm/flash   # Creates <app_name>/blocks/nvm/derivatives/flash/blocks/bist/...")
+            .arg(req_sv_arg!("name", "NAME", "The name of the new block, including its parents if applicable"))
+            .arg(sv_opt!("parent", "PARENT", "Create the new block nested within this existing block").short('p'))
+            .arg(dry_run_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!(FLOW_CMD, FLOW_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates a new test program flow within the application's flows directory.
+
+Examples:
+  origen new flow probe                 # Creates <app_name>/flows/probe.py
+  origen new flow production/wt1        # Creates <app_name>/flows/production/wt1.py")
+            .arg(req_sv_arg!("name", "NAME", "The name of the new flow, including any sub-directories"))
+            .arg(dry_run_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!(PATTERN_CMD, PATTERN_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates a new pattern source file within the application's patterns directory.
+
+Examples:
+  origen new pattern startup            # Creates <app_name>/patterns/startup.py
+  origen new pattern nvm/erase          # Creates <app_name>/patterns/nvm/erase.py")
+            .arg(req_sv_arg!("name", "NAME", "The name of the new pattern, including any sub-directories"))
+            .arg(dry_run_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!(TESTER_CMD, TESTER_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates a custom tester, implemented in Python by processing the pattern AST, and a
+target which registers and selects it.
+
+Examples:
+  origen new tester my_ate              # Creates <app_name>/testers/my_ate.py and targets/tester/my_ate.py")
+            .arg(req_sv_arg!("name", "NAME", "The name of the new tester"))
+            .arg(dry_run_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!(SERVICE_CMD, SERVICE_HELP, { |cmd: App| {
+        cmd.long_about(
+"This generator creates the skeleton of a protocol service which can then be instantiated within a
+block's services.py to handle its register transactions.
+
+Examples:
+  origen new service my_bus             # Creates <app_name>/services/my_bus.py")
+            .arg(req_sv_arg!("name", "NAME", "The name of the new service"))
+            .arg(dry_run_opt!())
+    }})
+);
+
+pub(crate) fn run(invocation: &clap::ArgMatches) -> Result<()> {
+    let app = origen::app().unwrap();
+    match invocation.subcommand() {
+        Some((LIST_CMD, _)) => list(app),
+        Some((n, subcmd)) => {
+            let mut gen = Generation::new(app)?;
+            match n {
+                DUT_CMD => gen.dut(subcmd.get_one::<String>("name"))?,
+                BLOCK_CMD => gen.block(
+                    subcmd.get_one::<String>("name").unwrap(),
+                    subcmd.get_one::<String>("parent"),
+                )?,
+                FLOW_CMD => gen.source_file(FLOW_CMD, "flows", subcmd.get_one::<String>("name").unwrap())?,
+                PATTERN_CMD => gen.source_file(PATTERN_CMD, "patterns", subcmd.get_one::<String>("name").unwrap())?,
+                TESTER_CMD => gen.tester(subcmd.get_one::<String>("name").unwrap())?,
+                SERVICE_CMD => gen.service(subcmd.get_one::<String>("name").unwrap())?,
+                _ => unreachable_invalid_subc!(n),
             }
+            gen.apply(*subcmd.get_one::<bool>("dry_run").unwrap())
+        }
+        None => Ok(()),
+    }
+}
 
-            let name = clean_and_validate_resource_name(&name, "NAME");
+fn list(app: &Application) -> Result<()> {
+    let dirs = template_dirs(app);
+    for (name, help, templates) in GENERATORS {
+        displayln!("{:<10}{}", name, help);
+        for t in templates.iter() {
+            match template_override(&dirs, t) {
+                Some(f) => displayln!("    {}  (overridden by {})", t, relative(app, &f).display()),
+                None => displayln!("    {}", t),
+            }
+        }
+    }
+    displayln!("");
+    if dirs.is_empty() {
+        displayln!("Templates can be overridden by adding a file of the same name to a directory listed by the app's 'generator_template_dirs' config");
+    } else {
+        displayln!("Templates are overridden by files of the same name in:");
+        for d in &dirs {
+            displayln!("    {}", relative(app, d).display());
+        }
+    }
+    displayln!("Run 'origen new <GENERATOR> --dry-run' to preview the files that a generator would create");
+    Ok(())
+}
 
-            let mut top = true;
-            let mut path = origen::app().unwrap().app_dir().join("blocks");
+enum Action {
+    /// Render the given template to a new file, an existing file will not be modified
+    Create { path: PathBuf, template: String, context: Context },
+    /// Append the given text to the end of a file
+    Append { path: PathBuf, text: String },
+}
 
-            for n in name.split("/") {
-                if !top {
-                    path = path.join("derivatives");
+/// Collects the actions required by a generator so that they can be either previewed or applied
+struct Generation<'a> {
+    app: &'a Application,
+    tera: Tera,
+    actions: Vec<Action>,
+    /// Messages to be shown to the user once the files have been created
+    notes: Vec<String>,
+}
+
+impl<'a> Generation<'a> {
+    fn new(app: &'a Application) -> Result<Self> {
+        let dirs = template_dirs(app);
+        let mut tera = Tera::default();
+        for (name, contents) in PY_GENERATORS.entries() {
+            match template_override(&dirs, name) {
+                Some(f) => {
+                    log_debug!("Using template '{}' from '{}'", name, f.display());
+                    tera.add_template_file(&f, Some(name))?;
                 }
-                path = path.join(n);
+                None => tera.add_raw_template(name, contents)?,
+            }
+        }
+        Ok(Self {
+            app,
+            tera,
+            actions: vec![],
+            notes: vec![],
+        })
+    }
+
+    fn create(&mut self, path: PathBuf, template: &str, context: &Context) {
+        self.actions.push(Action::Create {
+            path,
+            template: template.to_string(),
+            context: context.clone(),
+        });
+    }
 
-                generate_dut(&path, top);
-                top = false;
+    fn is_planned(&self, path: &Path) -> bool {
+        self.actions.iter().any(|a| match a {
+            Action::Create { path: p, .. } => p == path,
+            _ => false,
+        })
+    }
+
+    fn dut(&mut self, name: Option<&String>) -> Result<()> {
+        // Add the leading 'dut' to the fully qualified new DUT name if missing
+        let name = match name {
+            Some(n) if n.starts_with("dut/") => n.to_string(),
+            Some(n) => format!("dut/{}", n),
+            None => "dut".to_string(),
+        };
+        let name = clean_block_name(self.app, &name, "NAME")?;
+
+        let mut top = true;
+        let mut path = self.app.app_dir().join("blocks");
+        let names = name.split("/").collect::<Vec<&str>>();
+        for (i, n) in names.iter().enumerate() {
+            if !top {
+                path = path.join("derivatives");
+            }
+            path = path.join(n);
+            if i == names.len() - 1 && path.exists() {
+                bail!("The DUT '{}' already exists", relative(self.app, &path).display());
             }
+            let mut context = Context::new();
+            context.insert("top", &top);
+            context.insert("dut", &true);
+            self.block_files(&path, &context, true);
+            top = false;
+        }
 
-            // Create a target file for the new DUT
-            let last_name = format!("{}.py", name.split("/").last().unwrap());
-            let target_file = origen::app()
-                .unwrap()
-                .root
-                .join("targets")
-                .join("dut")
-                .join(last_name);
-            let content = format!("origen.app.instantiate_dut(\"{}\")", name.replace("/", "."));
-            if !target_file.exists() {
-                display_green!("      create  ");
-                displayln!(
-                    "{}",
-                    target_file
-                        .strip_prefix(&origen::app().unwrap().root)
-                        .unwrap()
-                        .display()
-                );
-                if !target_file.parent().unwrap().exists() {
-                    let _ = std::fs::create_dir_all(target_file.parent().unwrap());
-                }
-                std::fs::write(&target_file, &content)
-                    .expect(&format!("Couldn't create '{}'", &target_file.display()));
+        // Create a target file for the new DUT
+        let target_file = self
+            .app
+            .root
+            .join("targets")
+            .join("dut")
+            .join(format!("{}.py", name.split("/").last().unwrap()));
+        let mut context = Context::new();
+        context.insert("name", &name.replace("/", "."));
+        self.create(target_file, "dut/target.py", &context);
+        Ok(())
+    }
+
+    fn block(&mut self, name: &str, parent: Option<&String>) -> Result<()> {
+        let mut nested = false;
+        let mut block_name = clean_block_name(self.app, name, "NAME")?;
+        if let Some(p) = parent {
+            nested = true;
+            if name.contains("/") {
+                bail!("The NAME '{}' is invalid, when specifying a PARENT argument the NAME cannot also contain a leading parent name(s)", name);
             }
+            let par = clean_block_name(self.app, p, "PARENT")?;
+            block_name = format!("{}/{}", &par, &block_name);
         }
-        Some("block") => {
-            let matches = matches.subcommand_matches("block").unwrap();
-            let name = matches.get_one::<String>("name").unwrap();
-            let mut nested = false;
-            let parent = matches.get_one::<&str>("parent");
-            let mut block_name = clean_and_validate_resource_name(&name, "NAME");
-
-            if let Some(p) = parent {
-                nested = true;
-                if name.contains("/") {
-                    display_red!("ERROR: ");
-                    displayln!("The NAME '{}' is invalid, when specifying a PARENT argument the NAME cannot also contain a leading parent name(s)",  name);
-                    std::process::exit(1);
+
+        let mut top = true;
+        let mut path = self.app.app_dir().join("blocks");
+        let names = block_name.split("/").collect::<Vec<&str>>();
+        let mut sub_blocks_py = path.clone();
+
+        for (i, n) in names.iter().enumerate() {
+            let last = i == names.len() - 1;
+            // Make sure the parent block has a sub_blocks.py since we will append to it later
+            if nested && last {
+                sub_blocks_py = path.join("sub_blocks.py");
+            }
+            if !top {
+                if nested && last {
+                    path = path.join("blocks");
+                } else {
+                    path = path.join("derivatives");
                 }
-                let par = clean_and_validate_resource_name(p, "PARENT");
-                block_name = format!("{}/{}", &par, &block_name);
             }
+            path = path.join(n);
+            if last && path.exists() {
+                bail!("The block '{}' already exists", relative(self.app, &path).display());
+            }
+            let mut context = Context::new();
+            context.insert("top", &top);
+            context.insert("nested", &(nested && last));
+            // Assume levels and timing won't be required for most cases when a block is nested within
+            // another, though the user could add them manually and they would work
+            self.block_files(&path, &context, !(nested && last));
+            top = false;
+        }
+
+        // If a nested block then automatically instantiate it within its parent
+        if nested {
+            if !sub_blocks_py.exists() && !self.is_planned(&sub_blocks_py) {
+                self.create(sub_blocks_py.clone(), "block/sub_blocks.py", &Context::new());
+            }
+            self.actions.push(Action::Append {
+                path: sub_blocks_py,
+                text: format!("SubBlock(\"{}\", \"{}\")", name, name),
+            });
+
+        // Otherwise (for now?) just print a message telling the user how to add it to a block's
+        // sub_blocks.py file
+        } else {
+            self.notes.push("Block created, instantiate it within an existing DUT or block within this app by adding the following code to its sub_blocks.py file:".to_string());
+            self.notes.push("".to_string());
+            self.notes.push(format!(
+                "  SubBlock(\"my_instance_name\", \"{}\")",
+                &block_name.replace("/", ".")
+            ));
+            self.notes.push("".to_string());
+            self.notes.push(
+                "To instantiate it within a block defined by another application, do this:"
+                    .to_string(),
+            );
+            self.notes.push("".to_string());
+            self.notes.push(format!(
+                "  SubBlock(\"my_instance_name\", \"{}.{}\")",
+                self.app.name(),
+                &block_name.replace("/", ".")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Plans the files for a new DUT or block in the given dir, nothing will be done if the dir
+    /// already exists, i.e. when it is an existing parent of the new block
+    fn block_files(&mut self, dir: &Path, context: &Context, with_levels_and_timing: bool) {
+        if dir.exists() {
+            return;
+        }
+        for t in BLOCK_TEMPLATES {
+            if !with_levels_and_timing && (*t == "block/levels.py" || *t == "block/timing.py") {
+                continue;
+            }
+            self.create(dir.join(Path::new(t).file_name().unwrap()), t, context);
+        }
+    }
+
+    /// Creates a single flow or pattern source file within the given app sub-dir
+    fn source_file(&mut self, kind: &str, dir: &str, name: &str) -> Result<()> {
+        let name = clean_name(name.trim_end_matches(".py"), "NAME")?;
+        let path = self.app.app_dir().join(dir).join(format!("{}.py", name));
+        if path.exists() {
+            bail!("The {} '{}' already exists", kind, relative(self.app, &path).display());
+        }
+        let mut context = Context::new();
+        context.insert("name", &name);
+        context.insert("file", &relative(self.app, &path).display().to_string());
+        self.create(path, &format!("{}/{}.py", kind, kind), &context);
+        Ok(())
+    }
 
-            let mut top = true;
-            let mut path = origen::app().unwrap().app_dir().join("blocks");
-            let mut i = 0;
-            let names = block_name.split("/").collect::<Vec<&str>>();
-            let mut sub_blocks_py = path.clone();
+    fn tester(&mut self, name: &str) -> Result<()> {
+        let name = clean_name(name, "NAME")?;
+        let path = self.app.app_dir().join("testers").join(format!("{}.py", name));
+        if path.exists() {
+            bail!("The tester '{}' already exists", relative(self.app, &path).display());
+        }
+        let mut context = Context::new();
+        context.insert("name", &name);
+        context.insert("class_name", &class_name(&name));
+        context.insert(
+            "module",
+            &format!("{}.testers.{}", self.app.name(), name.replace("/", ".")),
+        );
+        self.create(path, "tester/tester.py", &context);
+        let last = name.split("/").last().unwrap();
+        let target = self.app.root.join("targets").join("tester").join(format!("{}.py", last));
+        self.create(target, "tester/target.py", &context);
+        self.notes.push(format!(
+            "Tester created, select it by running 'origen target add tester/{}'",
+            last
+        ));
+        Ok(())
+    }
 
-            for n in &names {
-                // Make sure the parent block has a sub_blocks.py since we will append to it later
-                if nested && i == names.len() - 1 {
-                    sub_blocks_py = path.join("sub_blocks.py");
+    fn service(&mut self, name: &str) -> Result<()> {
+        let name = clean_name(name, "NAME")?;
+        let path = self.app.app_dir().join("services").join(format!("{}.py", name));
+        if path.exists() {
+            bail!("The service '{}' already exists", relative(self.app, &path).display());
+        }
+        let last = name.split("/").last().unwrap().to_string();
+        let mut context = Context::new();
+        context.insert("name", &last);
+        context.insert("class_name", &class_name(&name));
+        self.create(path, "service/service.py", &context);
+        self.notes.push("Service created, instantiate it within a DUT or block by adding the following code to its services.py file:".to_string());
+        self.notes.push("".to_string());
+        self.notes.push(format!(
+            "  from {}.services.{} import {}",
+            self.app.name(),
+            name.replace("/", "."),
+            class_name(&name)
+        ));
+        self.notes.push(format!("  Service(\"{}\", {}())", last, class_name(&name)));
+        Ok(())
+    }
+
+    /// Renders all planned files, writing them out unless this is a dry run
+    fn apply(&self, dry_run: bool) -> Result<()> {
+        for action in &self.actions {
+            match action {
+                Action::Create {
+                    path,
+                    template,
+                    context,
+                } => {
+                    let contents = self.tera.render(template, context)?;
+                    if path.exists() {
+                        display_yellow!("      exists  ");
+                        displayln!("{}", relative(self.app, path).display());
+                        continue;
+                    }
+                    display_green!("      create  ");
+                    displayln!("{}", relative(self.app, path).display());
+                    if !dry_run {
+                        std::fs::create_dir_all(path.parent().unwrap())?;
+                        std::fs::write(path, &contents)?;
+                    }
                 }
-                if !top {
-                    if nested && i == names.len() - 1 {
-                        path = path.join("blocks");
-                    } else {
-                        path = path.join("derivatives");
+                Action::Append { path, text } => {
+                    display_green!("      append  ");
+                    displayln!("{}", relative(self.app, path).display());
+                    if !dry_run {
+                        fa::append(path, text)?;
                     }
                 }
-                path = path.join(n);
-
-                generate_block(&path, top, nested && i == names.len() - 1);
-                top = false;
-                i += 1;
             }
-            // If a nested block then automatically instantiate it within its parent
-            if nested {
-                if !sub_blocks_py.exists() {
-                    let context = Context::new();
-                    write_block_file(sub_blocks_py.parent().unwrap(), &context, "sub_blocks.py");
-                }
-                let _ = fa::append(
-                    &sub_blocks_py,
-                    &format!("SubBlock(\"{}\", \"{}\")", name, name),
-                );
-
-            // Otherwise (for now?) just print a message telling the user how to add it to a block's
-            // sub_blocks.py file
-            } else {
-                displayln!("");
-                displayln!("Block created, instantiate it within an existing DUT or block within this app by adding the following code to its sub_blocks.py file:");
-                displayln!("");
-                displayln!(
-                    "  SubBlock(\"my_instance_name\", \"{}\")",
-                    &block_name.replace("/", ".")
-                );
-                displayln!("");
-                displayln!(
-                    "To instantiate it within a block defined by another application, do this:"
-                );
-                displayln!("");
-                displayln!(
-                    "  SubBlock(\"my_instance_name\", \"{}.{}\")",
-                    origen::app().unwrap().name(),
-                    &block_name.replace("/", ".")
-                );
-                displayln!("");
+        }
+        if dry_run {
+            displayln!("");
+            displayln!("This was a dry run, no files have been written");
+        } else if !self.notes.is_empty() {
+            displayln!("");
+            for n in &self.notes {
+                displayln!("{}", n);
             }
+            displayln!("");
         }
-        None => unreachable!(),
-        _ => unreachable!(),
+        Ok(())
     }
 }
 
-fn generate_dut(dir: &Path, top: bool) {
-    let mut context = Context::new();
-
-    context.insert("top", &top);
-    context.insert("dut", &true);
-
-    if !dir.exists() {
-        std::fs::create_dir_all(dir).expect(&format!("Couldn't create '{}'", dir.display()));
-        write_block_file(dir, &context, "attributes.py");
-        write_block_file(dir, &context, "controller.py");
-        write_block_file(dir, &context, "levels.py");
-        write_block_file(dir, &context, "pins.py");
-        write_block_file(dir, &context, "registers.py");
-        write_block_file(dir, &context, "services.py");
-        write_block_file(dir, &context, "sub_blocks.py");
-        write_block_file(dir, &context, "timing.py");
-    } else {
-        // Need to do anything here, should we check and build a controller if missing?
+/// Returns the app's generator template dirs, highest priority first
+fn template_dirs(app: &Application) -> Vec<PathBuf> {
+    match &app.config().generator_template_dirs {
+        Some(dirs) => dirs.iter().map(|d| app.root.join(d)).collect(),
+        None => vec![],
     }
 }
 
-fn generate_block(dir: &Path, top: bool, nested: bool) {
-    let mut context = Context::new();
-
-    context.insert("top", &top);
-    context.insert("nested", &nested);
-
-    if !dir.exists() {
-        std::fs::create_dir_all(dir).expect(&format!("Couldn't create '{}'", dir.display()));
-        write_block_file(dir, &context, "attributes.py");
-        write_block_file(dir, &context, "controller.py");
-        write_block_file(dir, &context, "registers.py");
-        write_block_file(dir, &context, "sub_blocks.py");
-        write_block_file(dir, &context, "services.py");
-        // Assume these won't be required for most cases when a block is nested within another, though
-        // the user could add them manually and they would work
-        if !nested {
-            write_block_file(dir, &context, "levels.py");
-            write_block_file(dir, &context, "timing.py");
+/// Returns the first file in the given dirs which overrides the given built-in template, the
+/// template can be given with or without a '.tera' extension
+fn template_override(dirs: &[PathBuf], template: &str) -> Option<PathBuf> {
+    for d in dirs {
+        for f in [d.join(format!("{}.tera", template)), d.join(template)] {
+            if f.is_file() {
+                return Some(f);
+            }
         }
-    } else {
-        // Need to do anything here, should we check and build a controller if missing?
     }
+    None
 }
 
-fn write_block_file(dir: &Path, context: &Context, file_name: &str) {
-    let full_path = dir.join(file_name);
-    let local_path = full_path
-        .strip_prefix(&origen::app().unwrap().root)
-        .unwrap();
-    display_green!("      create  ");
-    displayln!("{}", local_path.display());
-    let mut tera = Tera::default();
-    let contents = tera.render_str(&PY_BLOCK[file_name], &context).unwrap();
-    std::fs::write(&full_path, &contents)
-        .expect(&format!("Couldn't create '{}'", &full_path.display()));
+fn relative<'p>(app: &Application, path: &'p Path) -> &'p Path {
+    path.strip_prefix(&app.root).unwrap_or(path)
+}
+
+/// Converts the last part of the given resource name to a Python class name, e.g. my_ate -> MyAte
+fn class_name(name: &str) -> String {
+    name.split("/")
+        .last()
+        .unwrap()
+        .split("_")
+        .map(|w| {
+            let mut c = w.chars();
+            match c.next() {
+                Some(f) => f.to_uppercase().chain(c).collect::<String>(),
+                None => "".to_string(),
+            }
+        })
+        .collect()
 }
 
 /// Validate that the given name meets the following criteria:
@@ -206,31 +548,154 @@ fn write_block_file(dir: &Path, context: &Context, file_name: &str) {
 ///   * starts with a letter
 ///   * doesn't contain any special characters
 ///
-/// If not an error message will be output to the console and the command will terminate
-///
-/// The following mods will be make to the returned value:
-///   * any '\' will be replace with '/'
-///   * 'derivatives/' or 'blocks' in the name will be removed
-///   * if it leads with the top-level app name or 'blocks' then it will be removed
-fn clean_and_validate_resource_name(name: &str, resource_id: &str) -> String {
+/// The following mods will be made to the returned value:
+///   * any '\' will be replaced with '/'
+///   * any trailing '/' will be removed
+fn clean_name(name: &str, resource_id: &str) -> Result<String> {
     let contains_special_chars = Regex::new(r"[^0-9a-z_]").unwrap();
     let starts_with_number = Regex::new(r"^[0-9]").unwrap();
 
-    let mut name = name.replace(r#"\"#, "/");
-    while name.ends_with("/") || name.ends_with("\\") {
-        name.truncate(name.len() - 1);
-    }
-    let mut names: Vec<&str> = vec![];
-
+    let name = name.replace(r#"\"#, "/");
+    let name = name.trim_end_matches("/");
     for n in name.split('/') {
-        if contains_special_chars.is_match(n) || starts_with_number.is_match(n) {
-            display_red!("ERROR: ");
-            displayln!("The {} '{}' is invalid, all resource names must be lowercased, underscored, start with a letter and contain no special characters", resource_id, name);
-            std::process::exit(1);
+        if n.is_empty() || contains_special_chars.is_match(n) || starts_with_number.is_match(n) {
+            bail!("The {} '{}' is invalid, all resource names must be lowercased, underscored, start with a letter and contain no special characters", resource_id, name);
         }
-        if n != origen::app().unwrap().name() && n != "blocks" && n != "derivatives" {
-            names.push(n);
+    }
+    Ok(name.to_string())
+}
+
+/// Like clean_name, but additionally:
+///   * 'derivatives/' or 'blocks' in the name will be removed
+///   * if it leads with the top-level app name then it will be removed
+fn clean_block_name(app: &Application, name: &str, resource_id: &str) -> Result<String> {
+    let name = clean_name(name, resource_id)?;
+    let app_name = app.name();
+    Ok(name
+        .split('/')
+        .filter(|n| *n != app_name && *n != "blocks" && *n != "derivatives")
+        .collect::<Vec<&str>>()
+        .join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn planned(g: &Generation) -> Vec<String> {
+        g.actions
+            .iter()
+            .map(|a| match a {
+                Action::Create { path, .. } => relative(g.app, path).display().to_string(),
+                Action::Append { path, text } => {
+                    format!("{} << {}", relative(g.app, path).display(), text)
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resource_names_are_validated() {
+        assert_eq!(
+            clean_name("production\\wt1/", "NAME").unwrap(),
+            "production/wt1"
+        );
+        assert!(clean_name("Production/wt1", "NAME").is_err());
+        assert!(clean_name("production/1wt", "NAME").is_err());
+        assert!(clean_name("production//wt1", "NAME").is_err());
+        assert_eq!(class_name("ate/my_ate2"), "MyAte2");
+    }
+
+    #[test]
+    fn templates_can_be_overridden() {
+        let temp = tempdir().unwrap();
+        let d1 = temp.path().join("d1");
+        let d2 = temp.path().join("d2");
+        let dirs = vec![d1.clone(), d2.clone()];
+        assert_eq!(template_override(&dirs, "flow/flow.py"), None);
+        write(&d2.join("flow/flow.py"), "");
+        assert_eq!(
+            template_override(&dirs, "flow/flow.py"),
+            Some(d2.join("flow/flow.py"))
+        );
+        // A .tera file takes precedence over a plain one in the same dir
+        write(&d2.join("flow/flow.py.tera"), "");
+        assert_eq!(
+            template_override(&dirs, "flow/flow.py"),
+            Some(d2.join("flow/flow.py.tera"))
+        );
+        // While the dirs are searched in order
+        write(&d1.join("flow/flow.py"), "");
+        assert_eq!(
+            template_override(&dirs, "flow/flow.py"),
+            Some(d1.join("flow/flow.py"))
+        );
+    }
+
+    #[test]
+    fn generators_plan_files_without_writing_them_on_a_dry_run() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        write(
+            &root.join("config/application.toml"),
+            "name = \"example\"\ngenerator_template_dirs = [\"tpl\"]\n",
+        );
+        write(
+            &root.join("tpl/tester/target.py.tera"),
+            "# Custom target for {{ class_name }}\n",
+        );
+        let app = Application::new(root.to_path_buf());
+
+        let mut g = Generation::new(&app).unwrap();
+        g.dut(Some(&"falcon".to_string())).unwrap();
+        g.block("bist", Some(&"dut/falcon".to_string())).unwrap();
+        g.tester("my_ate").unwrap();
+        let files = planned(&g);
+        for f in [
+            "example/blocks/dut/registers.py",
+            "example/blocks/dut/derivatives/falcon/timing.py",
+            "targets/dut/falcon.py",
+            "example/blocks/dut/derivatives/falcon/blocks/bist/registers.py",
+            "example/blocks/dut/derivatives/falcon/sub_blocks.py << SubBlock(\"bist\", \"bist\")",
+            "example/testers/my_ate.py",
+            "targets/tester/my_ate.py",
+        ] {
+            assert!(files.contains(&f.to_string()), "{} was not planned", f);
         }
+        // Nested blocks don't get levels or timing
+        assert!(!files.iter().any(|f| f.contains("bist/timing.py")));
+
+        g.apply(true).unwrap();
+        assert!(!root.join("example").exists());
+        assert!(!root.join("targets").exists());
+
+        g.apply(false).unwrap();
+        assert!(root
+            .join("example/blocks/dut/derivatives/falcon/blocks/bist/controller.py")
+            .is_file());
+        assert_eq!(
+            fs::read_to_string(root.join("targets/tester/my_ate.py")).unwrap(),
+            "# Custom target for MyAte\n"
+        );
+        // The existing tester is not overwritten
+        assert!(Generation::new(&app).unwrap().tester("my_ate").is_err());
+
+        // Nor are existing blocks, and nothing is appended to their parent's sub_blocks.py
+        let mut g = Generation::new(&app).unwrap();
+        assert!(g.dut(Some(&"falcon".to_string())).is_err());
+        assert!(g.block("bist", Some(&"dut/falcon".to_string())).is_err());
+        assert!(g.actions.is_empty() && g.notes.is_empty());
+        // While new derivatives can still be added to an existing DUT
+        g.dut(Some(&"falcon/eagle".to_string())).unwrap();
+        assert!(planned(&g).contains(
+            &"example/blocks/dut/derivatives/falcon/derivatives/eagle/pins.py".to_string()
+        ));
     }
-    names.join("/")
 }
@@ -0,0 +1,120 @@
+use super::bom::BOM;
+use origen::Result;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static HEADER: &str = "# This file is generated by 'origen proj create/update' to record the resolved revision of every\n# package in the workspace, do not modify it by hand\n\n";
+
+/// Records the state of a single package within a workspace
+#[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
+pub struct LockedPackage {
+    pub id: String,
+    pub path: Option<PathBuf>,
+    /// The version requested by the BOM, e.g. a branch, tag or DesignSync selector
+    pub version: Option<String>,
+    /// The revision control system that the package was fetched from, e.g. "Git" or "DesignSync"
+    pub system: Option<String>,
+    /// The revision that was actually checked out, the commit SHA for Git or the workspace
+    /// selector for DesignSync
+    pub revision: Option<String>,
+    pub copy: Option<PathBuf>,
+    pub link: Option<PathBuf>,
+}
+
+impl fmt::Display for LockedPackage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(x) = &self.link {
+            write!(f, "link to '{}'", x.display())
+        } else if let Some(x) = &self.copy {
+            write!(f, "copy of '{}'", x.display())
+        } else {
+            let revision = self.revision.as_deref().unwrap_or("unknown");
+            match &self.version {
+                Some(v) if v != revision => write!(f, "{} ({})", revision, v),
+                _ => write!(f, "{}", revision),
+            }
+        }
+    }
+}
+
+impl LockedPackage {
+    /// Returns true if the package resolves to the same content as the given one, the requested
+    /// version is ignored as a branch may have moved to the same revision as another reference
+    fn same_as(&self, other: &LockedPackage) -> bool {
+        self.path == other.path
+            && self.revision == other.revision
+            && self.copy == other.copy
+            && self.link == other.link
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Change<'a> {
+    Added(&'a LockedPackage),
+    Removed(&'a LockedPackage),
+    Moved(&'a LockedPackage, &'a LockedPackage),
+    Unchanged(&'a LockedPackage),
+}
+
+/// A workspace lockfile, this records the resolved revision of every package after the
+/// workspace has been created or updated so that it can be reproduced exactly later
+#[derive(Debug, Deserialize, Serialize, Default)]
+pub struct Lock {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+impl Lock {
+    /// Resolves the current state of all packages in the given workspace
+    pub fn for_workspace(bom: &BOM) -> Result<Lock> {
+        let mut lock = Lock::default();
+        for (_id, package) in &bom.packages {
+            lock.packages.push(package.lock(bom.root())?);
+        }
+        Ok(lock)
+    }
+
+    pub fn load(file: &Path) -> Result<Lock> {
+        let content = match fs::read_to_string(file) {
+            Ok(x) => x,
+            Err(e) => bail!(
+                "There was a problem reading lockfile '{}':\n{}",
+                file.display(),
+                e
+            ),
+        };
+        match toml::from_str(&content) {
+            Ok(x) => Ok(x),
+            Err(e) => bail!("Malformed lockfile '{}':\n{}", file.display(), e),
+        }
+    }
+
+    pub fn write(&self, file: &Path) -> Result<()> {
+        fs::write(file, format!("{}{}", HEADER, toml::to_string(self)?))?;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|p| p.id == id)
+    }
+
+    /// Compares this lock (the old state) to the given one (the new state), returning the
+    /// change to every package in either
+    pub fn diff<'a>(&'a self, other: &'a Lock) -> Vec<Change<'a>> {
+        let mut changes = vec![];
+        for p in &self.packages {
+            match other.get(&p.id) {
+                None => changes.push(Change::Removed(p)),
+                Some(o) if p.same_as(o) => changes.push(Change::Unchanged(p)),
+                Some(o) => changes.push(Change::Moved(p, o)),
+            }
+        }
+        for o in &other.packages {
+            if self.get(&o.id).is_none() {
+                changes.push(Change::Added(o));
+            }
+        }
+        changes
+    }
+}
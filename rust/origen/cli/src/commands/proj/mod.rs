@@ -1,25 +1,141 @@
 mod bom;
 mod group;
+mod lock;
 mod package;
 #[cfg(test)]
 mod tests;
 
+use crate::commands::_prelude::*;
 use bom::BOM;
 use clap::ArgMatches;
 use group::Group;
+use lock::{Change, Lock};
 use origen::core::file_handler::File;
 use origen::core::term;
+use origen_metal::tera::{Context, Tera};
 use origen_metal::utils::revision_control::RevisionControlAPI;
 use package::Package;
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::{env, fs};
-use tera::{Context, Tera};
+
+pub const BASE_CMD: &'static str = "proj";
 
 static BOM_FILE: &str = "bom.toml";
+static LOCK_FILE: &str = "bom.lock";
 static README_FILE: &str = "README.md";
 
-pub fn run(matches: &ArgMatches) {
+macro_rules! dir_arg {
+    () => {{
+        Arg::new("dir")
+            .action(SetArg)
+            .help("The path to a directory (PWD will be used by default if not given)")
+            .value_name("DIR")
+    }};
+}
+
+gen_core_cmd_funcs__no_exts__no_app_opts!(
+    BASE_CMD,
+    "Manage multi-repository project areas and workspaces",
+    { |cmd: App<'a>| { cmd.arg_required_else_help(true) }},
+    core_subcmd__no_exts__no_app_opts!("init", "Initialize a new project directory (create an initial project BOM)", { |cmd: App| {
+        cmd.arg(
+            Arg::new("dir")
+                .action(SetArg)
+                .help("The path to the project directory to initialize (PWD will be used by default if not given)")
+                .value_name("DIR"),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("packages", "Displays the IDs of all packages and package groups defined by the BOM", { |cmd: App| {
+        cmd.arg(dir_arg!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!("create", "Create a new project workspace from the project BOM", { |cmd: App| {
+        cmd.arg(req_sv_arg!("path", "PATH", "The path to the new workspace directory"))
+            .arg(
+                Arg::new("locked")
+                    .long("locked")
+                    .action(SetArgTrue)
+                    .help("Reproduce the exact package revisions recorded in a lockfile, by default the 'bom.lock' next to the project BOM"),
+            )
+            .arg(
+                sv_opt!("lockfile", "LOCKFILE", "The lockfile to reproduce, implies --locked")
+            )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("update", "Update an existing project workspace per its current BOM", { |cmd: App| {
+        cmd.arg(
+            Arg::new("force")
+                .short('f')
+                .long("force")
+                .action(SetArgTrue)
+                .help("Force the update and potentially lose any local modifications"),
+        )
+        .arg(
+            Arg::new("links")
+                .short('l')
+                .long("links")
+                .action(SetArgTrue)
+                .help("Update the workspace links"),
+        )
+        .arg(
+            Arg::new("packages")
+                .value_name("PACKAGES")
+                .action(AppendArgs)
+                .multiple(true)
+                .help("Packages and/or groups to be updated, run 'origen proj packages' to see a list of possible package IDs")
+                .required_unless_present("links"),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("mods", "Display a list of modified files within the given package(s)", { |cmd: App| {
+        cmd.arg(
+            Arg::new("packages")
+                .help("Package(s) to look for modifications in, use 'all' to see the modification to all packages")
+                .action(AppendArgs)
+                .multiple(true)
+                .value_name("PACKAGES")
+                .required(true),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("clean", "Revert all local modifications within the given package(s)", { |cmd: App| {
+        cmd.arg(
+            Arg::new("packages")
+                .help("Package(s) to revert local modifications in, use 'all' to clean all packages")
+                .action(AppendArgs)
+                .multiple(true)
+                .value_name("PACKAGES")
+                .required(true),
+        )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("tag", "Apply the given tag to the current view of the given package(s)", { |cmd: App| {
+        cmd.arg(req_sv_arg!("name", "NAME", "Name of the tag to be applied"))
+            .arg(
+                Arg::new("packages")
+                    .help("Package(s) to be tagged, use 'all' to tag all packages")
+                    .multiple(true)
+                    .action(AppendArgs)
+                    .value_name("PACKAGES")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("force")
+                    .short('f')
+                    .long("force")
+                    .action(SetArgTrue)
+                    .help("Force the application of the tag even if there are local modifications"),
+            )
+            .arg(
+                sv_opt!("message", "MESSAGE", "A message to be applied with the tag").short('m')
+            )
+    }}),
+    core_subcmd__no_exts__no_app_opts!("bom", "View the active BOM in the current or given directory", { |cmd: App| {
+        cmd.arg(dir_arg!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!("diff", "Compare two workspace lockfiles to show which packages moved between them", { |cmd: App| {
+        cmd.arg(req_sv_arg!("from", "FROM", "The lockfile of the earlier workspace state"))
+            .arg(req_sv_arg!("to", "TO", "The lockfile of the later workspace state"))
+    }})
+);
+
+pub(crate) fn run(matches: &ArgMatches) -> Result<()> {
     match matches.subcommand_name() {
         Some("init") => {
             let dir = get_dir_or_pwd(matches.subcommand_matches("init").unwrap(), false);
@@ -45,12 +161,9 @@ pub fn run(matches: &ArgMatches) {
             }
         }
         Some("create") => {
+            let matches = matches.subcommand_matches("create").unwrap();
             // First convert the new workspace location to an absolute path
-            let path = matches
-                .subcommand_matches("create")
-                .unwrap()
-                .get_one::<&str>("path")
-                .unwrap();
+            let path = matches.get_one::<String>("path").unwrap();
             let mut path = PathBuf::from(path);
             if !path.is_absolute() {
                 path = pwd().join(path);
@@ -110,6 +223,50 @@ pub fn run(matches: &ArgMatches) {
                     Some(1),
                 );
             }
+            // Converting this to a vector here as the template was printing out the package list
+            // in reverse order when given the index map
+            let mut packages: Vec<Package> = bom.packages.iter().map(|(_id, pkg)| pkg.clone()).collect();
+            // When reproducing a lockfile, pin every revision controlled package to its recorded revision
+            let lockfile = match matches.get_one::<String>("lockfile") {
+                Some(f) => Some(PathBuf::from(f)),
+                None if *matches.get_one::<bool>("locked").unwrap() => {
+                    Some(bom.files.last().unwrap().parent().unwrap().join(LOCK_FILE))
+                }
+                None => None,
+            };
+            if let Some(f) = &lockfile {
+                let lock = match Lock::load(f) {
+                    Ok(x) => x,
+                    Err(e) => {
+                        error_and_exit(&e.to_string(), Some(1));
+                        unreachable!()
+                    }
+                };
+                for package in packages.iter_mut() {
+                    if package.has_repo() {
+                        match lock.get(&package.id).and_then(|p| p.revision.as_ref()) {
+                            Some(revision) => package.version = Some(revision.clone()),
+                            None => error_and_exit(
+                                &format!(
+                                    "Lockfile '{}' has no revision recorded for package '{}'",
+                                    f.display(),
+                                    package.id
+                                ),
+                                Some(1),
+                            ),
+                        }
+                    }
+                }
+                for p in &lock.packages {
+                    if !bom.packages.contains_key(&p.id) {
+                        log_warning!(
+                            "Lockfile '{}' refers to package '{}' but no package with that ID is defined, ignoring it",
+                            f.display(),
+                            p.id
+                        );
+                    }
+                }
+            }
             fs::create_dir_all(&path).expect(&format!(
                 "Couldn't create '{}', do you have the required permissions?",
                 path.display()
@@ -117,10 +274,8 @@ pub fn run(matches: &ArgMatches) {
             // Write out a BOM file for the new workspace
             let mut tera = Tera::default();
             let mut context = Context::new();
-            // Converting this to a vector here as the template was printing out the package list
-            // in reverse order when given the index map
-            let packages: Vec<&Package> = bom.packages.iter().map(|(_id, pkg)| pkg).collect();
             context.insert("packages", &packages);
+            context.insert("locked", &lockfile.is_some());
             let groups: Vec<&Group> = bom.groups.iter().map(|(_id, grp)| grp).collect();
             context.insert("groups", &groups);
             let contents = tera
@@ -128,15 +283,15 @@ pub fn run(matches: &ArgMatches) {
                 .unwrap();
             File::create(path.join(BOM_FILE)).write(&contents);
             // Now populate the packages
-            log_info!("Fetching {} packages", bom.packages.len());
+            log_info!("Fetching {} packages", packages.len());
             let mut errors = false;
-            for (id, package) in &bom.packages {
-                display!("Populating '{}' ... ", id);
+            for package in &packages {
+                display!("Populating '{}' ... ", package.id);
                 match package.create(&path) {
                     Ok(()) => display_greenln!("OK"),
                     Err(e) => {
                         log_error!("{}", e);
-                        log_error!("Failed to create package '{}'", id);
+                        log_error!("Failed to create package '{}'", package.id);
                         errors = true;
                     }
                 }
@@ -154,6 +309,9 @@ pub fn run(matches: &ArgMatches) {
                     }
                 }
             }
+            if !errors && !write_lock(&BOM::for_dir(&path)) {
+                errors = true;
+            }
             if errors {
                 exit_error!();
             } else {
@@ -162,10 +320,10 @@ pub fn run(matches: &ArgMatches) {
         }
         Some("update") => {
             let matches = matches.subcommand_matches("update").unwrap();
-            let force = matches.contains_id("force");
-            let mut links = matches.contains_id("links");
-            if let Some(packages) = matches.get_many::<&str>("packages") {
-                if packages.map(|p| *p).collect::<Vec<&str>>().contains(&"all") {
+            let force = *matches.get_one::<bool>("force").unwrap();
+            let mut links = *matches.get_one::<bool>("links").unwrap();
+            if let Some(mut packages) = matches.get_many::<String>("packages") {
+                if packages.any(|p| p == "all") {
                     links = true;
                 }
             }
@@ -225,6 +383,9 @@ pub fn run(matches: &ArgMatches) {
                     }
                 }
             }
+            if !errors && !write_lock(&bom) {
+                errors = true;
+            }
             if errors {
                 exit_error!();
             } else {
@@ -370,9 +531,9 @@ pub fn run(matches: &ArgMatches) {
         }
         Some("tag") => {
             let matches = matches.subcommand_matches("tag").unwrap();
-            let force = matches.contains_id("force");
-            let tagname = matches.get_one::<&str>("name").unwrap();
-            let message = matches.get_one::<&str>("message");
+            let force = *matches.get_one::<bool>("force").unwrap();
+            let tagname = matches.get_one::<String>("name").unwrap();
+            let message = matches.get_one::<String>("message");
             let package_ids = get_package_ids_from_args(matches, true);
             let mut packages_with_existing_tag: Vec<&str> = vec![];
             let bom = BOM::for_dir(&pwd());
@@ -384,7 +545,7 @@ pub fn run(matches: &ArgMatches) {
                     if package.has_repo() {
                         display!("{} ... ", package.id);
                         let rc = package.rc(bom.root()).unwrap();
-                        match rc.tag(tagname, force, message.map(|s| *s)) {
+                        match rc.tag(tagname, force, message.map(|s| s.as_str())) {
                             Err(e) => {
                                 if e.to_string().contains("tag already exists") {
                                     packages_with_existing_tag.push(&package.id);
@@ -421,8 +582,59 @@ pub fn run(matches: &ArgMatches) {
                 exit(1);
             }
         }
+        Some("diff") => {
+            let matches = matches.subcommand_matches("diff").unwrap();
+            let from = Lock::load(Path::new(matches.get_one::<String>("from").unwrap()))?;
+            let to = Lock::load(Path::new(matches.get_one::<String>("to").unwrap()))?;
+            let (mut moved, mut added, mut removed, mut unchanged) = (0, 0, 0, 0);
+            for change in from.diff(&to) {
+                match change {
+                    Change::Moved(old, new) => {
+                        displayln!("  {}: {} -> {}", old.id, old, new);
+                        moved += 1;
+                    }
+                    Change::Added(new) => {
+                        display_greenln!("+ {}: {}", new.id, new);
+                        added += 1;
+                    }
+                    Change::Removed(old) => {
+                        display_redln!("- {}: {}", old.id, old);
+                        removed += 1;
+                    }
+                    Change::Unchanged(_) => unchanged += 1,
+                }
+            }
+            if moved + added + removed > 0 {
+                displayln!("");
+            }
+            displayln!(
+                "{} moved, {} added, {} removed, {} unchanged",
+                moved,
+                added,
+                removed,
+                unchanged
+            );
+        }
+        Some(n) => unreachable_invalid_subc!(n),
         None => unreachable!(),
-        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Records the resolved revision of every package in the given workspace to its lockfile,
+/// returns false if there was a problem
+fn write_lock(bom: &BOM) -> bool {
+    display!("Writing lockfile ... ");
+    match Lock::for_workspace(bom).and_then(|lock| lock.write(&bom.root().join(LOCK_FILE))) {
+        Ok(()) => {
+            display_greenln!("OK");
+            true
+        }
+        Err(e) => {
+            log_error!("There was a problem writing the workspace's lockfile:");
+            log_error!("{}", e);
+            false
+        }
     }
 }
 
@@ -459,7 +671,7 @@ fn pwd() -> PathBuf {
 /// If validate is not true then the path returned may not be absolute and the caller
 /// is responsible for handling that (if required) after then have created it
 fn get_dir_or_pwd(matches: &ArgMatches, validate: bool) -> PathBuf {
-    let dir = match matches.get_one::<&str>("dir") {
+    let dir = match matches.get_one::<String>("dir") {
         Some(x) => PathBuf::from(x),
         None => pwd(),
     };
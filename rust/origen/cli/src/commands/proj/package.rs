@@ -1,4 +1,5 @@
 use super::error_and_exit;
+use super::lock::LockedPackage;
 use flate2::read::GzDecoder;
 use origen::utility::file_utils::{copy, copy_contents, mv};
 use origen::Result;
@@ -106,6 +107,33 @@ impl Package {
         Ok((force_required, conflicts))
     }
 
+    /// Returns a lockfile entry recording the current state of the package in the given workspace,
+    /// will return an error if the revision of a revision controlled package can't be resolved
+    pub fn lock(&self, workspace_dir: &Path) -> Result<LockedPackage> {
+        let mut system = None;
+        let mut revision = None;
+        if let Some(rc) = self.rc(workspace_dir) {
+            system = Some(rc.system().to_string());
+            match rc.revision() {
+                Ok(r) => revision = Some(r),
+                Err(e) => bail!(
+                    "Couldn't resolve the revision of package '{}':\n{}",
+                    self.id,
+                    e
+                ),
+            }
+        }
+        Ok(LockedPackage {
+            id: self.id.clone(),
+            path: self.path.clone(),
+            version: self.version.clone(),
+            system,
+            revision,
+            copy: self.copy.clone(),
+            link: self.link.clone(),
+        })
+    }
+
     /// Returns a revision control driver for the package, if applicable
     pub fn rc(&self, workspace_dir: &Path) -> Option<RevisionControl> {
         if self.has_repo() {
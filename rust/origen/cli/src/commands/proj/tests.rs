@@ -1,3 +1,4 @@
+use crate::commands::proj::lock::{Change, Lock};
 use crate::commands::proj::BOM;
 use std::fs::File;
 use std::io::Write;
@@ -66,3 +67,82 @@ version = "t2"
     // At workspace level a package version takes priority
     assert_eq!(bom.packages["pkg2"].version.as_ref().unwrap(), "t2");
 }
+
+#[test]
+fn lockfile_diff() {
+    let temp = tempdir().unwrap();
+    let old = temp.path().join("old.lock");
+    let new = temp.path().join("new.lock");
+
+    let mut f = File::create(&old).unwrap();
+    writeln!(
+        f,
+        r#"
+[[package]]
+id = "pkg1"
+version = "master"
+system = "Git"
+revision = "1111111111111111111111111111111111111111"
+
+[[package]]
+id = "pkg2"
+version = "v1.0"
+system = "Git"
+revision = "2222222222222222222222222222222222222222"
+
+[[package]]
+id = "pkg3"
+link = "/some/path"
+    "#
+    )
+    .expect("Couldn't write lockfile");
+
+    let mut f = File::create(&new).unwrap();
+    writeln!(
+        f,
+        r#"
+[[package]]
+id = "pkg1"
+version = "master"
+system = "Git"
+revision = "3333333333333333333333333333333333333333"
+
+[[package]]
+id = "pkg2"
+version = "2222222222222222222222222222222222222222"
+system = "Git"
+revision = "2222222222222222222222222222222222222222"
+
+[[package]]
+id = "pkg4"
+copy = "/some/other/path"
+    "#
+    )
+    .expect("Couldn't write lockfile");
+
+    // A lock should be unchanged by being written back out
+    let lock = Lock::load(&new).unwrap();
+    let rewritten = temp.path().join("rewritten.lock");
+    lock.write(&rewritten).unwrap();
+    assert_eq!(Lock::load(&rewritten).unwrap().packages, lock.packages);
+
+    let old = Lock::load(&old).unwrap();
+    let changes = old.diff(&lock);
+    assert_eq!(changes.len(), 4);
+    assert_eq!(
+        changes[0],
+        Change::Moved(&old.packages[0], &lock.packages[0])
+    );
+    // Only the resolved revision matters, not how it was requested
+    assert_eq!(changes[1], Change::Unchanged(&old.packages[1]));
+    assert_eq!(changes[2], Change::Removed(&old.packages[2]));
+    assert_eq!(changes[3], Change::Added(&lock.packages[2]));
+    assert_eq!(
+        format!("{}", old.packages[0]),
+        "1111111111111111111111111111111111111111 (master)"
+    );
+    assert_eq!(
+        format!("{}", lock.packages[1]),
+        "2222222222222222222222222222222222222222"
+    );
+}
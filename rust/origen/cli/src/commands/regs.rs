@@ -0,0 +1,21 @@
+use super::_prelude::*;
+
+pub const BASE_CMD: &'static str = "regs";
+
+gen_core_cmd_funcs!(
+    BASE_CMD,
+    "Work with the register model of the current target",
+    { |cmd: App<'a>| { cmd.setting(AppSettings::ArgRequiredElseHelp) }},
+    core_subcmd!("check", "Check the register model for consistency problems like overlapping registers or fields", { |cmd: App| {
+        cmd.arg(
+            Arg::new("strict")
+                .help("Treat warnings (e.g. duplicate register names) as errors")
+                .action(SetArgTrue)
+                .required(false)
+                .long("strict")
+                .short('s'),
+        )
+    }})
+);
+
+gen_simple_run_func!();
@@ -1,6 +1,14 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-use origen_metal::framework::reference_files;
+use origen::core::producer::report::{Report, JSON_REPORT};
+use origen::core::tester::{diff_tool, differ_for, FileKind};
+use origen::STATUS;
+use origen_metal::framework::reference_files::{
+    self, PendingRef, ReviewDecision, ReviewSession, SaveRefType,
+};
+use origen_metal::utils::terminal::select_with_user;
 use crate::commands::_prelude::*;
 
 pub const BASE_CMD: &'static str = "save_ref";
@@ -16,7 +24,7 @@ gen_core_cmd_funcs!(
                     .action(SetArg)
                     .value_name("FILES")
                     .multiple(true)
-                    .required_unless_one(&["new", "changed"]),
+                    .required_unless_one(["new", "changed", "review"]),
             )
             .arg(
                 Arg::new("new")
@@ -32,11 +40,30 @@ gen_core_cmd_funcs!(
                     .action(SetArgTrue)
                     .help("Update all CHANGED file references from the last generate run"),
             )
+            .arg(
+                Arg::new("review")
+                    .long("review")
+                    .required(false)
+                    .action(SetArgTrue)
+                    .conflicts_with_all(&["files", "new", "changed"])
+                    .help("Review the CHANGED files from the last generate run one at a time, resuming any previous review"),
+            )
+            .arg(
+                Arg::new("restart")
+                    .long("restart")
+                    .required(false)
+                    .action(SetArgTrue)
+                    .requires("review")
+                    .help("Discard the decisions made by a previous review and start again"),
+            )
     }}
 );
 
 
 pub fn run(matches: &clap::ArgMatches) -> Result<()> {
+    if *matches.get_one::<bool>("review").unwrap() {
+        return review(*matches.get_one::<bool>("restart").unwrap());
+    }
     let new = matches.contains_id("new");
     let changed = matches.contains_id("changed");
     let files = matches.get_many::<String>("files");
@@ -65,3 +92,196 @@ pub fn run(matches: &clap::ArgMatches) -> Result<()> {
     }
     Ok(())
 }
+
+/// Walks through each changed file, showing its diff and letting the user decide whether to
+/// accept it as the new reference. Decisions are recorded as they are made so that the review
+/// can be quit and resumed later.
+fn review(restart: bool) -> Result<()> {
+    if restart {
+        ReviewSession::clear()?;
+    }
+    let mut session = ReviewSession::load()?;
+    let refs: Vec<_> = reference_files::pending_refs()?
+        .into_iter()
+        .filter(|r| r.kind == SaveRefType::Changed)
+        .collect();
+    if refs.is_empty() {
+        displayln!("No changed files to review");
+        return Ok(());
+    }
+
+    // The last generate report tells which tester produced each file so that the same diff
+    // rules can be applied here
+    let report = Report::default_dir(&STATUS.output_dir()).join(JSON_REPORT);
+    let mut targets: HashMap<PathBuf, (String, FileKind)> = HashMap::new();
+    if report.exists() {
+        for f in Report::read(&report)?.files {
+            targets.insert(f.path, (f.target, f.kind));
+        }
+    }
+
+    review_refs(
+        &refs,
+        &mut session,
+        |i, r| {
+            displayln!("");
+            display_cyanln!("[{}/{}] {}", i + 1, refs.len(), r.key.display());
+            let (target, kind) = match targets.get(&r.source) {
+                Some((t, k)) => (Some(t.as_str()), *k),
+                None => (None, FileKind::Pattern),
+            };
+            let mut differ = differ_for(target, kind, &r.dest, &r.source);
+            if !differ.has_diffs()? {
+                display_greenln!(
+                    "No diffs remain, it may have been updated since it was generated"
+                );
+            }
+            match differ.unified_diff(3)? {
+                Some(diff) => {
+                    for line in diff.lines() {
+                        if line.starts_with('+') {
+                            display_greenln!("{}", line);
+                        } else if line.starts_with('-') {
+                            display_redln!("{}", line);
+                        } else {
+                            displayln!("{}", line);
+                        }
+                    }
+                }
+                None => displayln!("No inline diff is available for this file"),
+            }
+            Ok(())
+        },
+        || {
+            select_with_user(
+                "Save as the new reference?",
+                &["Accept", "Skip", "Open in diff tool", "Quit (resume later)"],
+                1,
+            )
+        },
+    )?;
+
+    let decided: Vec<_> = refs.iter().filter_map(|r| session.decision(&r.key)).collect();
+    let accepted = decided.iter().filter(|d| **d == ReviewDecision::Accepted).count();
+    displayln!("");
+    displayln!(
+        "Reviewed {} of {} changed files: {} accepted, {} skipped",
+        decided.len(),
+        refs.len(),
+        accepted,
+        decided.len() - accepted
+    );
+    if decided.len() < refs.len() {
+        displayln!("Run 'origen save_ref --review' again to resume the review");
+    }
+    Ok(())
+}
+
+/// Shows each of the given references which is yet to be decided on and applies the choice
+/// made for it: 0 to accept, 1 to skip, 2 to open the diff tool and choose again, anything else
+/// quits the review
+fn review_refs<S, C>(
+    refs: &[PendingRef],
+    session: &mut ReviewSession,
+    mut show: S,
+    mut choose: C,
+) -> Result<()>
+where
+    S: FnMut(usize, &PendingRef) -> Result<()>,
+    C: FnMut() -> Result<usize>,
+{
+    for (i, r) in refs.iter().enumerate() {
+        if session.decision(&r.key).is_some() {
+            continue;
+        }
+        show(i, r)?;
+        loop {
+            match choose()? {
+                0 => {
+                    reference_files::apply_ref(&r.key)?;
+                    session.record(&r.key, ReviewDecision::Accepted)?;
+                }
+                1 => session.record(&r.key, ReviewDecision::Skipped)?,
+                2 => {
+                    let tool = diff_tool();
+                    if let Err(e) = Command::new(&tool).arg(&r.dest).arg(&r.source).status() {
+                        log_error!("Failed to run '{}' - {}", tool, e);
+                    }
+                    continue;
+                }
+                _ => return Ok(()),
+            }
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reviews_pending_refs_and_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        reference_files::set_save_ref_dir(dir.path().join("save_refs"));
+        reference_files::clear_save_refs().unwrap();
+        let mut keys = vec![];
+        for name in ["a", "b", "c"] {
+            let new = dir.path().join("output").join(name);
+            let reference = dir.path().join("ref").join(name);
+            std::fs::create_dir_all(new.parent().unwrap()).unwrap();
+            std::fs::create_dir_all(reference.parent().unwrap()).unwrap();
+            std::fs::write(&new, format!("{} new", name)).unwrap();
+            std::fs::write(&reference, format!("{} old", name)).unwrap();
+            let key = PathBuf::from("pats").join(name);
+            reference_files::create_changed_ref(&key, &new, &reference).unwrap();
+            keys.push(key);
+        }
+        let refs = reference_files::pending_refs().unwrap();
+        assert_eq!(
+            refs.iter().map(|r| &r.key).collect::<Vec<_>>(),
+            keys.iter().collect::<Vec<_>>()
+        );
+        assert!(refs.iter().all(|r| r.kind == SaveRefType::Changed));
+        let read = |name: &str| std::fs::read_to_string(dir.path().join("ref").join(name)).unwrap();
+
+        // Accept the first, skip the second and quit at the third
+        let mut session = ReviewSession::load().unwrap();
+        let mut choices = vec![0, 1, 3].into_iter();
+        let mut shown = vec![];
+        review_refs(
+            &refs,
+            &mut session,
+            |i, _| {
+                shown.push(i);
+                Ok(())
+            },
+            || Ok(choices.next().unwrap()),
+        )
+        .unwrap();
+        assert_eq!(shown, vec![0, 1, 2]);
+        assert_eq!(read("a"), "a new");
+        assert_eq!(read("b"), "b old");
+        assert_eq!(read("c"), "c old");
+
+        // Resuming only shows the undecided file
+        let mut session = ReviewSession::load().unwrap();
+        assert_eq!(session.decision(&keys[0]), Some(ReviewDecision::Accepted));
+        assert_eq!(session.decision(&keys[1]), Some(ReviewDecision::Skipped));
+        assert_eq!(session.decision(&keys[2]), None);
+        let mut shown = vec![];
+        let show = |i, _: &PendingRef| {
+            shown.push(i);
+            Ok(())
+        };
+        review_refs(&refs, &mut session, show, || Ok(0)).unwrap();
+        assert_eq!(shown, vec![2]);
+        assert_eq!(read("c"), "c new");
+
+        // Clearing the save refs discards the review
+        reference_files::clear_save_refs().unwrap();
+        assert!(reference_files::pending_refs().unwrap().is_empty());
+        assert_eq!(ReviewSession::load().unwrap().decision(&keys[0]), None);
+    }
+}
@@ -63,9 +63,39 @@ gen_core_cmd_funcs__no_exts__no_app_opts!(
     core_subcmd__no_exts__no_app_opts!("view", "Views the currently activated target(s)", { |cmd: App| {
         cmd.visible_alias("v")
         .arg(full_paths_opt!())
+    }}),
+    core_subcmd__no_exts__no_app_opts!("presets", "Lists the target presets defined by the app and their target combinations", { |cmd: App| {
+        cmd.visible_alias("p")
+        .arg(
+            Arg::new("preset")
+                .help("Only show this preset")
+                .action(SetArg)
+                .value_name("PRESET")
+        )
     }})
 );
 
+fn view_presets(preset: Option<&String>) -> Result<()> {
+    let names = match preset {
+        Some(p) => vec![p.to_string()],
+        None => target::presets(),
+    };
+    if names.is_empty() {
+        println!("No target presets are defined, they can be added to config/application.toml under [target_presets]");
+    }
+    for name in names {
+        let combinations = target::preset_combinations(&name)?;
+        println!("{}:", name);
+        for c in combinations {
+            match &c.mode {
+                Some(m) => println!("    {} ({})", c.targets.join(", "), m),
+                None => println!("    {}", c.targets.join(", ")),
+            }
+        }
+    }
+    Ok(())
+}
+
 macro_rules! view {
     ($invocation:expr) => {
         view_targets(*$invocation.get_one::<bool>(FULL_PATHS_OPT).unwrap())
@@ -106,6 +136,9 @@ pub(crate) fn run(invocation: &clap::ArgMatches) -> origen::Result<()> {
                 view!(subcmd)?;
                 return Ok(());
             }
+            "presets" => {
+                return view_presets(subcmd.get_one::<String>("preset"));
+            }
             _ => unreachable_invalid_subc!(n)
         }
         // Show the effect after running the command
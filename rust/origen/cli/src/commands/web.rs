@@ -0,0 +1,155 @@
+use super::_prelude::*;
+use origen::core::application::website::BuildOptions;
+
+pub const BASE_CMD: &'static str = "web";
+
+macro_rules! view_opts {
+    ($cmd:expr) => {
+        $cmd.arg(
+            Arg::new("port")
+                .long("port")
+                .short('p')
+                .help("Serve the site on the given port, defaults to 8000 or any free port if that is taken")
+                .action(SetArg)
+                .value_name("PORT"),
+        )
+        .arg(
+            Arg::new("no-browser")
+                .long("no-browser")
+                .help("Don't launch a web browser, only serve the site")
+                .action(SetArgTrue),
+        )
+    };
+}
+
+gen_core_cmd_funcs__no_exts__no_app_opts!(
+    BASE_CMD,
+    "Create, Build, and View Web Documentation",
+    { |cmd: App<'a>| { cmd.visible_alias("w").arg_required_else_help(true) }},
+    core_subcmd__no_exts__no_app_opts!("build", "Builds the web documentation", { |cmd: App| {
+        let cmd = cmd
+            .visible_alias("b")
+            .visible_alias("compile") // If coming from O1
+            .visible_alias("html") // If coming from Sphinx and using quickstart's Makefile
+            .arg(
+                Arg::new("view")
+                    .long("view")
+                    .help("Serve the site and launch your web browser after the build")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("clean")
+                    .long("clean")
+                    .help("Clean up directories from previous builds and force a rebuild")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("release")
+                    .long("release")
+                    .short('r')
+                    .help("Release (deploy) the resulting web pages")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("archive")
+                    .long("archive")
+                    .short('a')
+                    .help("Archive the resulting web pages after building, under the given ID or the app's version if none is given")
+                    .action(SetArg)
+                    .value_name("ID")
+                    .min_values(0)
+                    .max_values(1),
+            )
+            .arg(
+                Arg::new("as-release")
+                    .long("as-release")
+                    .help("Build webpages with release checks")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("release-with-warnings")
+                    .long("release-with-warnings")
+                    .help("Release webpages even if warnings persists")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("no-api")
+                    .long("no-api")
+                    .help("Skip building the API and register pages")
+                    .action(SetArgTrue),
+            )
+            .arg(
+                Arg::new("sphinx-args")
+                    .long("sphinx-args")
+                    .help(
+                        "Additional arguments to pass to the 'sphinx-build' command
+  Argument will passed as a single string and appended to the build command
+  E.g.: 'origen web build --sphinx-args \"-q -D my_config_define=1\"'
+     -> 'sphinx-build <source_dir> <output_dir> -q -D my_config_define=1'",
+                    )
+                    .action(SetArg)
+                    .value_name("ARGS")
+                    .allow_hyphen_values(true),
+            );
+        view_opts!(cmd)
+    }}),
+    core_subcmd__no_exts__no_app_opts!("view", "Serves previously built documentation locally and launches your web browser to view it", { |cmd: App| {
+        view_opts!(cmd.visible_alias("v"))
+    }}),
+    core_subcmd__no_exts__no_app_opts!("clean", "Cleans the output directory and all cached files", { |cmd: App| {
+        cmd
+    }})
+);
+
+fn view(invocation: &clap::ArgMatches) -> Result<()> {
+    let port = match invocation.get_one::<String>("port") {
+        Some(p) => match p.parse::<u16>() {
+            Ok(p) => Some(p),
+            Err(_) => bail!("Invalid port '{}'", p),
+        },
+        None => None,
+    };
+    origen::app()
+        .unwrap()
+        .website()
+        .serve(port, !*invocation.get_one::<bool>("no-browser").unwrap())
+}
+
+pub(crate) fn run(invocation: &clap::ArgMatches) -> Result<()> {
+    let app = origen::app().unwrap();
+    let website = app.website();
+    match invocation.subcommand() {
+        Some(("build", subcmd)) => {
+            let flag = |n: &str| *subcmd.get_one::<bool>(n).unwrap();
+            if flag("clean") {
+                website.clean()?;
+            }
+            let release = flag("release");
+            website.build(&BuildOptions {
+                no_api: flag("no-api"),
+                as_release: release || flag("as-release"),
+                release_with_warnings: flag("release-with-warnings"),
+                sphinx_args: subcmd.get_one::<String>("sphinx-args").map(|a| a.to_string()),
+            })?;
+            if release || subcmd.contains_id("archive") {
+                let archive = if subcmd.contains_id("archive") {
+                    match subcmd.get_one::<String>("archive") {
+                        Some(id) => Some(id.to_string()),
+                        None => Some(app.version()?.to_string()),
+                    }
+                } else {
+                    None
+                };
+                website.release(archive.as_deref(), !release)?;
+            }
+            if flag("view") {
+                view(subcmd)?;
+            }
+            Ok(())
+        }
+        Some(("view", subcmd)) => view(subcmd),
+        Some(("clean", _)) => website.clean(),
+        Some((n, _)) => unreachable_invalid_subc!(n),
+        None => Ok(()),
+    }
+}
@@ -50,3 +50,49 @@ fn origen_bad_arg() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+// Publishing is handled entirely by the Rust CLI, so this needs no Python environment
+#[test]
+fn app_publish_dry_run_resolves_the_version() -> Result<(), Box<dyn std::error::Error>> {
+    let tmp = tempfile::tempdir()?;
+    let root = tmp.path();
+    std::fs::create_dir(root.join("config"))?;
+    std::fs::write(root.join("config").join("origen.toml"), "")?;
+    std::fs::write(
+        root.join("config").join("application.toml"),
+        format!(
+            "name = \"example\"\n\
+             revision_control = {{ system = \"git\", local = '{}', remote = \"https://dummy.origen.git\" }}\n\
+             publisher = {{ package_app = \"false\" }}\n",
+            root.display()
+        ),
+    )?;
+    let pyproject = "[tool.poetry]\nname = \"example\"\nversion = \"1.2.3\"\n";
+    std::fs::write(root.join("pyproject.toml"), pyproject)?;
+    for args in [
+        vec!["init", "-q"],
+        vec!["add", "-A"],
+        vec!["-c", "user.name=o", "-c", "user.email=o@o", "commit", "-qm", "init"],
+    ] {
+        assert!(Command::new("git").args(args).current_dir(root).status()?.success());
+    }
+
+    let output = Command::new(ogn_cmd())
+        .args(["app", "publish", "--dry-run", "--version", "minor"])
+        .args(["--no-release-title", "--release-note", "A note"])
+        .current_dir(root)
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    assert!(stdout.contains("Updating version from 1.2.3 to 1.3.0"));
+    // A dry run records the release in a separate history file and leaves the version alone
+    let history = std::fs::read_to_string(
+        root.join("config").join("history.generated.dry_run.toml"),
+    )?;
+    assert!(history.contains("[releases.\"1.3.0\"]"));
+    assert!(history.contains("A note"));
+    assert_eq!(std::fs::read_to_string(root.join("pyproject.toml"))?, pyproject);
+
+    Ok(())
+}
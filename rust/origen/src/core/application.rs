@@ -1,15 +1,23 @@
 pub mod config;
 pub mod target;
+pub mod website;
 
 use super::application::config::Config;
-use origen_metal::utils::version::Version;
+use super::application::website::{BuildOptions, Website};
+use crate::core::frontend::{Publisher, ReleaseScribe as FrontendReleaseScribe, UnitTester};
+use crate::utility::publisher::Poetry;
+use crate::utility::release_scribe::ReleaseScribe;
+use crate::utility::unit_tester::PyTest;
+use origen_metal::utils::version::{ReleaseType, Version};
 use crate::Result;
 use indexmap::IndexMap;
 use origen_metal::framework::reference_files;
 use origen_metal::utils::revision_control::RevisionControl;
+use origen_metal::utils::revision_control::RevisionControlAPI;
 use origen_metal::utils::revision_control::Status;
 use origen_metal::Outcome;
 use regex::Regex;
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
@@ -121,7 +129,17 @@ impl Application {
         })?)
     }
 
+    /// Returns true if a frontend is available to handle the app's RC, publisher, etc.
+    /// Otherwise, operations which can be handled purely from the app's config will use
+    /// their Rust implementations.
+    fn has_frontend(&self) -> bool {
+        crate::FRONTEND.read().unwrap().frontend().is_some()
+    }
+
     pub fn rc_init(&self) -> Result<Outcome> {
+        if !self.has_frontend() {
+            return self.rc()?.init();
+        }
         crate::with_frontend_app(|app| {
             let rc = app.get_rc()?;
             Ok(rc.init()?)
@@ -129,6 +147,9 @@ impl Application {
     }
 
     pub fn rc_status(&self) -> Result<Status> {
+        if !self.has_frontend() {
+            return self.rc()?.status(None);
+        }
         crate::with_frontend_app(|app| {
             let rc = app.get_rc()?;
             Ok(rc.status()?)
@@ -141,15 +162,66 @@ impl Application {
         msg: &str,
         dry_run: bool,
     ) -> Result<Outcome> {
+        if !self.has_frontend() {
+            return self.rc()?.checkin(pathspecs, msg, dry_run);
+        }
         crate::with_frontend_app(|app| {
             let rc = app.get_rc()?;
             Ok(rc.checkin(pathspecs.clone(), msg, dry_run)?)
         })
     }
 
-    pub fn build_package(&self) -> Result<Outcome> {
+    pub fn rc_tag(&self, tagname: &str, force: bool, msg: Option<&str>) -> Result<()> {
+        if !self.has_frontend() {
+            return self.rc()?.tag(tagname, force, msg);
+        }
         crate::with_frontend_app(|app| {
-            let publisher = app.get_publisher()?;
+            let rc = app.get_rc()?;
+            rc.tag(tagname, force, msg)
+        })
+    }
+
+    /// Runs the given function with the app's publisher, which is the frontend's if available,
+    /// otherwise the Rust implementation of the publisher given in the app's config
+    fn with_publisher<T, F>(&self, mut func: F) -> Result<T>
+    where
+        F: FnMut(&dyn Publisher) -> Result<T>,
+    {
+        if !self.has_frontend() {
+            let p = self.with_config(|c| Poetry::new(c.publisher.as_ref()))?;
+            return func(&p);
+        }
+        crate::with_frontend_app(|app| func(app.get_publisher()?))
+    }
+
+    /// As with_publisher, but for the app's unit tester
+    fn with_unit_tester<T, F>(&self, mut func: F) -> Result<T>
+    where
+        F: FnMut(&dyn UnitTester) -> Result<T>,
+    {
+        if !self.has_frontend() {
+            let ut = self.with_config(|c| PyTest::new(c.unit_tester.as_ref()))?;
+            return func(&ut);
+        }
+        crate::with_frontend_app(|app| func(app.get_unit_tester()?))
+    }
+
+    /// As with_publisher, but for the app's release scribe
+    fn with_release_scribe<T, F>(&self, mut func: F) -> Result<T>
+    where
+        F: FnMut(&dyn FrontendReleaseScribe) -> Result<T>,
+    {
+        if !self.has_frontend() {
+            let rs = self.with_config(|c| {
+                ReleaseScribe::new(c.release_scribe.as_ref().unwrap_or(&HashMap::new()))
+            })?;
+            return func(&rs);
+        }
+        crate::with_frontend_app(|app| func(app.get_release_scribe()?))
+    }
+
+    pub fn build_package(&self) -> Result<Outcome> {
+        self.with_publisher(|publisher| {
             log_info!("Building Package...");
             publisher.build_package()
         })
@@ -176,45 +248,53 @@ impl Application {
         release_note: Option<&str>,
         dry_run: bool,
     ) -> Result<Outcome> {
-        Ok(crate::with_frontend_app(|app| {
-            // TODO
-            // log_info!("Performing pre-publish checks...");
-            // app.check_production_status()?;
-
-            let v = Version::new_pep440(&self.version()?.to_string())?;
-            let new_v = match version.as_ref() {
-                Some(ver) => ver.clone(),
-                None => v.update_dialogue()?,
-            };
-
-            println!("Updating version from {} to {}", v, new_v);
+        log_info!("Performing pre-publish checks...");
+        let mut stat = ProductionStatus::default();
+        let s = self.rc_status()?;
+        stat.push_clean_work_space_check(!s.is_modified(), None);
+        if stat.failed() {
+            stat.summarize();
             if dry_run {
-                log_info!("(Dry run - not updating version file)");
+                log_warning!("(Dry run - continuing despite failed pre-publish checks)");
             } else {
-                self.set_version(&new_v)?;
+                bail!("Pre-publish checks failed, the app cannot be published");
             }
-            let mut files = vec![self.version_file()];
+        }
 
-            let rs = app.get_release_scribe()?;
-            files.append(&mut rs.publish(&new_v, release_title, release_note, dry_run)?);
+        let v = Version::new_pep440(&self.version()?.to_string())?;
+        let new_v = match version.as_ref() {
+            Some(ver) => ver.clone(),
+            None => v.update_dialogue()?,
+        };
 
-            if dry_run {
-                println!("(Dry run - not checking in any files. Would check in:");
-                for f in files.iter() {
-                    println!("\t{}", f.display());
-                }
-            } else {
-                let rc = app.get_rc()?;
-                rc.checkin(
-                    Some(files.iter().map(|f| f.as_path()).collect()),
-                    "Recorded new version in the version tracker",
-                    false,
-                )?;
-                rc.tag(&new_v.to_string(), false, None)?;
+        println!("Updating version from {} to {}", v, new_v);
+        if dry_run {
+            log_info!("(Dry run - not updating version file)");
+        } else {
+            self.set_version(&new_v)?;
+        }
+        let mut files = vec![self.version_file()];
+
+        files.append(&mut self.with_release_scribe(|rs| {
+            rs.publish(&new_v, release_title, release_note, dry_run)
+        })?);
+
+        if dry_run {
+            println!("(Dry run - not checking in any files. Would check in:");
+            for f in files.iter() {
+                println!("\t{}", f.display());
             }
+        } else {
+            self.rc_checkin(
+                Some(files.iter().map(|f| f.as_path()).collect()),
+                "Recorded new version in the version tracker",
+                false,
+            )?;
+            self.rc_tag(&new_v.to_string(), false, None)?;
+        }
 
-            if self.should_package_app()? {
-                let publisher = app.get_publisher()?;
+        if self.should_package_app()? {
+            self.with_publisher(|publisher| {
                 log_info!("Building Package...");
                 let package_result = publisher.build_package()?;
                 if package_result.succeeded() {
@@ -248,29 +328,57 @@ impl Application {
                         bail!("Failed to upload package!");
                     }
                 }
+                Ok(())
+            })?;
+        } else {
+            log_trace!("Bypassing building and uploading app");
+        }
+
+        // Mailer here is 'optional', in that we won't hold up the
+        // release because of it, but will throw out lots of red text
+        // if not explicitly marked as okay to skip
+        // if let Some(m) = app.mailer()? {
+        //     // let mailer = app.get_mailer_or_none()?;
+        //     // mailer.send("...")?;
+        // } else {
+        //     log_error!("No mailer available - no release email sent");
+        // }
+
+        if self.with_config(|c| Ok(c.website_release_location.is_some()))? {
+            log_info!("Building website...");
+            let website = self.website();
+            website.build(&BuildOptions {
+                as_release: true,
+                ..Default::default()
+            })?;
+            if dry_run {
+                log_info!("(Dry run - not releasing website)");
             } else {
-                log_trace!("Bypassing building and uploading app");
+                website.release(Some(&new_v.to_string()), false)?;
             }
+        } else {
+            log_trace!("No website release location given, bypassing website release");
+        }
 
-            // Mailer here is 'optional', in that we won't hold up the
-            // release because of it, but will throw out lots of red text
-            // if not explicitly marked as okay to skip
-            // if let Some(m) = app.mailer()? {
-            //     // let mailer = app.get_mailer_or_none()?;
-            //     // mailer.send("...")?;
-            // } else {
-            //     log_error!("No mailer available - no release email sent");
-            // }
-
-            // let website = app.get_website()?;
-            // website.publish()?;
-
-            //     Ok(())
-            // })?)
-            let mut r = Outcome::new_success();
-            r.set_msg("Successfully released application!");
-            Ok(r)
-        })?)
+        //     Ok(())
+        // })?)
+        let mut r = Outcome::new_success();
+        r.set_msg("Successfully released application!");
+        Ok(r)
+    }
+
+    /// Resolves the version to publish from a user-given value, which may either be an explicit
+    /// version (e.g. "1.2.3") or a release type to increment the current version by
+    /// (e.g. "minor"). If no value is given, the user will be asked to choose the next version.
+    pub fn next_version(&self, requested: Option<&str>) -> Result<Version> {
+        let v = Version::new_pep440(&self.version()?.to_string())?;
+        match requested {
+            Some(r) => match ReleaseType::try_from(r) {
+                Ok(rt) => Ok(v.increment(&rt)?),
+                Err(_) => Ok(Version::new_pep440(r)?),
+            },
+            None => Ok(v.update_dialogue()?),
+        }
     }
 
     pub fn run_publish_checks(&self, stop_at_first_fail: bool) -> Result<ProductionStatus> {
@@ -281,37 +389,34 @@ impl Application {
         log_info!("Checking production status...");
         let mut stat = ProductionStatus::default();
 
-        crate::with_frontend_app(|app| {
-            // log_info!("Running any application-defined checks: pre-Origen checks...");
-            // stat.push_checks(app.production_status_checks_pre(stop_at_first_fail)?);
+        // log_info!("Running any application-defined checks: pre-Origen checks...");
+        // stat.push_checks(app.production_status_checks_pre(stop_at_first_fail)?);
 
-            // Check for modified files
-            log_info!("Checking for modified files...");
-            let s = app.get_rc()?.status()?;
-            stat.push_clean_work_space_check(!s.is_modified(), None);
+        // Check for modified files
+        log_info!("Checking for modified files...");
+        let s = self.rc_status()?;
+        stat.push_clean_work_space_check(!s.is_modified(), None);
 
-            if stat.failed() && stop_at_first_fail {
-                return Ok(());
-            }
+        if stat.failed() && stop_at_first_fail {
+            return Ok(stat);
+        }
 
-            log_info!("Running unit tests...");
-            let s = app.get_unit_tester()?.run()?;
-            stat.push_unit_test_check(s.passed(), s.msg().to_owned());
+        log_info!("Running unit tests...");
+        let s = self.with_unit_tester(|ut| ut.run())?;
+        stat.push_unit_test_check(s.passed(), s.msg().to_owned());
 
-            // log_info!("Checking for local dependencies...");
-            // let s = app.list_local_dependencies()?;
-            // stat.push_local_deps_check(s.empty?(), )
+        // log_info!("Checking for local dependencies...");
+        // let s = app.list_local_dependencies()?;
+        // stat.push_local_deps_check(s.empty?(), )
 
-            // TODOs:
-            // log_info!("Checking for local dependencies...");
-            // log_info!("Checking for lint errors...");
-            // log_info!("Ensuring the package builds...")
-            // log_info!("Ensuring the website builds...")
-            // log_info!("Running any application-defined checks: post-Origen checks...");
-            // stat.push_checks(app.production_status_checks_post(stop_at_first_fail)?);
+        // TODOs:
+        // log_info!("Checking for local dependencies...");
+        // log_info!("Checking for lint errors...");
+        // log_info!("Ensuring the package builds...")
+        // log_info!("Ensuring the website builds...")
+        // log_info!("Running any application-defined checks: post-Origen checks...");
+        // stat.push_checks(app.production_status_checks_post(stop_at_first_fail)?);
 
-            Ok(())
-        })?;
         Ok(stat)
     }
 
@@ -355,6 +460,10 @@ impl Application {
         .unwrap()
     }
 
+    pub fn website(&self) -> Website {
+        Website::new(self)
+    }
+
     pub fn website_output_directory(&self) -> PathBuf {
         self.with_config(|config| {
             Ok(self.resolve_path(config.website_output_directory.as_ref(), "output/web"))
@@ -425,21 +534,25 @@ impl ProductionStatus {
         !self.passed
     }
 
+    pub fn summarize(&self) {
+        for (n, check) in self.checks.iter() {
+            if check.0 {
+                display_greenln!("{}... PASSED!", n);
+            } else {
+                if let Some(m) = check.2.as_ref() {
+                    display_redln!("{}... FAILED with message: {}", n, m);
+                } else {
+                    display_redln!("{}... FAILED", n);
+                }
+            }
+        }
+    }
+
     pub fn summarize_and_exit(&self) {
         if self.passed() {
             exit_pass!();
         } else {
-            for (n, check) in self.checks.iter() {
-                if check.0 {
-                    display_greenln!("{}... PASSED!", n);
-                } else {
-                    if let Some(m) = check.2.as_ref() {
-                        display_redln!("{}... FAILED with message: {}", n, m);
-                    } else {
-                        display_redln!("{}... FAILED", n);
-                    }
-                }
-            }
+            self.summarize();
             exit_fail!();
         }
     }
@@ -62,6 +62,7 @@ impl CurrentState {
 pub struct Config {
     pub name: String,
     pub target: Option<Vec<String>>,
+    pub target_presets: Option<HashMap<String, target::TargetPreset>>,
     pub mode: String,
     /// Don't use this unless you know what you're doing, use origen::STATUS::output_dir() instead, since
     /// that accounts for the output directory being overridden by the current command
@@ -81,6 +82,9 @@ pub struct Config {
     pub release_scribe: Option<HashMap<String, String>>,
     pub app_session_root: Option<String>,
     pub commands: Option<Vec<String>>,
+    /// Directories (relative to the app root) containing templates which override the built-in
+    /// ones used by the 'origen new' generators, searched in order
+    pub generator_template_dirs: Option<Vec<String>>,
 }
 
 impl Config {
@@ -88,6 +92,7 @@ impl Config {
         let latest = Self::build(self.root.as_ref().unwrap(), false);
         self.name = latest.name;
         self.target = latest.target;
+        self.target_presets = latest.target_presets;
         self.mode = latest.mode;
         self.reference_directory = latest.reference_directory;
         self.website_output_directory = latest.website_output_directory;
@@ -101,6 +106,7 @@ impl Config {
         self.release_scribe = latest.release_scribe;
         self.app_session_root = latest.app_session_root;
         self.commands = latest.commands;
+        self.generator_template_dirs = latest.generator_template_dirs;
     }
 
     /// Builds a new config from all application.toml files found at the given app root
@@ -109,6 +115,8 @@ impl Config {
         let mut s = config::Config::builder()
             .set_default("target", None::<Vec<String>>)
             .unwrap()
+            .set_default("target_presets", None::<HashMap<String, String>>)
+            .unwrap()
             .set_default("mode", "development".to_string())
             .unwrap()
             .set_default("revision_control", None::<HashMap<String, String>>)
@@ -124,6 +132,8 @@ impl Config {
             .set_default("app_session_root", None::<String>)
             .unwrap()
             .set_default("commands", None::<Vec<String>>)
+            .unwrap()
+            .set_default("generator_template_dirs", None::<Vec<String>>)
             .unwrap();
 
             let mut files: Vec<PathBuf> = Vec::new();
@@ -175,7 +185,7 @@ impl Config {
             log_trace!("Loading Origen config file from '{}'", f.display());
             s = s.add_source(File::with_name(&format!("{}", f.display())));
         }
-        s = s.add_source(Environment::with_prefix("origen_app").list_separator(",").with_list_parse_key("target").with_list_parse_key("commands").try_parsing(true));
+        s = s.add_source(Environment::with_prefix("origen_app").list_separator(",").with_list_parse_key("target").with_list_parse_key("commands").with_list_parse_key("generator_template_dirs").try_parsing(true));
 
         let cb = exit_on_bad_config!(s.build());
         let mut c: Self = exit_on_bad_config!(cb.try_deserialize());
@@ -7,8 +7,22 @@
 //! The target can be further overridden for a particular origen command invocation via
 //! the -t and -e options, or programmatically within the application code, however that is all
 //! handled on the front end in Python code.
+//!
+//! Named target presets can also be defined in config/application.toml, these can define a
+//! matrix of DUT, tester and mode combinations which 'origen generate --target-matrix' will
+//! run through, e.g.
+//!
+//! ```toml
+//! [target_presets.regression]
+//! duts = ["dut/eagle", "dut/falcon"]
+//! testers = ["tester/v93k_smt7", "tester/j750"]
+//! modes = ["production", "development"]
+//! # Any additional targets to be loaded for every combination
+//! targets = ["eagle_with_simulator"]
+//! ```
 use crate::app;
-use std::path::PathBuf;
+use crate::Result;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 // Can be used to turn a relative path in an absolute
 //use path_clean::{PathClean};
@@ -31,6 +45,150 @@ macro_rules! toml {
     }}
 }
 
+/// A named set of targets defined by the app's config/application.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TargetPreset {
+    /// Targets to be loaded in every combination
+    pub targets: Option<Vec<String>>,
+    pub duts: Option<Vec<String>>,
+    pub testers: Option<Vec<String>>,
+    pub modes: Option<Vec<String>>,
+}
+
+/// One combination of the targets (and optionally mode) defined by a target preset
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetCombination {
+    pub targets: Vec<String>,
+    pub mode: Option<String>,
+}
+
+impl TargetCombination {
+    /// Returns a name for the combination which is suitable for use as a directory name, made up
+    /// from the target file names and mode, e.g. "eagle-v93k_smt7-production"
+    pub fn id(&self) -> String {
+        let mut parts: Vec<String> = self
+            .targets
+            .iter()
+            .map(|t| {
+                Path::new(t)
+                    .file_stem()
+                    .map_or(t.to_string(), |s| s.to_string_lossy().to_string())
+            })
+            .collect();
+        if let Some(m) = &self.mode {
+            parts.push(m.to_string());
+        }
+        parts.join("-")
+    }
+}
+
+impl TargetPreset {
+    /// Returns every combination of the preset's DUTs, testers and modes, each DUT is followed
+    /// by the tester and then any common targets
+    pub fn combinations(&self) -> Result<Vec<TargetCombination>> {
+        fn axis(vals: &Option<Vec<String>>) -> Vec<Option<String>> {
+            match vals {
+                Some(v) if !v.is_empty() => v.iter().map(|x| Some(x.to_string())).collect(),
+                _ => vec![None],
+            }
+        }
+        let common = self.targets.clone().unwrap_or_default();
+        let mut combinations = vec![];
+        for dut in axis(&self.duts) {
+            for tester in axis(&self.testers) {
+                for mode in axis(&self.modes) {
+                    let mut targets: Vec<String> =
+                        dut.iter().chain(tester.iter()).cloned().collect();
+                    targets.extend(common.iter().cloned());
+                    if targets.is_empty() {
+                        bail!("The target preset does not define any targets");
+                    }
+                    combinations.push(TargetCombination {
+                        targets,
+                        mode: mode.clone(),
+                    });
+                }
+            }
+        }
+        let mut ids: Vec<String> = combinations.iter().map(|c| c.id()).collect();
+        ids.sort();
+        for pair in ids.windows(2) {
+            if pair[0] == pair[1] {
+                bail!(
+                    "The target preset has multiple combinations named '{}', the target file names must be unique",
+                    pair[0]
+                );
+            }
+        }
+        Ok(combinations)
+    }
+}
+
+/// Returns the names of the target presets defined by the app
+pub fn presets() -> Vec<String> {
+    app()
+        .unwrap()
+        .with_config(|config| {
+            let mut names: Vec<String> = match &config.target_presets {
+                Some(p) => p.keys().cloned().collect(),
+                None => vec![],
+            };
+            names.sort();
+            Ok(names)
+        })
+        .unwrap()
+}
+
+/// Returns the combinations of the given target preset, with the target and mode names resolved.
+/// This will exit the process if any of them do not uniquely identify a target or mode, the same
+/// as if they had been given on the command line.
+pub fn preset_combinations(name: &str) -> Result<Vec<TargetCombination>> {
+    let root = app().unwrap().root.clone();
+    let preset = app().unwrap().with_config(|config| {
+        Ok(config.target_presets.as_ref().and_then(|p| p.get(name)).cloned())
+    })?;
+    let preset = match preset {
+        Some(p) => p,
+        None => {
+            let available = presets();
+            bail!(
+                "No target preset named '{}' is defined in config/application.toml{}",
+                name,
+                if available.is_empty() {
+                    "".to_string()
+                } else {
+                    format!(", the available presets are: {}", available.join(", "))
+                }
+            )
+        }
+    };
+    let clean = |names: &Option<Vec<String>>| {
+        names.as_ref().map(|names| {
+            names
+                .iter()
+                .map(|t| {
+                    clean_name(t, "targets", false, &root)
+                        .trim_end_matches(".py")
+                        .replace("\\", "/")
+                })
+                .collect::<Vec<String>>()
+        })
+    };
+    let resolved = TargetPreset {
+        targets: clean(&preset.targets),
+        duts: clean(&preset.duts),
+        testers: clean(&preset.testers),
+        modes: preset
+            .modes
+            .as_ref()
+            .map(|modes| modes.iter().map(|m| crate::clean_mode(m)).collect()),
+    };
+    match resolved.combinations() {
+        Ok(c) => Ok(c),
+        Err(e) => bail!("Invalid target preset '{}': {}", name, e.msg),
+    }
+}
+
 /// Sanitizes the given target/env name and returns it, but will exit the process if it does
 /// not uniquely identify a single target/env file.
 /// Set the last arg to true to return the path to the matching target instead.
@@ -348,3 +506,50 @@ fn ensure_app_dot_toml_at_root(root: &PathBuf) {
         fs::write(&path, data).expect(&format!("Unable to write file {}", &path.display()));
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(v: &[&str]) -> Option<Vec<String>> {
+        Some(v.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn presets_expand_to_every_combination() {
+        let preset = TargetPreset {
+            targets: names(&["sim"]),
+            duts: names(&["dut/eagle", "dut/falcon"]),
+            testers: names(&["tester/j750", "tester/v93k_smt7"]),
+            modes: names(&["production"]),
+        };
+        let c = preset.combinations().unwrap();
+        assert_eq!(c.len(), 4);
+        assert_eq!(c[1].targets, vec!["dut/eagle", "tester/v93k_smt7", "sim"]);
+        assert_eq!(c[1].mode, Some("production".to_string()));
+        assert_eq!(
+            c.iter().map(|c| c.id()).collect::<Vec<String>>(),
+            vec![
+                "eagle-j750-sim-production",
+                "eagle-v93k_smt7-sim-production",
+                "falcon-j750-sim-production",
+                "falcon-v93k_smt7-sim-production"
+            ]
+        );
+
+        let preset = TargetPreset {
+            targets: names(&["eagle_with_smt7"]),
+            ..Default::default()
+        };
+        let c = preset.combinations().unwrap();
+        assert_eq!(c.len(), 1);
+        assert_eq!(c[0].id(), "eagle_with_smt7");
+
+        assert!(TargetPreset::default().combinations().is_err());
+        let preset = TargetPreset {
+            duts: names(&["a/eagle", "b/eagle"]),
+            ..Default::default()
+        };
+        assert!(preset.combinations().is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//! Builds, serves and releases the application's documentation website.
+//!
+//! The site itself is a Sphinx project living in the app's website source directory, this
+//! handles everything around the Sphinx invocation: generating pages from the app (the
+//! register pages are written by the Origen Sphinx extension since they need the DUT to be
+//! loaded, the test program flow pages are written here from the last generate report),
+//! serving the built site on a loopback port and copying it to the release location.
+
+use super::Application;
+use crate::core::producer::report::{Report, JSON_REPORT};
+use crate::core::tester::FileKind;
+use crate::utility::file_utils::copy_contents;
+use crate::utility::location::Location;
+use crate::Result;
+use indexmap::IndexMap;
+use pathdiff::diff_paths;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The sub-directory of the website output directory which Sphinx builds into
+pub const SPHINX_OUTPUT: &str = "sphinx_build";
+/// The port which 'origen web view' serves on by default, if it is free
+pub const DEFAULT_PORT: u16 = 8000;
+
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    /// Skip building the API pages (including the register pages)
+    pub no_api: bool,
+    /// Build with the same checks as when releasing, i.e. treat warnings as errors
+    pub as_release: bool,
+    /// Don't treat warnings as errors when building for release
+    pub release_with_warnings: bool,
+    /// Additional arguments for sphinx-build, given as a single string
+    pub sphinx_args: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Website {
+    pub source_dir: PathBuf,
+    pub output_dir: PathBuf,
+    pub release_location: Option<Location>,
+    pub release_name: String,
+}
+
+impl Website {
+    pub fn new(app: &Application) -> Self {
+        let (release_location, release_name) = app
+            .with_config(|c| {
+                Ok((
+                    c.website_release_location.clone(),
+                    c.website_release_name.clone(),
+                ))
+            })
+            .unwrap();
+        Self {
+            source_dir: app.website_source_directory(),
+            output_dir: app.website_output_directory(),
+            release_location,
+            release_name: release_name.unwrap_or(app.name()),
+        }
+    }
+
+    pub fn build_dir(&self) -> PathBuf {
+        self.output_dir.join(SPHINX_OUTPUT)
+    }
+
+    pub fn index_file(&self) -> PathBuf {
+        self.build_dir().join("index.html")
+    }
+
+    /// Dynamically generated content which is part of the Sphinx project but which is never
+    /// checked in, this is rebuilt every time
+    pub fn interbuild_dir(&self) -> PathBuf {
+        self.source_dir.join("interbuild")
+    }
+
+    /// Pages generated by Origen from the app
+    pub fn generated_pages_dir(&self) -> PathBuf {
+        self.interbuild_dir().join("origen")
+    }
+
+    pub fn static_dir(&self) -> PathBuf {
+        self.source_dir.join("_static")
+    }
+
+    /// Static content which is generated during the build, e.g. subproject docs
+    pub fn unmanaged_static_dir(&self) -> PathBuf {
+        self.static_dir().join("build")
+    }
+
+    pub fn templates_dir(&self) -> PathBuf {
+        self.source_dir.join("_templates")
+    }
+
+    pub fn is_built(&self) -> bool {
+        self.index_file().exists()
+    }
+
+    /// Removes the built site and all content generated by previous builds
+    pub fn clean(&self) -> Result<()> {
+        if self.output_dir.exists() {
+            log_info!("Removing built website at '{}'", self.output_dir.display());
+            fs::remove_dir_all(&self.output_dir)?;
+        } else {
+            log_info!("No built website to clean!");
+        }
+        for d in [self.interbuild_dir(), self.unmanaged_static_dir()] {
+            if d.exists() {
+                log_debug!("Removing '{}'", d.display());
+                fs::remove_dir_all(&d)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the arguments to be given to sphinx-build for the given options
+    pub fn sphinx_args(&self, options: &BuildOptions) -> Vec<String> {
+        let mut args = vec![
+            self.source_dir.display().to_string(),
+            self.build_dir().display().to_string(),
+        ];
+        if options.no_api {
+            args.push("-D".to_string());
+            args.push("origen_no_api=True".to_string());
+        }
+        if options.as_release {
+            if !options.release_with_warnings {
+                args.push("-W".to_string());
+                args.push("--keep-going".to_string());
+            }
+            args.push("-D".to_string());
+            args.push("origen_releasing_build=1".to_string());
+        }
+        if let Some(a) = &options.sphinx_args {
+            args.append(&mut split_args(a));
+        }
+        args
+    }
+
+    /// Builds the site, returning an error if Sphinx fails
+    pub fn build(&self, options: &BuildOptions) -> Result<()> {
+        for d in [
+            self.static_dir(),
+            self.unmanaged_static_dir(),
+            self.templates_dir(),
+            self.build_dir(),
+            self.interbuild_dir(),
+        ] {
+            fs::create_dir_all(&d)?;
+        }
+        self.write_flow_pages(&crate::STATUS.output_dir())?;
+
+        let args = self.sphinx_args(options);
+        log_info!("Running: poetry run sphinx-build {}", args.join(" "));
+        let status = Command::new("poetry")
+            .arg("run")
+            .arg("sphinx-build")
+            .args(&args)
+            .status()?;
+        if !status.success() {
+            bail!("Failed to build the webpages!");
+        }
+        display_greenln!("Website built at '{}'", self.build_dir().display());
+        Ok(())
+    }
+
+    /// Writes a page showing the test program flows produced by the last generate run in the
+    /// given output directory, returning its path.
+    /// Nothing is written if no generate report is found or it contains no programs.
+    pub fn write_flow_pages(&self, output_dir: &Path) -> Result<Option<PathBuf>> {
+        let page = self.generated_pages_dir().join("flows.rst");
+        if page.exists() {
+            fs::remove_file(&page)?;
+        }
+        let report = Report::default_dir(output_dir).join(JSON_REPORT);
+        if !report.exists() {
+            log_debug!(
+                "No generate report found at '{}', skipping flow pages",
+                report.display()
+            );
+            return Ok(None);
+        }
+        let mut targets: IndexMap<String, Vec<PathBuf>> = IndexMap::new();
+        for f in Report::read(&report)?.files {
+            if f.kind == FileKind::Program && f.path.exists() {
+                targets.entry(f.target).or_insert(vec![]).push(f.path);
+            }
+        }
+        if targets.is_empty() {
+            return Ok(None);
+        }
+
+        let dir = self.generated_pages_dir();
+        let mut lines: Vec<String> = vec![":orphan:".to_string(), "".to_string()];
+        lines.push("Test Program Flows".to_string());
+        lines.push("==================".to_string());
+        lines.push(format!(
+            ".. This page was generated by Origen from '{}'",
+            report.display()
+        ));
+        for (target, files) in &targets {
+            lines.push("".to_string());
+            lines.push(target.to_string());
+            lines.push("-".repeat(target.chars().count()));
+            for f in files {
+                let name = diff_paths(f, output_dir).unwrap_or(f.to_path_buf());
+                let name = name.display().to_string();
+                // Sphinx treats absolute include paths as relative to the source dir
+                let rel = diff_paths(f, &dir).unwrap_or(f.to_path_buf());
+                lines.push("".to_string());
+                lines.push(name.to_string());
+                lines.push("^".repeat(name.chars().count()));
+                lines.push("".to_string());
+                lines.push(format!(
+                    ".. literalinclude:: {}",
+                    rel.display().to_string().replace('\\', "/")
+                ));
+                lines.push("   :language: none".to_string());
+            }
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(&page, lines.join("\n") + "\n")?;
+        log_info!("Flow pages written to '{}'", page.display());
+        Ok(Some(page))
+    }
+
+    /// Copies the built site to the release location under the release name. If an archive ID
+    /// is given then it is also copied to '<location>/archive/<name>/<ID>', giving archive_only
+    /// will skip the main release and only archive it.
+    pub fn release(&self, archive: Option<&str>, archive_only: bool) -> Result<()> {
+        if !self.is_built() {
+            bail!(
+                "Could not find a built website at '{}', build it before releasing it",
+                self.build_dir().display()
+            );
+        }
+        let location = match self.release_location.as_ref() {
+            Some(l) => l,
+            None => bail!("No website_release_location is given by the app's config"),
+        };
+        let path = match location.path() {
+            Some(p) => p,
+            None => bail!(
+                "Releasing the website to '{}' is not supported, only a path can be given as the website_release_location",
+                location.location
+            ),
+        };
+
+        if !archive_only {
+            let dest = path.join(&self.release_name);
+            displayln!("Releasing website to '{}'", dest.display());
+            replace_contents(&self.build_dir(), &dest)?;
+        }
+        if let Some(id) = archive {
+            let dest = path.join("archive").join(&self.release_name).join(id);
+            displayln!("Archiving website to '{}'", dest.display());
+            replace_contents(&self.build_dir(), &dest)?;
+        }
+        display_greenln!("Successfully released website for {}", self.release_name);
+        Ok(())
+    }
+
+    /// Serves the built site on the loopback interface until the process is killed. If no port
+    /// is given then the default port is used, or any free port if that is taken.
+    pub fn serve(&self, port: Option<u16>, open_browser: bool) -> Result<()> {
+        if !self.is_built() {
+            bail!(
+                "Could not find a built website at '{}'. Please run 'origen web build --view' to build the site and view the results.",
+                self.build_dir().display()
+            );
+        }
+        let listener = match port {
+            Some(p) => TcpListener::bind(("127.0.0.1", p))?,
+            None => match TcpListener::bind(("127.0.0.1", DEFAULT_PORT)) {
+                Ok(l) => l,
+                Err(_) => TcpListener::bind(("127.0.0.1", 0))?,
+            },
+        };
+        let url = format!("http://{}/", listener.local_addr()?);
+        displayln!("Serving '{}' at {}", self.build_dir().display(), url);
+        displayln!("Press CTRL-C to stop");
+        if open_browser {
+            launch_browser(&url);
+        }
+
+        let root = self.build_dir();
+        for stream in listener.incoming() {
+            match stream {
+                Ok(s) => {
+                    let root = root.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = respond(s, &root) {
+                            log_debug!("Failed to respond to web request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => log_debug!("Failed to accept web connection: {}", e),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the contents of the given dest directory with those of the source directory
+fn replace_contents(source: &Path, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::create_dir_all(dest)?;
+    copy_contents(source, dest)
+}
+
+fn launch_browser(url: &str) {
+    let result = if cfg!(windows) {
+        Command::new("cmd").args(["/C", "start", "", url]).spawn()
+    } else if cfg!(target_os = "macos") {
+        Command::new("open").arg(url).spawn()
+    } else {
+        Command::new("xdg-open").arg(url).spawn()
+    };
+    if let Err(e) = result {
+        log_warning!(
+            "Could not launch a web browser ({}), open {} manually",
+            e,
+            url
+        );
+    }
+}
+
+/// Splits the given string into arguments on whitespace, allowing arguments containing
+/// whitespace to be grouped by single or double quotes
+fn split_args(args: &str) -> Vec<String> {
+    let mut result: Vec<String> = vec![];
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+    for c in args.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            None if c.is_whitespace() => {
+                if in_arg {
+                    result.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        result.push(current);
+    }
+    result
+}
+
+/// Returns the file to be served for the given request target, None is returned if the target
+/// is malformed or would resolve to a location outside of the root
+fn resolve_request(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or("");
+    let path = percent_decode(path)?;
+    let mut file = root.to_path_buf();
+    for component in path.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." => return None,
+            c if c.contains('\\') || c.contains(':') => return None,
+            c => file.push(c),
+        }
+    }
+    if file.is_dir() {
+        file.push("index.html");
+    }
+    Some(file)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded: Vec<u8> = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn content_type(file: &Path) -> &'static str {
+    match file
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .as_deref()
+    {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("ico") => "image/x-icon",
+        Some("pdf") => "application/pdf",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn respond(stream: TcpStream, root: &Path) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers are not needed, but must be consumed before responding
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request.split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+
+    let (status, ctype, body) = if method != "GET" && method != "HEAD" {
+        (
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method Not Allowed".to_vec(),
+        )
+    } else {
+        match resolve_request(root, target).map(|f| (fs::read(&f), f)) {
+            Some((Ok(content), f)) => ("200 OK", content_type(&f), content),
+            _ => ("404 Not Found", "text/plain", b"Not Found".to_vec()),
+        }
+    };
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        ctype,
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sphinx_args_are_split_like_a_shell() {
+        assert_eq!(
+            split_args("-q -D 'my_define=a b'  -D \"x=1\""),
+            vec!["-q", "-D", "my_define=a b", "-D", "x=1"]
+        );
+        assert!(split_args("  ").is_empty());
+    }
+
+    #[test]
+    fn requests_resolve_within_the_root() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("sub dir")).unwrap();
+        assert_eq!(
+            resolve_request(root.path(), "/"),
+            Some(root.path().join("index.html"))
+        );
+        assert_eq!(
+            resolve_request(root.path(), "/sub%20dir/?x=1"),
+            Some(root.path().join("sub dir").join("index.html"))
+        );
+        assert_eq!(
+            resolve_request(root.path(), "/_static/origen.css#top"),
+            Some(root.path().join("_static").join("origen.css"))
+        );
+        assert_eq!(resolve_request(root.path(), "/../secret"), None);
+        assert_eq!(resolve_request(root.path(), "/%2e%2e/secret"), None);
+        assert_eq!(
+            content_type(Path::new("a/b.HTML")),
+            "text/html; charset=utf-8"
+        );
+    }
+}
@@ -9,6 +9,8 @@
 //! println!("Server: {}", &ORIGEN_CONFIG.pkg_server);  // => "Server: https://pkgs.company.net:9292"
 //! ```
 
+pub mod provenance;
+
 use crate::STATUS;
 use origen_metal::config;
 use origen_metal::config::{Environment, File};
@@ -198,6 +200,7 @@ pub struct ConfigMetadata {
 // If you add an attribute to this you must also update:
 // * pyapi/src/lib.rs to convert it to Python
 // * default function below to define the default value (no nil in Rust)
+// * the schema in config/provenance.rs so that it is recognized by 'origen config validate'
 // * add an example of it to src/app_generators/templates/app/config/origen.toml
 pub struct Config {
     pub python_cmd: String,
@@ -0,0 +1,529 @@
+//! Tracks where each of the values in the merged Origen config came from.
+//!
+//! The config crate used to build [`Config`](super::Config) does not retain the source of each
+//! value, so the files that it was assembled from are re-read here into layers, ordered from
+//! highest to lowest precedence, with the line number of every key being recorded.
+//! This also provides validation of the config files against the known config keys and types.
+
+use super::{
+    AuxillaryCommandsTOML, DatasetConfig, DefaultUserConfig, InitialUserConfig, LDAPConfig,
+    PluginsConfig,
+};
+use crate::Result;
+use origen_metal::prelude::config::{MailerTOMLConfig, MaillistsTOMLConfig};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Checks a value given for a config key, returning an error message if it is the wrong type,
+/// otherwise the (dotted) paths of any unknown keys found within it
+type Check = fn(&toml::Value) -> std::result::Result<Vec<String>, String>;
+
+/// The known top-level config keys and how to check their values, this must be kept in sync
+/// with the attributes of the Config struct
+fn schema() -> Vec<(&'static str, Check)> {
+    vec![
+        ("python_cmd", typed::<String>),
+        ("pkg_server", typed::<String>),
+        ("pkg_server_push", typed::<String>),
+        ("pkg_server_pull", typed::<String>),
+        ("some_val", typed::<u32>),
+        ("plugins", strict::<PluginsConfig>),
+        ("maillists", strict::<MaillistsTOMLConfig>),
+        ("mailer", strict::<MailerTOMLConfig>),
+        ("ldaps", typed::<HashMap<String, LDAPConfig>>),
+        ("default_encryption_key", typed::<String>),
+        ("default_encryption_nonce", typed::<String>),
+        ("user__data_lookup_hierarchy", typed::<Vec<String>>),
+        // Datasets may carry additional keys which are consumed by the frontend
+        ("user__datasets", typed::<HashMap<String, DatasetConfig>>),
+        ("user__password_auth_attempts", typed::<u8>),
+        ("user__password_cache_option", typed::<String>),
+        ("user__current_user_lookup_function", typed::<String>),
+        ("user__dataset_motives", typed::<HashMap<String, String>>),
+        ("password_encryption_key", typed::<String>),
+        ("password_encryption_nonce", typed::<String>),
+        (
+            "user__dataset_mappings",
+            typed::<HashMap<String, HashMap<String, String>>>,
+        ),
+        (
+            "default_users",
+            strict::<HashMap<String, DefaultUserConfig>>,
+        ),
+        ("initial_user", strict::<InitialUserConfig>),
+        ("session__user_root", typed::<String>),
+        ("additional_config_dirs", typed::<Vec<String>>),
+        ("additional_configs", typed::<Vec<String>>),
+        ("auxillary_commands", strict::<Vec<AuxillaryCommandsTOML>>),
+    ]
+}
+
+fn typed<T: DeserializeOwned>(value: &toml::Value) -> std::result::Result<Vec<String>, String> {
+    match value.clone().try_into::<T>() {
+        Ok(_) => Ok(vec![]),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Like typed, but also reports any keys which were dropped when deserializing the value, i.e.
+/// those which are not known to the target type
+fn strict<T: DeserializeOwned + Serialize>(
+    value: &toml::Value,
+) -> std::result::Result<Vec<String>, String> {
+    let t = match value.clone().try_into::<T>() {
+        Ok(t) => t,
+        Err(e) => return Err(e.to_string()),
+    };
+    match toml::Value::try_from(&t) {
+        Ok(known) => {
+            let mut unknown = vec![];
+            unknown_keys(value, &known, "", &mut unknown);
+            Ok(unknown)
+        }
+        Err(_) => Ok(vec![]),
+    }
+}
+
+fn unknown_keys(given: &toml::Value, known: &toml::Value, prefix: &str, unknown: &mut Vec<String>) {
+    match (given, known) {
+        (toml::Value::Table(g), toml::Value::Table(k)) => {
+            for (key, v) in g {
+                let path = join(prefix, key);
+                match k.get(key) {
+                    Some(kv) => unknown_keys(v, kv, &path, unknown),
+                    None => unknown.push(path),
+                }
+            }
+        }
+        (toml::Value::Array(g), toml::Value::Array(k)) => {
+            for (v, kv) in g.iter().zip(k.iter()) {
+                unknown_keys(v, kv, prefix, unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    if prefix.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+/// Renders the given value on a single line, as it would be written inline in a TOML file
+pub fn inline(value: &toml::Value) -> String {
+    match value {
+        toml::Value::Array(a) => format!(
+            "[{}]",
+            a.iter().map(inline).collect::<Vec<String>>().join(", ")
+        ),
+        toml::Value::Table(t) if t.is_empty() => "{}".to_string(),
+        toml::Value::Table(t) => format!(
+            "{{ {} }}",
+            t.iter()
+                .map(|(k, v)| format!("{} = {}", k, inline(v)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        _ => value.to_string(),
+    }
+}
+
+/// Keys whose values are combined from every layer which gives them, rather than being taken
+/// from the highest precedence one. This must be kept in sync with the config loading.
+const MERGED_KEYS: &[&str] = &["auxillary_commands"];
+
+/// Returns true if the given top-level key is a known config key
+pub fn is_known_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    schema().iter().any(|(k, _)| *k == key)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Source {
+    File(PathBuf),
+    /// An environment variable with the 'ORIGEN_' prefix
+    Env(String),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::File(p) => write!(f, "{}", p.display()),
+            Source::Env(v) => write!(f, "environment variable '{}'", v),
+        }
+    }
+}
+
+/// The values given by a single config source, keyed by their full dotted path, e.g.
+/// "mailer.server"
+#[derive(Debug)]
+pub struct Layer {
+    pub source: Source,
+    pub values: BTreeMap<String, toml::Value>,
+    lines: HashMap<String, usize>,
+}
+
+impl Layer {
+    fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let table: toml::value::Table = match toml::from_str(&text) {
+            Ok(t) => t,
+            Err(e) => bail!("Malformed config file '{}': {}", path.display(), e),
+        };
+        let mut values = BTreeMap::new();
+        flatten("", &toml::Value::Table(table), &mut values);
+        Ok(Self {
+            source: Source::File(path.to_path_buf()),
+            values,
+            lines: key_lines(&text),
+        })
+    }
+
+    /// Returns the line number that the given key is defined on, if known
+    pub fn line(&self, key: &str) -> Option<usize> {
+        self.lines.get(key).copied()
+    }
+
+    /// Returns a description of where the given key was defined, e.g. "path/to/origen.toml:12"
+    pub fn location(&self, key: &str) -> String {
+        match self.line(key) {
+            Some(l) => format!("{}:{}", self.source, l),
+            None => self.source.to_string(),
+        }
+    }
+}
+
+fn flatten(prefix: &str, value: &toml::Value, values: &mut BTreeMap<String, toml::Value>) {
+    match value {
+        toml::Value::Table(t) if !t.is_empty() => {
+            for (k, v) in t {
+                flatten(&join(prefix, &k.to_lowercase()), v, values);
+            }
+        }
+        _ => {
+            values.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Returns the line numbers (1-based) of the keys and table headers defined in the given TOML
+/// text, keyed by their full dotted path.
+/// This is a line-based scan rather than a full parse, but it is only used to annotate the
+/// values returned by the real parser so an unusual layout just results in a missing line number.
+fn key_lines(text: &str) -> HashMap<String, usize> {
+    fn normalize(key: &str) -> String {
+        key.split('.')
+            .map(|k| {
+                k.trim()
+                    .trim_matches(|c| c == '"' || c == '\'')
+                    .to_lowercase()
+            })
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+
+    let mut lines = HashMap::new();
+    let mut table = "".to_string();
+    let mut multiline: Option<&str> = None;
+    for (i, line) in text.lines().enumerate() {
+        let l = line.trim();
+        if let Some(delim) = multiline {
+            if l.contains(delim) {
+                multiline = None;
+            }
+            continue;
+        }
+        if l.is_empty() || l.starts_with('#') {
+            continue;
+        }
+        let key = if l.starts_with('[') {
+            table = normalize(l.trim_start_matches('[').split(']').next().unwrap_or(""));
+            table.clone()
+        } else if let Some(eq) = l.find('=') {
+            let rest = l[eq + 1..].trim();
+            for delim in ["\"\"\"", "'''"] {
+                if rest.starts_with(delim) && !rest[3..].contains(delim) {
+                    multiline = Some(delim);
+                }
+            }
+            join(&table, &normalize(&l[..eq]))
+        } else {
+            continue;
+        };
+        // Also record the parents of a dotted key, e.g. "a" and "a.b" for "a.b.c"
+        let parts: Vec<&str> = key.split('.').collect();
+        for n in 1..=parts.len() {
+            lines.entry(parts[..n].join(".")).or_insert(i + 1);
+        }
+    }
+    lines
+}
+
+/// The config layers which make up the current Origen config, highest precedence first
+#[derive(Debug)]
+pub struct Provenance {
+    pub layers: Vec<Layer>,
+}
+
+impl Provenance {
+    /// Collects the layers from the config files that were found when loading the current Origen
+    /// config, and from the environment
+    pub fn collect() -> Result<Self> {
+        let files = crate::origen_config_metadata().files.clone();
+        Self::new(&files, std::env::vars())
+    }
+
+    /// Collects the layers from the given config files, which are expected to be in precedence
+    /// order, and any 'ORIGEN_' prefixed environment variables which set a known config key
+    pub fn new(files: &[PathBuf], env: impl Iterator<Item = (String, String)>) -> Result<Self> {
+        let mut layers = vec![];
+        let mut env = env.collect::<Vec<(String, String)>>();
+        env.sort();
+        for (var, val) in env {
+            let lower = var.to_lowercase();
+            if let Some(key) = lower.strip_prefix("origen_") {
+                if is_known_key(key) {
+                    let mut values = BTreeMap::new();
+                    values.insert(key.to_string(), toml::Value::String(val));
+                    layers.push(Layer {
+                        source: Source::Env(var),
+                        values,
+                        lines: HashMap::new(),
+                    });
+                }
+            }
+        }
+        for f in files {
+            layers.push(Layer::from_file(f)?);
+        }
+        Ok(Self { layers })
+    }
+
+    /// Returns the effective value of every key that has been set, along with the layers that
+    /// it comes from, highest precedence first.
+    /// This is a single layer for all keys except those which are merged across layers (e.g.
+    /// the auxillary commands), where every layer that contributes to the value is given.
+    pub fn effective(&self) -> BTreeMap<&str, (toml::Value, Vec<&Layer>)> {
+        let mut values: BTreeMap<&str, (toml::Value, Vec<&Layer>)> = BTreeMap::new();
+        for layer in self.layers.iter().rev() {
+            for (k, v) in &layer.values {
+                if MERGED_KEYS.contains(&k.as_str()) {
+                    if let Some((toml::Value::Array(merged), layers)) = values.get_mut(k.as_str()) {
+                        // Lower precedence entries come first, as when the config is loaded
+                        if let toml::Value::Array(a) = v {
+                            merged.extend(a.iter().cloned());
+                            layers.insert(0, layer);
+                            continue;
+                        }
+                    }
+                }
+                // A value from a higher precedence layer replaces any lower ones at or beneath
+                // the same key, e.g. a table given as an inline value
+                let nested = format!("{}.", k);
+                values.retain(|key: &&str, _| !key.starts_with(&nested));
+                values.insert(k.as_str(), (v.clone(), vec![layer]));
+            }
+        }
+        values
+    }
+
+    /// Returns every definition of the given key (or of keys beneath it), highest precedence
+    /// first. Each is returned with its layer and full key.
+    pub fn origins(&self, key: &str) -> Vec<(&Layer, &str, &toml::Value)> {
+        let key = key.to_lowercase();
+        let nested = format!("{}.", key);
+        let mut origins = vec![];
+        for layer in &self.layers {
+            for (k, v) in &layer.values {
+                if *k == key || k.starts_with(&nested) {
+                    origins.push((layer, k.as_str(), v));
+                }
+            }
+        }
+        origins
+    }
+}
+
+/// A problem found in a config file
+#[derive(Debug)]
+pub struct Issue {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(l) => write!(f, "{}:{}: {}", self.file.display(), l, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// Validates the given config file against the known config keys and their types, returning
+/// any problems found
+pub fn validate(file: &Path) -> Vec<Issue> {
+    let issue = |line: Option<usize>, message: String| Issue {
+        file: file.to_path_buf(),
+        line,
+        message,
+    };
+    let text = match std::fs::read_to_string(file) {
+        Ok(t) => t,
+        Err(e) => return vec![issue(None, format!("Unable to read file: {}", e))],
+    };
+    let table: toml::value::Table = match toml::from_str(&text) {
+        Ok(t) => t,
+        Err(e) => return vec![issue(e.line_col().map(|(l, _)| l + 1), e.to_string())],
+    };
+    let lines = key_lines(&text);
+    let schema = schema();
+    let mut issues = vec![];
+    for (key, value) in &table {
+        let line = lines.get(&key.to_lowercase()).copied();
+        match schema.iter().find(|(k, _)| *k == key.to_lowercase()) {
+            None => issues.push(issue(line, format!("Unknown config key '{}'", key))),
+            Some((_, check)) => match check(value) {
+                Ok(unknown) => {
+                    for u in unknown {
+                        let path = join(&key.to_lowercase(), &u);
+                        issues.push(issue(
+                            lines.get(&path.to_lowercase()).copied().or(line),
+                            format!("Unknown config key '{}'", path),
+                        ));
+                    }
+                }
+                Err(e) => issues.push(issue(line, format!("Invalid value for '{}': {}", key, e))),
+            },
+        }
+    }
+    issues.sort_by_key(|i| i.line);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let f = dir.join(name);
+        std::fs::write(&f, text).unwrap();
+        f
+    }
+
+    #[test]
+    fn tracks_the_source_of_each_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = write(
+            dir.path(),
+            "app.toml",
+            "python_cmd = \"poetry\"\n\n[mailer]\nserver = \"smtp.app.com\"\n",
+        );
+        let site = write(
+            dir.path(),
+            "site.toml",
+            "python_cmd = \"python\"\n[mailer]\nserver = \"smtp.site.com\"\nport = 25\n",
+        );
+        let env = vec![
+            ("ORIGEN_PKG_SERVER".to_string(), "https://pkgs".to_string()),
+            ("ORIGEN_CONFIG_PATHS".to_string(), "ignored".to_string()),
+        ];
+        let p = Provenance::new(&[app.clone(), site.clone()], env.into_iter()).unwrap();
+        assert_eq!(p.layers.len(), 3);
+
+        let eff = p.effective();
+        assert_eq!(eff["python_cmd"].0.as_str(), Some("poetry"));
+        assert_eq!(eff["python_cmd"].1.len(), 1);
+        assert_eq!(eff["python_cmd"].1[0].source, Source::File(app.clone()));
+        assert_eq!(eff["mailer.server"].0.as_str(), Some("smtp.app.com"));
+        assert_eq!(eff["mailer.port"].1[0].source, Source::File(site.clone()));
+        assert_eq!(
+            eff["pkg_server"].1[0].source,
+            Source::Env("ORIGEN_PKG_SERVER".to_string())
+        );
+
+        let origins = p.origins("mailer");
+        assert_eq!(origins.len(), 3);
+        assert_eq!(
+            origins[0].0.location(origins[0].1),
+            format!("{}:4", app.display())
+        );
+        assert_eq!(origins[2].1, "mailer.server");
+        assert_eq!(
+            origins[2].0.location(origins[2].1),
+            format!("{}:3", site.display())
+        );
+
+        let v: toml::Value = toml::from_str("a = [{ path = 'x' }, {}]").unwrap();
+        assert_eq!(inline(&v["a"]), "[{ path = \"x\" }, {}]");
+    }
+
+    #[test]
+    fn merges_auxillary_commands_from_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = write(
+            dir.path(),
+            "app.toml",
+            "python_cmd = \"poetry\"\n\n[[auxillary_commands]]\nname = \"app_cmds\"\npath = \"app.toml\"\n",
+        );
+        let site = write(
+            dir.path(),
+            "site.toml",
+            "auxillary_commands = [{ name = \"site_cmds\", path = \"site.toml\" }]\n",
+        );
+        let p = Provenance::new(&[app.clone(), site.clone()], vec![].into_iter()).unwrap();
+
+        let eff = p.effective();
+        let (v, layers) = &eff["auxillary_commands"];
+        let names: Vec<&str> = v
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["site_cmds", "app_cmds"]);
+        let sources: Vec<&Source> = layers.iter().map(|l| &l.source).collect();
+        assert_eq!(
+            sources,
+            vec![&Source::File(app.clone()), &Source::File(site.clone())]
+        );
+        assert_eq!(
+            layers[0].location("auxillary_commands"),
+            format!("{}:3", app.display())
+        );
+        assert_eq!(
+            layers[1].location("auxillary_commands"),
+            format!("{}:1", site.display())
+        );
+        // Other keys still come from a single file
+        assert_eq!(eff["python_cmd"].1.len(), 1);
+    }
+
+    #[test]
+    fn reports_unknown_keys_and_bad_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let f = write(
+            dir.path(),
+            "origen.toml",
+            "python_cmd = \"poetry\"\nuser__password_auth_attempts = \"three\"\npython_cmnd = 1\n\n[mailer]\nserver = \"x\"\nprot = 25\n",
+        );
+        let issues = validate(&f)
+            .iter()
+            .map(|i| i.to_string().replace(&format!("{}:", f.display()), ""))
+            .collect::<Vec<String>>();
+        assert_eq!(issues.len(), 3);
+        assert!(issues[0].starts_with("2: Invalid value for 'user__password_auth_attempts': "));
+        assert_eq!(issues[1], "3: Unknown config key 'python_cmnd'");
+        assert_eq!(issues[2], "7: Unknown config key 'mailer.prot'");
+
+        let f = write(dir.path(), "bad.toml", "a = 1\nb = \n");
+        assert!(validate(&f)[0].to_string().contains("bad.toml:2: "));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::model::memories::Memory;
 use crate::core::model::pins::pin::Pin;
 use crate::core::model::pins::pin_group::PinGroup;
 use crate::core::model::pins::pin_header::PinHeader;
@@ -98,6 +99,7 @@ pub struct Dut {
     pub pins: Vec<Pin>,
     pub pin_groups: Vec<PinGroup>,
     pub pin_headers: Vec<PinHeader>,
+    pub memories: Vec<Memory>,
     pub id_mappings: Vec<IndexMap<String, usize>>,
     /// Cache of descriptions parsed from reg definition files
     pub reg_descriptions: IndexMap<String, IndexMap<usize, String>>,
@@ -125,6 +127,7 @@ impl Dut {
             pins: Vec::<Pin>::new(),
             pin_groups: Vec::<PinGroup>::new(),
             pin_headers: Vec::<PinHeader>::new(),
+            memories: Vec::<Memory>::new(),
             id_mappings: Vec::<IndexMap<String, usize>>::new(),
             reg_descriptions: IndexMap::new(),
         }
@@ -149,6 +152,7 @@ impl Dut {
         self.wave_groups.clear();
         self.waves.clear();
         self.wave_events.clear();
+        self.memories.clear();
         self.id_mappings.clear();
         self.reg_descriptions.clear();
         // Add the model for the DUT top-level (always ID 0)
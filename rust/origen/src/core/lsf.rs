@@ -1,9 +1,213 @@
 //! This module is responsible for abstracting and managing job submissions to LSF
 
+use crate::Result;
+use std::collections::HashMap;
 use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
 
 pub fn is_running_remotely() -> bool {
     // See here for info about LSB env vars:
     //   https://www.ibm.com/support/knowledgecenter/SSWRJV_10.1.0/lsf_config_ref/lsf_envars_job_exec.html
     env::var("LSB_JOBID").is_ok()
 }
+
+/// The commands and options used to submit and monitor LSF jobs. The commands can be replaced
+/// with stand-in scripts, e.g. for testing without an LSF installation.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub bsub: String,
+    pub bjobs: String,
+    pub queue: Option<String>,
+    /// Additional options given to bsub, e.g. resource requirements
+    pub options: Vec<String>,
+    /// How often to poll bjobs for the state of submitted jobs
+    pub poll_interval: Duration,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bsub: "bsub".to_string(),
+            bjobs: "bjobs".to_string(),
+            queue: None,
+            options: vec![],
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Pending,
+    Running,
+    Suspended,
+    Done,
+    Exited,
+    /// Reported by LSF in a state it can't currently vouch for, e.g. UNKWN when it has lost
+    /// contact with the execution host or ZOMBI when a kill has not yet been confirmed, or in a
+    /// state not recognized here. The job may still be running so it should be polled again.
+    Indeterminate,
+    /// No longer known to LSF, e.g. its record has been cleaned up after completion
+    Unknown,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobState::Done | JobState::Exited | JobState::Unknown)
+    }
+
+    fn from_stat(stat: &str) -> Self {
+        match stat {
+            "PEND" | "WAIT" => JobState::Pending,
+            "RUN" | "PROV" => JobState::Running,
+            "PSUSP" | "USUSP" | "SSUSP" => JobState::Suspended,
+            "DONE" => JobState::Done,
+            "EXIT" => JobState::Exited,
+            _ => JobState::Indeterminate,
+        }
+    }
+}
+
+/// Submits the given command to LSF with its output written to the given log file, returning
+/// the LSF job ID. The submitting environment, including the given variables, is propagated
+/// to the job by bsub.
+pub fn submit(
+    config: &Config,
+    name: &str,
+    command: &[String],
+    log: &Path,
+    env: &[(&str, String)],
+) -> Result<String> {
+    let mut cmd = Command::new(&config.bsub);
+    cmd.arg("-J").arg(name).arg("-o").arg(log);
+    if let Some(q) = &config.queue {
+        cmd.arg("-q").arg(q);
+    }
+    cmd.args(&config.options).args(command);
+    for (k, v) in env {
+        cmd.env(k, v);
+    }
+    let output = match cmd.output() {
+        Ok(o) => o,
+        Err(e) => bail!("Failed to run '{}' - {}", config.bsub, e),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match parse_job_id(&stdout) {
+        Some(id) if output.status.success() => Ok(id),
+        _ => bail!(
+            "LSF submission of job '{}' failed:\n{}{}",
+            name,
+            stdout,
+            String::from_utf8_lossy(&output.stderr)
+        ),
+    }
+}
+
+/// Returns the current state of each of the given jobs.
+///
+/// A job is only reported as Unknown when bjobs says that it has no record of it, or when bjobs
+/// succeeded without mentioning it. Jobs whose state could not be determined, e.g. because bjobs
+/// failed part way through, are left out of the result and should be polled again later. An
+/// error is returned if bjobs failed to report anything at all, e.g. if LSF is temporarily
+/// unavailable.
+pub fn states(config: &Config, ids: &[String]) -> Result<HashMap<String, JobState>> {
+    let output = match Command::new(&config.bjobs)
+        .arg("-a")
+        .arg("-noheader")
+        .args(ids)
+        .output()
+    {
+        Ok(o) => o,
+        Err(e) => bail!("Failed to run '{}' - {}", config.bjobs, e),
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut found = parse_states(&stdout);
+    for id in parse_not_found(&stderr) {
+        found.insert(id, JobState::Unknown);
+    }
+    if found.is_empty() {
+        bail!(
+            "'{}' did not report the state of any LSF jobs (exit status: {}):\n{}{}",
+            config.bjobs,
+            output.status,
+            stdout,
+            stderr
+        );
+    }
+    let complete = output.status.success();
+    Ok(ids
+        .iter()
+        .filter_map(|id| match found.get(id) {
+            Some(state) => Some((id.to_string(), *state)),
+            None if complete => Some((id.to_string(), JobState::Unknown)),
+            None => None,
+        })
+        .collect())
+}
+
+/// Extracts the job ID from bsub's response, e.g. "Job <1234> is submitted to queue <normal>."
+fn parse_job_id(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("Job <")?;
+        let end = rest.find('>')?;
+        Some(rest[..end].to_string())
+    })
+}
+
+/// Extracts the job ID and STAT columns from bjobs' default output format
+fn parse_states(output: &str) -> HashMap<String, JobState> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let cols: Vec<&str> = line.split_whitespace().collect();
+            if cols.len() >= 3 {
+                Some((cols[0].to_string(), JobState::from_stat(cols[2])))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Extracts the IDs of the jobs which bjobs no longer has a record of, e.g. "Job <1234> is not found"
+fn parse_not_found(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| line.contains("not found"))
+        .filter_map(parse_job_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bsub_and_bjobs_output() {
+        assert_eq!(
+            parse_job_id("Job <4021> is submitted to queue <normal>.\n"),
+            Some("4021".to_string())
+        );
+        assert_eq!(parse_job_id("Request aborted by esub"), None);
+        let s = parse_states(concat!(
+            "4021    jdoe    DONE  normal     host1       host2       origen_g   Oct 19 10:01\n",
+            "4022    jdoe    RUN   normal     host1       host3       origen_g   Oct 19 10:01\n",
+            "4023    jdoe    EXIT  normal     host1       host3       origen_g   Oct 19 10:01\n",
+        ));
+        assert_eq!(s["4021"], JobState::Done);
+        assert_eq!(s["4022"], JobState::Running);
+        assert!(s["4023"].is_finished());
+        assert!(!s.contains_key("4024"));
+        for stat in ["UNKWN", "ZOMBI", "NEWSTAT"] {
+            assert_eq!(JobState::from_stat(stat), JobState::Indeterminate);
+            assert!(!JobState::from_stat(stat).is_finished());
+        }
+        assert_eq!(
+            parse_not_found("Job <4024> is not found\nLSF is down, please wait\n"),
+            vec!["4024".to_string()]
+        );
+    }
+}
@@ -0,0 +1,644 @@
+//! Memories model arrays such as flash or SRAM blocks as a first-class part of a model, sitting
+//! alongside its registers.
+//! A memory is a set of words of a given width, starting at a base address (relative to the
+//! model's address) and with a backing data store which can be initialized from a memory image.
+//!
+//! Words are accessed through a protocol service, e.g. a Simple protocol or an ArmDebug MemAP,
+//! with each access being recorded in the pattern as a MemWrite or MemVerify node wrapping the
+//! service's own transaction nodes, in the same way that register accesses are wrapped in
+//! RegWrite/RegVerify nodes.
+
+use super::Model;
+use crate::generator::PAT;
+use crate::services::Service;
+use crate::utility::mem_image::MemImage;
+use crate::{Dut, Result, Services, Transaction, TEST};
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::collections::BTreeMap;
+use std::sync::{MutexGuard, RwLock};
+
+/// The March C- algorithm, see Memory::march for the syntax
+pub const MARCH_C_MINUS: &str = "any(w0); up(r0,w1); up(r1,w0); down(r0,w1); down(r1,w0); any(r0)";
+
+#[derive(Debug)]
+pub struct Memory {
+    pub id: usize,
+    pub model_id: usize,
+    pub name: String,
+    /// The width of each word in bits
+    pub width: usize,
+    /// The number of words
+    pub depth: usize,
+    /// The address of the first word, relative to the owning model's address
+    pub base_address: u128,
+    /// The name of the default service used to access the memory, this is looked up within the
+    /// services of the owning model and its parents
+    pub service: Option<String>,
+    /// The number of address units occupied by each word
+    word_units: u128,
+    // Backing data store (word index => data), only words which have been given a value are held.
+    // It is wrapped in a RwLock so that it can be updated from an immutable DUT reference.
+    contents: RwLock<BTreeMap<usize, BigUint>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarchOrder {
+    Up,
+    Down,
+    Any,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MarchOp {
+    /// Read (verify) the given background, 0 meaning all bits clear and 1 all bits set
+    Read(u8),
+    /// Write the given background
+    Write(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarchElement {
+    pub order: MarchOrder,
+    pub ops: Vec<MarchOp>,
+}
+
+/// Parses a march algorithm string, see Memory::march for the syntax
+pub fn parse_march(algorithm: &str) -> Result<Vec<MarchElement>> {
+    let mut elements = vec![];
+    for e in algorithm
+        .split(';')
+        .map(|e| e.trim())
+        .filter(|e| !e.is_empty())
+    {
+        let (order, ops) = match (e.find('('), e.ends_with(')')) {
+            (Some(i), true) => (&e[..i], &e[i + 1..e.len() - 1]),
+            _ => bail!(
+                "Malformed march element '{}', expected something like 'up(r0,w1)'",
+                e
+            ),
+        };
+        let order = match order.trim().to_lowercase().as_str() {
+            "up" => MarchOrder::Up,
+            "down" => MarchOrder::Down,
+            "any" | "" => MarchOrder::Any,
+            x => bail!(
+                "Unknown march address order '{}', expected 'up', 'down' or 'any'",
+                x
+            ),
+        };
+        let mut parsed_ops = vec![];
+        for op in ops.split(',').map(|o| o.trim()) {
+            parsed_ops.push(match op.to_lowercase().as_str() {
+                "r0" => MarchOp::Read(0),
+                "r1" => MarchOp::Read(1),
+                "w0" => MarchOp::Write(0),
+                "w1" => MarchOp::Write(1),
+                _ => bail!(
+                    "Unknown march operation '{}' in element '{}', expected one of r0, r1, w0 or w1",
+                    op,
+                    e
+                ),
+            });
+        }
+        elements.push(MarchElement {
+            order,
+            ops: parsed_ops,
+        });
+    }
+    if elements.is_empty() {
+        bail!("The march algorithm '{}' contains no elements", algorithm);
+    }
+    Ok(elements)
+}
+
+impl Memory {
+    /// Returns the address of the first word, fully resolved to include the owning model's address
+    pub fn address(&self, dut: &MutexGuard<Dut>) -> Result<u128> {
+        Ok(dut.get_model(self.model_id)?.address(dut)? + self.base_address)
+    }
+
+    /// Returns the fully resolved address of the word at the given index
+    pub fn word_address(&self, index: usize, dut: &MutexGuard<Dut>) -> Result<u128> {
+        self.check_index(index)?;
+        Ok(self.address(dut)? + index as u128 * self.word_units)
+    }
+
+    /// Returns the value of the word at the given index held by the backing data store, None if
+    /// it has not been given a value
+    pub fn word(&self, index: usize) -> Result<Option<BigUint>> {
+        self.check_index(index)?;
+        Ok(self.contents.read().unwrap().get(&index).cloned())
+    }
+
+    /// Sets the value of the given word in the backing data store, this does not generate any
+    /// pattern activity
+    pub fn set_word(&self, index: usize, data: BigUint) -> Result<()> {
+        self.check_index(index)?;
+        Transaction::check_size(&data, self.width)?;
+        self.contents.write().unwrap().insert(index, data);
+        Ok(())
+    }
+
+    /// Returns the indexes of all words which hold a value in the backing data store, in order
+    pub fn defined_words(&self) -> Vec<usize> {
+        self.contents.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Clears the backing data store
+    pub fn clear(&self) {
+        self.contents.write().unwrap().clear();
+    }
+
+    /// Loads the given image into the backing data store, the image's addresses are taken to be
+    /// fully resolved byte addresses and data is assembled into words in little-endian byte order.
+    /// Any bytes within a word which are not defined by the image retain their current value (or 0).
+    /// An error will be returned if the image contains data which falls outside of the memory.
+    /// Returns the number of words which were updated.
+    pub fn load_image(&self, image: &MemImage, dut: &MutexGuard<Dut>) -> Result<usize> {
+        let aub = dut.get_model(self.model_id)?.address_unit_bits;
+        if aub != 8 {
+            bail!(
+                "Memory images can only be loaded into byte-addressed memories, '{}' is within a block with an address unit of {} bits",
+                self.name,
+                aub
+            );
+        }
+        let start = self.address(dut)?;
+        let end = start + self.depth as u128 * self.word_units;
+        let mut words: BTreeMap<usize, Vec<Option<u8>>> = BTreeMap::new();
+        for (addr, byte) in image.bytes() {
+            let addr = addr as u128;
+            if addr < start || addr >= end {
+                bail!(
+                    "Memory image data at address 0x{:X} is outside of memory '{}' (0x{:X} - 0x{:X})",
+                    addr,
+                    self.name,
+                    start,
+                    end - 1
+                );
+            }
+            let index = ((addr - start) / self.word_units) as usize;
+            let offset = ((addr - start) % self.word_units) as usize;
+            words
+                .entry(index)
+                .or_insert_with(|| vec![None; self.word_units as usize])[offset] = Some(byte);
+        }
+        let mut contents = self.contents.write().unwrap();
+        for (index, bytes) in &words {
+            let mut current = match contents.get(index) {
+                Some(d) => d.to_bytes_le(),
+                None => vec![],
+            };
+            current.resize(bytes.len(), 0);
+            for (i, b) in bytes.iter().enumerate() {
+                if let Some(b) = b {
+                    current[i] = *b;
+                }
+            }
+            contents.insert(*index, BigUint::from_bytes_le(&current));
+        }
+        Ok(words.len())
+    }
+
+    /// Resolves the ID of the service to be used to access the memory. If a service name is not
+    /// given then the memory's default service is used. The service is looked up within the
+    /// owning model and then its parents.
+    pub fn service_id(&self, name: Option<&str>, dut: &MutexGuard<Dut>) -> Result<usize> {
+        let name = match name.or(self.service.as_deref()) {
+            Some(n) => n,
+            None => bail!(
+                "No service was given to access memory '{}' and it has no default service",
+                self.name
+            ),
+        };
+        let mut model = dut.get_model(self.model_id)?;
+        loop {
+            if let Some(id) = model.services.get(name) {
+                return Ok(*id);
+            }
+            match model.parent_id {
+                Some(id) => model = dut.get_model(id)?,
+                None => bail!(
+                    "No service named '{}' is available to memory '{}'",
+                    name,
+                    self.name
+                ),
+            }
+        }
+    }
+
+    /// Writes the given word through the given service. If data is given then the backing data
+    /// store is updated first, otherwise the word's current value is written.
+    pub fn write_word(
+        &self,
+        index: usize,
+        data: Option<BigUint>,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        if let Some(d) = data {
+            self.set_word(index, d)?;
+        }
+        let data = self.defined_word(index)?;
+        let t = Transaction::new_write_with_addr(data, self.width, self.word_address(index, dut)?)?;
+        self.access(t, service_id, dut, services)
+    }
+
+    /// Verifies the given word through the given service, expecting the given data or, if not
+    /// given, the word's current value
+    pub fn verify_word(
+        &self,
+        index: usize,
+        data: Option<BigUint>,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        let data = match data {
+            Some(d) => d,
+            None => self.defined_word(index)?,
+        };
+        let mut t = Transaction::new_verify(data, self.width)?;
+        t.address = Some(BigUint::from(self.word_address(index, dut)?));
+        self.access(t, service_id, dut, services)
+    }
+
+    /// Writes the given data to consecutive words starting from the given index
+    pub fn write_words(
+        &self,
+        start: usize,
+        data: &[BigUint],
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        for (i, d) in data.iter().enumerate() {
+            self.write_word(start + i, Some(d.clone()), service_id, dut, services)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies the given number of consecutive words starting from the given index against
+    /// their current values
+    pub fn verify_words(
+        &self,
+        start: usize,
+        count: usize,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        for i in start..start + count {
+            self.verify_word(i, None, service_id, dut, services)?;
+        }
+        Ok(())
+    }
+
+    /// Writes all words which currently hold a value, e.g. to program a loaded image
+    pub fn write_contents(
+        &self,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        for i in self.defined_words() {
+            self.write_word(i, None, service_id, dut, services)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies all words which currently hold a value
+    pub fn verify_contents(
+        &self,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        for i in self.defined_words() {
+            self.verify_word(i, None, service_id, dut, services)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the checkerboard data for the given word, alternating 0x55.. and 0xAA.. from word
+    /// to word, starting with 0x55.. unless inverse is set
+    pub fn checkerboard_word(&self, index: usize, inverse: bool) -> BigUint {
+        let mut bits = BigUint::zero();
+        for i in (0..self.width).step_by(2) {
+            bits.set_bit(i as u64, true);
+        }
+        if (index % 2 == 1) != inverse {
+            bits ^= self.background(1);
+        }
+        bits
+    }
+
+    /// Writes a checkerboard pattern to the given range of words (the whole memory by default)
+    /// and then reads it back
+    pub fn checkerboard(
+        &self,
+        start: usize,
+        count: Option<usize>,
+        inverse: bool,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        let range = self.range(start, count)?;
+        TEST.push(node!(
+            PAT::Comment,
+            0,
+            self.comment(
+                if inverse {
+                    "Inverse checkerboard"
+                } else {
+                    "Checkerboard"
+                },
+                &range
+            )
+        ));
+        for i in range.clone() {
+            let d = self.checkerboard_word(i, inverse);
+            self.write_word(i, Some(d), service_id, dut, services)?;
+        }
+        for i in range {
+            self.verify_word(i, None, service_id, dut, services)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a march algorithm to the given range of words (the whole memory by default).
+    /// The algorithm is given as a series of elements separated by ';', each element is an
+    /// address order (up, down or any) followed by a list of operations to be applied to each
+    /// word in turn, e.g. "up(r0,w1)".
+    /// Operations are r0/r1 to read (verify) all zeros/ones and w0/w1 to write all zeros/ones.
+    /// See MARCH_C_MINUS for an example.
+    pub fn march(
+        &self,
+        algorithm: &str,
+        start: usize,
+        count: Option<usize>,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        let elements = parse_march(algorithm)?;
+        let range = self.range(start, count)?;
+        TEST.push(node!(
+            PAT::Comment,
+            0,
+            self.comment(&format!("March '{}'", algorithm), &range)
+        ));
+        for e in &elements {
+            let indexes: Vec<usize> = match e.order {
+                MarchOrder::Down => range.clone().rev().collect(),
+                _ => range.clone().collect(),
+            };
+            for i in indexes {
+                for op in &e.ops {
+                    match op {
+                        MarchOp::Read(b) => self.verify_word(
+                            i,
+                            Some(self.background(*b)),
+                            service_id,
+                            dut,
+                            services,
+                        )?,
+                        MarchOp::Write(b) => self.write_word(
+                            i,
+                            Some(self.background(*b)),
+                            service_id,
+                            dut,
+                            services,
+                        )?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Wraps the given transaction in a MemWrite/MemVerify node and passes it to the service
+    fn access(
+        &self,
+        t: Transaction,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<()> {
+        let write = t.action == Some(crate::TransactionAction::Write);
+        let n_id = if write {
+            TEST.push_and_open(node!(PAT::MemWrite, self.id, t.clone()))
+        } else {
+            TEST.push_and_open(node!(PAT::MemVerify, self.id, t.clone()))
+        };
+        match services.get_service(service_id)? {
+            Service::Simple(s) => {
+                if write {
+                    s.write(dut, t)?;
+                } else {
+                    s.verify(dut, t)?;
+                }
+            }
+            Service::ArmDebugMemAP(s) => {
+                if self.width > 32 {
+                    bail!(
+                        "Memory '{}' has {}-bit words, accesses through a MemAP are limited to 32 bits",
+                        self.name,
+                        self.width
+                    );
+                }
+                if write {
+                    s.write(dut, services, &t)?;
+                } else {
+                    s.verify(dut, services, &t)?;
+                }
+            }
+            s => bail!(
+                "Memory '{}' cannot be accessed through service {:?}, a Simple or ArmDebug MemAP service is required",
+                self.name,
+                s
+            ),
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn defined_word(&self, index: usize) -> Result<BigUint> {
+        match self.word(index)? {
+            Some(d) => Ok(d),
+            None => bail!(
+                "Word {} of memory '{}' has not been given a value",
+                index,
+                self.name
+            ),
+        }
+    }
+
+    fn background(&self, bit: u8) -> BigUint {
+        if bit == 0 {
+            BigUint::zero()
+        } else {
+            (BigUint::from(1u8) << self.width) - 1u8
+        }
+    }
+
+    fn range(&self, start: usize, count: Option<usize>) -> Result<std::ops::Range<usize>> {
+        let end = match count {
+            Some(c) => start + c,
+            None => self.depth,
+        };
+        if start >= end || end > self.depth {
+            bail!(
+                "Invalid word range {}..{} for memory '{}' which has {} words",
+                start,
+                end,
+                self.name,
+                self.depth
+            );
+        }
+        Ok(start..end)
+    }
+
+    fn comment(&self, title: &str, range: &std::ops::Range<usize>) -> String {
+        format!(
+            "{} of memory '{}', words {} to {}",
+            title,
+            self.name,
+            range.start,
+            range.end - 1
+        )
+    }
+
+    fn check_index(&self, index: usize) -> Result<()> {
+        if index >= self.depth {
+            bail!(
+                "Word {} is out of range for memory '{}' which has {} words",
+                index,
+                self.name,
+                self.depth
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Model {
+    pub fn get_memory_id(&self, name: &str) -> Option<usize> {
+        self.memories.get(name).copied()
+    }
+}
+
+impl Dut {
+    pub fn create_memory(
+        &mut self,
+        model_id: usize,
+        name: &str,
+        width: usize,
+        depth: usize,
+        base_address: Option<u128>,
+        service: Option<&str>,
+    ) -> Result<usize> {
+        let id = self.memories.len();
+        let model = self.get_mut_model(model_id)?;
+        if model.memories.contains_key(name) {
+            return crate::duplicate_error!("memory", model.name, name);
+        }
+        if width == 0 || depth == 0 {
+            bail!(
+                "Memory '{}' must have a non-zero width and depth (given {} bits x {} words)",
+                name,
+                width,
+                depth
+            );
+        }
+        let aub = model.address_unit_bits as usize;
+        if !width.is_multiple_of(aub) {
+            bail!(
+                "The width of memory '{}' ({} bits) must be a multiple of its block's address unit ({} bits)",
+                name,
+                width,
+                aub
+            );
+        }
+        model.memories.insert(name.to_string(), id);
+        self.memories.push(Memory {
+            id,
+            model_id,
+            name: name.to_string(),
+            width,
+            depth,
+            base_address: base_address.unwrap_or(0),
+            service: service.map(|s| s.to_string()),
+            word_units: (width / aub) as u128,
+            contents: RwLock::new(BTreeMap::new()),
+        });
+        Ok(id)
+    }
+
+    pub fn get_memory(&self, id: usize) -> Result<&Memory> {
+        match self.memories.get(id) {
+            Some(x) => Ok(x),
+            None => bail!(
+                "Something has gone wrong, no memory exists with ID '{}'",
+                id
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn loads_images_and_generates_data() {
+        let mut dut = Dut::new("memory_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let nvm = dut.create_model(Some(top), "nvm", Some(0x1000)).unwrap();
+        let id = dut
+            .create_memory(nvm, "flash", 32, 16, Some(0x100), None)
+            .unwrap();
+        assert!(dut.create_memory(nvm, "flash", 32, 16, None, None).is_err());
+        assert!(dut.create_memory(nvm, "odd", 12, 16, None, None).is_err());
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let mem = dut.get_memory(id).unwrap();
+        assert_eq!(mem.word_address(2, &dut).unwrap(), 0x1108);
+        assert!(mem.word_address(16, &dut).is_err());
+        assert!(mem.service_id(None, &dut).is_err());
+
+        let img = MemImage::from_binary(&[0x11, 0x22, 0x33, 0x44, 0x55], 0x1104);
+        assert_eq!(mem.load_image(&img, &dut).unwrap(), 2);
+        assert_eq!(mem.word(1).unwrap(), Some(BigUint::from(0x4433_2211u32)));
+        assert_eq!(mem.word(2).unwrap(), Some(BigUint::from(0x55u32)));
+        assert_eq!(mem.word(0).unwrap(), None);
+        assert!(mem
+            .load_image(&MemImage::from_binary(&[0], 0x1000), &dut)
+            .is_err());
+
+        assert_eq!(
+            mem.checkerboard_word(0, false),
+            BigUint::from(0x5555_5555u32)
+        );
+        assert_eq!(
+            mem.checkerboard_word(1, false),
+            BigUint::from(0xAAAA_AAAAu32)
+        );
+        assert_eq!(
+            mem.checkerboard_word(0, true),
+            BigUint::from(0xAAAA_AAAAu32)
+        );
+
+        let m = parse_march(MARCH_C_MINUS).unwrap();
+        assert_eq!(m.len(), 6);
+        assert_eq!(m[3].order, MarchOrder::Down);
+        assert_eq!(m[3].ops, vec![MarchOp::Read(0), MarchOp::Write(1)]);
+        assert!(parse_march("up(r2)").is_err());
+        assert!(parse_march("sideways(r0)").is_err());
+    }
+}
@@ -1,5 +1,7 @@
+pub mod memories;
 pub mod pins;
 pub mod registers;
+pub mod reset_domains;
 pub mod timesets;
 use crate::Dut;
 use crate::Result;
@@ -36,6 +38,10 @@ pub struct Model {
     /// This defaults to 0 but can be overridden by instantiation.
     pub offset: u128,
     pub services: IndexMap<String, usize>,
+    /// Named reset domains defined by this model, see the reset_domains module
+    pub reset_domains: IndexMap<String, reset_domains::ResetDomain>,
+    /// Memories (arrays) owned by this model, see the memories module
+    pub memories: IndexMap<String, usize>,
 }
 
 impl Model {
@@ -56,6 +62,8 @@ impl Model {
                 None => 0,
             },
             services: IndexMap::new(),
+            reset_domains: IndexMap::new(),
+            memories: IndexMap::new(),
         }
     }
 
@@ -10,6 +10,10 @@ pub mod macro_api;
 pub mod memory_map;
 pub mod register;
 pub mod register_file;
+pub mod rst;
+pub mod uvm_ral;
+pub mod validation;
+mod walk;
 
 pub use address_block::AddressBlock;
 pub use bit::Bit;
@@ -116,6 +120,44 @@ impl AccessType {
     pub fn is_unimplemented(&self) -> bool {
         *self == AccessType::Unimplemented
     }
+
+    /// Returns the equivalent UVM field access policy, None is returned for unimplemented bits
+    /// which have no UVM equivalent.
+    /// Most access types map directly, the remainder are mapped to the closest UVM policy.
+    pub fn to_uvm(&self) -> Option<&'static str> {
+        match self {
+            AccessType::RO => Some("RO"),
+            AccessType::RW => Some("RW"),
+            AccessType::RC => Some("RC"),
+            AccessType::RS => Some("RS"),
+            AccessType::WRC => Some("WRC"),
+            AccessType::WRS => Some("WRS"),
+            AccessType::WC => Some("WC"),
+            AccessType::WS => Some("WS"),
+            AccessType::WSRC => Some("WSRC"),
+            AccessType::WCRS => Some("WCRS"),
+            AccessType::W1C => Some("W1C"),
+            AccessType::W1S => Some("W1S"),
+            AccessType::W1T => Some("W1T"),
+            AccessType::W0C => Some("W0C"),
+            AccessType::W0S => Some("W0S"),
+            AccessType::W0T => Some("W0T"),
+            AccessType::W1SRC => Some("W1SRC"),
+            AccessType::W1CRS => Some("W1CRS"),
+            AccessType::W0SRC => Some("W0SRC"),
+            AccessType::W0CRS => Some("W0CRS"),
+            AccessType::WO => Some("WO"),
+            AccessType::WOC => Some("WOC"),
+            AccessType::WOS => Some("WOS"),
+            AccessType::W1 => Some("W1"),
+            AccessType::WO1 => Some("WO1"),
+            // No direct UVM equivalents for these, use the closest match
+            AccessType::DC => Some("RW"),
+            AccessType::ROWZ => Some("RC"),
+            AccessType::WORZ => Some("WO"),
+            AccessType::Unimplemented => None,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -13,6 +13,8 @@ pub struct MemoryMap {
     /// consecutive addressable units in the memory map.
     /// Its value defaults to 8 indicating a byte addressable memory map.
     pub address_unit_bits: u32,
+    /// The number of addressable units in the memory map, 0 means that it is unbounded
+    pub range: u64,
     pub address_blocks: IndexMap<String, usize>,
 }
 
@@ -23,6 +25,7 @@ impl Default for MemoryMap {
             model_id: 0,
             name: "default".to_string(),
             address_unit_bits: 8,
+            range: 0,
             address_blocks: IndexMap::new(),
         }
     }
@@ -0,0 +1,184 @@
+//! Documents the register model of a block and its sub-blocks as a reStructuredText page,
+//! this is used to add register pages to the app's website (see 'origen web build').
+//!
+//! Each block becomes a section containing a sub-section per register, with the register's
+//! address, reset value and description, followed by a table of its fields in MSB to LSB order.
+
+use super::walk::{models_top_down, registers};
+use super::{Field, Register};
+use crate::Dut;
+use crate::Result;
+use num_bigint::BigUint;
+use std::sync::MutexGuard;
+
+/// Returns a reStructuredText page documenting the registers of the given model (block) and all
+/// of its sub-blocks.
+/// The page is marked as orphan so that Sphinx does not warn if it is not referenced by any
+/// toctree.
+pub fn generate(dut: &MutexGuard<Dut>, model_id: usize, title: Option<&str>) -> Result<String> {
+    let model = dut.get_model(model_id)?;
+    let mut lines: Vec<String> = vec![":orphan:".to_string(), "".to_string()];
+    lines.append(&mut heading(title.unwrap_or("Registers"), '='));
+    lines.push(format!(
+        ".. This page was generated by Origen from the register model of '{}'",
+        model.friendly_path(dut)?
+    ));
+    for id in models_top_down(dut, model_id)? {
+        let m = dut.get_model(id)?;
+        let regs = registers(dut, m)?;
+        if regs.is_empty() {
+            continue;
+        }
+        lines.push("".to_string());
+        lines.append(&mut heading(&m.friendly_path(dut)?, '-'));
+        for reg in regs {
+            lines.push("".to_string());
+            lines.append(&mut reg_section(dut, reg)?);
+        }
+    }
+    Ok(lines.join("\n") + "\n")
+}
+
+fn heading(title: &str, underline: char) -> Vec<String> {
+    vec![
+        escape(title),
+        underline.to_string().repeat(escape(title).chars().count()),
+    ]
+}
+
+/// Escapes characters which have a meaning in inline RST markup
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('*', "\\*")
+        .replace('`', "\\`")
+        .replace('_', "\\_")
+        .replace('|', "\\|")
+}
+
+fn hex(value: &BigUint) -> String {
+    format!("0x{:X}", value)
+}
+
+fn hard_reset(field: &Field) -> Option<BigUint> {
+    field.resets.get("hard").map(|r| match &r.mask {
+        Some(m) => &r.value & m,
+        None => r.value.clone(),
+    })
+}
+
+/// Returns the register's reset value, this is only known if every implemented field has one
+fn reg_reset(reg: &Register) -> Option<BigUint> {
+    let mut value = BigUint::from(0u8);
+    for f in reg.fields.values().filter(|f| !f.access.is_unimplemented()) {
+        value += hard_reset(f)? << f.offset;
+    }
+    Some(value)
+}
+
+fn bits(field: &Field) -> String {
+    if field.width == 1 {
+        format!("[{}]", field.offset)
+    } else {
+        format!("[{}:{}]", field.offset + field.width - 1, field.offset)
+    }
+}
+
+fn reg_section(dut: &MutexGuard<Dut>, reg: &Register) -> Result<Vec<String>> {
+    let mut lines = heading(&reg.name, '^');
+    lines.push("".to_string());
+    lines.push(format!(":Address: 0x{:X}", reg.address(dut, None)?));
+    lines.push(format!(":Size: {} bits", reg.size));
+    if let Some(r) = reg_reset(reg) {
+        lines.push(format!(":Reset: {}", hex(&r)));
+    }
+    if let Some(d) = &reg.description {
+        lines.push("".to_string());
+        for l in d.lines() {
+            lines.push(l.trim().to_string());
+        }
+    }
+    lines.push("".to_string());
+    lines.push(".. list-table::".to_string());
+    lines.push("   :header-rows: 1".to_string());
+    lines.push("".to_string());
+    for (i, h) in ["Bits", "Field", "Access", "Reset", "Description"]
+        .iter()
+        .enumerate()
+    {
+        lines.push(format!("   {} {}", if i == 0 { "* -" } else { "  -" }, h));
+    }
+    let mut fields: Vec<&Field> = reg.fields.values().collect();
+    fields.sort_by_key(|f| std::cmp::Reverse(f.offset));
+    for f in fields {
+        let cells = [
+            bits(f),
+            escape(&f.name),
+            f.access.to_string(),
+            hard_reset(f).map_or("".to_string(), |r| hex(&r)),
+            f.description.as_ref().map_or("".to_string(), |d| {
+                escape(&d.split_whitespace().collect::<Vec<&str>>().join(" "))
+            }),
+        ];
+        for (i, c) in cells.iter().enumerate() {
+            let marker = if i == 0 { "* -" } else { "  -" };
+            if c.is_empty() {
+                lines.push(format!("   {}", marker));
+            } else {
+                lines.push(format!("   {} {}", marker, c));
+            }
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::model::registers::register::{FieldContainer, Register, ResetVal};
+    use crate::Dut;
+    use std::sync::Mutex;
+
+    #[test]
+    fn documents_registers_and_fields() {
+        let mut dut = Dut::new("rst_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let core = dut.create_model(Some(top), "core0", Some(0x1000)).unwrap();
+        let map = dut.create_memory_map(core, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, None, None, None)
+            .unwrap();
+        let fields = vec![
+            FieldContainer::internal_new("en", 0, 1, "rw", vec![], None, ""),
+            FieldContainer::internal_new("status", 4, 4, "ro", vec![], None, ""),
+        ];
+        Register::add_reg(
+            &mut dut,
+            ab,
+            None,
+            "ctrl_reg",
+            0x10,
+            Some(32),
+            "LSB0",
+            None,
+            None,
+            None,
+            None,
+            Some(vec![ResetVal::new("hard", 0x31, None)]),
+            fields,
+        )
+        .unwrap();
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let rst = super::generate(&dut, top, None).unwrap();
+
+        assert!(rst.starts_with(":orphan:\n\nRegisters\n=========\n"));
+        assert!(rst.contains("\ndut.core0\n---------\n"));
+        assert!(rst.contains(
+            "\nctrl\\_reg\n^^^^^^^^^\n\n:Address: 0x1010\n:Size: 32 bits\n:Reset: 0x31\n"
+        ));
+        // Fields are listed MSB first
+        assert!(rst.contains(
+            "   * - [7:4]\n     - status\n     - RO\n     - 0x3\n     -\n   * - [0]\n     - en"
+        ));
+    }
+}
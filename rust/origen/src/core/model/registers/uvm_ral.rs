@@ -0,0 +1,346 @@
+//! Exports the register model of a block and its sub-blocks as a UVM register abstraction layer
+//! (RAL) model, i.e. a SystemVerilog package of uvm_reg and uvm_reg_block classes.
+//!
+//! Each model becomes a uvm_reg_block, each memory map becomes a uvm_reg_map within it (the
+//! 'default' memory map being the block's default_map), and sub-blocks are added as sub-maps
+//! of their parent's default map at their instantiation offset.
+
+use super::walk::{models_depth_first, registers};
+use super::{AccessType, Field, Register};
+use crate::core::model::Model;
+use crate::Dut;
+use crate::Result;
+use num_bigint::BigUint;
+use std::sync::MutexGuard;
+
+/// Returns a SystemVerilog package containing the UVM RAL model of the given model (block)
+/// and all of its sub-blocks.
+/// The package name defaults to "<model_name>_reg_pkg".
+pub fn generate(dut: &MutexGuard<Dut>, model_id: usize, package: Option<&str>) -> Result<String> {
+    let model = dut.get_model(model_id)?;
+    let pkg = match package {
+        Some(p) => p.to_string(),
+        None => format!("{}_reg_pkg", model.name),
+    };
+    let mut lines: Vec<String> = vec![];
+    lines.push(format!(
+        "// UVM register model generated by Origen from '{}'",
+        model.friendly_path(dut)?
+    ));
+    lines.push(format!("package {};", pkg));
+    lines.push("  import uvm_pkg::*;".to_string());
+    lines.push("  `include \"uvm_macros.svh\"".to_string());
+    // Sub-block classes must be declared before the parent blocks which instantiate them
+    for id in models_depth_first(dut, model_id)? {
+        let m = dut.get_model(id)?;
+        for reg in registers(dut, m)? {
+            lines.push("".to_string());
+            lines.append(&mut reg_class(dut, reg)?);
+        }
+        lines.push("".to_string());
+        lines.append(&mut block_class(dut, m, id == model_id)?);
+    }
+    lines.push("".to_string());
+    lines.push(format!("endpackage : {}", pkg));
+    Ok(lines.join("\n") + "\n")
+}
+
+/// Converts a path like "dut.core0.my_reg" into a legal SV identifier like "dut_core0_my_reg"
+fn sv_name(path: &str) -> String {
+    path.replace(".", "_")
+}
+
+fn block_class_name(dut: &MutexGuard<Dut>, model: &Model) -> Result<String> {
+    Ok(format!("{}_block", sv_name(&model.friendly_path(dut)?)))
+}
+
+fn reg_class_name(dut: &MutexGuard<Dut>, reg: &Register) -> Result<String> {
+    Ok(sv_name(&reg.friendly_path(dut)?))
+}
+
+/// The name of the reg's handle within its block, this is the reg name qualified by any
+/// non-default memory map, address block or register file that it lives in
+fn reg_instance_name(dut: &MutexGuard<Dut>, reg: &Register) -> Result<String> {
+    let model_path = reg.model(dut)?.friendly_path(dut)?;
+    let path = reg.friendly_path(dut)?;
+    Ok(sv_name(&path[model_path.len() + 1..]))
+}
+
+fn map_instance_name(name: &str) -> String {
+    if name == "default" {
+        "default_map".to_string()
+    } else {
+        format!("{}_map", name)
+    }
+}
+
+/// Formats the given value as a sized SV hex literal
+fn sv_hex(value: &BigUint, width: usize) -> String {
+    format!("{}'h{:X}", width, value)
+}
+
+/// Origen's default reset is called "hard", which also happens to be UVM's default reset kind
+fn reset_kind(name: &str) -> String {
+    name.to_uppercase()
+}
+
+fn reset_value(field: &Field, name: &str) -> Option<BigUint> {
+    match field.resets.get(name) {
+        Some(r) => match &r.mask {
+            Some(m) => Some(&r.value & m),
+            None => Some(r.value.clone()),
+        },
+        None => None,
+    }
+}
+
+fn reg_class(dut: &MutexGuard<Dut>, reg: &Register) -> Result<Vec<String>> {
+    let name = reg_class_name(dut, reg)?;
+    let fields: Vec<(&String, &Field)> = reg
+        .fields
+        .iter()
+        .filter(|(_, f)| f.access.to_uvm().is_some())
+        .collect();
+    let mut lines: Vec<String> = vec![];
+    if let Some(d) = &reg.description {
+        for l in d.lines() {
+            lines.push(format!("  // {}", l));
+        }
+    }
+    lines.push(format!("  class {} extends uvm_reg;", name));
+    lines.push(format!("    `uvm_object_utils({})", name));
+    lines.push("".to_string());
+    for (fname, _) in &fields {
+        lines.push(format!("    rand uvm_reg_field {};", fname));
+    }
+    lines.push("".to_string());
+    lines.push(format!("    function new(string name = \"{}\");", name));
+    lines.push(format!(
+        "      super.new(name, {}, UVM_NO_COVERAGE);",
+        reg.size
+    ));
+    lines.push("    endfunction".to_string());
+    lines.push("".to_string());
+    lines.push("    virtual function void build();".to_string());
+    for (fname, field) in &fields {
+        let hard = reset_value(field, "hard");
+        lines.push(format!(
+            "      {} = uvm_reg_field::type_id::create(\"{}\");",
+            fname, fname
+        ));
+        lines.push(format!(
+            "      {}.configure(this, {}, {}, \"{}\", {}, {}, {}, {}, 0);",
+            fname,
+            field.width,
+            field.offset,
+            field.access.to_uvm().unwrap(),
+            match field.access {
+                AccessType::RC | AccessType::RS | AccessType::ROWZ => 1,
+                _ => 0,
+            },
+            sv_hex(hard.as_ref().unwrap_or(&BigUint::from(0u8)), field.width),
+            if hard.is_some() { 1 } else { 0 },
+            if field.access.is_writeable() { 1 } else { 0 },
+        ));
+        for (rname, _) in &field.resets {
+            if rname != "hard" {
+                lines.push(format!(
+                    "      {}.set_reset({}, \"{}\");",
+                    fname,
+                    sv_hex(&reset_value(field, rname).unwrap(), field.width),
+                    reset_kind(rname)
+                ));
+            }
+        }
+        if field.access == AccessType::DC {
+            lines.push(format!("      {}.set_compare(UVM_NO_CHECK);", fname));
+        }
+    }
+    lines.push("    endfunction".to_string());
+    lines.push(format!("  endclass : {}", name));
+    Ok(lines)
+}
+
+fn block_class(dut: &MutexGuard<Dut>, model: &Model, top: bool) -> Result<Vec<String>> {
+    let name = block_class_name(dut, model)?;
+    let regs = registers(dut, model)?;
+    let mut lines: Vec<String> = vec![];
+    lines.push(format!("  class {} extends uvm_reg_block;", name));
+    lines.push(format!("    `uvm_object_utils({})", name));
+    lines.push("".to_string());
+    for reg in &regs {
+        lines.push(format!(
+            "    rand {} {};",
+            reg_class_name(dut, reg)?,
+            reg_instance_name(dut, reg)?
+        ));
+    }
+    for (sb_name, sb_id) in &model.sub_blocks {
+        let sb = dut.get_model(*sb_id)?;
+        lines.push(format!(
+            "    rand {} {};",
+            block_class_name(dut, sb)?,
+            sb_name
+        ));
+    }
+    for (map_name, _) in &model.memory_maps {
+        if map_name != "default" {
+            lines.push(format!("    uvm_reg_map {};", map_instance_name(map_name)));
+        }
+    }
+    lines.push("".to_string());
+    lines.push(format!("    function new(string name = \"{}\");", name));
+    lines.push("      super.new(name, UVM_NO_COVERAGE);".to_string());
+    lines.push("    endfunction".to_string());
+    lines.push("".to_string());
+    lines.push("    virtual function void build();".to_string());
+
+    // A default map is always created, even if the block has no registers of its own, since
+    // it is needed to place the sub-blocks in the address space
+    if !model.memory_maps.contains_key("default") {
+        lines.push(format!(
+            "      default_map = create_map(\"default_map\", 'h0, {}, UVM_LITTLE_ENDIAN, {});",
+            model.address_unit_bits * 4 / 8,
+            if model.address_unit_bits == 8 { 1 } else { 0 }
+        ));
+    }
+    for (map_name, map_id) in &model.memory_maps {
+        let map = dut.get_memory_map(*map_id)?;
+        let mut width = (map.address_unit_bits * 4) as u64;
+        for (_, ab_id) in &map.address_blocks {
+            if let Some(w) = dut.get_address_block(*ab_id)?.width {
+                if w > width {
+                    width = w;
+                }
+            }
+        }
+        lines.push(format!(
+            "      {} = create_map(\"{}\", 'h0, {}, UVM_LITTLE_ENDIAN, {});",
+            map_instance_name(map_name),
+            map_instance_name(map_name),
+            width.div_ceil(8),
+            if map.address_unit_bits == 8 { 1 } else { 0 }
+        ));
+    }
+    let model_bit_address = model.bit_address(dut)?;
+    for reg in &regs {
+        let inst = reg_instance_name(dut, reg)?;
+        let map = reg.address_block(dut)?.memory_map(dut)?;
+        let offset = (reg.bit_address(dut)? - model_bit_address) / map.address_unit_bits as u128;
+        lines.push("".to_string());
+        lines.push(format!(
+            "      {} = {}::type_id::create(\"{}\");",
+            inst,
+            reg_class_name(dut, reg)?,
+            inst
+        ));
+        lines.push(format!("      {}.configure(this, null, \"\");", inst));
+        lines.push(format!("      {}.build();", inst));
+        lines.push(format!(
+            "      {}.add_reg({}, 'h{:X}, \"{}\");",
+            map_instance_name(&map.name),
+            inst,
+            offset,
+            reg_rights(reg)
+        ));
+    }
+    for (sb_name, sb_id) in &model.sub_blocks {
+        let sb = dut.get_model(*sb_id)?;
+        lines.push("".to_string());
+        lines.push(format!(
+            "      {} = {}::type_id::create(\"{}\");",
+            sb_name,
+            block_class_name(dut, sb)?,
+            sb_name
+        ));
+        lines.push(format!("      {}.configure(this, \"\");", sb_name));
+        lines.push(format!("      {}.build();", sb_name));
+        lines.push(format!(
+            "      default_map.add_submap({}.default_map, 'h{:X});",
+            sb_name, sb.offset
+        ));
+    }
+    if top {
+        lines.push("".to_string());
+        lines.push("      lock_model();".to_string());
+    }
+    lines.push("    endfunction".to_string());
+    lines.push(format!("  endclass : {}", name));
+    Ok(lines)
+}
+
+/// Returns the access rights of the register within its map as a whole
+fn reg_rights(reg: &Register) -> &'static str {
+    let fields = reg.fields.values().filter(|f| !f.access.is_unimplemented());
+    let (mut readable, mut writeable) = (false, false);
+    for f in fields {
+        readable |= f.access.is_readable() && f.access != AccessType::WORZ;
+        writeable |= f.access.is_writeable();
+    }
+    match (readable, writeable) {
+        (true, false) => "RO",
+        (false, true) => "WO",
+        _ => "RW",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::model::registers::register::{FieldContainer, Register, ResetVal};
+    use crate::Dut;
+    use std::sync::Mutex;
+
+    #[test]
+    fn generates_reg_and_block_classes() {
+        let mut dut = Dut::new("uvm_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let core = dut.create_model(Some(top), "core0", Some(0x1000)).unwrap();
+        let map = dut.create_memory_map(core, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, None, None, None)
+            .unwrap();
+        let fields = vec![
+            FieldContainer::internal_new("en", 0, 1, "rw", vec![], None, ""),
+            FieldContainer::internal_new("status", 4, 4, "ro", vec![], None, ""),
+            FieldContainer::internal_new("flag", 8, 1, "w1c", vec![], None, ""),
+        ];
+        Register::add_reg(
+            &mut dut,
+            ab,
+            None,
+            "ctrl",
+            0x10,
+            Some(32),
+            "LSB0",
+            None,
+            None,
+            None,
+            None,
+            Some(vec![
+                ResetVal::new("hard", 0x31, None),
+                ResetVal::new("warm", 0x1, None),
+            ]),
+            fields,
+        )
+        .unwrap();
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let sv = super::generate(&dut, top, None).unwrap();
+
+        assert!(sv.starts_with(
+            "// UVM register model generated by Origen from 'dut'\npackage dut_reg_pkg;"
+        ));
+        assert!(sv.contains("  class dut_core0_ctrl extends uvm_reg;"));
+        assert!(sv.contains("      en.configure(this, 1, 0, \"RW\", 0, 1'h1, 1, 1, 0);"));
+        assert!(sv.contains("      status.configure(this, 4, 4, \"RO\", 0, 4'h3, 1, 0, 0);"));
+        assert!(sv.contains("      flag.configure(this, 1, 8, \"W1C\", 0, 1'h0, 1, 1, 0);"));
+        assert!(sv.contains("      en.set_reset(1'h1, \"WARM\");"));
+        assert!(sv.contains("      default_map.add_reg(ctrl, 'h10, \"RW\");"));
+        assert!(sv.contains("    rand dut_core0_block core0;"));
+        assert!(sv.contains("      default_map.add_submap(core0.default_map, 'h1000);"));
+        // Sub-blocks must be declared before their parent
+        assert!(sv.find("class dut_core0_block").unwrap() < sv.find("class dut_block").unwrap());
+        assert_eq!(sv.matches("lock_model();").count(), 1);
+    }
+}
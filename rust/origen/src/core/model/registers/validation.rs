@@ -0,0 +1,383 @@
+//! A consistency checker for the register model, the model itself accepts definitions like
+//! overlapping registers or fields without complaint so this provides a pass over a block
+//! (and its sub-blocks) to report any such problems back to the user.
+//! Address blocks and registers are only checked against the range of their memory map or
+//! address block when it has been given, i.e. when it is non-zero.
+
+use super::{AddressBlock, Register};
+use crate::Dut;
+use crate::Result;
+use std::fmt;
+use std::sync::MutexGuard;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IssueKind {
+    OverlappingRegisters,
+    OverlappingFields,
+    FieldBeyondRegister,
+    ResetTooWide,
+    EnumTooWide,
+    DuplicateRegisterName,
+    RegisterOutsideAddressBlock,
+    AddressBlockOutsideMemoryMap,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub severity: Severity,
+    /// The path to the offending object, e.g. "dut.core0.my_reg"
+    pub path: String,
+    pub message: String,
+    /// The (Python) source file where the offending object was defined, if known
+    pub filename: Option<String>,
+    /// The (Python) source file line number where the offending object was defined, if known
+    pub lineno: Option<usize>,
+}
+
+impl Issue {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Returns the source location of the offending object like "path/to/file.py:12", if known
+    pub fn location(&self) -> Option<String> {
+        match &self.filename {
+            Some(f) => match self.lineno {
+                Some(l) => Some(format!("{}:{}", f, l)),
+                None => Some(f.to_string()),
+            },
+            None => None,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location() {
+            Some(loc) => write!(f, "{}: {} - {}", loc, self.path, self.message),
+            None => write!(f, "{} - {}", self.path, self.message),
+        }
+    }
+}
+
+fn reg_issue(
+    kind: IssueKind,
+    severity: Severity,
+    path: String,
+    reg: &Register,
+    msg: String,
+) -> Issue {
+    Issue {
+        kind,
+        severity,
+        path,
+        message: msg,
+        filename: reg.filename.clone(),
+        lineno: reg.lineno,
+    }
+}
+
+/// Checks the register model of the given model (block) and all of its sub-blocks, returning
+/// all problems found.
+/// An empty vector means that the model is consistent.
+pub fn validate(dut: &MutexGuard<Dut>, model_id: usize) -> Result<Vec<Issue>> {
+    let mut issues: Vec<Issue> = vec![];
+    let model = dut.get_model(model_id)?;
+    // Reg name -> path of the first reg seen with that name, used to flag duplicates
+    let mut reg_names: Vec<(String, String)> = vec![];
+
+    for (_, map_id) in &model.memory_maps {
+        let map = dut.get_memory_map(*map_id)?;
+        for (_, ab_id) in &map.address_blocks {
+            let ab = dut.get_address_block(*ab_id)?;
+            validate_address_block(dut, ab, &mut reg_names, &mut issues)?;
+
+            // A block without a range of its own must still start within the memory map
+            if map.range > 0 && ab.offset + ab.range.max(1) as u128 > map.range as u128 {
+                issues.push(Issue {
+                    kind: IssueKind::AddressBlockOutsideMemoryMap,
+                    severity: Severity::Error,
+                    path: ab.friendly_path(dut)?,
+                    message: format!(
+                        "Address block '{}' (offset {:#X}, range {:#X}) extends beyond the end of memory map '{}' (range {:#X})",
+                        ab.name, ab.offset, ab.range, map.name, map.range
+                    ),
+                    filename: None,
+                    lineno: None,
+                });
+            }
+        }
+    }
+
+    for (_, id) in &model.sub_blocks {
+        issues.append(&mut validate(dut, *id)?);
+    }
+    Ok(issues)
+}
+
+fn validate_address_block(
+    dut: &MutexGuard<Dut>,
+    ab: &AddressBlock,
+    reg_names: &mut Vec<(String, String)>,
+    issues: &mut Vec<Issue>,
+) -> Result<()> {
+    let ab_start = ab.bit_address(dut)?;
+    let aub = ab.address_unit_bits(dut)? as u128;
+    // (start bit address, end bit address, reg)
+    let mut ranges: Vec<(u128, u128, &Register)> = vec![];
+
+    for (_, reg_id) in &ab.registers {
+        let reg = dut.get_register(*reg_id)?;
+        let path = reg.friendly_path(dut)?;
+        validate_fields(reg, &path, issues);
+
+        match reg_names.iter().find(|(n, _)| n == &reg.name) {
+            Some((_, first)) => issues.push(reg_issue(
+                IssueKind::DuplicateRegisterName,
+                Severity::Warning,
+                path.clone(),
+                reg,
+                format!("Register name '{}' is also used by '{}'", reg.name, first),
+            )),
+            None => reg_names.push((reg.name.clone(), path.clone())),
+        }
+
+        let start = reg.bit_address(dut)?;
+        let end = start + reg.size as u128;
+        if ab.range > 0 && end > ab_start + (ab.range as u128 * aub) {
+            issues.push(reg_issue(
+                IssueKind::RegisterOutsideAddressBlock,
+                Severity::Error,
+                path.clone(),
+                reg,
+                format!(
+                    "Register at offset {:#X} extends beyond the end of address block '{}' (range {:#X})",
+                    reg.offset, ab.name, ab.range
+                ),
+            ));
+        }
+        ranges.push((start, end, reg));
+    }
+
+    ranges.sort_by_key(|r| r.0);
+    for i in 1..ranges.len() {
+        // Compare against all earlier regs which may extend over this one, not just the
+        // immediately preceding one
+        for j in 0..i {
+            if ranges[j].1 > ranges[i].0 {
+                let reg = ranges[i].2;
+                issues.push(reg_issue(
+                    IssueKind::OverlappingRegisters,
+                    Severity::Error,
+                    reg.friendly_path(dut)?,
+                    reg,
+                    format!(
+                        "Register at offset {:#X} overlaps register '{}' at offset {:#X}",
+                        reg.offset, ranges[j].2.name, ranges[j].2.offset
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_fields(reg: &Register, reg_path: &str, issues: &mut Vec<Issue>) {
+    let mut fields: Vec<_> = reg.fields.values().collect();
+    fields.sort_by_key(|f| f.offset);
+
+    for (i, field) in fields.iter().enumerate() {
+        let path = format!("{}.{}", reg_path, field.name);
+        let issue = |kind, msg| Issue {
+            kind,
+            severity: Severity::Error,
+            path: path.clone(),
+            message: msg,
+            filename: field.filename.clone().or(reg.filename.clone()),
+            lineno: if field.filename.is_some() {
+                field.lineno
+            } else {
+                reg.lineno
+            },
+        };
+
+        if field.offset + field.width > reg.size {
+            issues.push(issue(
+                IssueKind::FieldBeyondRegister,
+                format!(
+                    "Field occupies bits {}..{} but the register is only {} bits wide",
+                    field.offset + field.width - 1,
+                    field.offset,
+                    reg.size
+                ),
+            ));
+        }
+        for other in &fields[..i] {
+            if other.offset + other.width > field.offset {
+                issues.push(issue(
+                    IssueKind::OverlappingFields,
+                    format!(
+                        "Field at bits {}..{} overlaps field '{}' at bits {}..{}",
+                        field.offset + field.width - 1,
+                        field.offset,
+                        other.name,
+                        other.offset + other.width - 1,
+                        other.offset
+                    ),
+                ));
+            }
+        }
+        for (name, reset) in &field.resets {
+            if reset.value.bits() as usize > field.width {
+                issues.push(issue(
+                    IssueKind::ResetTooWide,
+                    format!(
+                        "Reset value '{}' ({:#X}) does not fit within the field's {} bits",
+                        name, reset.value, field.width
+                    ),
+                ));
+            }
+            if let Some(m) = &reset.mask {
+                if m.bits() as usize > field.width {
+                    issues.push(issue(
+                        IssueKind::ResetTooWide,
+                        format!(
+                            "Reset mask '{}' ({:#X}) does not fit within the field's {} bits",
+                            name, m, field.width
+                        ),
+                    ));
+                }
+            }
+        }
+        for (name, e) in &field.enums {
+            if e.value.bits() as usize > field.width {
+                issues.push(issue(
+                    IssueKind::EnumTooWide,
+                    format!(
+                        "Enum '{}' ({:#X}) does not fit within the field's {} bits",
+                        name, e.value, field.width
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IssueKind;
+    use crate::core::model::registers::register::{FieldContainer, FieldEnum, Register, ResetVal};
+    use crate::Dut;
+    use std::sync::Mutex;
+
+    fn add_reg(dut: &mut Dut, ab: usize, name: &str, offset: usize, fields: Vec<FieldContainer>) {
+        Register::add_reg(
+            dut,
+            ab,
+            None,
+            name,
+            offset,
+            Some(32),
+            "LSB0",
+            None,
+            None,
+            None,
+            None,
+            None,
+            fields,
+        )
+        .unwrap();
+    }
+
+    fn field(name: &str, offset: usize, width: usize) -> FieldContainer {
+        FieldContainer::internal_new(name, offset, width, "rw", vec![], None, "")
+    }
+
+    #[test]
+    fn consistent_model_has_no_issues() {
+        let mut dut = Dut::new("validation_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let map = dut.create_memory_map(top, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, Some(0x10), None, None)
+            .unwrap();
+        add_reg(
+            &mut dut,
+            ab,
+            "reg1",
+            0,
+            vec![field("a", 0, 4), field("b", 4, 4)],
+        );
+        add_reg(&mut dut, ab, "reg2", 4, vec![field("a", 0, 32)]);
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        assert!(super::validate(&dut, top).unwrap().is_empty());
+    }
+
+    #[test]
+    fn problems_are_reported() {
+        let mut dut = Dut::new("validation_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let sub = dut.create_model(Some(top), "sub", None).unwrap();
+        let map = dut.create_memory_map(top, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, Some(0x8), None, None)
+            .unwrap();
+        add_reg(&mut dut, ab, "reg1", 0, vec![field("a", 0, 4)]);
+        add_reg(&mut dut, ab, "reg2", 2, vec![field("a", 0, 4)]);
+        add_reg(&mut dut, ab, "reg3", 8, vec![field("a", 0, 4)]);
+        let mut f = FieldContainer::internal_new(
+            "e",
+            0,
+            2,
+            "rw",
+            vec![FieldEnum::new("big".to_string(), "".to_string(), 4)],
+            Some(vec![ResetVal::new("hard", 0x7, None)]),
+            "",
+        );
+        f.filename = Some("regs.py".to_string());
+        f.lineno = Some(12);
+        let map = dut.create_memory_map(sub, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, None, None, None)
+            .unwrap();
+        add_reg(&mut dut, ab, "reg1", 0, vec![f]);
+        // The second block starts inside the map but runs past its end
+        let map = dut.create_memory_map(sub, "small", None).unwrap();
+        dut.get_mut_memory_map(map).unwrap().range = 0x100;
+        dut.create_address_block(map, "ok", Some(0), Some(0x100), None, None)
+            .unwrap();
+        dut.create_address_block(map, "big", Some(0x80), Some(0x100), None, None)
+            .unwrap();
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let issues = super::validate(&dut, top).unwrap();
+        let kinds: Vec<IssueKind> = issues.iter().map(|i| i.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                IssueKind::RegisterOutsideAddressBlock,
+                IssueKind::OverlappingRegisters,
+                IssueKind::ResetTooWide,
+                IssueKind::EnumTooWide,
+                IssueKind::AddressBlockOutsideMemoryMap,
+            ]
+        );
+        assert_eq!(issues[0].path, "dut.reg3");
+        assert_eq!(issues[1].path, "dut.reg2");
+        assert_eq!(issues[4].path, "dut.sub.small.big");
+        assert_eq!(
+            format!("{}", issues[3]),
+            "regs.py:12: dut.sub.reg1.e - Enum 'big' (0x4) does not fit within the field's 2 bits"
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Helpers for walking the register model of a block and its sub-blocks, shared by the register
+//! model exporters
+
+use super::Register;
+use crate::core::model::Model;
+use crate::Dut;
+use crate::Result;
+use std::sync::MutexGuard;
+
+/// Returns the IDs of the given model and all models beneath it, parents are returned before
+/// their children
+pub(super) fn models_top_down(dut: &MutexGuard<Dut>, model_id: usize) -> Result<Vec<usize>> {
+    let mut ids = vec![model_id];
+    for (_, id) in &dut.get_model(model_id)?.sub_blocks {
+        ids.append(&mut models_top_down(dut, *id)?);
+    }
+    Ok(ids)
+}
+
+/// Returns the IDs of the given model and all models beneath it, children are returned before
+/// their parents
+pub(super) fn models_depth_first(dut: &MutexGuard<Dut>, model_id: usize) -> Result<Vec<usize>> {
+    let mut ids: Vec<usize> = vec![];
+    for (_, id) in &dut.get_model(model_id)?.sub_blocks {
+        ids.append(&mut models_depth_first(dut, *id)?);
+    }
+    ids.push(model_id);
+    Ok(ids)
+}
+
+/// Returns all registers owned by the given model, across all of its memory maps and address
+/// blocks
+pub(super) fn registers<'a>(dut: &'a MutexGuard<Dut>, model: &Model) -> Result<Vec<&'a Register>> {
+    let mut regs: Vec<&Register> = vec![];
+    for (_, map_id) in &model.memory_maps {
+        for (_, ab_id) in &dut.get_memory_map(*map_id)?.address_blocks {
+            for (_, reg_id) in &dut.get_address_block(*ab_id)?.registers {
+                regs.push(dut.get_register(*reg_id)?);
+            }
+        }
+    }
+    Ok(regs)
+}
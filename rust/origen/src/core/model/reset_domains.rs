@@ -0,0 +1,344 @@
+//! Reset domains group the registers of a sub-tree of the DUT under a name so that they can be
+//! reset together, e.g. a "por" domain defined on the top-level applying the "hard" reset to all
+//! registers, and a "debug" domain defined on a debug sub-block applying a "debug" reset to only
+//! the registers within that sub-block.
+//!
+//! Domains cascade in two ways:
+//!   * A domain applies to all registers in the sub-tree of the model that defines it, a sub-block
+//!     can re-define a domain of the same name to override the reset type used within its own sub-tree
+//!   * A domain can be given a parent domain (defined on the same model or on any of its parents),
+//!     whenever the parent is reset then the child domain is too. The child's reset type takes
+//!     precedence over the parent's for any registers covered by both.
+
+use super::Model;
+use crate::generator::PAT;
+use crate::{Dut, Result, TEST};
+use indexmap::map::IndexMap;
+use std::sync::MutexGuard;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResetDomain {
+    pub name: String,
+    /// The name of the reset type (e.g. "hard", "warm") applied to the domain's registers
+    pub reset: String,
+    /// The name of the parent domain, resetting the parent will also reset this domain
+    pub parent: Option<String>,
+}
+
+impl Model {
+    pub fn get_reset_domain(&self, name: &str) -> Option<&ResetDomain> {
+        self.reset_domains.get(name)
+    }
+
+    pub fn contains_reset_domain(&self, name: &str) -> bool {
+        self.reset_domains.contains_key(name)
+    }
+
+    /// Returns the reset domain of the given name defined either by this model or by the closest
+    /// of its parents which defines it
+    pub fn find_reset_domain<'a>(
+        &'a self,
+        name: &str,
+        dut: &'a MutexGuard<Dut>,
+    ) -> Result<&'a ResetDomain> {
+        let mut model = self;
+        loop {
+            if let Some(d) = model.get_reset_domain(name) {
+                return Ok(d);
+            }
+            match model.parent_id {
+                Some(id) => model = dut.get_model(id)?,
+                None => bail!(
+                    "No reset domain named '{}' is defined by block '{}' or its parents",
+                    name,
+                    self.display_path(dut)
+                ),
+            }
+        }
+    }
+
+    /// Returns the IDs of all registers which would be affected by resetting the given domain
+    /// within this model's sub-tree, including those of any cascaded (child) domains.
+    /// Each is returned along with the name of the reset type that would be applied to it.
+    pub fn reset_domain_regs(
+        &self,
+        name: &str,
+        dut: &MutexGuard<Dut>,
+    ) -> Result<IndexMap<usize, String>> {
+        let mut regs = IndexMap::new();
+        let mut domains = vec![];
+        collect_domain(dut, self.id, name, &mut regs, &mut domains)?;
+        Ok(regs)
+    }
+
+    /// Returns the names of the given domain and all domains which cascade from it
+    pub fn cascaded_reset_domains(&self, name: &str, dut: &MutexGuard<Dut>) -> Result<Vec<String>> {
+        let mut regs = IndexMap::new();
+        let mut domains = vec![];
+        collect_domain(dut, self.id, name, &mut regs, &mut domains)?;
+        let mut names: Vec<String> = vec![];
+        for (_, n) in domains {
+            if !names.contains(&n) {
+                names.push(n);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Issues a reset of the given domain within this model's sub-tree, applying the domain's
+    /// reset to all of its registers (and cascading to any child domains).
+    /// The reset will be recorded in the current pattern as a ResetDomain node.
+    pub fn reset_domain(&self, name: &str, dut: &MutexGuard<Dut>) -> Result<()> {
+        let regs = self.reset_domain_regs(name, dut)?;
+        let domains = self.cascaded_reset_domains(name, dut)?;
+        let n_id = TEST.push_and_open(node!(PAT::ResetDomain, self.id, name.to_string()));
+        let mut msg = format!("Reset domain '{}' of {}", name, self.display_path(dut));
+        if domains.len() > 1 {
+            msg += &format!(" (cascading to: {})", domains[1..].join(", "));
+        }
+        TEST.push(node!(PAT::Comment, 0, msg));
+        for (id, reset) in &regs {
+            dut.get_register(*id)?.reset(reset, dut);
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Returns true if all registers within the given domain currently hold the value of the
+    /// domain's reset type.
+    /// Registers which do not define a value for the domain's reset type will cause this to
+    /// return false.
+    pub fn is_in_reset_state_for_domain(&self, name: &str, dut: &MutexGuard<Dut>) -> Result<bool> {
+        for (id, reset) in &self.reset_domain_regs(name, dut)? {
+            let reg = dut.get_register(*id)?;
+            if !reg.bits(dut).is_in_reset_state(Some(reset), dut)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns true if the data value of any register within the given domain has been changed
+    /// since it was last reset
+    pub fn is_modified_since_reset_for_domain(
+        &self,
+        name: &str,
+        dut: &MutexGuard<Dut>,
+    ) -> Result<bool> {
+        for (id, _) in &self.reset_domain_regs(name, dut)? {
+            if dut.get_register(*id)?.bits(dut).is_modified_since_reset() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
+
+impl Dut {
+    pub fn create_reset_domain(
+        &mut self,
+        model_id: usize,
+        name: &str,
+        reset: Option<&str>,
+        parent: Option<&str>,
+    ) -> Result<&ResetDomain> {
+        if let Some(p) = parent {
+            if p == name {
+                bail!("Reset domain '{}' cannot be its own parent", name);
+            }
+            // Make sure the parent exists
+            let mut model = self.get_model(model_id)?;
+            while !model.contains_reset_domain(p) {
+                match model.parent_id {
+                    Some(id) => model = self.get_model(id)?,
+                    None => bail!(
+                        "Parent reset domain '{}' is not defined by block '{}' or its parents",
+                        p,
+                        self.get_model(model_id)?.name
+                    ),
+                }
+            }
+        }
+        let model = self.get_mut_model(model_id)?;
+        if model.contains_reset_domain(name) {
+            return crate::duplicate_error!("reset domain", model.name, name);
+        }
+        model.reset_domains.insert(
+            name.to_string(),
+            ResetDomain {
+                name: name.to_string(),
+                reset: reset.unwrap_or("hard").to_string(),
+                parent: parent.map(|p| p.to_string()),
+            },
+        );
+        Ok(&model.reset_domains[name])
+    }
+}
+
+/// Collects the registers for the given domain within the sub-tree of the given model and then
+/// those of any domains which cascade from it.
+/// The (model ID, domain name) pairs already visited are tracked to guard against cycles.
+fn collect_domain(
+    dut: &MutexGuard<Dut>,
+    scope_id: usize,
+    name: &str,
+    regs: &mut IndexMap<usize, String>,
+    domains: &mut Vec<(usize, String)>,
+) -> Result<()> {
+    if domains.iter().any(|(id, n)| *id == scope_id && n == name) {
+        return Ok(());
+    }
+    domains.push((scope_id, name.to_string()));
+    let scope = dut.get_model(scope_id)?;
+    let domain = scope.find_reset_domain(name, dut)?;
+    collect_regs(dut, scope_id, name, &domain.reset, regs)?;
+
+    // Children defined by parent models apply to the whole of this scope, while those defined
+    // within the scope apply only to their own sub-tree
+    let mut children: Vec<(usize, String)> = vec![];
+    let mut model = scope;
+    while let Some(id) = model.parent_id {
+        model = dut.get_model(id)?;
+        for d in model.reset_domains.values() {
+            if d.parent.as_deref() == Some(name) {
+                children.push((scope_id, d.name.clone()));
+            }
+        }
+    }
+    child_domains(dut, scope_id, name, &mut children)?;
+    for (id, child) in children {
+        collect_domain(dut, id, &child, regs, domains)?;
+    }
+    Ok(())
+}
+
+fn child_domains(
+    dut: &MutexGuard<Dut>,
+    model_id: usize,
+    name: &str,
+    children: &mut Vec<(usize, String)>,
+) -> Result<()> {
+    let model = dut.get_model(model_id)?;
+    for d in model.reset_domains.values() {
+        if d.parent.as_deref() == Some(name) {
+            children.push((model_id, d.name.clone()));
+        }
+    }
+    for (_, id) in &model.sub_blocks {
+        child_domains(dut, *id, name, children)?;
+    }
+    Ok(())
+}
+
+fn collect_regs(
+    dut: &MutexGuard<Dut>,
+    model_id: usize,
+    name: &str,
+    reset: &str,
+    regs: &mut IndexMap<usize, String>,
+) -> Result<()> {
+    let model = dut.get_model(model_id)?;
+    // A sub-block can override the reset type used by the domain within its sub-tree
+    let reset = match model.get_reset_domain(name) {
+        Some(d) => &d.reset,
+        None => reset,
+    };
+    for (_, map_id) in &model.memory_maps {
+        for (_, ab_id) in &dut.get_memory_map(*map_id)?.address_blocks {
+            for (_, reg_id) in &dut.get_address_block(*ab_id)?.registers {
+                regs.insert(*reg_id, reset.to_string());
+            }
+        }
+    }
+    for (_, id) in &model.sub_blocks {
+        collect_regs(dut, *id, name, reset, regs)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::model::registers::register::{FieldContainer, Register, ResetVal};
+    use crate::Dut;
+    use std::sync::Mutex;
+
+    fn add_reg(dut: &mut Dut, model_id: usize, name: &str) -> usize {
+        let map = dut.create_memory_map(model_id, "default", None).unwrap();
+        let ab = dut
+            .create_address_block(map, "default", None, None, None, None)
+            .unwrap();
+        let resets = vec![
+            ResetVal::new("hard", 0x1, None),
+            ResetVal::new("debug", 0x2, None),
+        ];
+        let f = FieldContainer::internal_new("data", 0, 8, "rw", vec![], Some(resets), "");
+        Register::add_reg(
+            dut,
+            ab,
+            None,
+            name,
+            0,
+            Some(8),
+            "LSB0",
+            None,
+            None,
+            None,
+            None,
+            None,
+            vec![f],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn domains_cascade() {
+        let mut dut = Dut::new("reset_domain_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        let core = dut.create_model(Some(top), "core", None).unwrap();
+        let dbg = dut.create_model(Some(top), "dbg", None).unwrap();
+        let r1 = add_reg(&mut dut, core, "reg1");
+        let r2 = add_reg(&mut dut, dbg, "reg2");
+        dut.create_reset_domain(top, "por", None, None).unwrap();
+        dut.create_reset_domain(dbg, "debug", Some("debug"), Some("por"))
+            .unwrap();
+        assert!(dut
+            .create_reset_domain(core, "warm", None, Some("blah"))
+            .is_err());
+        assert!(dut.create_reset_domain(top, "por", None, None).is_err());
+
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let top = dut.get_model(top).unwrap();
+        let dbg = dut.get_model(dbg).unwrap();
+        let regs = top.reset_domain_regs("por", &dut).unwrap();
+        assert_eq!(regs[&r1], "hard");
+        assert_eq!(regs[&r2], "debug");
+        assert_eq!(
+            top.cascaded_reset_domains("por", &dut).unwrap(),
+            vec!["por".to_string(), "debug".to_string()]
+        );
+        // The debug domain only covers its own sub-tree
+        assert_eq!(dbg.reset_domain_regs("debug", &dut).unwrap().len(), 1);
+
+        top.reset_domain("por", &dut).unwrap();
+        assert!(top.is_in_reset_state_for_domain("por", &dut).unwrap());
+        assert!(!top.is_modified_since_reset_for_domain("por", &dut).unwrap());
+        assert_eq!(
+            dut.get_register(r2).unwrap().bits(&dut).data().unwrap(),
+            2u8.into()
+        );
+
+        dut.get_register(r2)
+            .unwrap()
+            .bits(&dut)
+            .set_data(5u8.into());
+        assert!(top.is_modified_since_reset_for_domain("por", &dut).unwrap());
+        assert!(dbg
+            .is_modified_since_reset_for_domain("debug", &dut)
+            .unwrap());
+        assert!(!top.is_in_reset_state_for_domain("por", &dut).unwrap());
+
+        dbg.reset_domain("debug", &dut).unwrap();
+        assert!(top.is_in_reset_state_for_domain("por", &dut).unwrap());
+    }
+}
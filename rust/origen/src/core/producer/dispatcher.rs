@@ -0,0 +1,466 @@
+//! The dispatcher runs the jobs of a command, e.g. one per file given to `origen generate`, as
+//! separate Origen processes, either on a pool of local workers or by submitting them to LSF.
+//!
+//! Each job's output is written to its own log file, and the job writes its stats and the
+//! outcome of checking each of its generated files against its reference to a results file
+//! (see write_job_results) which the dispatcher then collects into a single summary.
+
+use super::report::Report;
+use crate::core::lsf;
+use crate::core::tester::{GeneratedFile, RefStatus, Stats};
+use crate::Result;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// The environment variable giving a dispatched job the path to write its results to
+pub const RESULTS_ENV: &str = "ORIGEN_JOB_RESULTS";
+
+const LOCAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The number of consecutive failures to query the state of LSF jobs that will be tolerated
+/// before giving up on them
+const MAX_LSF_POLL_FAILURES: usize = 5;
+
+#[derive(Debug, Clone)]
+pub enum Backend {
+    /// Runs the jobs locally, with up to the given number running at once
+    Local {
+        workers: usize,
+    },
+    Lsf(lsf::Config),
+}
+
+#[derive(Debug, Clone)]
+pub struct DispatchedJob {
+    pub id: usize,
+    /// A name to identify the job by, e.g. its source file
+    pub name: String,
+    /// The arguments given to the Origen executable to run the job
+    pub args: Vec<String>,
+    pub log: PathBuf,
+    pub results: PathBuf,
+}
+
+/// The results written by a dispatched job on completion
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobResults {
+    pub stats: Stats,
+    pub files: Vec<GeneratedFile>,
+}
+
+impl JobResults {
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Returns true if this process is running a job started by the dispatcher
+pub fn is_dispatched_job() -> bool {
+    env::var(RESULTS_ENV).is_ok()
+}
+
+/// Writes the results of this process if it is running as a dispatched job, returning true if so
+pub fn write_job_results() -> Result<bool> {
+    match env::var(RESULTS_ENV) {
+        Ok(path) => {
+            let results = {
+                let tester = crate::tester();
+                JobResults {
+                    stats: tester.stats.clone(),
+                    files: tester.generated_files.clone(),
+                }
+            };
+            results.write(Path::new(&path))?;
+            Ok(true)
+        }
+        Err(_) => Ok(false),
+    }
+}
+
+#[derive(Debug)]
+pub struct JobOutcome {
+    pub job: DispatchedJob,
+    pub passed: bool,
+    /// None if the job did not write its results, e.g. it failed before completing
+    pub results: Option<JobResults>,
+}
+
+#[derive(Debug)]
+pub struct Summary {
+    pub outcomes: Vec<JobOutcome>,
+    /// The combined stats of all jobs
+    pub stats: Stats,
+}
+
+impl Summary {
+    pub fn failed(&self) -> Vec<&JobOutcome> {
+        self.outcomes.iter().filter(|o| !o.passed).collect()
+    }
+
+    /// Returns all generated files with the given reference status
+    pub fn files(&self, status: RefStatus) -> Vec<&GeneratedFile> {
+        self.outcomes
+            .iter()
+            .filter_map(|o| o.results.as_ref())
+            .flat_map(|r| r.files.iter())
+            .filter(|f| f.status == status)
+            .collect()
+    }
+
+    /// Returns a report of the files generated by all jobs
+    pub fn report(&self) -> Report {
+        Report {
+            stats: self.stats.clone(),
+            files: self
+                .outcomes
+                .iter()
+                .filter_map(|o| o.results.as_ref())
+                .flat_map(|r| r.files.iter().cloned())
+                .collect(),
+            failed_jobs: self
+                .failed()
+                .iter()
+                .map(|o| (o.job.name.to_string(), o.job.log.to_path_buf()))
+                .collect(),
+        }
+    }
+
+    pub fn display(&self) {
+        let failed = self.failed();
+        displayln!("");
+        displayln!(
+            "Ran {} jobs: {} passed, {} failed",
+            self.outcomes.len(),
+            self.outcomes.len() - failed.len(),
+            failed.len()
+        );
+        for o in &failed {
+            display_redln!("  FAILED: {} (log: {})", o.job.name, o.job.log.display());
+        }
+        displayln!(
+            "Patterns: {} generated, {} changed, {} new",
+            self.stats.generated_pattern_files,
+            self.stats.changed_pattern_files,
+            self.stats.new_pattern_files
+        );
+        displayln!(
+            "Program files: {} generated, {} changed, {} new",
+            self.stats.generated_program_files,
+            self.stats.changed_program_files,
+            self.stats.new_program_files
+        );
+        for (status, label) in [(RefStatus::Changed, "Changed"), (RefStatus::New, "New")] {
+            let files = self.files(status);
+            if !files.is_empty() {
+                displayln!("{} files:", label);
+                for f in files {
+                    displayln!("  {}", f.path.display());
+                }
+            }
+        }
+        if !self.files(RefStatus::Changed).is_empty() {
+            displayln!("To review the changed files run:");
+            displayln!("  origen save_ref --review");
+        }
+    }
+}
+
+pub struct Dispatcher {
+    /// The Origen executable which runs each job
+    pub program: PathBuf,
+    pub backend: Backend,
+    /// Where the jobs' logs and results files are written
+    pub dir: PathBuf,
+    pub jobs: Vec<DispatchedJob>,
+}
+
+impl Dispatcher {
+    pub fn new(program: &Path, backend: Backend, dir: &Path) -> Self {
+        Self {
+            program: program.to_path_buf(),
+            backend,
+            dir: dir.to_path_buf(),
+            jobs: vec![],
+        }
+    }
+
+    pub fn add_job(&mut self, name: &str, args: Vec<String>) -> &DispatchedJob {
+        let id = self.jobs.len();
+        self.jobs.push(DispatchedJob {
+            id,
+            name: name.to_string(),
+            args,
+            log: self.dir.join(format!("job_{}.log", id)),
+            results: self.dir.join(format!("job_{}.json", id)),
+        });
+        &self.jobs[id]
+    }
+
+    /// Runs all jobs to completion and collects their results
+    pub fn run(&self) -> Result<Summary> {
+        fs::create_dir_all(&self.dir)?;
+        for job in &self.jobs {
+            // Clear anything left over from a previous run so that stale results aren't reported
+            for f in [&job.log, &job.results] {
+                if f.exists() {
+                    fs::remove_file(f)?;
+                }
+            }
+        }
+        let passed = match &self.backend {
+            Backend::Local { workers } => self.run_local(*workers)?,
+            Backend::Lsf(config) => self.run_lsf(config)?,
+        };
+        let mut stats = Stats::default();
+        let mut outcomes = vec![];
+        for (job, passed) in self.jobs.iter().zip(passed) {
+            let results = if job.results.exists() {
+                match JobResults::read(&job.results) {
+                    Ok(r) => Some(r),
+                    Err(e) => {
+                        log_error!("Could not read the results of job '{}' - {}", job.name, e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+            if let Some(r) = &results {
+                stats.merge(&r.stats);
+            }
+            outcomes.push(JobOutcome {
+                job: job.clone(),
+                passed: passed && results.is_some(),
+                results,
+            });
+        }
+        Ok(Summary { outcomes, stats })
+    }
+
+    fn run_local(&self, workers: usize) -> Result<Vec<bool>> {
+        let workers = workers.max(1);
+        let mut passed = vec![false; self.jobs.len()];
+        let mut queue = self.jobs.iter();
+        let mut running: Vec<(usize, Child)> = vec![];
+        loop {
+            while running.len() < workers {
+                match queue.next() {
+                    Some(job) => {
+                        log_info!("Starting job {}: {}", job.id, job.name);
+                        running.push((job.id, self.spawn(job)?));
+                    }
+                    None => break,
+                }
+            }
+            if running.is_empty() {
+                break;
+            }
+            let mut i = 0;
+            while i < running.len() {
+                if let Some(status) = running[i].1.try_wait()? {
+                    let (id, _) = running.remove(i);
+                    log_info!("Completed job {}: {}", id, self.jobs[id].name);
+                    passed[id] = status.success();
+                } else {
+                    i += 1;
+                }
+            }
+            thread::sleep(LOCAL_POLL_INTERVAL);
+        }
+        Ok(passed)
+    }
+
+    fn spawn(&self, job: &DispatchedJob) -> Result<Child> {
+        let log = fs::File::create(&job.log)?;
+        match Command::new(&self.program)
+            .args(&job.args)
+            .env(RESULTS_ENV, &job.results)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log)
+            .spawn()
+        {
+            Ok(c) => Ok(c),
+            Err(e) => bail!(
+                "Failed to start job '{}' with '{}' - {}",
+                job.name,
+                self.program.display(),
+                e
+            ),
+        }
+    }
+
+    fn run_lsf(&self, config: &lsf::Config) -> Result<Vec<bool>> {
+        let mut ids: HashMap<String, usize> = HashMap::new();
+        for job in &self.jobs {
+            let mut cmd = vec![self.program.display().to_string()];
+            cmd.extend(job.args.iter().cloned());
+            let id = lsf::submit(
+                config,
+                &format!("origen_{}", job.id),
+                &cmd,
+                &job.log,
+                &[(RESULTS_ENV, job.results.display().to_string())],
+            )?;
+            log_info!("Submitted job {} to LSF as {}: {}", job.id, id, job.name);
+            ids.insert(id, job.id);
+        }
+        let mut passed = vec![false; self.jobs.len()];
+        let mut pending: Vec<String> = ids.keys().cloned().collect();
+        let mut failures = 0;
+        while !pending.is_empty() {
+            let states = match lsf::states(config, &pending) {
+                Ok(s) => {
+                    failures = 0;
+                    s
+                }
+                Err(e) => {
+                    failures += 1;
+                    if failures >= MAX_LSF_POLL_FAILURES {
+                        return Err(e);
+                    }
+                    log_warning!("{}", e);
+                    thread::sleep(config.poll_interval);
+                    continue;
+                }
+            };
+            pending.retain(|id| {
+                // Keep polling any job whose state was not reported this time
+                let state = match states.get(id) {
+                    Some(s) => *s,
+                    None => return true,
+                };
+                if state.is_finished() {
+                    // A job which LSF no longer knows about is judged by whether it wrote its
+                    // results
+                    passed[ids[id]] = match state {
+                        lsf::JobState::Done => true,
+                        lsf::JobState::Unknown => self.jobs[ids[id]].results.exists(),
+                        _ => false,
+                    };
+                    false
+                } else {
+                    true
+                }
+            });
+            if !pending.is_empty() {
+                thread::sleep(config.poll_interval);
+            }
+        }
+        Ok(passed)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{}", content)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    // Stands in for Origen, writing results for each file except those named "fail"
+    const WORKER: &str = r#"
+echo "generating $1"
+[ "$1" = "fail" ] && exit 1
+echo '{"stats":{"generated_pattern_files":1,"changed_pattern_files":1,"new_pattern_files":0,"generated_program_files":0,"changed_program_files":0,"new_program_files":0},"files":[{"kind":"Pattern","target":"J750","path":"'$1'.atp","reference":null,"status":"Changed","diff_command":"tkdiff a b","runtime":0.1}]}' > "$ORIGEN_JOB_RESULTS"
+"#;
+
+    // Stand-in bsub which runs the job immediately and records its exit status, and a bjobs
+    // which reports it after an initial failure and a round in which LSF has lost track of them
+    const BSUB: &str = r#"
+dir=$(dirname "$0")
+while [ "${1#-}" != "$1" ]; do
+  [ "$1" = "-o" ] && log="$2"
+  shift 2
+done
+n=$(ls "$dir" | grep -c '\.status$')
+id=$((100 + n))
+"$@" > "$log" 2>&1
+echo $? > "$dir/$id.status"
+echo "Job <$id> is submitted to queue <normal>."
+"#;
+    const BJOBS: &str = r#"
+dir=$(dirname "$0")
+# Fail the first query, as LSF sometimes does, to check that the dispatcher keeps polling
+if [ ! -e "$dir/polled" ]; then
+  touch "$dir/polled"
+  echo "LSF is down, please wait" >&2
+  exit 255
+fi
+# Then report the jobs as UNKWN, which must not be mistaken for them having finished
+if [ ! -e "$dir/lost" ]; then
+  touch "$dir/lost"
+  for id in "$@"; do
+    case "$id" in -*) continue;; esac
+    echo "$id user UNKWN normal host host origen_job Oct 19 10:00"
+  done
+  exit 0
+fi
+touch "$dir/resolved"
+for id in "$@"; do
+  case "$id" in -*) continue;; esac
+  if [ "$(cat "$dir/$id.status")" = "0" ]; then s=DONE; else s=EXIT; fi
+  echo "$id user $s normal host host origen_job Oct 19 10:00"
+done
+"#;
+
+    fn check(summary: &Summary, dir: &Path) {
+        assert_eq!(summary.outcomes.len(), 3);
+        assert_eq!(summary.failed().len(), 1);
+        assert_eq!(summary.failed()[0].job.name, "fail");
+        assert_eq!(summary.stats.generated_pattern_files, 2);
+        assert_eq!(summary.files(RefStatus::Changed).len(), 2);
+        let log = fs::read_to_string(dir.join("jobs").join("job_2.log")).unwrap();
+        assert!(log.contains("generating pat_b"));
+    }
+
+    fn dispatcher(worker: &Path, backend: Backend, dir: &Path) -> Dispatcher {
+        let mut d = Dispatcher::new(worker, backend, &dir.join("jobs"));
+        for name in ["pat_a", "fail", "pat_b"] {
+            d.add_job(name, vec![name.to_string()]);
+        }
+        d
+    }
+
+    #[test]
+    fn runs_jobs_on_local_workers_and_lsf() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let worker = script(dir, "worker", WORKER);
+
+        let summary = dispatcher(&worker, Backend::Local { workers: 2 }, dir)
+            .run()
+            .unwrap();
+        check(&summary, dir);
+        assert_eq!(summary.report().failed_jobs.len(), 1);
+
+        let lsf_dir = dir.join("lsf");
+        fs::create_dir_all(&lsf_dir).unwrap();
+        let config = lsf::Config {
+            bsub: script(&lsf_dir, "bsub", BSUB).display().to_string(),
+            bjobs: script(&lsf_dir, "bjobs", BJOBS).display().to_string(),
+            poll_interval: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let summary = dispatcher(&worker, Backend::Lsf(config), dir)
+            .run()
+            .unwrap();
+        check(&summary, dir);
+        assert!(lsf_dir.join("resolved").exists());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::Result;
 use normpath::PathExt;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 
 /// A job represents the execution of an Origen application source file.
 /// For example, if the user runs `origen g <pat1> <pat2>` then two jobs will be created,
@@ -13,6 +14,7 @@ pub struct Job {
     /// supplied by the user, then if that file imports other files, e.g. a sub-flow, then
     /// that will be added to this stack and then popped off when completed.
     pub files: Vec<PathBuf>,
+    pub started: Instant,
 }
 
 impl Job {
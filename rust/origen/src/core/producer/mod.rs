@@ -1,8 +1,11 @@
+pub mod dispatcher;
 pub mod job;
+pub mod report;
 
 use crate::Result;
 use job::Job;
 use std::path::Path;
+use std::time::Instant;
 
 /// The producer is a singleton instantiated as origen::PRODUCER, it provides static storage and
 /// state tracking for all jobs created by an origen command invocation (e.g. origen g blah).
@@ -36,6 +39,7 @@ impl Producer {
             results: None,
             id: id,
             files: vec![],
+            started: Instant::now(),
         };
         if let Some(f) = file {
             j.files.push(f.to_path_buf());
@@ -0,0 +1,204 @@
+//! Machine-readable reports of a generate run, the same information that is displayed to the
+//! console for each generated file (its status against its reference, the diff command, etc.)
+//! written in JSON and in JUnit XML so that CI systems can present it.
+
+use super::dispatcher;
+use crate::core::tester::{FileKind, GeneratedFile, RefStatus, Stats};
+use crate::utility::file_utils::to_relative_path;
+use crate::Result;
+use indexmap::IndexMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const JSON_REPORT: &str = "generate.json";
+pub const JUNIT_REPORT: &str = "generate.xml";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub stats: Stats,
+    pub files: Vec<GeneratedFile>,
+    /// Dispatched jobs which failed to complete, given as their name and log file
+    pub failed_jobs: Vec<(String, PathBuf)>,
+}
+
+impl Report {
+    /// Returns a report of the files generated by this process
+    pub fn from_tester() -> Self {
+        let tester = crate::tester();
+        Self {
+            stats: tester.stats.clone(),
+            files: tester.generated_files.clone(),
+            failed_jobs: vec![],
+        }
+    }
+
+    /// The directory which reports are written to by default for the given output directory
+    pub fn default_dir(output_dir: &Path) -> PathBuf {
+        output_dir.join("reports")
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Returns the report in JUnit XML, with a test suite per target and a test case per file.
+    /// Changed files are reported as failures and new files as skipped since they have nothing
+    /// to be checked against yet.
+    pub fn to_junit(&self) -> String {
+        let mut suites: IndexMap<&str, Vec<&GeneratedFile>> = IndexMap::new();
+        for f in &self.files {
+            suites.entry(&f.target).or_insert(vec![]).push(f);
+        }
+        let count =
+            |files: &[&GeneratedFile], status| files.iter().filter(|f| f.status == status).count();
+        let time = |files: &[&GeneratedFile]| files.iter().map(|f| f.runtime).sum::<f64>();
+
+        let all: Vec<&GeneratedFile> = self.files.iter().collect();
+        let mut xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n".to_string();
+        xml += &format!(
+            "<testsuites name=\"origen generate\" tests=\"{}\" failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            all.len() + self.failed_jobs.len(),
+            count(&all, RefStatus::Changed),
+            self.failed_jobs.len(),
+            count(&all, RefStatus::New),
+            time(&all)
+        );
+        for (target, files) in &suites {
+            xml += &format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+                escape(target),
+                files.len(),
+                count(files, RefStatus::Changed),
+                count(files, RefStatus::New),
+                time(files)
+            );
+            for f in files {
+                let kind = match f.kind {
+                    FileKind::Pattern => "pattern",
+                    FileKind::Program => "program",
+                };
+                let name = to_relative_path(&f.path, None).unwrap_or(f.path.to_path_buf());
+                xml += &format!(
+                    "    <testcase name=\"{}\" classname=\"{}.{}\" time=\"{:.3}\"",
+                    escape(&name.display().to_string()),
+                    escape(target),
+                    kind,
+                    f.runtime
+                );
+                match f.status {
+                    RefStatus::Changed => {
+                        xml += ">\n";
+                        xml += &format!(
+                            "      <failure message=\"Diffs found\" type=\"changed\">{}</failure>\n",
+                            escape(f.diff_command.as_deref().unwrap_or(""))
+                        );
+                        xml += "    </testcase>\n";
+                    }
+                    RefStatus::New => {
+                        xml += ">\n";
+                        xml += "      <skipped message=\"New file, no reference exists yet\"/>\n";
+                        xml += "    </testcase>\n";
+                    }
+                    _ => xml += "/>\n",
+                }
+            }
+            xml += "  </testsuite>\n";
+        }
+        if !self.failed_jobs.is_empty() {
+            xml += &format!(
+                "  <testsuite name=\"jobs\" tests=\"{0}\" failures=\"0\" errors=\"{0}\" skipped=\"0\">\n",
+                self.failed_jobs.len()
+            );
+            for (name, log) in &self.failed_jobs {
+                xml += &format!(
+                    "    <testcase name=\"{}\" classname=\"jobs\">\n      <error message=\"Job failed\">See log: {}</error>\n    </testcase>\n",
+                    escape(name),
+                    escape(&log.display().to_string())
+                );
+            }
+            xml += "  </testsuite>\n";
+        }
+        xml += "</testsuites>\n";
+        xml
+    }
+
+    /// Writes the JSON and JUnit reports to the given directory, returning their paths
+    pub fn write(&self, dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        fs::create_dir_all(dir)?;
+        let json = dir.join(JSON_REPORT);
+        let junit = dir.join(JUNIT_REPORT);
+        fs::write(&json, self.to_json()?)?;
+        fs::write(&junit, self.to_junit())?;
+        log_info!("Generate reports written to '{}'", dir.display());
+        Ok((json, junit))
+    }
+}
+
+/// Called at the end of a generate command to write its reports, or when running as a
+/// dispatched job to write its results for the dispatcher to report instead
+pub fn write_generate_reports() -> Result<()> {
+    if !dispatcher::write_job_results()? {
+        Report::from_tester().write(&Report::default_dir(&crate::STATUS.output_dir()))?;
+    }
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(target: &str, path: &str, status: RefStatus) -> GeneratedFile {
+        let mut f: GeneratedFile = serde_json::from_str(&format!(
+            r#"{{"kind":"Pattern","target":"{}","path":"{}","reference":null,"status":"NotChecked","diff_command":null,"runtime":0.5}}"#,
+            target, path
+        ))
+        .unwrap();
+        f.status = status;
+        if status == RefStatus::Changed {
+            f.diff_command = Some(format!("tkdiff .ref/{0} {0}", path));
+        }
+        f
+    }
+
+    #[test]
+    fn junit_report_has_a_suite_per_target() {
+        let report = Report {
+            stats: Stats::default(),
+            files: vec![
+                file("V93K_SMT7", "/out/v93k/a&b.avc", RefStatus::Changed),
+                file("V93K_SMT7", "/out/v93k/c.avc", RefStatus::Unchanged),
+                file("J750", "/out/j750/c.atp", RefStatus::New),
+            ],
+            failed_jobs: vec![(
+                "d.py".to_string(),
+                PathBuf::from("/out/.dispatch/job_3.log"),
+            )],
+        };
+        let xml = report.to_junit();
+        assert!(xml.contains(
+            "<testsuites name=\"origen generate\" tests=\"4\" failures=\"1\" errors=\"1\" skipped=\"1\" time=\"1.500\">"
+        ));
+        assert!(xml.contains(
+            "<testsuite name=\"V93K_SMT7\" tests=\"2\" failures=\"1\" errors=\"0\" skipped=\"0\" time=\"1.000\">"
+        ));
+        assert!(xml.contains("a&amp;b.avc\" classname=\"V93K_SMT7.pattern\""));
+        assert!(xml.contains("type=\"changed\">tkdiff .ref//out/v93k/a&amp;b.avc"));
+        assert!(xml.contains("<skipped message="));
+        assert!(xml.contains("See log: /out/.dispatch/job_3.log</error>"));
+        let json: Report = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json.files.len(), 3);
+        assert_eq!(json.files[0].diff_command, report.files[0].diff_command);
+    }
+}
@@ -24,6 +24,7 @@ use std::env;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Instant;
 
 #[macro_export]
 macro_rules! push_pin_actions {
@@ -168,9 +169,12 @@ pub struct Tester {
     /// Keeps track of some stats, like how many patterns have been generated, how many with
     /// diffs, etc.
     pub stats: Stats,
+    /// The files generated by this process and the outcome of checking each against its
+    /// reference
+    pub generated_files: Vec<GeneratedFile>,
 }
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Stats {
     pub generated_pattern_files: usize,
     pub changed_pattern_files: usize,
@@ -184,6 +188,118 @@ impl Stats {
     pub fn to_pickle(&self) -> Vec<u8> {
         serde_pickle::to_vec(self, true).unwrap()
     }
+
+    /// Adds the given stats to these, e.g. to combine those from several generate jobs
+    pub fn merge(&mut self, other: &Stats) {
+        self.generated_pattern_files += other.generated_pattern_files;
+        self.changed_pattern_files += other.changed_pattern_files;
+        self.new_pattern_files += other.new_pattern_files;
+        self.generated_program_files += other.generated_program_files;
+        self.changed_program_files += other.changed_program_files;
+        self.new_program_files += other.new_program_files;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileKind {
+    Pattern,
+    Program,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RefStatus {
+    /// No reference exists yet
+    New,
+    /// Differs from its reference
+    Changed,
+    Unchanged,
+    /// Not compared, e.g. no reference directory is set or no differ is available
+    NotChecked,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeneratedFile {
+    pub kind: FileKind,
+    /// The name of the tester target which generated the file
+    pub target: String,
+    pub path: PathBuf,
+    pub reference: Option<PathBuf>,
+    pub status: RefStatus,
+    /// The command to review the differences when the file has changed
+    pub diff_command: Option<String>,
+    /// The time in seconds taken to produce the file and check it against its reference. For
+    /// patterns this is measured from the start of the job which generated it.
+    pub runtime: f64,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl GeneratedFile {
+    fn new(kind: FileKind, target: &str, path: &Path, started: Instant) -> Self {
+        Self {
+            kind,
+            target: target.to_string(),
+            path: path.to_path_buf(),
+            reference: None,
+            status: RefStatus::NotChecked,
+            diff_command: None,
+            runtime: started.elapsed().as_secs_f64(),
+            started: Some(started),
+        }
+    }
+}
+
+/// Records the reference check outcome of the last generated file
+fn update_last_file(files: &mut [GeneratedFile], status: RefStatus, reference: &Path) {
+    if let Some(f) = files.last_mut() {
+        f.status = status;
+        f.reference = Some(reference.to_path_buf());
+        if status == RefStatus::Changed {
+            f.diff_command = Some(diff_command(reference, &f.path));
+        }
+        if let Some(started) = f.started {
+            f.runtime = started.elapsed().as_secs_f64();
+        }
+    }
+}
+
+/// Returns the differ which the given tester target applies when checking a generated file
+/// against its reference, falling back to a plain ASCII differ if the target is not given or
+/// does not define one
+pub fn differ_for(
+    target: Option<&str>,
+    kind: FileKind,
+    reference: &Path,
+    new: &Path,
+) -> Box<dyn Differ> {
+    if let Some(t) = target {
+        match SupportedTester::new(t).and_then(|t| instantiate_tester(&t)) {
+            Ok(tester) => {
+                let differ = match kind {
+                    FileKind::Pattern => tester.pattern_differ(reference, new),
+                    FileKind::Program => tester.program_differ(reference, new),
+                };
+                if let Some(d) = differ {
+                    return d;
+                }
+            }
+            Err(e) => log_debug!("No differ available from tester '{}' - {}", t, e),
+        }
+    }
+    Box::new(ASCIIDiffer::new(reference, new))
+}
+
+/// Returns the command to review the differences between the given reference and new files
+pub fn diff_command(reference: &Path, new: &Path) -> String {
+    let old = to_relative_path(reference, None).unwrap_or(reference.to_path_buf());
+    let new = to_relative_path(new, None).unwrap_or(new.to_path_buf());
+    format!("{} {} {}", diff_tool(), old.display(), new.display())
+}
+
+/// Returns the external tool used to review differences, given by the ORIGEN_DIFF_TOOL
+/// environment variable (tkdiff by default)
+pub fn diff_tool() -> String {
+    env::var("ORIGEN_DIFF_TOOL").unwrap_or("tkdiff".to_string())
 }
 
 impl Tester {
@@ -194,6 +310,7 @@ impl Tester {
             external_testers: IndexMap::new(),
             target_testers: vec![],
             stats: Stats::default(),
+            generated_files: vec![],
         }
     }
 
@@ -527,10 +644,14 @@ impl Tester {
                 bail!("Tester '{}' is Python-based and pattern rendering must be invoked from Python code", &gen)
             }
             TesterSource::Internal(gen) => {
+                let started = crate::with_current_job(|job| Ok(job.started))
+                    .unwrap_or_else(|_| Instant::now());
                 let paths = TEST.with_ast(|ast| gen.render_pattern(ast))?;
                 if !paths.is_empty() {
                     for path in &paths {
                         self.stats.generated_pattern_files += 1;
+                        self.generated_files
+                            .push(GeneratedFile::new(FileKind::Pattern, &gen.name(), path, started));
                         log_debug!("Tester '{}' created file '{}'", gen.name(), path.display());
                         if diff_and_display {
                             if let Ok(p) = to_relative_path(path, None) {
@@ -557,25 +678,24 @@ impl Tester {
                                                         log_error!("{}", e);
                                                     }
                                                     self.stats.changed_pattern_files += 1;
+                                                    update_last_file(
+                                                        &mut self.generated_files,
+                                                        RefStatus::Changed,
+                                                        &ref_pat,
+                                                    );
                                                     display_redln!("Diffs found");
                                                     display_diff_on_ci(&ref_pat, &path);
-                                                    let old = to_relative_path(&ref_pat, None)
-                                                        .unwrap_or(ref_pat);
-                                                    let new = to_relative_path(&path, None)
-                                                        .unwrap_or(path.to_owned());
-                                                    let diff_tool = env::var("ORIGEN_DIFF_TOOL")
-                                                        .unwrap_or("tkdiff".to_string());
-                                                    displayln!(
-                                                        "  {} {} {} &",
-                                                        &diff_tool,
-                                                        old.display(),
-                                                        new.display()
-                                                    );
+                                                    displayln!("  {} &", diff_command(&ref_pat, &path));
                                                     display!(
                                                         "  origen save_ref {}",
                                                         stem.display()
                                                     );
                                                 } else {
+                                                    update_last_file(
+                                                        &mut self.generated_files,
+                                                        RefStatus::Unchanged,
+                                                        &ref_pat,
+                                                    );
                                                     display_green!("No diffs");
                                                 }
                                             } else {
@@ -587,6 +707,11 @@ impl Tester {
                                             }
                                         } else {
                                             self.stats.new_pattern_files += 1;
+                                            update_last_file(
+                                                &mut self.generated_files,
+                                                RefStatus::New,
+                                                &ref_pat,
+                                            );
                                             if let Err(e) = reference_files::create_new_ref(
                                                 &stem, &path, &ref_pat,
                                             ) {
@@ -625,10 +750,13 @@ impl Tester {
             }
             TesterSource::Internal(gen) => {
                 log_info!("Rendering program for {}", &gen.name());
+                let started = Instant::now();
                 let (paths, model) = gen.render_program()?;
                 if !paths.is_empty() {
                     for path in &paths {
                         self.stats.generated_program_files += 1;
+                        self.generated_files
+                            .push(GeneratedFile::new(FileKind::Program, &gen.name(), path, started));
                         log_debug!("Tester '{}' created file '{}'", gen.name(), path.display());
                         if diff_and_display {
                             if let Ok(p) = to_relative_path(path, None) {
@@ -655,25 +783,24 @@ impl Tester {
                                                         log_error!("{}", e);
                                                     }
                                                     self.stats.changed_program_files += 1;
+                                                    update_last_file(
+                                                        &mut self.generated_files,
+                                                        RefStatus::Changed,
+                                                        &ref_pat,
+                                                    );
                                                     display_redln!("Diffs found");
                                                     display_diff_on_ci(&ref_pat, &path);
-                                                    let old = to_relative_path(&ref_pat, None)
-                                                        .unwrap_or(ref_pat);
-                                                    let new = to_relative_path(&path, None)
-                                                        .unwrap_or(path.to_owned());
-                                                    let diff_tool = env::var("ORIGEN_DIFF_TOOL")
-                                                        .unwrap_or("tkdiff".to_string());
-                                                    displayln!(
-                                                        "  {} {} {} &",
-                                                        &diff_tool,
-                                                        old.display(),
-                                                        new.display()
-                                                    );
+                                                    displayln!("  {} &", diff_command(&ref_pat, &path));
                                                     display!(
                                                         "  origen save_ref {}",
                                                         stem.display()
                                                     );
                                                 } else {
+                                                    update_last_file(
+                                                        &mut self.generated_files,
+                                                        RefStatus::Unchanged,
+                                                        &ref_pat,
+                                                    );
                                                     display_green!("No diffs");
                                                 }
                                             } else {
@@ -685,6 +812,11 @@ impl Tester {
                                             }
                                         } else {
                                             self.stats.new_program_files += 1;
+                                            update_last_file(
+                                                &mut self.generated_files,
+                                                RefStatus::New,
+                                                &ref_pat,
+                                            );
                                             if let Err(e) = reference_files::create_new_ref(
                                                 &stem, &path, &ref_pat,
                                             ) {
@@ -720,6 +852,8 @@ impl Tester {
                         writeln!(&mut f, "{}", pat)?;
                     }
 
+                    self.generated_files
+                        .push(GeneratedFile::new(FileKind::Program, &gen.name(), &list, started));
                     if let Ok(p) = to_relative_path(&list, None) {
                         display!("Created: {}", p.display());
                     } else {
@@ -743,24 +877,30 @@ impl Tester {
                                             log_error!("{}", e);
                                         }
                                         self.stats.changed_program_files += 1;
+                                        update_last_file(
+                                            &mut self.generated_files,
+                                            RefStatus::Changed,
+                                            &ref_list,
+                                        );
                                         display_redln!("Diffs found");
                                         display_diff_on_ci(&ref_list, &list);
-                                        let old = to_relative_path(&ref_list, None).unwrap_or(ref_list);
-                                        let new = to_relative_path(&list, None).unwrap_or(list.to_owned());
-                                        let diff_tool = std::env::var("ORIGEN_DIFF_TOOL")
-                                            .unwrap_or("tkdiff".to_string());
-                                        displayln!(
-                                            "  {} {} {} &",
-                                            &diff_tool,
-                                            old.display(),
-                                            new.display()
-                                        );
+                                        displayln!("  {} &", diff_command(&ref_list, &list));
                                         display!("  origen save_ref referenced.list");
                                     } else {
+                                        update_last_file(
+                                            &mut self.generated_files,
+                                            RefStatus::Unchanged,
+                                            &ref_list,
+                                        );
                                         display_green!("No diffs");
                                     }
                                 } else {
                                     self.stats.new_program_files += 1;
+                                    update_last_file(
+                                        &mut self.generated_files,
+                                        RefStatus::New,
+                                        &ref_list,
+                                    );
                                     if let Err(e) = reference_files::create_new_ref(
                                         Path::new("referenced.list"),
                                         &list,
@@ -837,8 +977,9 @@ impl Tester {
     /// so state can be established here which will persist for the rest of the command
     pub fn prepare_for_generate(&mut self) -> Result<()> {
         let on_lsf = crate::core::lsf::is_running_remotely();
-        // Jobs to be done before launching to the LSF
-        if !on_lsf {
+        // Jobs to be done before launching to the LSF, or to local workers, in which case the
+        // dispatching process has already done them
+        if !on_lsf && !crate::core::producer::dispatcher::is_dispatched_job() {
             reference_files::clear_save_refs()?;
         }
         Ok(())
@@ -1,4 +1,5 @@
 use super::utility::transaction::Transaction;
+use crate::services::jtag::TAPState;
 use crate::services::swd::Acknowledgements;
 use crate::testers::SupportedTester;
 use indexmap::IndexMap;
@@ -68,6 +69,12 @@ pub enum PAT {
     ), // reg_id, data, verify_enable, capture_enable, overlay_enable, overlay_str
     RegCapture(Transaction),
     RegOverlay(Transaction),
+    ResetDomain(usize, String), // model_id, domain name
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    //// Memory transaction nodes
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    MemWrite(usize, Transaction),  // memory_id, transaction
+    MemVerify(usize, Transaction), // memory_id, transaction
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
     //// JTAG nodes
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -91,7 +98,34 @@ pub enum PAT {
         Transaction,
         Metadata,
     ),
+    JTAGWriteVerifyDR(
+        Id,          // JTAG ID
+        Transaction, // Write
+        Transaction, // Verify
+        Metadata,
+    ),
     JTAGReset(Id, Metadata),
+    JTAGTrstReset(Id, Metadata),
+    JTAGMoveToState(Id, TAPState, TAPState), // JTAG ID, from state, to state
+    JTAGIdle(Id, u32),                       // JTAG ID, cycles
+    CJTAGEscape(Id, usize),                  // JTAG ID, TMSC toggles
+    CJTAGActivateOScan1(Id),                 // JTAG ID
+    IJTAGApply(Id),                          // IJTAG ID, wraps the scans applying an iApply
+    IEEE1500WriteWIR(
+        Id,     // IEEE 1500 ID
+        String, // Core name
+        Transaction,
+    ),
+    IEEE1500WriteWDR(
+        Id,     // IEEE 1500 ID
+        String, // Core name
+        Transaction,
+    ),
+    IEEE1500VerifyWDR(
+        Id,     // IEEE 1500 ID
+        String, // Core name
+        Transaction,
+    ),
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
     //// SWD nodes
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -122,6 +156,47 @@ pub enum PAT {
         Metadata,
     ),
     SWDLineReset,
+    SWDTargetSel(Id, u32), // SWD ID, TARGETSEL value
+
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    //// SPI nodes
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    SPIWrite(
+        Id, // SPI ID
+        Transaction,
+        Metadata,
+    ),
+    SPIVerify(
+        Id, // SPI ID
+        Transaction,
+        Metadata,
+    ),
+    SPICapture(
+        Id, // SPI ID
+        Transaction,
+        Metadata,
+    ),
+    SPIReset(Id), // SPI ID
+
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    //// I2C (and I3C SDR) nodes
+    //////////////////////////////////////////////////////////////////////////////////////////////////////////
+    I2CWrite(
+        Id, // I2C ID
+        Transaction,
+        Metadata,
+    ),
+    I2CVerify(
+        Id, // I2C ID
+        Transaction,
+        Metadata,
+    ),
+    I2CStart(Id),              // I2C ID
+    I2CRepeatedStart(Id),      // I2C ID
+    I2CStop(Id),               // I2C ID
+    I2CAddress(Id, u16, bool), // I2C ID, target address, read
+    I2CClockStretch(Id, u32),  // I2C ID, cycles for which SCL is released and nothing is compared
+    I2CReset(Id),              // I2C ID
 
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
     //// Arm Debug's JTAG DP Nodes
@@ -162,6 +237,20 @@ pub enum PAT {
         Transaction,
         Metadata,
     ),
+    ArmDebugMemAPWriteBlock(
+        Id,    // MemAP ID
+        usize, // MemAP address
+        usize, // Start address
+        usize, // Number of transfers
+        usize, // Transfer size (bits)
+    ),
+    ArmDebugMemAPVerifyBlock(
+        Id,    // MemAP ID
+        usize, // MemAP address
+        usize, // Start address
+        usize, // Number of transfers
+        usize, // Transfer size (bits)
+    ),
     ArmDebugWriteDP(
         Id, // DP ID
         Transaction,
@@ -174,8 +263,15 @@ pub enum PAT {
     ),
     ArmDebugSwjJTAGToSWD(Id), // arm_debug_id - Switch DP from JTAG to SWD
     ArmDebugSwjSWDToJTAG(Id), // arm_debug_id - Switch DP from SWD to JTAG
-    // ArmDebugSWJ__EnterDormant, // Switch DP to dormant
-    // ArmDebugSWJ__ExitDormant, // Switch DP from dormant back to whatever it was prior to entering dormant.
+    ArmDebugVerifyTopology(Id), // arm_debug_id
+    ArmDebugVerifyComponent(
+        Id,     // arm_debug_id
+        String, // Component name
+        usize,  // Component address
+    ),
+    ArmDebugSwjSWDToDormant(Id), // arm_debug_id - Switch DP from SWD to dormant
+    ArmDebugSwjDormantToSWD(Id), // arm_debug_id - Switch DP from dormant to SWD
+    ArmDebugSelectTarget(Id, Id), // arm_debug_id, dp_id - Select a multi-drop SWD target
 
     //////////////////////////////////////////////////////////////////////////////////////////////////////////
     //// Simple (Dummy) Protocol nodes
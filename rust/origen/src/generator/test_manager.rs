@@ -22,6 +22,15 @@ impl TestManager {
         }
     }
 
+    /// Returns a guard which serializes the unit tests that generate into the global TEST, to be
+    /// held for the duration of such a test
+    #[cfg(test)]
+    pub fn lock(&self) -> std::sync::MutexGuard<'static, ()> {
+        static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+        // A failed test shouldn't fail the others too
+        LOCK.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     /// Starts a new test (deletes the current AST and starts a new one)
     pub fn start(&self, name: &str) {
         let mut ast = self.ast.write().unwrap();
@@ -417,6 +417,52 @@ impl Transaction {
         self.width = new_width;
         Ok(())
     }
+
+    /// Returns bit i of this transaction as a single bit transaction, for protocols which shift
+    /// a bit at a time. Any overlay (to be applied for the given number of cycles) or capture of
+    /// the bit is retained. Bits beyond the width of the transaction are driven low for writes
+    /// and otherwise ignored.
+    pub fn bit_slice(&self, i: usize, overlay_cycles: usize) -> Result<Self> {
+        let one = BigUint::from(1u8);
+        let enabled = |enables: &Option<BigUint>| match enables {
+            Some(e) => ((e >> i) & &one) == one,
+            None => true,
+        };
+        if i >= self.width {
+            return match self.action {
+                Some(Action::Write) => Self::new_write(BigUint::from(0u8), 1),
+                _ => Self::new_highz(1),
+            };
+        }
+        let mut b = self.clone();
+        b.width = 1;
+        b.address = None;
+        b.address_width = None;
+        b.data = (&self.data >> i) & &one;
+        b.bit_enable = (&self.bit_enable >> i) & &one;
+        b.capture = match &self.capture {
+            Some(c) if enabled(&c.enables) => {
+                let mut c = c.clone();
+                c.cycles = Some(1);
+                c.enables = None;
+                Some(c)
+            }
+            _ => None,
+        };
+        b.overlay = match &self.overlay {
+            Some(o) if enabled(&o.enables) => {
+                let mut o = o.clone();
+                o.cycles = Some(overlay_cycles);
+                o.enables = None;
+                Some(o)
+            }
+            _ => None,
+        };
+        if let Some(actions) = &self.set_actions {
+            b.set_actions = Some(vec![actions[i].clone()]);
+        }
+        Ok(b)
+    }
 }
 
 impl NumHelpers for Transaction {
@@ -426,3 +472,23 @@ impl NumHelpers for Transaction {
         self.data.even_parity()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_slices_retain_overlays_and_captures() {
+        let mut t = Transaction::new_verify(BigUint::from(0b10u8), 2).unwrap();
+        t.apply_overlay(Some("ovl".to_string()), None, Some(BigUint::from(0b10u8)))
+            .unwrap();
+        let b0 = t.bit_slice(0, 1).unwrap();
+        let b1 = t.bit_slice(1, 1).unwrap();
+        assert_eq!(b0.data, BigUint::from(0u8));
+        assert!(b0.overlay.is_none());
+        assert_eq!(b1.data, BigUint::from(1u8));
+        assert_eq!(b1.overlay.unwrap().cycles, Some(1));
+        // Padding bits of a verify are ignored
+        assert_eq!(t.bit_slice(3, 1).unwrap().bit_enable, BigUint::from(0u8));
+    }
+}
@@ -0,0 +1,225 @@
+//! Verification of a device's CoreSight debug topology: the DP's and APs' identification
+//! registers, the debug power-up handshake and the ROM tables of each MEM-AP, along with the
+//! component and peripheral IDs of the components they reference.
+//!
+//! Pattern generation can't branch on the values read back, so rather than discovering the
+//! topology the ROM tables are walked as they are expected to be, verifying each entry
+//! (including the terminating zero entry) and then recursing into the components.
+
+use super::{ArmDebug, MemAP};
+use crate::generator::PAT;
+use crate::testers::api::ControllerAPI;
+use crate::{Dut, Result, Services, TEST};
+use std::sync::MutexGuard;
+
+/// Offsets of the identification registers, from the start of a component's 4KB block
+const PIDR4: u32 = 0xFD0;
+const PIDR0: u32 = 0xFE0;
+const CIDR0: u32 = 0xFF0;
+
+/// Only the address offset, format and present bits of a ROM table entry are compared, the
+/// power domain fields are implementation defined
+const ENTRY_ENABLES: u32 = 0xFFFF_F003;
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct Topology {
+    /// Expected DPIDR (IDCODE) value
+    pub dpidr: Option<u32>,
+    /// Request debug and system power-up and verify that it is acknowledged ahead of any AP
+    /// accesses
+    pub power_up: bool,
+    pub aps: Vec<ApTopology>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct ApTopology {
+    /// Service ID of the MemAP
+    pub mem_ap_id: usize,
+    /// Expected IDR value
+    pub idr: Option<u32>,
+    /// Address of the top-level ROM table, expected in the BASE register
+    pub base: Option<u32>,
+    /// The top-level ROM table, its offset is relative to the base address
+    pub rom_table: Option<Component>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub struct Component {
+    pub name: String,
+    /// Signed offset of the component from the ROM table referencing it, must be 4KB aligned
+    pub offset: i64,
+    pub cidr: Option<u32>,
+    /// PIDR7-0, as a 64-bit value
+    pub pidr: Option<u64>,
+    /// The entries of the component if it is itself a ROM table
+    pub entries: Option<Vec<Component>>,
+}
+
+impl Component {
+    /// The ROM table entry referencing this component: its offset, in 32-bit format and present
+    pub fn rom_table_entry(&self) -> Result<u32> {
+        if self.offset % 0x1000 != 0 {
+            bail!(
+                "The offset of CoreSight component '{}' must be 4KB aligned, given {:#X}",
+                self.name,
+                self.offset
+            );
+        }
+        Ok(((self.offset as u32) & 0xFFFF_F000) | 0x3)
+    }
+
+    /// The address of this component given the address of the ROM table referencing it
+    pub fn address(&self, table: u32) -> Result<u32> {
+        match u32::try_from(table as i64 + self.offset) {
+            Ok(a) => Ok(a),
+            Err(_) => bail!(
+                "CoreSight component '{}' at offset {:#X} from 0x{:08X} is outside of the 32-bit address space",
+                self.name,
+                self.offset,
+                table
+            ),
+        }
+    }
+}
+
+impl ArmDebug {
+    /// Verifies the debug topology: the DPIDR, the power-up handshake and then the IDR,
+    /// BASE and ROM table(s) of each AP
+    pub fn verify_topology(
+        &self,
+        dut: &mut MutexGuard<Dut>,
+        services: &Services,
+        topology: &Topology,
+    ) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::ArmDebugVerifyTopology, self.id));
+        let dp = services.get_as_dp(self.dp_id()?)?;
+        if let Some(dpidr) = topology.dpidr {
+            self.comment(&format!("Verify DPIDR: 0x{:08X}", dpidr));
+            dp.verify_dpidr(dut, services, dpidr)?;
+        }
+        if topology.power_up {
+            self.comment("Debug power-up handshake");
+            dp.power_up(dut, services)?;
+            dp.verify_powered_up(dut, services)?;
+        }
+        for ap in topology.aps.iter() {
+            let mem_ap = services.get_as_mem_ap(ap.mem_ap_id)?;
+            if let Some(idr) = ap.idr {
+                self.comment(&format!("Verify AP IDR: 0x{:08X}", idr));
+                mem_ap.verify_idr(dut, services, idr)?;
+            }
+            if let Some(base) = ap.base {
+                self.comment(&format!("Verify AP BASE: 0x{:08X}", base));
+                mem_ap.verify_base(dut, services, base)?;
+            }
+            if let Some(table) = &ap.rom_table {
+                let base = match ap.base {
+                    Some(b) => b,
+                    None => bail!(
+                        "A ROM table was given for the MemAP at service ID {} but not its base address",
+                        ap.mem_ap_id
+                    ),
+                };
+                self.verify_component(dut, services, mem_ap, table.address(base)?, table)?;
+            }
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn verify_component(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+        mem_ap: &MemAP,
+        addr: u32,
+        component: &Component,
+    ) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(
+            PAT::ArmDebugVerifyComponent,
+            self.id,
+            component.name.clone(),
+            addr as usize
+        ));
+        self.comment(&format!(
+            "CoreSight component '{}' at 0x{:08X}",
+            component.name, addr
+        ));
+        if let Some(cidr) = component.cidr {
+            for (i, byte) in id_bytes(cidr as u64, 4).enumerate() {
+                mem_ap.verify_word(dut, services, addr + CIDR0 + 4 * i as u32, byte, 0xFF)?;
+            }
+        }
+        if let Some(pidr) = component.pidr {
+            for (i, byte) in id_bytes(pidr, 8).enumerate() {
+                let offset = if i < 4 {
+                    PIDR0 + 4 * i as u32
+                } else {
+                    PIDR4 + 4 * (i as u32 - 4)
+                };
+                mem_ap.verify_word(dut, services, addr + offset, byte, 0xFF)?;
+            }
+        }
+        if let Some(entries) = &component.entries {
+            self.comment(&format!("ROM table '{}' entries", component.name));
+            for (i, entry) in entries.iter().enumerate() {
+                let e_addr = addr + 4 * i as u32;
+                mem_ap.verify_word(
+                    dut,
+                    services,
+                    e_addr,
+                    entry.rom_table_entry()?,
+                    ENTRY_ENABLES,
+                )?;
+            }
+            mem_ap.verify_word(
+                dut,
+                services,
+                addr + 4 * entries.len() as u32,
+                0,
+                0xFFFF_FFFF,
+            )?;
+            for entry in entries.iter() {
+                self.verify_component(dut, services, mem_ap, entry.address(addr)?, entry)?;
+            }
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+}
+
+/// The bytes of an ID, least significant first, each occupying the bottom byte of its register
+fn id_bytes(id: u64, n: usize) -> impl Iterator<Item = u32> {
+    (0..n).map(move |i| ((id >> (8 * i)) & 0xFF) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(offset: i64) -> Component {
+        Component {
+            name: "c".to_string(),
+            offset,
+            cidr: None,
+            pidr: None,
+            entries: None,
+        }
+    }
+
+    #[test]
+    fn rom_table_entries_encode_signed_offsets() {
+        assert_eq!(component(0x1000).rom_table_entry().unwrap(), 0x0000_1003);
+        assert_eq!(component(-0x1000).rom_table_entry().unwrap(), 0xFFFF_F003);
+        assert_eq!(
+            component(-0x1000).address(0xE00F_F000).unwrap(),
+            0xE00F_E000
+        );
+        assert!(component(0x800).rom_table_entry().is_err());
+        assert!(component(-0x1000).address(0).is_err());
+        assert_eq!(
+            id_bytes(0x04_000B_B4C9, 8).collect::<Vec<u32>>(),
+            vec![0xC9, 0xB4, 0x0B, 0x00, 0x04, 0, 0, 0]
+        );
+    }
+}
@@ -20,6 +20,8 @@ pub struct DP {
     address_block_id: usize,
     arm_debug_id: usize,
     id: usize,
+    /// The value written to TARGETSEL to select this DP on a multi-drop SWD bus
+    targetsel: Option<u32>,
 }
 
 impl DP {
@@ -28,6 +30,7 @@ impl DP {
         services: &mut crate::Services,
         model_id: usize,
         arm_debug_id: usize,
+        targetsel: Option<u32>,
     ) -> Result<usize> {
         let memory_map_id = dut.create_memory_map(model_id, "default", None)?;
         let ab_id =
@@ -134,20 +137,28 @@ impl DP {
             ab_id,
             "targetid",
             0x4,
-            None,
+            Some("RO"),
             some_hard_reset_val!(0),
-            vec!(),
-            ""
+            vec!(
+                field!("TREVISION", 28, 4, "RO", vec!(), None, ""),
+                field!("TPARTNO", 12, 16, "RO", vec!(), None, ""),
+                field!("TDESIGNER", 1, 11, "RO", vec!(), None, ""),
+                field!("RAO", 0, 1, "RO", vec!(), None, "")
+            ),
+            "Identifies the target in a multi-drop system, matched against TARGETSEL."
         );
         add_reg_32bit!(
             dut,
             ab_id,
             "dlpidr",
             0x4,
-            None,
+            Some("RO"),
             some_hard_reset_val!(0),
-            vec!(),
-            ""
+            vec!(
+                field!("TINSTANCE", 28, 4, "RO", vec!(), None, ""),
+                field!("PROTVSN", 0, 4, "RO", vec!(), None, "")
+            ),
+            "Identifies the instance of the target in a multi-drop system, matched against TARGETSEL."
         );
         add_reg_32bit!(
             dut,
@@ -205,6 +216,7 @@ impl DP {
             address_block_id: ab_id,
             arm_debug_id: arm_debug_id,
             id: id,
+            targetsel,
         }));
         Ok(id)
     }
@@ -253,6 +265,59 @@ impl DP {
         Ok(())
     }
 
+    /// Verifies the DP's identification register (DPIDR, or IDCODE on older DPs)
+    pub fn verify_dpidr(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        expected: u32,
+    ) -> Result<()> {
+        let bc = get_reg_as_bc!(dut, self.address_block_id, "idcode");
+        bc.set_data(BigUint::from(expected));
+        bc.set_verify_flag(None)?;
+        self.verify_register(dut, services, &bc)?;
+        bc.clear_flags();
+        Ok(())
+    }
+
+    /// Reads DPIDR without comparing it
+    pub fn read_dpidr(&self, dut: &MutexGuard<Dut>, services: &crate::Services) -> Result<()> {
+        self.verify_masked(dut, services, "idcode", 0, 0)
+    }
+
+    /// Verifies that the responding DP is the one selected by the given TARGETSEL value, i.e.
+    /// that TARGETID's part number and designer, and DLPIDR's instance, match it
+    pub fn verify_targetsel(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        targetsel: u32,
+    ) -> Result<()> {
+        self.verify_masked(dut, services, "targetid", targetsel, 0x0FFF_FFFF)?;
+        self.verify_masked(dut, services, "dlpidr", targetsel, 0xF000_0000)
+    }
+
+    /// Reads the given register, only comparing the bits set in enables
+    fn verify_masked(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        name: &str,
+        expected: u32,
+        enables: u32,
+    ) -> Result<()> {
+        let bc = get_reg_as_bc!(dut, self.address_block_id, name);
+        bc.set_data(BigUint::from(expected & enables));
+        bc.set_verify_flag(Some(BigUint::from(enables)))?;
+        self.verify_register(dut, services, &bc)?;
+        bc.clear_flags();
+        Ok(())
+    }
+
+    pub fn targetsel(&self) -> Option<u32> {
+        self.targetsel
+    }
+
     pub fn update_select(
         &self,
         dut: &MutexGuard<Dut>,
@@ -308,6 +373,7 @@ impl DP {
     ) -> Result<()> {
         let ad_service = services.get_service(self.arm_debug_id)?;
         let arm_debug = ad_service.as_arm_debug()?;
+        arm_debug.ensure_selected(dut, services, self.id)?;
         let jtag_dp;
         let swd_service;
         let jtag_dp_service;
@@ -348,7 +414,7 @@ impl DP {
         }
         if let Some(sel) = select {
             let bc = get_reg_as_bc!(dut, self.address_block_id, "select");
-            bc.set_data(BigUint::from(1 as u8));
+            bc.set_data(BigUint::from(sel as u32));
             TEST.push(node!(
                 PAT::Comment,
                 0,
@@ -397,3 +463,58 @@ impl DP {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{swd, ArmDebug, Services};
+    use std::sync::Mutex;
+
+    fn dp_writes(node: &Node<PAT>, found: &mut Vec<BigUint>) {
+        if let PAT::SWDWriteDP(_, t, ..) = &node.attrs {
+            found.push(t.data.clone());
+        }
+        for child in &node.children {
+            dp_writes(child, found);
+        }
+    }
+
+    #[test]
+    fn tracks_the_selected_bank() {
+        let mut dut = Dut::new("dp_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        dut.add_pin(top, "swdclk", None, None, None, None).unwrap();
+        dut.add_pin(top, "swdio", None, None, None, None).unwrap();
+        let mut services = Services::new();
+        let swd_id = services.next_id();
+        services.push_service(Service::SWD(
+            swd::Service::new(&dut, swd_id, None, None).unwrap(),
+        ));
+        let ad_id = ArmDebug::model_init(&mut dut, &mut services, top, Some(swd_id), None).unwrap();
+        let dp_model = dut.create_model(Some(top), "dp", None).unwrap();
+        let dp_id = DP::model_init(&mut dut, &mut services, dp_model, ad_id, None).unwrap();
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        *services
+            .get_as_arm_debug(ad_id)
+            .unwrap()
+            .jtagnswd
+            .write()
+            .unwrap() = false;
+        let dp = services.get_as_dp(dp_id).unwrap();
+
+        let _lock = TEST.lock();
+        TEST.start("dp_banks");
+        dp.verify_targetsel(&dut, &services, 0x1234_5677).unwrap();
+        let mut writes = vec![];
+        dp_writes(&TEST.to_node(), &mut writes);
+        // TARGETID is in bank 2, DLPIDR in bank 3
+        assert_eq!(writes, vec![BigUint::from(2u32), BigUint::from(3u32)]);
+        // And the SELECT model tracks the last one, so it isn't written again to stay in bank 3
+        TEST.start("dp_banks");
+        dp.update_select(&dut, &services, 3).unwrap();
+        let mut writes = vec![];
+        dp_writes(&TEST.to_node(), &mut writes);
+        assert!(writes.is_empty(), "{:?}", writes);
+    }
+}
@@ -124,7 +124,7 @@ impl JtagDP {
         let jtag_service = services.get_service(self.jtag_id)?;
         let jtag = jtag_service.as_jtag()?;
         let ir_trans = Transaction::new_write(BigUint::from(ir_value), self.default_ir_size)?;
-        jtag.write_ir(&dut, &ir_trans)?;
+        jtag.write_ir(&dut, &ir_trans, None)?;
         Ok(())
     }
 
@@ -196,7 +196,7 @@ impl JtagDP {
         t.bit_enable = t.bit_enable & BigUint::from(0x7_FFFF_FFF8 as u64);
         let jtag_service = services.get_service(self.jtag_id)?;
         let jtag = jtag_service.as_jtag()?;
-        jtag.verify_dr(&dut, &t)?;
+        jtag.verify_dr(&dut, &t, None)?;
         TEST.close(n_id)?;
         Ok(())
     }
@@ -268,7 +268,7 @@ impl JtagDP {
         t.bit_enable = t.bit_enable & BigUint::from(0x7_FFFF_FFF8 as u64);
         let jtag_service = services.get_service(self.jtag_id)?;
         let jtag = jtag_service.as_jtag()?;
-        jtag.verify_dr(&dut, &t)?;
+        jtag.verify_dr(&dut, &t, None)?;
         TEST.close(n_id)?;
         Ok(())
     }
@@ -284,7 +284,7 @@ impl JtagDP {
 
         let jtag_service = services.get_service(self.jtag_id)?;
         let jtag = jtag_service.as_jtag()?;
-        jtag.write_dr(&dut, &trans)?;
+        jtag.write_dr(&dut, &trans, None)?;
 
         TEST.close(n_id)?;
         Ok(())
@@ -304,7 +304,7 @@ impl JtagDP {
 
         // Write the header and data
         let trans = transaction.clone();
-        jtag.verify_dr(&dut, &trans)?;
+        jtag.verify_dr(&dut, &trans, None)?;
 
         TEST.close(n_id)?;
         Ok(())
@@ -1,11 +1,64 @@
 use super::super::super::services::Service;
 use crate::core::model::pins::PinCollection;
 use crate::generator::PAT;
+use crate::testers::api::ControllerAPI;
 use crate::Transaction;
 use crate::{add_reg_32bit, field, get_reg, some_hard_reset_val, Dut, Result, TEST};
 use num_bigint::BigUint;
 use std::sync::MutexGuard;
 
+impl ControllerAPI for MemAP {
+    fn name(&self) -> String {
+        "MemAP".to_string()
+    }
+}
+
+// TAR auto-increment is only guaranteed within a 1KB block
+const TAR_WRAP: u32 = 0x400;
+
+/// The size of each memory access of a block transfer (CSW.Size)
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum TransferSize {
+    Byte,
+    Halfword,
+    Word,
+}
+
+impl TransferSize {
+    pub fn from_bits(bits: usize) -> Result<Self> {
+        match bits {
+            8 => Ok(Self::Byte),
+            16 => Ok(Self::Halfword),
+            32 => Ok(Self::Word),
+            _ => bail!("MemAP transfers must be 8, 16 or 32 bits, given {}", bits),
+        }
+    }
+
+    pub fn bytes(&self) -> u32 {
+        match self {
+            Self::Byte => 1,
+            Self::Halfword => 2,
+            Self::Word => 4,
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        match self {
+            Self::Word => 0xFFFF_FFFF,
+            _ => (1 << (8 * self.bytes())) - 1,
+        }
+    }
+}
+
+/// A single DRW access of a block transfer, flagged when TAR has to be (re)written ahead of it
+#[derive(Debug, PartialEq)]
+struct Beat {
+    addr: u32,
+    data: u32,
+    enables: u32,
+    write_tar: bool,
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct MemAP {
     id: usize,
@@ -23,6 +76,7 @@ impl MemAP {
         model_id: usize,
         arm_debug_id: usize,
         addr: usize,
+        dp_id: Option<usize>,
     ) -> Result<usize> {
         // Create the model
         let memory_map_id = dut.create_memory_map(model_id, "default", None)?;
@@ -70,9 +124,21 @@ impl MemAP {
             ),
             "Identifies the Access Port. An IDR value of zero indicates that there is no AP present."
         );
+        add_reg_32bit!(dut, ab_id, "base", 0xF8, Some("RO"), some_hard_reset_val!(0),
+            vec!(
+                field!("BASEADDR", 12, 20, "RO", vec!(), None, ""),
+                field!("Format", 1, 1, "RO", vec!(), None, ""),
+                field!("Present", 0, 1, "RO", vec!(), None, "")
+            ),
+            "Points to the start of the debug register space, the top-level ROM table of the connected memory system."
+        );
 
         let id = services.next_id();
-        let dp_id = services.get_as_arm_debug(arm_debug_id)?.dp_id()?;
+        // Belongs to the default DP unless that of a multi-drop target is given
+        let dp_id = match dp_id {
+            Some(id) => id,
+            None => services.get_as_arm_debug(arm_debug_id)?.dp_id()?,
+        };
         services.push_service(Service::ArmDebugMemAP(Self {
             id: id,
             arm_debug_id: arm_debug_id,
@@ -112,9 +178,23 @@ impl MemAP {
         services: &crate::Services,
         t: &Transaction,
     ) -> Result<()> {
-        let reg_write_node = t.as_write_node()?;
-        let n_id = TEST.push_and_open(reg_write_node.clone());
+        let n_id = TEST.push_and_open(t.as_write_node()?);
+        self.write(dut, services, t)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Writes the given transaction through the MemAP. Unlike write_register, the transaction
+    /// is not required to be associated with a register (e.g. a memory transaction), in which
+    /// case it is treated as an access to the memory system.
+    pub fn write(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        t: &Transaction,
+    ) -> Result<()> {
         let arm_debug = services.get_as_arm_debug(self.arm_debug_id)?;
+        arm_debug.ensure_selected(dut, services, self.dp_id)?;
 
         let jtag_dp;
         let swd_service;
@@ -134,57 +214,42 @@ impl MemAP {
             jtag_dp = None;
         }
 
-        match &reg_write_node.attrs {
-            PAT::RegWrite(reg_trans) => {
-                let reg_id = reg_trans.reg_id.unwrap();
-                let reg = dut.get_register(reg_id)?;
-                let trans = t.clone();
-                let addr = trans.addr()?;
-
-                if reg.address_block_id == self.address_block_id {
-                    let trans_node = node!(
-                        PAT::ArmDebugMemAPWriteInternalReg,
-                        self.id,
-                        self.addr,
-                        trans.clone(),
-                        None // metadata
-                    );
-                    let trans_node_id = TEST.push_and_open(trans_node);
-                    {
-                        let dp = services.get_as_dp(self.dp_id)?;
-                        dp.update_select(dut, services, (addr & 0xFFFF_FFF0) as usize)?;
-                    }
-
-                    if jtag_dp.is_some() {
-                        jtag_dp.unwrap().write_ap(dut, services, trans)?;
-                    } else {
-                        swd.unwrap().write_ap(dut, trans, crate::swd_ok!())?;
-                    }
-
-                    TEST.close(trans_node_id)?;
-                } else {
-                    let trans_node = node!(
-                        PAT::ArmDebugMemAPWriteReg,
-                        self.id,
-                        self.addr,
-                        trans.clone(),
-                        None // metadata
-                    );
-                    let trans_node_id = TEST.push_and_open(trans_node);
-                    let trans = t.clone();
-                    let drw_bits = self.prep_for_transfer(&trans, dut, services)?;
-                    self.write_register(dut, services, &drw_bits)?;
-                    TEST.close(trans_node_id)?;
-                }
+        let trans = t.clone();
+        if self.is_internal(dut, t)? {
+            let addr = trans.addr()?;
+            let trans_node = node!(
+                PAT::ArmDebugMemAPWriteInternalReg,
+                self.id,
+                self.addr,
+                trans.clone(),
+                None // metadata
+            );
+            let trans_node_id = TEST.push_and_open(trans_node);
+            {
+                let dp = services.get_as_dp(self.dp_id)?;
+                dp.update_select(dut, services, (addr & 0xFFFF_FFF0) as usize)?;
             }
-            _ => {
-                bail!(
-                    "Unexpected node in ArmDebug MemAP driver: {:?}",
-                    reg_write_node
-                )
+
+            if jtag_dp.is_some() {
+                jtag_dp.unwrap().write_ap(dut, services, trans)?;
+            } else {
+                swd.unwrap().write_ap(dut, trans, crate::swd_ok!())?;
             }
+
+            TEST.close(trans_node_id)?;
+        } else {
+            let trans_node = node!(
+                PAT::ArmDebugMemAPWriteReg,
+                self.id,
+                self.addr,
+                trans.clone(),
+                None // metadata
+            );
+            let trans_node_id = TEST.push_and_open(trans_node);
+            let drw_bits = self.prep_for_transfer(&trans, dut, services)?;
+            self.write_register(dut, services, &drw_bits)?;
+            TEST.close(trans_node_id)?;
         }
-        TEST.close(n_id)?;
         Ok(())
     }
 
@@ -194,16 +259,30 @@ impl MemAP {
         services: &crate::Services,
         t: &Transaction,
     ) -> Result<()> {
-        let trans_node;
         let reg_node;
         if t.is_capture() {
             reg_node = t.as_capture_node()?;
         } else {
             reg_node = t.as_verify_node()?;
         }
-        let n_id = TEST.push_and_open(reg_node.clone());
+        let n_id = TEST.push_and_open(reg_node);
+        self.verify(dut, services, t)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
 
+    /// Verifies (or captures) the given transaction through the MemAP. Unlike verify_register,
+    /// the transaction is not required to be associated with a register (e.g. a memory
+    /// transaction), in which case it is treated as an access to the memory system.
+    pub fn verify(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        t: &Transaction,
+    ) -> Result<()> {
+        let trans_node;
         let arm_debug = services.get_as_arm_debug(self.arm_debug_id)?;
+        arm_debug.ensure_selected(dut, services, self.dp_id)?;
         let jtag_dp;
         let swd_service;
         let jtag_dp_service;
@@ -222,83 +301,390 @@ impl MemAP {
             jtag_dp = None;
         }
 
-        match &reg_node.attrs {
-            PAT::RegVerify(reg_trans) | PAT::RegCapture(reg_trans) => {
-                let reg_id = reg_trans.reg_id.unwrap();
-                let reg = dut.get_register(reg_id)?;
-                let mut trans = t.clone();
-                if reg.address_block_id == self.address_block_id {
-                    // Internal (to the MemAP) register
-                    let addr = trans.addr()?;
-                    trans_node = node!(
-                        PAT::ArmDebugMemAPVerifyInternalReg,
-                        self.id,
-                        self.addr,
-                        trans.clone(),
-                        None // metadata
-                    );
-                    let trans_node_id = TEST.push_and_open(trans_node);
-                    {
-                        let dp = services.get_as_dp(self.dp_id)?;
-                        dp.update_select(dut, services, (addr & 0xFFFF_FFF0) as usize)?;
-                    }
-                    if jtag_dp.is_some() {
-                        jtag_dp
-                            .unwrap()
-                            .verify_ap(dut, services, trans.to_dummy()?, true)?;
-                        crate::testers::vector_based::api::repeat(100);
-
-                        jtag_dp.unwrap().verify_ap(dut, services, trans, false)?;
-                    } else {
-                        swd.unwrap()
-                            .verify_ap(dut, trans.to_dummy()?, crate::swd_ok!(), None)?;
-
-                        swd.unwrap().verify_ap(dut, trans, crate::swd_ok!(), None)?;
-                    }
-                    TEST.close(trans_node_id)?;
-                } else {
-                    // External (to the MemAP) register - that is, part of the register map
-                    trans_node = node!(
-                        PAT::ArmDebugMemAPVerifyReg,
-                        self.id,
-                        self.addr,
-                        t.clone(),
-                        None
-                    );
-                    let trans_node_id = TEST.push_and_open(trans_node);
-                    self.prep_for_transfer(&trans, dut, services)?;
-
-                    if jtag_dp.is_some() {
-                        trans.address = Some(BigUint::from(0xC as u32));
-                        jtag_dp
-                            .unwrap()
-                            .verify_ap(dut, services, trans.to_dummy()?, true)?;
-                        crate::testers::vector_based::api::repeat(100);
-
-                        trans.address = Some(BigUint::from(0xC as u32));
-                        jtag_dp.unwrap().verify_dp(dut, services, trans, false)?;
-                    } else {
-                        let swdio = PinCollection::from_group(
-                            dut,
-                            &swd.unwrap().swdio.0,
-                            swd.unwrap().swdio.1,
-                        )?;
-                        trans.address = Some(BigUint::from(0xC as u32)); // DRW
-                        swd.unwrap()
-                            .verify_ap(dut, trans.to_dummy()?, crate::swd_ok!(), None)?;
-                        swdio.drive_low().cycle();
-                        trans.address = Some(BigUint::from(0xC as u32)); // RDBUFF
-                        swd.unwrap().verify_dp(dut, trans, crate::swd_ok!(), None)?;
+        let mut trans = t.clone();
+        if self.is_internal(dut, t)? {
+            // Internal (to the MemAP) register
+            let addr = trans.addr()?;
+            trans_node = node!(
+                PAT::ArmDebugMemAPVerifyInternalReg,
+                self.id,
+                self.addr,
+                trans.clone(),
+                None // metadata
+            );
+            let trans_node_id = TEST.push_and_open(trans_node);
+            {
+                let dp = services.get_as_dp(self.dp_id)?;
+                dp.update_select(dut, services, (addr & 0xFFFF_FFF0) as usize)?;
+            }
+            if jtag_dp.is_some() {
+                jtag_dp
+                    .unwrap()
+                    .verify_ap(dut, services, trans.to_dummy()?, true)?;
+                crate::testers::vector_based::api::repeat(100);
+
+                jtag_dp.unwrap().verify_ap(dut, services, trans, false)?;
+            } else {
+                swd.unwrap()
+                    .verify_ap(dut, trans.to_dummy()?, crate::swd_ok!(), None)?;
+
+                swd.unwrap().verify_ap(dut, trans, crate::swd_ok!(), None)?;
+            }
+            TEST.close(trans_node_id)?;
+        } else {
+            // External (to the MemAP) register - that is, part of the register map, or memory
+            trans_node = node!(
+                PAT::ArmDebugMemAPVerifyReg,
+                self.id,
+                self.addr,
+                t.clone(),
+                None
+            );
+            let trans_node_id = TEST.push_and_open(trans_node);
+            self.prep_for_transfer(&trans, dut, services)?;
+
+            if jtag_dp.is_some() {
+                trans.address = Some(BigUint::from(0xC as u32));
+                jtag_dp
+                    .unwrap()
+                    .verify_ap(dut, services, trans.to_dummy()?, true)?;
+                crate::testers::vector_based::api::repeat(100);
+
+                trans.address = Some(BigUint::from(0xC as u32));
+                jtag_dp.unwrap().verify_dp(dut, services, trans, false)?;
+            } else {
+                let swdio =
+                    PinCollection::from_group(dut, &swd.unwrap().swdio.0, swd.unwrap().swdio.1)?;
+                trans.address = Some(BigUint::from(0xC as u32)); // DRW
+                swd.unwrap()
+                    .verify_ap(dut, trans.to_dummy()?, crate::swd_ok!(), None)?;
+                swdio.drive_low().cycle();
+                trans.address = Some(BigUint::from(0xC as u32)); // RDBUFF
+                swd.unwrap().verify_dp(dut, trans, crate::swd_ok!(), None)?;
+                swdio.drive_low().cycle();
+            }
+            TEST.close(trans_node_id)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the given elements of data to consecutive addresses from addr, each element being a
+    /// memory access of the given size.
+    ///
+    /// TAR is written once and then auto-incremented, so each element after the first costs only
+    /// a DRW write, it is only re-written when the 1KB auto-increment boundary is crossed. With
+    /// packed set, sub-word elements are packed into each DRW access, in which case the transfer
+    /// must start on a word boundary and be a whole number of words.
+    pub fn write_block(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        addr: u32,
+        data: &[u32],
+        size: TransferSize,
+        packed: bool,
+    ) -> Result<()> {
+        let beats = block_beats(addr, data, size, packed)?;
+        let n_id = TEST.push_and_open(node!(
+            PAT::ArmDebugMemAPWriteBlock,
+            self.id,
+            self.addr,
+            addr as usize,
+            data.len(),
+            size.bytes() as usize * 8
+        ));
+        self.comment(&format!(
+            "Block write of {} {}-bit transfers from 0x{:08X}",
+            data.len(),
+            size.bytes() * 8,
+            addr
+        ));
+        self.set_block_csw(dut, services, size, packed)?;
+        for beat in beats.iter() {
+            if beat.write_tar {
+                self.write_internal_reg(dut, services, "tar", beat.addr)?;
+            }
+            self.write_internal_reg(dut, services, "drw", beat.data)?;
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Verifies the given elements of data at consecutive addresses from addr, with the same
+    /// addressing as write_block.
+    ///
+    /// Over SWD the reads are pipelined, each DRW read returning the data of the previous one,
+    /// with RDBUFF read to collect the last one (and ahead of any TAR re-write).
+    pub fn verify_block(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        addr: u32,
+        data: &[u32],
+        size: TransferSize,
+        packed: bool,
+    ) -> Result<()> {
+        let beats = block_beats(addr, data, size, packed)?;
+        let n_id = TEST.push_and_open(node!(
+            PAT::ArmDebugMemAPVerifyBlock,
+            self.id,
+            self.addr,
+            addr as usize,
+            data.len(),
+            size.bytes() as usize * 8
+        ));
+        self.comment(&format!(
+            "Block verify of {} {}-bit transfers from 0x{:08X}",
+            data.len(),
+            size.bytes() * 8,
+            addr
+        ));
+        self.set_block_csw(dut, services, size, packed)?;
+        let arm_debug = services.get_as_arm_debug(self.arm_debug_id)?;
+        arm_debug.ensure_selected(dut, services, self.dp_id)?;
+        let drw = |beat: &Beat| -> Result<Transaction> {
+            let mut t = Transaction::new_verify(BigUint::from(beat.data), 32)?;
+            t.address = Some(BigUint::from(0xCu8));
+            t.bit_enable = BigUint::from(beat.enables);
+            Ok(t)
+        };
+        if *arm_debug.jtagnswd.read().unwrap() {
+            let jtag_dp = services.get_as_jtag_dp(arm_debug.jtag_dp_id.expect(
+                "Backend Error - JTAG DP requested but a JTAG DP instance is not available (this should have been caught upstream, please review)"
+            ))?;
+            for beat in beats.iter() {
+                if beat.write_tar {
+                    self.write_internal_reg(dut, services, "tar", beat.addr)?;
+                }
+                let t = drw(beat)?;
+                jtag_dp.verify_ap(dut, services, t.to_dummy()?, true)?;
+                crate::testers::vector_based::api::repeat(100);
+                jtag_dp.verify_dp(dut, services, t, false)?;
+            }
+        } else {
+            let swd = services.get_as_swd(arm_debug.swd_id.expect(
+                "Backend Error - SWD requested but a SWD instance is not available (this should have been caught upstream, please review)"
+            ))?;
+            let swdio = PinCollection::from_group(dut, &swd.swdio.0, swd.swdio.1)?;
+            let mut pending: Option<Transaction> = None;
+            for beat in beats.iter() {
+                let t = drw(beat)?;
+                if beat.write_tar {
+                    if let Some(p) = pending.take() {
+                        swd.verify_dp(dut, p, crate::swd_ok!(), None)?; // RDBUFF
                         swdio.drive_low().cycle();
                     }
-                    TEST.close(trans_node_id)?;
+                    self.write_internal_reg(dut, services, "tar", beat.addr)?;
+                }
+                // Each DRW read returns the result of the previous one
+                match pending.replace(t.clone()) {
+                    Some(p) => swd.verify_ap(dut, p, crate::swd_ok!(), None)?,
+                    None => swd.verify_ap(dut, t.to_dummy()?, crate::swd_ok!(), None)?,
                 }
+                swdio.drive_low().cycle();
             }
-            _ => {
-                bail!("Unexpected node in ArmDebug MemAP driver: {:?}", reg_node)
+            if let Some(p) = pending {
+                swd.verify_dp(dut, p, crate::swd_ok!(), None)?; // RDBUFF
+                swdio.drive_low().cycle();
             }
         }
         TEST.close(n_id)?;
         Ok(())
     }
+
+    /// Sets the access size and address auto-increment mode for a block transfer
+    fn set_block_csw(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        size: TransferSize,
+        packed: bool,
+    ) -> Result<()> {
+        let addr_inc: u32 = if packed && size != TransferSize::Word {
+            0b10
+        } else {
+            0b01
+        };
+        let size_bits = match size {
+            TransferSize::Byte => 0,
+            TransferSize::Halfword => 1,
+            TransferSize::Word => 2,
+        };
+        self.write_internal_reg(
+            dut,
+            services,
+            "csw",
+            0x2300_0000 | (addr_inc << 4) | size_bits,
+        )
+    }
+
+    fn write_internal_reg(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        name: &str,
+        data: u32,
+    ) -> Result<()> {
+        let bc = get_reg!(dut, self.address_block_id, name).bits(dut);
+        bc.set_data(BigUint::from(data));
+        self.write_register(dut, services, &bc.to_write_transaction(dut)?)
+    }
+
+    /// Verifies the AP's identification register
+    pub fn verify_idr(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        expected: u32,
+    ) -> Result<()> {
+        self.verify_internal_reg(dut, services, "idr", expected)
+    }
+
+    /// Verifies that the AP's BASE register points to a ROM table at the given address
+    pub fn verify_base(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        base: u32,
+    ) -> Result<()> {
+        if base & 0xFFF != 0 {
+            bail!(
+                "A ROM table base address must be 4KB aligned, given 0x{:X}",
+                base
+            );
+        }
+        // 32-bit format, entry present
+        self.verify_internal_reg(dut, services, "base", base | 0x3)
+    }
+
+    fn verify_internal_reg(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        name: &str,
+        expected: u32,
+    ) -> Result<()> {
+        let bc = get_reg!(dut, self.address_block_id, name).bits(dut);
+        bc.set_data(BigUint::from(expected));
+        let t = bc.to_verify_transaction(None, false, dut)?;
+        self.verify_register(dut, services, &t)?;
+        bc.clear_flags();
+        Ok(())
+    }
+
+    /// Verifies the 32-bit word at the given address of the memory system, only the bits set in
+    /// enables are compared
+    pub fn verify_word(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &crate::Services,
+        addr: u32,
+        data: u32,
+        enables: u32,
+    ) -> Result<()> {
+        let mut t = Transaction::new_verify(BigUint::from(data), 32)?;
+        t.address = Some(BigUint::from(addr));
+        t.address_width = Some(32);
+        t.bit_enable = BigUint::from(enables);
+        self.verify(dut, services, &t)
+    }
+
+    /// Returns true if the given transaction is for one of the MemAP's own registers, rather
+    /// than for the memory system behind it
+    fn is_internal(&self, dut: &MutexGuard<Dut>, t: &Transaction) -> Result<bool> {
+        match t.reg_id {
+            Some(id) => Ok(dut.get_register(id)?.address_block_id == self.address_block_id),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Maps the elements of a block transfer onto DRW accesses, placing each element on the byte
+/// lanes of its address
+fn block_beats(addr: u32, data: &[u32], size: TransferSize, packed: bool) -> Result<Vec<Beat>> {
+    let bytes = size.bytes();
+    if !addr.is_multiple_of(bytes) {
+        bail!(
+            "The start address of a {}-bit block transfer must be {} byte aligned, given 0x{:08X}",
+            bytes * 8,
+            bytes,
+            addr
+        );
+    }
+    if let Some(d) = data.iter().find(|d| **d > size.mask()) {
+        bail!(
+            "Block transfer data 0x{:X} does not fit in a {}-bit transfer",
+            d,
+            bytes * 8
+        );
+    }
+    let per_beat = if packed { 4 / bytes } else { 1 } as usize;
+    if per_beat > 1 && (!addr.is_multiple_of(4) || !data.len().is_multiple_of(per_beat)) {
+        bail!(
+            "Packed block transfers must start on a word boundary and be a whole number of words"
+        );
+    }
+    if (addr as u64) + (data.len() as u64) * (bytes as u64) > 1 << 32 {
+        bail!(
+            "Block transfer from 0x{:08X} overruns the 32-bit address space",
+            addr
+        );
+    }
+    let mut beats = vec![];
+    for (i, elements) in data.chunks(per_beat).enumerate() {
+        let beat_addr = addr + (i * per_beat) as u32 * bytes;
+        let mut beat = Beat {
+            addr: beat_addr,
+            data: 0,
+            enables: 0,
+            write_tar: i == 0 || beat_addr.is_multiple_of(TAR_WRAP),
+        };
+        for (j, e) in elements.iter().enumerate() {
+            let lane = 8 * ((beat_addr + j as u32 * bytes) % 4);
+            beat.data |= e << lane;
+            beat.enables |= size.mask() << lane;
+        }
+        beats.push(beat);
+    }
+    Ok(beats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_beats_place_byte_lanes_and_rewrite_tar_at_1kb() {
+        let beats =
+            block_beats(0x2000_0002, &[0xAA, 0xBB, 0xCC], TransferSize::Byte, false).unwrap();
+        let lanes: Vec<(u32, u32, u32)> =
+            beats.iter().map(|b| (b.addr, b.data, b.enables)).collect();
+        assert_eq!(
+            lanes,
+            vec![
+                (0x2000_0002, 0x00AA_0000, 0x00FF_0000),
+                (0x2000_0003, 0xBB00_0000, 0xFF00_0000),
+                (0x2000_0004, 0x0000_00CC, 0x0000_00FF)
+            ]
+        );
+
+        let beats = block_beats(
+            0x3F8,
+            &[0x1111, 0x2222, 0x3333, 0x4444, 0x5555, 0x6666],
+            TransferSize::Halfword,
+            true,
+        )
+        .unwrap();
+        assert_eq!(beats.len(), 3);
+        assert_eq!(beats[0].data, 0x2222_1111);
+        assert_eq!(beats[2].data, 0x6666_5555);
+        assert_eq!(
+            beats.iter().map(|b| b.write_tar).collect::<Vec<bool>>(),
+            vec![true, false, true]
+        );
+
+        assert!(block_beats(0x1, &[0], TransferSize::Halfword, false).is_err());
+        assert!(block_beats(0x0, &[0x100], TransferSize::Byte, false).is_err());
+        assert!(block_beats(0x0, &[1, 2, 3], TransferSize::Byte, true).is_err());
+    }
 }
@@ -1,7 +1,9 @@
+pub mod coresight;
 pub mod dp;
 pub mod jtag_dp;
 pub mod mem_ap;
 
+pub use coresight::{ApTopology, Component, Topology};
 pub use dp::DP;
 pub use jtag_dp::JtagDP;
 pub use mem_ap::MemAP;
@@ -10,6 +12,7 @@ use super::super::services::Service;
 use crate::core::model::pins::PinCollection;
 use crate::testers::api::ControllerAPI;
 use crate::{Dut, Services, Transaction};
+use num_bigint::BigUint;
 use std::collections::HashMap;
 use std::sync::MutexGuard;
 use std::sync::RwLock;
@@ -17,6 +20,10 @@ use std::sync::RwLock;
 use crate::generator::PAT;
 use crate::Result;
 
+// The 128-bit selection alert sequence, which precedes the activation code when leaving the
+// dormant state
+const SELECTION_ALERT: u128 = 0x19BC_0EA2_E3DD_AFE9_8685_2D95_6209_F392;
+
 impl ControllerAPI for ArmDebug {
     fn name(&self) -> String {
         "ArmDebug".to_string()
@@ -34,6 +41,10 @@ pub struct ArmDebug {
     mem_ap_ids: HashMap<usize, usize>,
     dp_id: Option<usize>,
     jtag_dp_id: Option<usize>,
+    /// IDs of the DPs of further targets sharing a multi-drop SWD bus with the default DP
+    target_dp_ids: Vec<usize>,
+    /// The DP last selected by a TARGETSEL write
+    pub selected_dp_id: RwLock<Option<usize>>,
 
     // Arm debug only support JTAG or SWD.
     // Store this just as a bool:
@@ -45,6 +56,9 @@ pub struct ArmDebug {
     pub swd_id: Option<usize>,
     /// The JTAG Service which operates this ArmDebug
     pub jtag_id: Option<usize>,
+
+    /// The expected debug topology, if defined
+    pub topology: Option<Topology>,
 }
 
 impl ArmDebug {
@@ -63,11 +77,14 @@ impl ArmDebug {
             id: id,
             dp_id: None,
             jtag_dp_id: None,
+            target_dp_ids: vec![],
+            selected_dp_id: RwLock::new(None),
             mem_ap_ids: HashMap::new(),
             model_id: model_id,
             jtagnswd: RwLock::new(true),
             swd_id: swd_id,
             jtag_id: jtag_id,
+            topology: None,
         };
         services.push_service(Service::ArmDebug(s));
         Ok(id)
@@ -96,12 +113,120 @@ impl ArmDebug {
 
                 crate::TEST.close(n_id)?;
                 *self.jtagnswd.write().unwrap() = false;
+                *self.selected_dp_id.write().unwrap() = None;
                 Ok(())
             }
             None => Err(error!("No SWD available - cannot switch to SWD")),
         }
     }
 
+    /// Switches the DP from SWD to the dormant state
+    pub fn switch_to_dormant(&self, dut: &MutexGuard<Dut>, services: &Services) -> Result<()> {
+        let swd = services.get_as_swd(self.swd_id()?)?;
+        let swdclk = PinCollection::from_group(dut, &swd.swdclk.0, swd.swdclk.1)?;
+        let swdio = PinCollection::from_group(dut, &swd.swdio.0, swd.swdio.1)?;
+        let n_id = crate::TEST.push_and_open(node!(PAT::ArmDebugSwjSWDToDormant, self.id));
+        self.comment("Switching ArmDebug protocol from SWD to dormant");
+        swdclk.drive_high();
+        swdio.drive_high().repeat(50);
+        swdio.push_transaction(&Transaction::new_write(BigUint::from(0xE3BCu32), 16)?)?;
+        crate::TEST.close(n_id)?;
+        *self.selected_dp_id.write().unwrap() = None;
+        Ok(())
+    }
+
+    /// Switches the DP(s) from the dormant state to SWD: the selection alert sequence followed by
+    /// the SWD activation code and a line reset. Multi-drop targets must then be selected before
+    /// they respond.
+    pub fn switch_dormant_to_swd(&self, dut: &MutexGuard<Dut>, services: &Services) -> Result<()> {
+        let swd = services.get_as_swd(self.swd_id()?)?;
+        let swdclk = PinCollection::from_group(dut, &swd.swdclk.0, swd.swdclk.1)?;
+        let swdio = PinCollection::from_group(dut, &swd.swdio.0, swd.swdio.1)?;
+        let n_id = crate::TEST.push_and_open(node!(PAT::ArmDebugSwjDormantToSWD, self.id));
+        self.comment("Switching ArmDebug protocol from dormant to SWD");
+        swdclk.drive_high();
+        swdio.drive_high().repeat(8);
+        self.comment("Selection alert sequence");
+        swdio.push_transaction(&Transaction::new_write(
+            BigUint::from(SELECTION_ALERT),
+            128,
+        )?)?;
+        swdio.drive_low().repeat(4);
+        self.comment("SWD activation code");
+        swdio.push_transaction(&Transaction::new_write(BigUint::from(0x1Au8), 8)?)?;
+        swd.line_reset(dut)?;
+        crate::TEST.close(n_id)?;
+        *self.jtagnswd.write().unwrap() = false;
+        *self.selected_dp_id.write().unwrap() = None;
+        Ok(())
+    }
+
+    /// Selects the multi-drop target of the given DP: a line reset followed by a TARGETSEL write
+    /// and the DPIDR read required to leave the reset state, verifying the DPIDR if given and
+    /// that the TARGETID and DLPIDR match the target selected
+    pub fn select_target(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+        dp_id: usize,
+        dpidr: Option<u32>,
+    ) -> Result<()> {
+        let dp = services.get_as_dp(dp_id)?;
+        let targetsel = match dp.targetsel() {
+            Some(t) => t,
+            None => bail!(
+                "The DP at service ID {} has no TARGETSEL value and cannot be selected as a multi-drop target",
+                dp_id
+            ),
+        };
+        let swd = services.get_as_swd(self.swd_id()?)?;
+        let n_id = crate::TEST.push_and_open(node!(PAT::ArmDebugSelectTarget, self.id, dp_id));
+        self.comment(&format!("Select multi-drop target 0x{:08X}", targetsel));
+        swd.line_reset(dut)?;
+        swd.write_targetsel(dut, targetsel)?;
+        *self.selected_dp_id.write().unwrap() = Some(dp_id);
+        match dpidr {
+            Some(id) => dp.verify_dpidr(dut, services, id)?,
+            None => dp.read_dpidr(dut, services)?,
+        }
+        dp.verify_targetsel(dut, services, targetsel)?;
+        crate::TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Selects the multi-drop target of the given DP unless it is already selected, this is a
+    /// no-op unless operating over SWD with a DP that has a TARGETSEL value
+    pub fn ensure_selected(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+        dp_id: usize,
+    ) -> Result<()> {
+        if *self.jtagnswd.read().unwrap()
+            || *self.selected_dp_id.read().unwrap() == Some(dp_id)
+            || services.get_as_dp(dp_id)?.targetsel().is_none()
+        {
+            return Ok(());
+        }
+        self.select_target(dut, services, dp_id, None)
+    }
+
+    pub fn add_target_dp_id(&mut self, dp_id: usize) -> Result<()> {
+        self.target_dp_ids.push(dp_id);
+        Ok(())
+    }
+
+    pub fn target_dp_ids(&self) -> &Vec<usize> {
+        &self.target_dp_ids
+    }
+
+    fn swd_id(&self) -> Result<usize> {
+        match self.swd_id {
+            Some(id) => Ok(id),
+            None => bail!("No SWD available for ArmDebug instance at {}", self.id),
+        }
+    }
+
     pub fn set_dp_id(&mut self, dp_id: usize) -> Result<()> {
         self.dp_id = Some(dp_id);
         Ok(())
@@ -134,3 +259,199 @@ impl ArmDebug {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::swd;
+    use crate::utility::mem_image::{MemImage, TransactionOptions, VerifyMode};
+    use crate::TEST;
+    use origen_metal::ast::Node;
+    use std::sync::Mutex;
+
+    /// Returns the state of the given pin in each cycle of the AST
+    fn waveform(node: &Node<PAT>, pin_id: usize, state: &mut String, wave: &mut String) {
+        match &node.attrs {
+            PAT::PinAction(id, action, _) if *id == pin_id => *state = action.to_string(),
+            PAT::Cycle(n, _) => {
+                for _ in 0..*n {
+                    wave.push_str(state);
+                }
+            }
+            _ => {}
+        }
+        for child in &node.children {
+            waveform(child, pin_id, state, wave);
+        }
+    }
+
+    fn find<'a>(node: &'a Node<PAT>, found: &mut Vec<&'a PAT>) {
+        match &node.attrs {
+            PAT::SWDTargetSel(..) | PAT::SWDVerifyDP(..) | PAT::SWDWriteDP(..) => {
+                found.push(&node.attrs)
+            }
+            _ => {}
+        }
+        for child in &node.children {
+            find(child, found);
+        }
+    }
+
+    /// The bits of the given value in the order they are driven, LSB first
+    fn lsb_first(value: u128, width: usize) -> String {
+        (0..width)
+            .map(|i| if (value >> i) & 1 == 1 { '1' } else { '0' })
+            .collect()
+    }
+
+    fn reg_accesses<'a>(node: &'a Node<PAT>, found: &mut Vec<&'a Transaction>) {
+        match &node.attrs {
+            PAT::RegWrite(t) | PAT::RegVerify(t) => found.push(t),
+            _ => {}
+        }
+        for child in &node.children {
+            reg_accesses(child, found);
+        }
+    }
+
+    #[test]
+    fn downloads_images_as_memory_accesses() {
+        let mut dut = Dut::new("arm_debug_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        dut.add_pin(top, "swdclk", None, None, None, None).unwrap();
+        dut.add_pin(top, "swdio", None, None, None, None).unwrap();
+        let mut services = Services::new();
+        let swd_id = services.next_id();
+        services.push_service(Service::SWD(
+            swd::Service::new(&dut, swd_id, None, None).unwrap(),
+        ));
+        let ad_id = ArmDebug::model_init(&mut dut, &mut services, top, Some(swd_id), None).unwrap();
+        let dp_model = dut.create_model(Some(top), "dp", None).unwrap();
+        let dp_id = DP::model_init(&mut dut, &mut services, dp_model, ad_id, None).unwrap();
+        services
+            .get_as_mut_arm_debug(ad_id)
+            .unwrap()
+            .set_dp_id(dp_id)
+            .unwrap();
+        let ap_model = dut.create_model(Some(top), "mem_ap", None).unwrap();
+        let ap_id = MemAP::model_init(&mut dut, &mut services, ap_model, ad_id, 0, None).unwrap();
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+
+        let mut image = MemImage::default();
+        image.add(0x2000_0000, &[1, 2, 3, 4, 5, 6, 7, 8]);
+        let _lock = TEST.lock();
+        TEST.start("download");
+        // Already in SWD
+        *services
+            .get_as_arm_debug(ad_id)
+            .unwrap()
+            .jtagnswd
+            .write()
+            .unwrap() = false;
+        let n = image
+            .download(
+                &TransactionOptions::default(),
+                VerifyMode::EachWrite,
+                ap_id,
+                &dut,
+                &services,
+            )
+            .unwrap();
+        assert_eq!(n, 2);
+        let ast = TEST.to_node();
+        let mut found = vec![];
+        reg_accesses(&ast, &mut found);
+        // Only the MemAP's own registers are accessed, the image isn't written as registers
+        assert!(!found.is_empty());
+        assert!(found.iter().all(|t| t.reg_id.is_some()), "{:?}", found);
+    }
+
+    #[test]
+    fn selects_multi_drop_targets() {
+        let mut dut = Dut::new("arm_debug_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        dut.add_pin(top, "swdclk", None, None, None, None).unwrap();
+        dut.add_pin(top, "swdio", None, None, None, None).unwrap();
+        let mut services = Services::new();
+        let swd_id = services.next_id();
+        services.push_service(Service::SWD(
+            swd::Service::new(&dut, swd_id, None, None).unwrap(),
+        ));
+        let ad_id = ArmDebug::model_init(&mut dut, &mut services, top, Some(swd_id), None).unwrap();
+        let mut dp_ids = vec![];
+        for (name, targetsel) in [("dp0", 0x1234_5677u32), ("dp1", 0x2234_5677)] {
+            let model_id = dut.create_model(Some(top), name, None).unwrap();
+            dp_ids.push(
+                DP::model_init(&mut dut, &mut services, model_id, ad_id, Some(targetsel)).unwrap(),
+            );
+        }
+        let swdio = PinCollection::from_group(&dut, "swdio", top)
+            .unwrap()
+            .as_ids()[0];
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let ad = services.get_as_arm_debug(ad_id).unwrap();
+
+        let _lock = TEST.lock();
+        TEST.start("dormant_to_swd");
+        ad.switch_dormant_to_swd(&dut, &services).unwrap();
+        let mut wave = String::new();
+        waveform(&TEST.to_node(), swdio, &mut String::new(), &mut wave);
+        let expected = format!(
+            "{}{}0000{}{}",
+            "1".repeat(8),
+            lsb_first(SELECTION_ALERT, 128),
+            lsb_first(0x1A, 8),
+            "1".repeat(50)
+        );
+        assert!(wave.starts_with(&expected), "{}", wave);
+
+        TEST.start("select_targets");
+        ad.select_target(&dut, &services, dp_ids[0], Some(0x0BC1_2477))
+            .unwrap();
+        // Accessing the other DP selects it first
+        let dp1 = services.get_as_dp(dp_ids[1]).unwrap();
+        dp1.read_dpidr(&dut, &services).unwrap();
+        dp1.read_dpidr(&dut, &services).unwrap();
+        let ast = TEST.to_node();
+        let mut found = vec![];
+        find(&ast, &mut found);
+        let verifies: Vec<(u32, u32)> = found
+            .iter()
+            .filter_map(|n| match n {
+                PAT::SWDVerifyDP(_, t, ..) => Some((
+                    t.data.clone().try_into().unwrap(),
+                    t.bit_enable.clone().try_into().unwrap(),
+                )),
+                _ => None,
+            })
+            .collect();
+        let targetsels: Vec<u32> = found
+            .iter()
+            .filter_map(|n| match n {
+                PAT::SWDTargetSel(_, t) => Some(*t),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(targetsels, vec![0x1234_5677, 0x2234_5677]);
+        assert_eq!(
+            verifies,
+            vec![
+                // DPIDR, TARGETID (all but the instance) and DLPIDR (only the instance)
+                (0x0BC1_2477, 0xFFFF_FFFF),
+                (0x0234_5677, 0x0FFF_FFFF),
+                (0x1000_0000, 0xF000_0000),
+                // The second target is switched to with its DPIDR read, then verified, before
+                // the requested read
+                (0, 0),
+                (0x0234_5677, 0x0FFF_FFFF),
+                (0x2000_0000, 0xF000_0000),
+                (0, 0),
+                // While it is not selected again for further accesses
+                (0, 0),
+            ]
+        );
+        assert_eq!(*ad.selected_dp_id.read().unwrap(), Some(dp_ids[1]));
+    }
+}
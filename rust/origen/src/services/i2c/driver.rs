@@ -0,0 +1,452 @@
+use super::service::{Mode, Service};
+use crate::core::model::pins::PinCollection;
+use crate::generator::PAT;
+use crate::testers::api::ControllerAPI;
+use crate::utility::num_helpers::NumHelpers;
+use crate::{Dut, Result, Transaction, TEST};
+use num_bigint::BigUint;
+use origen_metal::ast::Node;
+
+// The I3C broadcast address, sent ahead of the target's address
+const I3C_BROADCAST: u16 = 0x7E;
+
+impl ControllerAPI for Service {
+    fn name(&self) -> String {
+        match self.config.mode {
+            Mode::I2C => "I2C".to_string(),
+            Mode::I3CSDR => "I3C".to_string(),
+        }
+    }
+}
+
+struct Pins<'a> {
+    scl: PinCollection<'a>,
+    sda: PinCollection<'a>,
+}
+
+impl Service {
+    fn pins<'a>(&self, dut: &'a Dut) -> Result<Pins<'a>> {
+        Ok(Pins {
+            scl: PinCollection::from_group(dut, &self.scl.0, self.scl.1)?,
+            sda: PinCollection::from_group(dut, &self.sda.0, self.sda.1)?,
+        })
+    }
+
+    pub(crate) fn idle(&self, dut: &Dut) -> Result<()> {
+        let pins = self.pins(dut)?;
+        self.comment("Return bus to idle");
+        pins.scl.drive_high();
+        pins.sda.drive_high().cycle();
+        Ok(())
+    }
+
+    fn start(&self, pins: &Pins) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::I2CStart, self.id));
+        self.comment("START");
+        pins.scl.drive_high();
+        pins.sda.drive_high().cycle();
+        pins.sda.drive_low().cycle();
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn repeated_start(&self, pins: &Pins) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::I2CRepeatedStart, self.id));
+        self.comment("Repeated START");
+        pins.scl.drive_low();
+        pins.sda.drive_high().cycle();
+        pins.scl.drive_high().cycle();
+        pins.sda.drive_low().cycle();
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn stop(&self, pins: &Pins) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::I2CStop, self.id));
+        self.comment("STOP");
+        pins.scl.drive_low();
+        pins.sda.drive_low().cycle();
+        pins.scl.drive_high().cycle();
+        pins.sda.drive_high().cycle();
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Generates a single SCL period, SDA is set by the host while SCL is low and by the target
+    /// while SCL is high
+    fn clock(
+        &self,
+        pins: &Pins,
+        host: Option<&Transaction>,
+        target: Option<&Transaction>,
+    ) -> Result<()> {
+        pins.scl.drive_low();
+        match host {
+            Some(t) => {
+                pins.sda.push_transaction(t)?;
+            }
+            None => {
+                pins.sda.highz().cycle();
+            }
+        }
+        pins.scl.drive_high();
+        match target {
+            Some(t) => {
+                pins.sda.push_transaction(t)?;
+            }
+            None => {
+                let overlay = host.is_some_and(|t| t.has_overlay());
+                pins.scl.repeat2(1, !overlay);
+            }
+        }
+        Ok(())
+    }
+
+    /// The 9th clock of a byte, preceded by the clock stretching allowance
+    fn ninth_clock(
+        &self,
+        pins: &Pins,
+        host: Option<&Transaction>,
+        target: Option<&Transaction>,
+    ) -> Result<()> {
+        let cycles = self.config.stretch_cycles;
+        if cycles > 0 && self.config.mode == Mode::I2C {
+            let n_id = TEST.push_and_open(node!(PAT::I2CClockStretch, self.id, cycles));
+            self.comment("Allow clock stretching");
+            pins.scl.drive_low();
+            pins.sda.highz().cycle();
+            pins.scl.highz().repeat(cycles);
+            TEST.close(n_id)?;
+        }
+        self.clock(pins, host, target)
+    }
+
+    fn write_byte(&self, pins: &Pins, t: &Transaction, byte: usize) -> Result<()> {
+        for i in (byte * 8..(byte + 1) * 8).rev() {
+            self.clock(pins, Some(&t.bit_slice(i, 2)?), None)?;
+        }
+        Ok(())
+    }
+
+    fn verify_ack(&self, pins: &Pins) -> Result<()> {
+        self.ninth_clock(pins, None, Some(&Transaction::new_verify(0u8.into(), 1)?))
+    }
+
+    /// Sends the target's address, for 10-bit addresses a read only sends the first byte and
+    /// must follow a write of the full address
+    fn address(&self, pins: &Pins, address: u16, read: bool) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::I2CAddress, self.id, address, read));
+        self.comment(&format!(
+            "Address: 0x{:X} ({})",
+            address,
+            if read { "read" } else { "write" }
+        ));
+        if self.config.ten_bit {
+            let first = 0b1111_0000 | ((address >> 7) & 0b110) | read as u16;
+            self.write_byte(pins, &Transaction::new_write(first.into(), 8)?, 0)?;
+            self.verify_ack(pins)?;
+            if !read {
+                let second = address & 0xFF;
+                self.write_byte(pins, &Transaction::new_write(second.into(), 8)?, 0)?;
+                self.verify_ack(pins)?;
+            }
+        } else {
+            let byte = (address << 1) | read as u16;
+            self.write_byte(pins, &Transaction::new_write(byte.into(), 8)?, 0)?;
+            self.verify_ack(pins)?;
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Writes the bytes of the given transaction, most significant first. In I3C mode each byte
+    /// is followed by its parity (T) bit rather than an ACK.
+    fn write_bytes(&self, pins: &Pins, t: &Transaction) -> Result<()> {
+        for byte in (0..bytes(t.width)).rev() {
+            self.write_byte(pins, t, byte)?;
+            match self.config.mode {
+                Mode::I2C => self.verify_ack(pins)?,
+                Mode::I3CSDR => {
+                    let data = (&t.data >> (byte * 8)) & BigUint::from(0xFFu8);
+                    let parity = !data.even_parity();
+                    self.clock(
+                        pins,
+                        Some(&Transaction::new_write(BigUint::from(parity as u8), 1)?),
+                        None,
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the bytes of the given transaction, most significant first. The host ACKs all but
+    /// the last byte, in I3C mode the target's T bit is not compared.
+    fn read_bytes(&self, pins: &Pins, t: &Transaction) -> Result<()> {
+        let n = bytes(t.width);
+        for byte in (0..n).rev() {
+            for i in (byte * 8..(byte + 1) * 8).rev() {
+                self.clock(pins, None, Some(&t.bit_slice(i, 1)?))?;
+            }
+            match self.config.mode {
+                Mode::I2C => {
+                    let nack = byte == 0;
+                    let ack = Transaction::new_write(BigUint::from(nack as u8), 1)?;
+                    self.ninth_clock(pins, Some(&ack), None)?;
+                }
+                Mode::I3CSDR => self.clock(pins, None, None)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn register_address(&self, pins: &Pins, t: &Transaction) -> Result<()> {
+        let addr = match &t.address {
+            Some(a) => a.clone(),
+            None => bail!(
+                "{} service '{}' requires a register address for each transaction, but none was given: {:?}",
+                self.name(),
+                self.id,
+                t
+            ),
+        };
+        self.comment(&format!("Register address: 0x{:X}", addr));
+        self.write_bytes(
+            pins,
+            &Transaction::new_write(addr, self.config.reg_addr_width)?,
+        )
+    }
+
+    pub fn process_transaction(&self, dut: &Dut, node: &mut Node<PAT>) -> Result<()> {
+        let pins = self.pins(dut)?;
+        let c = &self.config;
+        match &node.attrs {
+            PAT::I2CWrite(_i2c_id, transaction, _metadata) => {
+                self.comment(&format!("Write - Data: 0x{:X}", transaction.data));
+                self.start(&pins)?;
+                if c.mode == Mode::I3CSDR {
+                    self.address(&pins, I3C_BROADCAST, false)?;
+                    self.repeated_start(&pins)?;
+                }
+                self.address(&pins, c.address, false)?;
+                if c.reg_addr_width > 0 {
+                    self.register_address(&pins, transaction)?;
+                }
+                self.comment("Write data: host -> target");
+                self.write_bytes(&pins, transaction)?;
+                self.stop(&pins)?;
+            }
+            PAT::I2CVerify(_i2c_id, transaction, _metadata) => {
+                self.comment(&format!("Verify - Data: 0x{:X}", transaction.data));
+                self.start(&pins)?;
+                if c.mode == Mode::I3CSDR {
+                    self.address(&pins, I3C_BROADCAST, false)?;
+                    self.repeated_start(&pins)?;
+                }
+                if c.reg_addr_width > 0 || c.ten_bit {
+                    self.address(&pins, c.address, false)?;
+                    if c.reg_addr_width > 0 {
+                        self.register_address(&pins, transaction)?;
+                    }
+                    self.repeated_start(&pins)?;
+                }
+                self.address(&pins, c.address, true)?;
+                self.comment("Read data: target -> host");
+                self.read_bytes(&pins, transaction)?;
+                self.stop(&pins)?;
+            }
+            _ => {
+                bail!("Unexpected node in {} driver: {:?}", self.name(), node)
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The number of whole bytes needed to hold the given number of bits
+fn bytes(width: usize) -> usize {
+    width.div_ceil(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::i2c::service::Config;
+    use std::sync::Mutex;
+
+    /// Returns the state of SCL and SDA in each cycle of the AST
+    fn cycles(
+        node: &Node<PAT>,
+        pins: (usize, usize),
+        state: &mut (String, String),
+        out: &mut Vec<(String, String)>,
+    ) {
+        match &node.attrs {
+            PAT::PinAction(id, action, _) if *id == pins.0 => state.0 = action.to_string(),
+            PAT::PinAction(id, action, _) if *id == pins.1 => state.1 = action.to_string(),
+            PAT::Cycle(n, _) => {
+                for _ in 0..*n {
+                    out.push(state.clone());
+                }
+            }
+            _ => {}
+        }
+        for child in &node.children {
+            cycles(child, pins, state, out);
+        }
+    }
+
+    /// Decodes the bus activity into a frame, with S for a (repeated) START, P for a STOP and
+    /// SDA's state at each rising edge of SCL for the bits in between
+    fn frame(out: &[(String, String)]) -> String {
+        let mut f = String::new();
+        let mut prev = ("1".to_string(), "1".to_string());
+        // SCL rises ahead of a START or STOP, that isn't a bit
+        let mut sampled = false;
+        for (scl, sda) in out {
+            if scl == "1" && prev.0 == "1" && sda != &prev.1 {
+                if sampled {
+                    f.pop();
+                    sampled = false;
+                }
+                f.push(if sda == "0" { 'S' } else { 'P' });
+            } else if scl == "1" && prev.0 != "1" {
+                f.push_str(sda);
+                sampled = true;
+            }
+            prev = (scl.clone(), sda.clone());
+        }
+        f
+    }
+
+    /// The bits of a byte sent by the host
+    fn host(byte: u8) -> String {
+        format!("{:08b}", byte)
+    }
+
+    /// The bits of a byte expected from the target
+    fn target(byte: u8) -> String {
+        host(byte).replace('0', "L").replace('1', "H")
+    }
+
+    fn generate<F>(config: Config, func: F) -> (String, Vec<(String, String)>)
+    where
+        F: Fn(&Dut, &Service),
+    {
+        let mut dut = Dut::new("i2c_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        dut.add_pin(top, "scl", None, None, None, None).unwrap();
+        dut.add_pin(top, "sda", None, None, None, None).unwrap();
+        let i2c = Service::new(&dut, 0, None, None, config).unwrap();
+        let scl = PinCollection::from_group(&dut, "scl", top)
+            .unwrap()
+            .as_ids()[0];
+        let sda = PinCollection::from_group(&dut, "sda", top)
+            .unwrap()
+            .as_ids()[0];
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let _lock = TEST.lock();
+        TEST.start("i2c");
+        func(&dut, &i2c);
+        let mut out = vec![];
+        let mut state = ("1".to_string(), "1".to_string());
+        cycles(&TEST.to_node(), (scl, sda), &mut state, &mut out);
+        (frame(&out), out)
+    }
+
+    fn transaction(data: u32, width: usize, address: u8) -> Transaction {
+        let mut t = Transaction::new_write(BigUint::from(data), width).unwrap();
+        t.address = Some(BigUint::from(address));
+        t
+    }
+
+    #[test]
+    fn writes_address_register_and_data() {
+        let config = Config {
+            address: 0x50,
+            ..Default::default()
+        };
+        let (f, _) = generate(config, |dut, i2c| {
+            i2c.write(dut, transaction(0xA5, 8, 0x12)).unwrap();
+        });
+        // Each byte is ACKed by the target
+        assert_eq!(
+            f,
+            format!("S{}L{}L{}LP", host(0xA0), host(0x12), host(0xA5))
+        );
+    }
+
+    #[test]
+    fn reads_with_a_repeated_start_and_nacks_the_last_byte() {
+        let config = Config {
+            address: 0x50,
+            ..Default::default()
+        };
+        let (f, _) = generate(config, |dut, i2c| {
+            let mut t = transaction(0x5AC3, 16, 0x34);
+            t.action = Some(crate::TransactionAction::Verify);
+            i2c.verify(dut, t).unwrap();
+        });
+        assert_eq!(
+            f,
+            format!(
+                "S{}L{}LS{}L{}0{}1P",
+                host(0xA0),
+                host(0x34),
+                host(0xA1),
+                target(0x5A),
+                target(0xC3)
+            )
+        );
+    }
+
+    #[test]
+    fn sends_ten_bit_addresses_and_allows_clock_stretching() {
+        let config = Config {
+            address: 0x2A5,
+            ten_bit: true,
+            reg_addr_width: 0,
+            stretch_cycles: 3,
+            ..Default::default()
+        };
+        let (f, out) = generate(config, |dut, i2c| {
+            i2c.write(
+                dut,
+                Transaction::new_write(BigUint::from(0x81u8), 8).unwrap(),
+            )
+            .unwrap();
+        });
+        // The two most significant address bits go in the first byte, with the R/W bit
+        assert_eq!(
+            f,
+            format!("S{}L{}L{}LP", host(0b1111_0100), host(0xA5), host(0x81))
+        );
+        // SCL is released ahead of each of the 3 ACKs
+        assert_eq!(out.iter().filter(|(scl, _)| scl == "Z").count(), 9);
+    }
+
+    #[test]
+    fn sends_i3c_private_writes_with_parity() {
+        let config = Config {
+            mode: Mode::I3CSDR,
+            address: 0x09,
+            ..Default::default()
+        };
+        let (f, _) = generate(config, |dut, i2c| {
+            i2c.write(dut, transaction(0xA4, 8, 0x12)).unwrap();
+        });
+        // The broadcast address comes first, then each data byte is followed by its odd parity
+        // bit rather than an ACK
+        assert_eq!(
+            f,
+            format!(
+                "S{}LS{}L{}1{}0P",
+                host(0xFC),
+                host(0x12),
+                host(0x12),
+                host(0xA4)
+            )
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::core::dut::Dut;
+use crate::generator::PAT;
+use crate::precludes::controller::*;
+use crate::{Result, TEST};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mode {
+    I2C,
+    /// I3C single data rate private transfers, the address is the target's dynamic address
+    I3CSDR,
+}
+
+/// The target's address and the framing of its register accesses, by default a 7-bit
+/// address with an 8-bit register address
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub mode: Mode,
+    pub address: u16,
+    pub ten_bit: bool,
+    /// The width of the register address sent ahead of the data, a multiple of 8 or 0 for
+    /// targets without addressable registers
+    pub reg_addr_width: usize,
+    /// The number of cycles for which SCL is released ahead of the 9th (ACK) clock of each byte,
+    /// with nothing compared, to tolerate the target stretching the clock. Not applicable in
+    /// I3C mode.
+    pub stretch_cycles: u32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            mode: Mode::I2C,
+            address: 0,
+            ten_bit: false,
+            reg_addr_width: 8,
+            stretch_cycles: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Service {
+    pub id: usize,
+    pub scl: (String, usize),
+    pub sda: (String, usize),
+    pub config: Config,
+}
+
+impl Service {
+    pub fn new(
+        _dut: &Dut,
+        id: usize,
+        scl: Option<&PinGroup>,
+        sda: Option<&PinGroup>,
+        config: Config,
+    ) -> Result<Self> {
+        let max = if config.ten_bit { 0x3FF } else { 0x7F };
+        if config.address > max {
+            bail!(
+                "I2C address 0x{:X} does not fit in {} bits",
+                config.address,
+                if config.ten_bit { 10 } else { 7 }
+            );
+        }
+        if config.ten_bit && config.mode == Mode::I3CSDR {
+            bail!("10-bit addressing is not supported in I3C mode");
+        }
+        if !config.reg_addr_width.is_multiple_of(8) {
+            bail!(
+                "The I2C register address width must be a multiple of 8, given {}",
+                config.reg_addr_width
+            );
+        }
+        Ok(Self {
+            id,
+            scl: match scl {
+                Some(grp) => grp.to_identifier(),
+                None => ("scl".to_string(), 0),
+            },
+            sda: match sda {
+                Some(grp) => grp.to_identifier(),
+                None => ("sda".to_string(), 0),
+            },
+            config,
+        })
+    }
+
+    /// Returns the bus to its idle state, with SCL and SDA high
+    pub fn reset(&self, dut: &Dut) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::I2CReset, self.id));
+        self.idle(dut)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Writes the given transaction's data to the register at its address, the data is sent
+    /// most significant byte first
+    pub fn write(&self, dut: &Dut, transaction: Transaction) -> Result<()> {
+        let sda = PinCollection::from_group(dut, &self.sda.0, self.sda.1)?;
+        let mut t = transaction.clone();
+        t.apply_overlay_pin_ids(&sda.as_ids())?;
+        let mut trans = node!(PAT::I2CWrite, self.id, t, None);
+        let n_id = TEST.push_and_open(trans.clone());
+        self.process_transaction(dut, &mut trans)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Reads the register at the transaction's address, verifying (or capturing) the data
+    pub fn verify(&self, dut: &Dut, transaction: Transaction) -> Result<()> {
+        let sda = PinCollection::from_group(dut, &self.sda.0, self.sda.1)?;
+        let mut t = transaction.clone();
+        t.apply_overlay_pin_ids(&sda.as_ids())?;
+        let mut trans = node!(PAT::I2CVerify, self.id, t, None);
+        let n_id = TEST.push_and_open(trans.clone());
+        self.process_transaction(dut, &mut trans)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! IEEE 1500 wrapper service, for core-level test access through the wrapper serial port (WSP)
+//! of one or more wrapped cores behind a JTAG TAP.
+//!
+//! The WIRs and WDRs are accessed with DR scans through the TAP, one TAP instruction connects
+//! the wrappers' WIRs between TDI and TDO (i.e. asserts SelectWIR) and another connects whichever
+//! data register each wrapper's current instruction selects. The wrappers are daisy-chained in the
+//! order they are added, from TDI to TDO, and those of the cores which are not selected are kept
+//! in WS_BYPASS so that each pads the WDR scans with its 1-bit WBY.
+//!
+//! A register in a wrapped core's model is accessed by giving it the opcode of the WIR instruction
+//! which selects it as its address, so binding the core's model to this service turns its
+//! register writes and verifies into WIR and WDR scans.
+
+use crate::generator::PAT;
+use crate::services::Services;
+use crate::testers::api::ControllerAPI;
+use crate::{Dut, Result, Transaction, TEST};
+use indexmap::IndexMap;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use std::sync::{MutexGuard, RwLock};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Core {
+    pub name: String,
+    pub wir_size: usize,
+    /// The WS_BYPASS instruction, which selects WBY
+    pub bypass: BigUint,
+    /// The opcodes of the core's other named instructions, e.g. WS_EXTEST to select WBR
+    pub instructions: IndexMap<String, BigUint>,
+}
+
+#[derive(Debug)]
+pub struct Service {
+    pub id: usize,
+    pub jtag_id: usize,
+    /// The TAP instruction which connects the WIRs, and its IR size
+    pub wir_instruction: (BigUint, usize),
+    /// The TAP instruction which connects the WDRs, and its IR size
+    pub wdr_instruction: (BigUint, usize),
+    cores: RwLock<Vec<Core>>,
+    selected: RwLock<Option<usize>>,
+    // The core and instruction last loaded into the WIRs, None if not known
+    loaded: RwLock<Option<(usize, BigUint)>>,
+    // Whether the TAP instruction last loaded connects the WIRs (true) or WDRs (false)
+    tap_instruction: RwLock<Option<bool>>,
+}
+
+impl ControllerAPI for Service {
+    fn name(&self) -> String {
+        "IEEE1500".to_string()
+    }
+}
+
+impl Service {
+    pub fn new(
+        id: usize,
+        jtag_id: usize,
+        wir_instruction: (BigUint, usize),
+        wdr_instruction: (BigUint, usize),
+    ) -> Self {
+        Self {
+            id,
+            jtag_id,
+            wir_instruction,
+            wdr_instruction,
+            cores: RwLock::new(vec![]),
+            selected: RwLock::new(None),
+            loaded: RwLock::new(None),
+            tap_instruction: RwLock::new(None),
+        }
+    }
+
+    /// Adds a wrapped core to the TDO end of the WSP chain, cores should therefore be added in
+    /// order starting from the one connected to TDI. WS_BYPASS defaults to all 0s.
+    pub fn add_core(&self, name: &str, wir_size: usize, bypass: Option<BigUint>) -> Result<()> {
+        let mut cores = self.cores.write().unwrap();
+        if cores.iter().any(|c| c.name == name) {
+            bail!(
+                "A core named '{}' already exists in the IEEE 1500 chain",
+                name
+            );
+        }
+        if wir_size == 0 {
+            bail!("The WIR of core '{}' must be at least 1 bit", name);
+        }
+        let bypass = bypass.unwrap_or_else(BigUint::zero);
+        Transaction::check_size(&bypass, wir_size)?;
+        cores.push(Core {
+            name: name.to_string(),
+            wir_size,
+            bypass,
+            instructions: IndexMap::new(),
+        });
+        Ok(())
+    }
+
+    /// Names the given WIR instruction of the given core
+    pub fn add_instruction(&self, core: &str, name: &str, opcode: BigUint) -> Result<()> {
+        let mut cores = self.cores.write().unwrap();
+        let i = core_index(&cores, core)?;
+        Transaction::check_size(&opcode, cores[i].wir_size)?;
+        cores[i].instructions.insert(name.to_string(), opcode);
+        Ok(())
+    }
+
+    /// Returns the wrapped cores, ordered from TDI to TDO
+    pub fn cores(&self) -> Vec<Core> {
+        self.cores.read().unwrap().clone()
+    }
+
+    /// Selects the core to be targeted by subsequent accesses, the others are kept in WS_BYPASS
+    pub fn select_core(&self, name: &str) -> Result<()> {
+        let i = core_index(&self.cores.read().unwrap(), name)?;
+        *self.selected.write().unwrap() = Some(i);
+        Ok(())
+    }
+
+    /// Returns the name of the currently selected core, if any
+    pub fn selected_core(&self) -> Option<String> {
+        let cores = self.cores.read().unwrap();
+        self.selected
+            .read()
+            .unwrap()
+            .map(|i| cores[i].name.to_string())
+    }
+
+    /// Returns the opcode of the selected core's instruction with the given name
+    pub fn instruction(&self, name: &str) -> Result<BigUint> {
+        let cores = self.cores.read().unwrap();
+        let core = &cores[self.target(&cores)?];
+        if name == "WS_BYPASS" {
+            return Ok(core.bypass.clone());
+        }
+        match core.instructions.get(name) {
+            Some(op) => Ok(op.clone()),
+            None => bail!(
+                "No instruction named '{}' has been defined for core '{}'",
+                name,
+                core.name
+            ),
+        }
+    }
+
+    /// Forgets the instructions loaded into the TAP and the WIRs, e.g. after the wrappers have
+    /// been reset by other means, so that they are re-loaded by the next register access
+    pub fn invalidate(&self) {
+        *self.loaded.write().unwrap() = None;
+        *self.tap_instruction.write().unwrap() = None;
+    }
+
+    /// Resets the TAP, the WIRs are re-loaded by the next register access
+    pub fn reset(&self, dut: &Dut, services: &Services) -> Result<()> {
+        services.get_as_jtag(self.jtag_id)?.reset(dut)?;
+        self.invalidate();
+        Ok(())
+    }
+
+    /// Loads the given instruction into the selected core's WIR, and WS_BYPASS into the others
+    pub fn write_wir(&self, dut: &Dut, services: &Services, opcode: &BigUint) -> Result<()> {
+        let (target, t) = {
+            let cores = self.cores.read().unwrap();
+            let target = self.target(&cores)?;
+            let t = Transaction::new_write(opcode.clone(), cores[target].wir_size)?;
+            (target, t)
+        };
+        let n_id = TEST.push_and_open(node!(
+            PAT::IEEE1500WriteWIR,
+            self.id,
+            self.cores.read().unwrap()[target].name.clone(),
+            t.clone()
+        ));
+        self.comment(&format!("Write WIR: 0x{:X}", opcode));
+        self.connect(dut, services, true)?;
+        let chain_t = wsp_transaction(&self.cores.read().unwrap(), target, &t, true)?;
+        services
+            .get_as_jtag(self.jtag_id)?
+            .write_dr(dut, &chain_t, None)?;
+        *self.loaded.write().unwrap() = Some((target, opcode.clone()));
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Writes the WDR currently selected by the selected core's WIR
+    pub fn write_wdr(&self, dut: &Dut, services: &Services, t: &Transaction) -> Result<()> {
+        self.shift_wdr(dut, services, t, true)
+    }
+
+    /// Verifies (or captures) the WDR currently selected by the selected core's WIR
+    pub fn verify_wdr(&self, dut: &Dut, services: &Services, t: &Transaction) -> Result<()> {
+        self.shift_wdr(dut, services, t, false)
+    }
+
+    fn shift_wdr(
+        &self,
+        dut: &Dut,
+        services: &Services,
+        t: &Transaction,
+        write: bool,
+    ) -> Result<()> {
+        let target = self.target(&self.cores.read().unwrap())?;
+        let core = self.cores.read().unwrap()[target].name.clone();
+        let n_id = if write {
+            TEST.push_and_open(node!(PAT::IEEE1500WriteWDR, self.id, core, t.clone()))
+        } else {
+            TEST.push_and_open(node!(PAT::IEEE1500VerifyWDR, self.id, core, t.clone()))
+        };
+        self.connect(dut, services, false)?;
+        let chain_t = wsp_transaction(&self.cores.read().unwrap(), target, t, false)?;
+        let jtag = services.get_as_jtag(self.jtag_id)?;
+        if write {
+            jtag.write_dr(dut, &chain_t, None)?;
+        } else {
+            jtag.verify_dr(dut, &chain_t, None)?;
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Writes the given register transaction, its address being the opcode of the WIR
+    /// instruction which selects it. The WIR is only written if the instruction is not already
+    /// loaded.
+    pub fn write_register(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+        t: &Transaction,
+    ) -> Result<()> {
+        let n_id = TEST.push_and_open(t.as_write_node()?);
+        self.select_wdr(dut, services, t)?;
+        self.write_wdr(dut, services, t)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Verifies the given register transaction, see write_register
+    pub fn verify_register(
+        &self,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+        t: &Transaction,
+    ) -> Result<()> {
+        let n_id = if t.is_capture() {
+            TEST.push_and_open(t.as_capture_node()?)
+        } else {
+            TEST.push_and_open(t.as_verify_node()?)
+        };
+        self.select_wdr(dut, services, t)?;
+        self.verify_wdr(dut, services, t)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn select_wdr(&self, dut: &Dut, services: &Services, t: &Transaction) -> Result<()> {
+        let opcode = match &t.address {
+            Some(a) => a.clone(),
+            None => bail!(
+                "IEEE 1500 register accesses require the WIR instruction selecting the register as its address, but none was given: {:?}",
+                t
+            ),
+        };
+        let target = self.target(&self.cores.read().unwrap())?;
+        if *self.loaded.read().unwrap() != Some((target, opcode.clone())) {
+            self.write_wir(dut, services, &opcode)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the TAP instruction connecting either the WIRs or WDRs, unless already loaded
+    fn connect(&self, dut: &Dut, services: &Services, wir: bool) -> Result<()> {
+        if *self.tap_instruction.read().unwrap() == Some(wir) {
+            return Ok(());
+        }
+        let (ir, size) = if wir {
+            &self.wir_instruction
+        } else {
+            &self.wdr_instruction
+        };
+        services.get_as_jtag(self.jtag_id)?.write_ir(
+            dut,
+            &Transaction::new_write(ir.clone(), *size)?,
+            None,
+        )?;
+        *self.tap_instruction.write().unwrap() = Some(wir);
+        Ok(())
+    }
+
+    fn target(&self, cores: &[Core]) -> Result<usize> {
+        match *self.selected.read().unwrap() {
+            Some(i) => Ok(i),
+            None => match cores.len() {
+                0 => bail!("No cores have been added to IEEE 1500 service {}", self.id),
+                1 => Ok(0),
+                n => bail!(
+                    "The IEEE 1500 chain contains {} cores, select the one to target before accessing it",
+                    n
+                ),
+            },
+        }
+    }
+}
+
+fn core_index(cores: &[Core], name: &str) -> Result<usize> {
+    match cores.iter().position(|c| c.name == name) {
+        Some(i) => Ok(i),
+        None => bail!("No core named '{}' exists in the IEEE 1500 chain", name),
+    }
+}
+
+/// Builds the transaction to be shifted through the whole WSP chain in order to apply the given
+/// transaction to the target core. The other cores are given WS_BYPASS for WIR shifts, or are
+/// padded with a bit for their WBY for WDR shifts, padding is driven low and not compared.
+fn wsp_transaction(
+    cores: &[Core],
+    target: usize,
+    transaction: &Transaction,
+    wir: bool,
+) -> Result<Transaction> {
+    if cores.len() > 1 && (transaction.capture.is_some() || transaction.overlay.is_some()) {
+        bail!("Captures and overlays are not supported yet when shifting through a multi-core IEEE 1500 chain");
+    }
+    let verify = !matches!(transaction.action, Some(crate::TransactionAction::Write));
+    let mut data = BigUint::zero();
+    let mut enables = BigUint::zero();
+    let mut width = 0;
+    for (i, core) in cores.iter().enumerate() {
+        let (d, w, e) = if i == target {
+            (
+                transaction.data.clone(),
+                transaction.width,
+                transaction.bit_enable.clone(),
+            )
+        } else {
+            let (d, w) = if wir {
+                (core.bypass.clone(), core.wir_size)
+            } else {
+                (BigUint::zero(), 1)
+            };
+            let e = if verify {
+                BigUint::zero()
+            } else {
+                Transaction::enable_of_width(w)?
+            };
+            (d, w, e)
+        };
+        data = (data << w) | d;
+        enables = (enables << w) | e;
+        width += w;
+    }
+    let mut t = transaction.clone();
+    t.data = data;
+    t.width = width;
+    t.bit_enable = enables;
+    Ok(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core(name: &str, wir_size: usize, bypass: u32) -> Core {
+        Core {
+            name: name.to_string(),
+            wir_size,
+            bypass: BigUint::from(bypass),
+            instructions: IndexMap::new(),
+        }
+    }
+
+    #[test]
+    fn pads_other_cores_with_bypass() {
+        // TDI -> a (3-bit WIR) -> b (4-bit WIR) -> c (2-bit WIR) -> TDO
+        let cores = vec![core("a", 3, 0), core("b", 4, 0xF), core("c", 2, 0x1)];
+        assert_eq!(core_index(&cores, "c").unwrap(), 2);
+        assert!(core_index(&cores, "d").is_err());
+
+        let wir = Transaction::new_write(BigUint::from(0x5u32), 3).unwrap();
+        let t = wsp_transaction(&cores, 0, &wir, true).unwrap();
+        assert_eq!(t.width, 9);
+        assert_eq!(t.data, BigUint::from((0x5u32 << 6) | (0xF << 2) | 0x1));
+
+        let wdr = Transaction::new_verify(BigUint::from(0xABu32), 8).unwrap();
+        let t = wsp_transaction(&cores, 1, &wdr, false).unwrap();
+        assert_eq!(t.width, 10);
+        assert_eq!(t.data, BigUint::from(0xABu32 << 1));
+        assert_eq!(t.bit_enable, BigUint::from(0xFFu32 << 1));
+    }
+}
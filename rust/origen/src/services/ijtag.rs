@@ -0,0 +1,273 @@
+//! IEEE 1687 (IJTAG) service, register accesses are retargeted through the scan network described
+//! by the ICL and applied as DR scans by a JTAG service.
+//!
+//! Accesses can be made directly via iwrite/iread/iapply, or by calling the iProcs from any
+//! PDL files which have been loaded.
+
+use crate::generator::PAT;
+use crate::om::icl::pdl::Command;
+use crate::om::icl::{self, Network, Shift, ICL, PDL};
+use crate::services::Services;
+use crate::testers::api::ControllerAPI;
+use crate::{Dut, Result, Transaction, TEST};
+use num_bigint::BigUint;
+use std::sync::RwLock;
+
+// Guards against iProcs which call themselves
+const MAX_CALL_DEPTH: usize = 64;
+
+#[derive(Debug)]
+pub struct Service {
+    pub id: usize,
+    pub jtag_id: usize,
+    /// The IR value and size of the instruction which connects the network to the TAP, if not
+    /// given the network is assumed to be connected already
+    pub instruction: Option<(BigUint, usize)>,
+    network: RwLock<Network>,
+    pdl: RwLock<PDL>,
+    instruction_loaded: RwLock<bool>,
+}
+
+impl ControllerAPI for Service {
+    fn name(&self) -> String {
+        "IJTAG".to_string()
+    }
+}
+
+impl Service {
+    /// Creates a service for the scan network of the given top-level ICL module (or the module
+    /// with an AccessLink if not given). The access_instruction selects which of the AccessLink's
+    /// instructions is being modeled, the first one by default.
+    pub fn new(
+        id: usize,
+        jtag_id: usize,
+        icl: &ICL,
+        top: Option<&str>,
+        access_instruction: Option<&str>,
+        instruction: Option<(BigUint, usize)>,
+    ) -> Result<Self> {
+        Ok(Self {
+            id,
+            jtag_id,
+            instruction,
+            network: RwLock::new(Network::new(icl, top, access_instruction)?),
+            pdl: RwLock::new(PDL::default()),
+            instruction_loaded: RwLock::new(false),
+        })
+    }
+
+    /// Makes the iProcs from the given PDL available to icall
+    pub fn load_pdl(&self, pdl: PDL) {
+        self.pdl.write().unwrap().merge(pdl);
+    }
+
+    /// Returns the current value of the given scan register as held by the model
+    pub fn value(&self, register: &str) -> Result<BigUint> {
+        Ok(self.network.read().unwrap().value(register)?.clone())
+    }
+
+    /// Returns the registers currently on the scan path, ordered from TDO to TDI
+    pub fn active_path(&self) -> Result<Vec<String>> {
+        self.network.read().unwrap().active_path()
+    }
+
+    pub fn iwrite(&self, register: &str, value: &BigUint) -> Result<()> {
+        self.network.write().unwrap().iwrite(register, value)
+    }
+
+    pub fn iread(&self, register: &str, value: &BigUint) -> Result<()> {
+        self.network.write().unwrap().iread(register, value)
+    }
+
+    /// Applies all pending writes and reads, generating the DR scans required to retarget the
+    /// network to them
+    pub fn iapply(&self, dut: &Dut, services: &Services) -> Result<()> {
+        let shifts = self.network.write().unwrap().iapply()?;
+        self.apply(dut, services, shifts)
+    }
+
+    /// Closes all open SIBs on the active scan path
+    pub fn close_sibs(&self, dut: &Dut, services: &Services) -> Result<()> {
+        let shifts = self.network.write().unwrap().close_sibs()?;
+        self.apply(dut, services, shifts)
+    }
+
+    /// Resets the TAP, which also returns the network to its reset state
+    pub fn ireset(&self, dut: &Dut, services: &Services) -> Result<()> {
+        services.get_as_jtag(self.jtag_id)?.reset(dut)?;
+        self.network.write().unwrap().reset();
+        *self.instruction_loaded.write().unwrap() = false;
+        Ok(())
+    }
+
+    fn apply(&self, dut: &Dut, services: &Services, shifts: Vec<Shift>) -> Result<()> {
+        if shifts.is_empty() {
+            return Ok(());
+        }
+        let jtag = services.get_as_jtag(self.jtag_id)?;
+        let n_id = TEST.push_and_open(node!(PAT::IJTAGApply, self.id));
+        if let Some((ir, size)) = &self.instruction {
+            if !*self.instruction_loaded.read().unwrap() {
+                jtag.write_ir(dut, &Transaction::new_write(ir.clone(), *size)?, None)?;
+                *self.instruction_loaded.write().unwrap() = true;
+            }
+        }
+        for shift in shifts {
+            self.comment(&format!("Scan path: {}", shift.registers.join(", ")));
+            let write = Transaction::new_write(shift.data, shift.width)?;
+            match shift.expect {
+                Some((data, enables)) => {
+                    let mut verify = Transaction::new_verify(data, shift.width)?;
+                    verify.bit_enable = enables;
+                    jtag.write_verify_dr(dut, &write, &verify, None)?;
+                }
+                None => jtag.write_dr(dut, &write, None)?,
+            }
+        }
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Calls the given iProc with the given arguments, the proc can be given as "inst.proc" to
+    /// call a proc defined for the instance's module
+    pub fn icall(&self, dut: &Dut, services: &Services, proc: &str, args: &[String]) -> Result<()> {
+        self.call("", proc, args, dut, services, 0)
+    }
+
+    /// Executes the given PDL commands, e.g. those given outside of an iProc
+    pub fn execute(&self, dut: &Dut, services: &Services, commands: &[Command]) -> Result<()> {
+        self.run("", commands, dut, services, 0)
+    }
+
+    fn call(
+        &self,
+        prefix: &str,
+        proc: &str,
+        args: &[String],
+        dut: &Dut,
+        services: &Services,
+        depth: usize,
+    ) -> Result<()> {
+        if depth > MAX_CALL_DEPTH {
+            bail!("iProc calls are nested too deeply, calling '{}'", proc);
+        }
+        let (inst, name) = match proc.rsplit_once('.') {
+            Some((i, n)) => (join(prefix, i), n),
+            None => (prefix.to_string(), proc),
+        };
+        let body = {
+            let network = self.network.read().unwrap();
+            let module = if inst.is_empty() {
+                Some(network.top.as_str())
+            } else {
+                match network.instances.get(&inst) {
+                    Some(m) => Some(m.as_str()),
+                    None => bail!("No instance named '{}' exists in the scan network", inst),
+                }
+            };
+            match self.pdl.read().unwrap().find_proc(name, module) {
+                Some(p) => p.expand(args)?,
+                None => bail!(
+                    "No iProc named '{}' has been defined for module '{}'",
+                    name,
+                    module.unwrap()
+                ),
+            }
+        };
+        self.run(&inst, &body, dut, services, depth + 1)
+    }
+
+    fn run(
+        &self,
+        scope: &str,
+        commands: &[Command],
+        dut: &Dut,
+        services: &Services,
+        depth: usize,
+    ) -> Result<()> {
+        let mut prefix = scope.to_string();
+        for c in commands {
+            match c.name.as_str() {
+                "iWrite" | "iRead" => {
+                    if c.args.len() != 2 {
+                        bail!(
+                            "Expected {} <register> <value>, got: {} {}",
+                            c.name,
+                            c.name,
+                            c.args.join(" ")
+                        );
+                    }
+                    let reg = join(&prefix, &c.args[0]);
+                    let value = icl::parse_number(&c.args[1])?.0;
+                    if c.name == "iWrite" {
+                        self.iwrite(&reg, &value)?;
+                    } else {
+                        self.iread(&reg, &value)?;
+                    }
+                }
+                "iApply" => self.iapply(dut, services)?,
+                "iNote" => self.comment(&c.args.join(" ")),
+                "iReset" => self.ireset(dut, services)?,
+                // The prefix is relative to the scope of the caller, not to any previous iPrefix
+                "iPrefix" => match c.args.first() {
+                    Some(p) => prefix = join(scope, p),
+                    None => bail!("iPrefix requires an instance name"),
+                },
+                "iRunLoop" => {
+                    // The cycle count is the last argument, e.g. "iRunLoop 100 -tck"
+                    let cycles = c.args.iter().find_map(|a| a.parse::<usize>().ok());
+                    match cycles {
+                        Some(n) => services.get_as_jtag(self.jtag_id)?.idle(dut, n)?,
+                        None => bail!("iRunLoop requires a cycle count"),
+                    }
+                }
+                "iCall" => match c.args.split_first() {
+                    Some((proc, args)) => self.call(&prefix, proc, args, dut, services, depth)?,
+                    None => bail!("iCall requires the name of an iProc"),
+                },
+                _ => bail!("Unsupported PDL command '{}'", c.name),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", prefix, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICL_SRC: &str = r#"
+        Module Instrument {
+            ScanInPort si; ScanOutPort so { Source tdr[0]; }
+            ScanRegister tdr[7:0] { ScanInSource si; ResetValue 8'h00; }
+        }
+        Module Chip {
+            ScanInPort si; ScanOutPort so { Source i2.so; }
+            Instance i1 Of Instrument { InputPort si = si; }
+            Instance i2 Of Instrument { InputPort si = i1.so; }
+        }
+    "#;
+
+    #[test]
+    fn iprefix_is_relative_to_the_calling_scope() {
+        let icl = ICL::from_str(ICL_SRC).unwrap();
+        let ijtag = Service::new(0, 0, &icl, Some("Chip"), None, None).unwrap();
+        let pdl =
+            PDL::from_str("iPrefix i1\niWrite tdr 0x11\niPrefix i2\niWrite tdr 0x22\n").unwrap();
+        ijtag
+            .execute(&Dut::new("ijtag_test"), &Services::new(), &pdl.commands)
+            .unwrap();
+        let mut network = ijtag.network.write().unwrap();
+        network.iapply().unwrap();
+        assert_eq!(network.value("i1.tdr").unwrap(), &BigUint::from(0x11u32));
+        assert_eq!(network.value("i2.tdr").unwrap(), &BigUint::from(0x22u32));
+    }
+}
@@ -0,0 +1,193 @@
+//! Describes a JTAG scan chain of several TAPs connected in series (a daisy chain), allowing
+//! shifts to target a single TAP while the others are padded with BYPASS instructions and bits.
+//!
+//! TAPs are given in order from TDI to TDO, i.e. the first TAP's TDI is connected to the TDI pin
+//! and the last TAP's TDO to the TDO pin. Since data is shifted in LSB first, the TAP closest to
+//! TDO occupies the least significant bits of a combined transaction.
+
+use crate::{Result, Transaction};
+use num_bigint::BigUint;
+use num_traits::Zero;
+
+/// The length of the IDCODE register
+pub const IDCODE_SIZE: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Tap {
+    pub name: String,
+    pub ir_size: usize,
+    /// The BYPASS instruction, defaults to all 1s as required by the standard
+    pub bypass: BigUint,
+    /// The expected IDCODE, a TAP without an IDCODE register selects BYPASS on reset instead
+    pub idcode: Option<u32>,
+    /// The length of the data register currently selected by the TAP, used to pad DR shifts
+    /// which are targeting other TAPs
+    pub dr_size: usize,
+}
+
+impl Tap {
+    pub fn new(
+        name: &str,
+        ir_size: usize,
+        bypass: Option<BigUint>,
+        idcode: Option<u32>,
+    ) -> Result<Self> {
+        if ir_size < 2 {
+            bail!(
+                "The IR of TAP '{}' must be at least 2 bits, given: {}",
+                name,
+                ir_size
+            );
+        }
+        let bypass = match bypass {
+            Some(b) => {
+                Transaction::check_size(&b, ir_size)?;
+                b
+            }
+            None => Transaction::enable_of_width(ir_size)?,
+        };
+        let mut tap = Self {
+            name: name.to_string(),
+            ir_size,
+            bypass,
+            idcode,
+            dr_size: 1,
+        };
+        tap.reset();
+        Ok(tap)
+    }
+
+    /// Updates the TAP's selected data register to that selected on a reset, i.e. IDCODE if it
+    /// has one, otherwise BYPASS
+    pub fn reset(&mut self) {
+        self.dr_size = match self.idcode {
+            Some(_) => IDCODE_SIZE,
+            None => 1,
+        };
+    }
+}
+
+/// Returns the index of the TAP with the given name
+pub fn tap_index(chain: &[Tap], name: &str) -> Result<usize> {
+    match chain.iter().position(|t| t.name == name) {
+        Some(i) => Ok(i),
+        None => bail!("No TAP named '{}' exists in the JTAG scan chain", name),
+    }
+}
+
+/// Builds the transaction to be shifted through the whole chain in order to apply the given
+/// transaction to the target TAP. The other TAPs are given their BYPASS instruction for IR shifts,
+/// or are padded to the length of their currently selected data register for DR shifts.
+/// Padding bits are driven low on writes and are not compared on verifies.
+pub fn chain_transaction(
+    chain: &[Tap],
+    target: usize,
+    transaction: &Transaction,
+    ir: bool,
+) -> Result<Transaction> {
+    if chain.len() > 1 && (transaction.capture.is_some() || transaction.overlay.is_some()) {
+        bail!("Captures and overlays are not supported yet when shifting through a multi-TAP JTAG scan chain");
+    }
+    let verify = !matches!(transaction.action, Some(crate::TransactionAction::Write));
+    let mut data = BigUint::zero();
+    let mut enables = BigUint::zero();
+    let mut width = 0;
+    for (i, tap) in chain.iter().enumerate() {
+        let (d, w, e) = if i == target {
+            if ir && transaction.width != tap.ir_size {
+                bail!(
+                    "The IR of TAP '{}' is {} bits, but a {}-bit instruction was given",
+                    tap.name,
+                    tap.ir_size,
+                    transaction.width
+                );
+            }
+            (
+                transaction.data.clone(),
+                transaction.width,
+                transaction.bit_enable.clone(),
+            )
+        } else {
+            let (d, w) = if ir {
+                (tap.bypass.clone(), tap.ir_size)
+            } else {
+                (BigUint::zero(), tap.dr_size)
+            };
+            let e = if verify {
+                BigUint::zero()
+            } else {
+                Transaction::enable_of_width(w)?
+            };
+            (d, w, e)
+        };
+        data = (data << w) | d;
+        enables = (enables << w) | e;
+        width += w;
+    }
+    let mut t = transaction.clone();
+    t.data = data;
+    t.width = width;
+    t.bit_enable = enables;
+    Ok(t)
+}
+
+/// Returns a verify transaction for a DR shift through the whole chain immediately after a
+/// reset, expecting the IDCODE of each TAP which has one and a 0 from the BYPASS register of
+/// those which don't
+pub fn idcodes_transaction(chain: &[Tap]) -> Result<Transaction> {
+    let mut data = BigUint::zero();
+    let mut width = 0;
+    for tap in chain {
+        match tap.idcode {
+            Some(id) => {
+                data = (data << IDCODE_SIZE) | BigUint::from(id);
+                width += IDCODE_SIZE;
+            }
+            None => {
+                data <<= 1;
+                width += 1;
+            }
+        }
+    }
+    if width == 0 {
+        bail!("The JTAG scan chain is empty");
+    }
+    Transaction::new_verify(data, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_chain_transactions() {
+        // TDI -> a (4-bit IR) -> b (5-bit IR, no IDCODE) -> c (3-bit IR) -> TDO
+        let chain = vec![
+            Tap::new("a", 4, None, Some(0x1234_5677)).unwrap(),
+            Tap::new("b", 5, Some(BigUint::from(0x1Eu32)), None).unwrap(),
+            Tap::new("c", 3, None, Some(0x0BA0_0477)).unwrap(),
+        ];
+        assert_eq!(tap_index(&chain, "b").unwrap(), 1);
+        assert!(tap_index(&chain, "d").is_err());
+
+        let ir = Transaction::new_write(BigUint::from(0x3u32), 5).unwrap();
+        let t = chain_transaction(&chain, 1, &ir, true).unwrap();
+        assert_eq!(t.width, 12);
+        assert_eq!(t.data, BigUint::from((0xFu32 << 8) | (0x3 << 3) | 0x7));
+        assert!(chain_transaction(&chain, 0, &ir, true).is_err());
+
+        // Other TAPs are still selecting IDCODE after a reset
+        let dr = Transaction::new_verify(BigUint::from(0x5u32), 3).unwrap();
+        let t = chain_transaction(&chain, 1, &dr, false).unwrap();
+        assert_eq!(t.width, 67);
+        assert_eq!(t.data, BigUint::from(0x5u32) << 32);
+        assert_eq!(t.bit_enable, BigUint::from(0x7u32) << 32);
+
+        let t = idcodes_transaction(&chain).unwrap();
+        assert_eq!(t.width, 65);
+        assert_eq!(
+            t.data,
+            (BigUint::from(0x1234_5677u32) << 33) | BigUint::from(0x0BA0_0477u32)
+        );
+    }
+}
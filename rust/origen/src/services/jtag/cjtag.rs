@@ -0,0 +1,181 @@
+//! IEEE 1149.7 (cJTAG) support for driving a TAP through only two pins, TMSC and TCKC.
+//!
+//! Escape sequences are TMSC edges while TCKC is held high, the number of edges selecting the
+//! escape's meaning. Once activated into the OScan1 scan format, each TCK of the standard TAP
+//! protocol is made up of three TCKC periods on TMSC: inverted TDI, TMS and then TDO driven by
+//! the target. The TAP state tracking and IR/DR shifts above this are unchanged.
+
+use crate::core::model::pins::PinCollection;
+use crate::generator::PAT;
+use crate::standards::actions::*;
+use crate::testers::vector_based::api::repeat2_node;
+use crate::{Result, Transaction};
+use origen_metal::ast::Node;
+use std::fmt;
+
+/// The STFMT (store format) control command, given as the first command part
+pub const STFMT: usize = 3;
+/// The STFMT command's second command part which selects the OScan1 format
+pub const OSCAN1: usize = 9;
+
+/// The online activation code sent after a selection escape, in the order transmitted: the
+/// OAC (1100b), EC (1000b) and CP (0000b) fields, each sent LSB first
+pub const ONLINE_ACTIVATION_CODE: [bool; 12] = [
+    false, false, true, true, false, false, false, true, false, false, false, false,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ScanFormat {
+    /// IEEE 1149.1 four-wire operation on TMS, TDI and TDO
+    Standard,
+    /// IEEE 1149.7 two-pin operation, each TCK is made up of the nTDI, TMS and TDO TCKC periods
+    OScan1,
+}
+
+impl fmt::Display for ScanFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScanFormat::Standard => write!(f, "Standard"),
+            ScanFormat::OScan1 => write!(f, "OScan1"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// Takes the TAP.7 controller offline
+    Deselection,
+    /// Precedes the online activation code
+    Selection,
+    /// Resets the TAP.7 controller, returning it to the standard scan format
+    Reset,
+}
+
+impl Escape {
+    /// The number of TMSC edges which make up the escape, the minimum which the standard
+    /// recognizes for each
+    pub fn toggles(&self) -> usize {
+        match self {
+            Escape::Deselection => 4,
+            Escape::Selection => 6,
+            Escape::Reset => 8,
+        }
+    }
+}
+
+/// The TMS sequence, from and returning to Run-Test/Idle, of a DR scan spending the given number
+/// of TCKs in Shift-DR. Control commands are conveyed by the lengths of such scans, zero giving
+/// a zero-bit scan (ZBS).
+pub fn command_tms(shift_count: usize) -> Vec<bool> {
+    if shift_count == 0 {
+        return vec![true, false, true, true, false];
+    }
+    let mut tms = vec![true, false, false];
+    tms.extend(vec![false; shift_count - 1]);
+    tms.extend([true, true, false]);
+    tms
+}
+
+/// Returns the TMSC symbols for each TCK of an OScan1 shift, i.e. the nTDI, TMS and TDO periods.
+/// TMS goes high with the last bit to exit the shift state, TDI is driven low when not writing and
+/// TDO is not compared when not verifying.
+pub fn oscan1_symbols(
+    write: Option<&Transaction>,
+    verify: Option<&Transaction>,
+) -> Result<Vec<[String; 3]>> {
+    for t in [write, verify].iter().flatten() {
+        if t.capture.is_some() || t.overlay.is_some() {
+            bail!("Captures and overlays are not supported yet in the cJTAG OScan1 format");
+        }
+    }
+    let w = match write {
+        Some(t) => Some(t.to_symbols()?),
+        None => None,
+    };
+    let v = match verify {
+        Some(t) => Some(t.to_symbols()?),
+        None => None,
+    };
+    let width = match (&w, &v) {
+        (Some(w), _) => w.len(),
+        (None, Some(v)) => v.len(),
+        (None, None) => bail!("No transaction given to shift in the cJTAG OScan1 format"),
+    };
+    Ok((0..width)
+        .map(|i| {
+            let ntdi = match &w {
+                Some(w) => match w[i].0.as_str() {
+                    s if s == DRIVE_HIGH => DRIVE_LOW.to_string(),
+                    s if s == DRIVE_LOW => DRIVE_HIGH.to_string(),
+                    s => s.to_string(),
+                },
+                None => DRIVE_HIGH.to_string(),
+            };
+            let tms = if i == width - 1 {
+                DRIVE_HIGH
+            } else {
+                DRIVE_LOW
+            };
+            let tdo = match &v {
+                Some(v) => v[i].0.to_string(),
+                None => HIGHZ.to_string(),
+            };
+            [ntdi, tms.to_string(), tdo]
+        })
+        .collect())
+}
+
+/// Returns the nodes applying the given OScan1 TCKs on TMSC, see oscan1_symbols
+pub fn oscan1_nodes(tmsc: &PinCollection, tcks: &[[String; 3]]) -> Vec<Node<PAT>> {
+    let mut nodes = vec![];
+    for tck in tcks {
+        for sym in tck {
+            nodes.append(&mut tmsc.set_action_nodes(sym));
+            nodes.push(repeat2_node(1, true));
+        }
+    }
+    nodes
+}
+
+/// Returns the OScan1 TCKs which clock the TAP with the given TMS value without shifting data
+pub fn oscan1_tms(tms: bool, count: usize) -> Vec<[String; 3]> {
+    let tms = if tms { DRIVE_HIGH } else { DRIVE_LOW };
+    (0..count)
+        .map(|_| [DRIVE_HIGH.to_string(), tms.to_string(), HIGHZ.to_string()])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::jtag::TAPState;
+    use num_bigint::BigUint;
+
+    #[test]
+    fn command_scans_have_the_given_shift_length() {
+        for n in 0..12 {
+            let mut state = TAPState::Idle;
+            let mut shifts = 0;
+            for tms in command_tms(n) {
+                state = state.next(tms);
+                if state == TAPState::ShiftDR {
+                    shifts += 1;
+                }
+            }
+            assert_eq!(state, TAPState::Idle);
+            assert_eq!(shifts, n);
+        }
+    }
+
+    #[test]
+    fn oscan1_inverts_tdi_and_exits_on_the_last_bit() {
+        let w = Transaction::new_write(BigUint::from(0b01u32), 2).unwrap();
+        let s = oscan1_symbols(Some(&w), None).unwrap();
+        assert_eq!(s[0], ["0", "0", "Z"]);
+        assert_eq!(s[1], ["1", "1", "Z"]);
+        let v = Transaction::new_verify(BigUint::from(0b10u32), 2).unwrap();
+        let s = oscan1_symbols(None, Some(&v)).unwrap();
+        assert_eq!(s[0], ["1", "0", "L"]);
+        assert_eq!(s[1], ["1", "1", "H"]);
+    }
+}
@@ -1,3 +1,9 @@
+pub mod chain;
+pub mod cjtag;
 mod service;
+pub mod tap;
 
+pub use chain::Tap;
+pub use cjtag::{Escape, ScanFormat};
 pub use service::Service;
+pub use tap::TAPState;
@@ -1,77 +1,18 @@
 //! The service implements the public API exposed to Python and provides
 //! all state storage for a JTAG driver instance
 
+use super::chain::{self, Tap};
+use super::cjtag::{self, Escape, ScanFormat};
+use super::TAPState;
 use crate::generator::PAT;
 use crate::precludes::controller::*;
 use crate::testers::api::ControllerAPI;
 use crate::{Dut, Result, Transaction, TEST};
+use num_bigint::BigUint;
+use origen_metal::ast::Node;
+use std::sync::RwLock;
 
-// pub enum TAPStates {
-//     Reset,
-//     Idle,
-
-//     // DR States
-//     SelectDR,
-//     CaptureDR,
-//     ShiftDR,
-//     Exit1DR,
-//     PauseDR,
-//     Exit2DR,
-//     UpdateDR,
-
-//     // IR States
-//     SelectIR,
-//     CaptureIR,
-//     ShiftIR,
-//     Exit1IR,
-//     PauseIR,
-//     Exit2IR,
-//     UpdateIR,
-// }
-
-// impl TAPStates{
-//     // pub fn cycles_to_idle(&self) -> usize {
-//     //     match self {
-//     //         Reset => 0,
-//     //         Idle => 3,
-//     //         _ => 0
-//     //     }
-//     // }
-
-//     // pub fn next_state(&self, tms_val) -> usize {
-//     //     // ...
-//     // }
-
-//     // pub fn cycles_to_reset(&self) -> usize {
-//     //     self.cycles_to_idle() + 3
-//     // }
-
-//     // pub fn to_idle(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-
-//     // pub fn to_reset(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-
-//     // pub fn to_shift_ir(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-
-//     // pub fn to_update_ir(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-
-//     // pub fn to_shift_dr(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-
-//     // pub fn to_update_dr(&self) -> Result<Self> {
-//     //     // ...
-//     // }
-// }
-
-#[derive(Debug, Clone, PartialEq, Serialize)]
+#[derive(Debug, Serialize)]
 pub struct Service {
     // For example, to keep track of the current IR value, would also add fields
     // here to record the pins (defined when the service was instantiated)
@@ -82,6 +23,20 @@ pub struct Service {
     tdo: (String, usize),
     tms: (String, usize),
     trstn: (String, usize),
+    // The two pins used in place of the above by IEEE 1149.7 (cJTAG) scan formats
+    tmsc: (String, usize),
+    tckc: (String, usize),
+    format: RwLock<ScanFormat>,
+    // The current state of the TAP controller, assumed to start in Run-Test/Idle. None when it
+    // has been marked as unknown, in which case it will be reset before its next operation.
+    state: RwLock<Option<TAPState>>,
+    // The state to move to after a shift when an end state is not given
+    end_state: RwLock<TAPState>,
+    // The TAPs in the scan chain, ordered from TDI to TDO. When empty the shifts are applied
+    // as given, as if there is a single TAP.
+    chain: RwLock<Vec<Tap>>,
+    // The index of the TAP in the chain which is targeted by shifts
+    target: RwLock<Option<usize>>,
 }
 
 impl ControllerAPI for Service {
@@ -100,6 +55,8 @@ impl Service {
         tdo: Option<&PinGroup>,
         tms: Option<&PinGroup>,
         trstn: Option<&PinGroup>,
+        tmsc: Option<&PinGroup>,
+        tckc: Option<&PinGroup>,
     ) -> Result<Service> {
         Ok(Service {
             id: id,
@@ -139,141 +96,736 @@ impl Service {
                     ("trstm".to_string(), 0)
                 }
             },
+            tmsc: {
+                if let Some(grp) = tmsc {
+                    grp.to_identifier()
+                } else {
+                    ("tmsc".to_string(), 0)
+                }
+            },
+            tckc: {
+                if let Some(grp) = tckc {
+                    grp.to_identifier()
+                } else {
+                    ("tckc".to_string(), 0)
+                }
+            },
+            format: RwLock::new(ScanFormat::Standard),
+            state: RwLock::new(Some(TAPState::Idle)),
+            end_state: RwLock::new(TAPState::Idle),
+            chain: RwLock::new(vec![]),
+            target: RwLock::new(None),
         })
     }
 
+    /// Adds a TAP to the TDO end of the scan chain, TAPs should therefore be added in order
+    /// starting from the one connected to TDI.
+    /// The BYPASS instruction defaults to all 1s, the IDCODE is only required to verify the chain
+    /// via verify_idcodes.
+    pub fn add_tap(
+        &self,
+        name: &str,
+        ir_size: usize,
+        bypass: Option<BigUint>,
+        idcode: Option<u32>,
+    ) -> Result<()> {
+        let mut c = self.chain.write().unwrap();
+        if c.iter().any(|t| t.name == name) {
+            bail!(
+                "A TAP named '{}' already exists in the JTAG scan chain",
+                name
+            );
+        }
+        c.push(Tap::new(name, ir_size, bypass, idcode)?);
+        Ok(())
+    }
+
+    /// Returns the TAPs in the scan chain, ordered from TDI to TDO
+    pub fn taps(&self) -> Vec<Tap> {
+        self.chain.read().unwrap().clone()
+    }
+
+    /// Selects the TAP to be targeted by subsequent shifts, the other TAPs will be padded with
+    /// BYPASS instructions and bits
+    pub fn select_tap(&self, name: &str) -> Result<()> {
+        let i = chain::tap_index(&self.chain.read().unwrap(), name)?;
+        *self.target.write().unwrap() = Some(i);
+        Ok(())
+    }
+
+    /// Returns the name of the currently targeted TAP, if any
+    pub fn selected_tap(&self) -> Option<String> {
+        let c = self.chain.read().unwrap();
+        self.target.read().unwrap().map(|i| c[i].name.to_string())
+    }
+
+    fn target(&self, chain: &[Tap]) -> Result<usize> {
+        match *self.target.read().unwrap() {
+            Some(i) => Ok(i),
+            None => {
+                if chain.len() == 1 {
+                    Ok(0)
+                } else {
+                    bail!(
+                        "The JTAG scan chain contains {} TAPs, select the one to target before shifting",
+                        chain.len()
+                    );
+                }
+            }
+        }
+    }
+
+    fn reset_chain(&self) {
+        for tap in self.chain.write().unwrap().iter_mut() {
+            tap.reset();
+        }
+    }
+
+    /// Returns the current state of the TAP controller, None if it is not known
+    pub fn state(&self) -> Option<TAPState> {
+        *self.state.read().unwrap()
+    }
+
+    /// Tells the driver which state the TAP controller is in, e.g. after it has been driven by
+    /// other means. A state of None marks it as unknown, causing it to be reset before the next
+    /// operation.
+    pub fn set_state(&self, state: Option<TAPState>) {
+        *self.state.write().unwrap() = state;
+    }
+
+    pub fn end_state(&self) -> TAPState {
+        *self.end_state.read().unwrap()
+    }
+
+    /// Sets the state that the TAP will be left in after a shift operation when no end state is
+    /// given, this must be Run-Test/Idle (the default), Pause-DR or Pause-IR
+    pub fn set_end_state(&self, state: TAPState) -> Result<()> {
+        Self::check_end_state(state)?;
+        *self.end_state.write().unwrap() = state;
+        Ok(())
+    }
+
+    /// Resets the TAP controller by holding TMS high, leaving it in Test-Logic-Reset
     pub fn reset(&self, dut: &Dut) -> Result<()> {
-        let tms = PinCollection::from_group(&dut, &self.tms.0, self.tms.1)?;
-        self._reset(&tms)
+        self._reset(dut)
     }
 
-    fn _reset(&self, tms: &PinCollection) -> Result<()> {
+    fn _reset(&self, dut: &Dut) -> Result<()> {
         let n_id = TEST.push_and_open(node!(PAT::JTAGReset, self.id, None));
         self.comment("Resetting JTAG Interface");
-        tms.drive_high().repeat(6);
+        match self.scan_format() {
+            ScanFormat::Standard => {
+                let tms = PinCollection::from_group(dut, &self.tms.0, self.tms.1)?;
+                tms.drive_high().repeat(6);
+            }
+            ScanFormat::OScan1 => self.clock_tms(dut, true, 6)?,
+        }
+        *self.state.write().unwrap() = Some(TAPState::Reset);
+        self.reset_chain();
         TEST.close(n_id)?;
         Ok(())
     }
 
-    // pub fn idle(&self) -> Result<()> {
-    //     // ...
-    // }
-
-    // pub fn move_to_state(&self, state: JTAGState) -> Result<()> {
-    //     // ...
-    // }
-
-    // fn _move_to_state(&self, state: JTAGState, tms: &PinCollection) -> Result<()> {
-    //     // ...
-    // }
-
-    pub fn write_ir(&self, dut: &Dut, transaction: &Transaction) -> Result<()> {
-        let tms = PinCollection::from_group(&dut, &self.tms.0, self.tms.1)?;
-        let tdi = PinCollection::from_group(&dut, &self.tdi.0, self.tdi.1)?;
-        let trans = node!(PAT::JTAGWriteIR, self.id, transaction.clone(), None);
-        let n_id = TEST.push_and_open(trans.clone());
-
-        self.comment("Move to Shift-IR");
-        tms.drive_low().cycle();
-        tms.drive_high().cycles(2);
-        tms.drive_low().cycles(2);
-        self.comment(&format!("Write IR {:?}", transaction.data));
-        let mut nodes = tdi.push_transaction_nodes(&transaction)?;
-        nodes.insert(
-            nodes.len() - 2,
-            tms.drive_high_nodes().first().unwrap().clone(),
-        );
-        TEST.append(&mut nodes);
-        self.comment("Completed IR Shift");
-        tdi.highz();
-        self.comment("Move to Update IR");
-        tms.drive_high().cycle();
-        self.comment("Move to IDLE");
-        tms.drive_low().cycle();
+    /// Resets the TAP controller by asserting TRST for the given number of cycles (5 by default),
+    /// with TMS held high so that it remains in Test-Logic-Reset afterwards
+    pub fn trst_reset(&self, dut: &Dut, cycles: Option<usize>) -> Result<()> {
+        if self.scan_format() != ScanFormat::Standard {
+            bail!(
+                "TRST is not available in the cJTAG {} format",
+                self.scan_format()
+            );
+        }
+        let tms = PinCollection::from_group(dut, &self.tms.0, self.tms.1)?;
+        let trstn = PinCollection::from_group(dut, &self.trstn.0, self.trstn.1)?;
+        let n_id = TEST.push_and_open(node!(PAT::JTAGTrstReset, self.id, None));
+        self.comment("Resetting JTAG Interface (TRST)");
+        tms.drive_high();
+        trstn.drive_low().cycles(cycles.unwrap_or(5));
+        trstn.drive_high().cycle();
+        *self.state.write().unwrap() = Some(TAPState::Reset);
+        self.reset_chain();
+        TEST.close(n_id)?;
+        Ok(())
+    }
 
+    /// Moves to Run-Test/Idle and remains there for the given number of cycles
+    pub fn idle(&self, dut: &Dut, cycles: usize) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::JTAGIdle, self.id, cycles as u32));
+        self._move_to_state(TAPState::Idle, dut)?;
+        if cycles > 0 {
+            self.clock_tms(dut, false, cycles)?;
+        }
         TEST.close(n_id)?;
         Ok(())
     }
 
-    pub fn verify_ir(&self, dut: &Dut, transaction: &Transaction) -> Result<()> {
-        let tms = PinCollection::from_group(&dut, &self.tms.0, self.tms.1)?;
-        let tdo = PinCollection::from_group(&dut, &self.tdo.0, self.tdo.1)?;
-        let trans = node!(PAT::JTAGVerifyIR, self.id, transaction.clone(), None);
-        let n_id = TEST.push_and_open(trans.clone());
-        self.comment("Move to Shift-IR");
-        tms.drive_low().cycle();
-        tms.drive_high().cycles(2);
-        tms.drive_low().cycles(2);
-        self.comment(&format!("Verify IR {:?}", transaction.data));
-        let mut nodes = tdo.push_transaction_nodes(&transaction)?;
-        nodes.insert(
-            nodes.len() - 2,
-            tms.drive_high_nodes().first().unwrap().clone(),
-        );
-        TEST.append(&mut nodes);
-        self.comment("Completed IR Shift");
-        tdo.highz();
-        self.comment("Move to Update IR");
-        tms.drive_high().cycle();
-        self.comment("Move to IDLE");
-        tms.drive_low().cycle();
+    /// Moves the TAP controller to the given state by the shortest TMS sequence
+    pub fn move_to_state(&self, dut: &Dut, state: TAPState) -> Result<()> {
+        self._move_to_state(state, dut)
+    }
+
+    fn _move_to_state(&self, state: TAPState, dut: &Dut) -> Result<()> {
+        let current = match self.state() {
+            Some(s) => s,
+            None => {
+                // The TAP has been marked as being in an unknown state, so reset it to get to
+                // a known one
+                self._reset(dut)?;
+                TAPState::Reset
+            }
+        };
+        if current == state {
+            return Ok(());
+        }
+        let n_id = TEST.push_and_open(node!(PAT::JTAGMoveToState, self.id, current, state));
+        self.comment(&format!("Move to {}", state));
+        let path = current.tms_path_to(state);
+        let mut i = 0;
+        while i < path.len() {
+            let n = path[i..].iter().take_while(|tms| **tms == path[i]).count();
+            self.clock_tms(dut, path[i], n)?;
+            i += n;
+        }
+        *self.state.write().unwrap() = Some(state);
         TEST.close(n_id)?;
         Ok(())
     }
 
-    pub fn write_dr(&self, dut: &Dut, transaction: &Transaction) -> Result<()> {
-        let tms = PinCollection::from_group(&dut, &self.tms.0, self.tms.1)?;
-        let tdi = PinCollection::from_group(&dut, &self.tdi.0, self.tdi.1)?;
-        let trans = node!(PAT::JTAGWriteDR, self.id, transaction.clone(), None);
-        let n_id = TEST.push_and_open(trans.clone());
-
-        self.comment("Move to Shift-DR");
-        tms.drive_low().cycle();
-        tms.drive_high().cycle();
-        tms.drive_low().cycles(2);
-        self.comment(&format!("Write DR {:?}", transaction.data));
-        let mut nodes = tdi.push_transaction_nodes(&transaction)?;
-        nodes.insert(
-            nodes.len() - 2,
-            tms.drive_high_nodes().first().unwrap().clone(),
-        );
-        TEST.append(&mut nodes);
-        self.comment("Completed DR Shift");
-        tdi.highz();
-        self.comment("Move to Update IR");
-        tms.drive_high().cycle();
-        self.comment("Move to IDLE");
-        tms.drive_low().cycle();
+    /// Clocks the TAP the given number of times with TMS at the given value, without shifting
+    fn clock_tms(&self, dut: &Dut, tms: bool, count: usize) -> Result<()> {
+        match self.scan_format() {
+            ScanFormat::Standard => {
+                let pin = PinCollection::from_group(dut, &self.tms.0, self.tms.1)?;
+                pin.drive(tms).cycles(count);
+            }
+            ScanFormat::OScan1 => {
+                let tmsc = PinCollection::from_group(dut, &self.tmsc.0, self.tmsc.1)?;
+                TEST.append(&mut cjtag::oscan1_nodes(
+                    &tmsc,
+                    &cjtag::oscan1_tms(tms, count),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the scan format currently in use
+    pub fn scan_format(&self) -> ScanFormat {
+        *self.format.read().unwrap()
+    }
 
+    /// Applies the given cJTAG escape sequence: TMSC is toggled while TCKC is held high, TCKC's
+    /// previous action is restored afterwards.
+    /// A reset escape returns the TAP.7 controller to the standard format and Test-Logic-Reset.
+    pub fn escape(&self, dut: &Dut, escape: Escape) -> Result<()> {
+        let tmsc = PinCollection::from_group(dut, &self.tmsc.0, self.tmsc.1)?;
+        let tckc = PinCollection::from_group(dut, &self.tckc.0, self.tckc.1)?;
+        let n_id = TEST.push_and_open(node!(PAT::CJTAGEscape, self.id, escape.toggles()));
+        self.comment(&format!("cJTAG {:?} escape", escape));
+        let tckc_actions = tckc.get_actions();
+        tmsc.drive_low().cycle();
+        tckc.drive_high();
+        for i in 0..escape.toggles() {
+            tmsc.drive(i % 2 == 0).cycle();
+        }
+        tmsc.drive_low();
+        tckc.set_actions(&tckc_actions.iter().map(|a| a.to_string()).collect())?;
+        tckc.cycle();
+        if escape == Escape::Reset {
+            *self.format.write().unwrap() = ScanFormat::Standard;
+            *self.state.write().unwrap() = Some(TAPState::Reset);
+            self.reset_chain();
+        }
         TEST.close(n_id)?;
         Ok(())
     }
 
-    pub fn verify_dr(&self, dut: &Dut, transaction: &Transaction) -> Result<()> {
-        let tms = PinCollection::from_group(&dut, &self.tms.0, self.tms.1)?;
-        let tdo = PinCollection::from_group(&dut, &self.tdo.0, self.tdo.1)?;
-        let trans = node!(PAT::JTAGVerifyDR, self.id, transaction.clone(), None);
-        let n_id = TEST.push_and_open(trans.clone());
-
-        self.comment("Move to Shift-DR");
-        tms.drive_low().cycle();
-        tms.drive_high().cycle();
-        tms.drive_low().cycles(2);
-        self.comment(&format!("Verify DR {:?}", transaction.data));
-        let mut nodes = tdo.push_transaction_nodes(&transaction)?;
-        nodes.insert(
-            nodes.len() - 2,
-            tms.drive_high_nodes().first().unwrap().clone(),
-        );
-        TEST.append(&mut nodes);
-        self.comment("Completed DR Shift");
-        tdo.highz();
-        self.comment("Move to Update IR");
-        tms.drive_high().cycle();
-        self.comment("Move to IDLE");
-        tms.drive_low().cycle();
+    /// Activates the cJTAG OScan1 format on TMSC/TCKC, after which the TAP is driven through
+    /// them by all reset, state movement and shift operations.
+    ///
+    /// A reset escape is followed by a selection escape and the online activation code, then
+    /// two zero-bit DR scans enter control level 2 in which the STFMT command selects OScan1.
+    pub fn activate_oscan1(&self, dut: &Dut) -> Result<()> {
+        let tmsc = PinCollection::from_group(dut, &self.tmsc.0, self.tmsc.1)?;
+        let n_id = TEST.push_and_open(node!(PAT::CJTAGActivateOScan1, self.id));
+        self.comment("Activate cJTAG OScan1 format");
+        self.escape(dut, Escape::Reset)?;
+        self.escape(dut, Escape::Selection)?;
+        self.comment("Online activation code");
+        for bit in cjtag::ONLINE_ACTIVATION_CODE.iter() {
+            tmsc.drive(*bit).cycle();
+        }
+        // The TAP is clocked on each TCKC with TMSC as TMS until the new format takes effect
+        let mut tms = vec![false];
+        tms.extend(cjtag::command_tms(0));
+        tms.extend(cjtag::command_tms(0));
+        self.comment(&format!(
+            "STFMT command: {}, {}",
+            cjtag::STFMT,
+            cjtag::OSCAN1
+        ));
+        tms.extend(cjtag::command_tms(cjtag::STFMT));
+        tms.extend(cjtag::command_tms(cjtag::OSCAN1));
+        for bit in tms {
+            tmsc.drive(bit).cycle();
+        }
+        *self.format.write().unwrap() = ScanFormat::OScan1;
+        *self.state.write().unwrap() = Some(TAPState::Idle);
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    fn check_end_state(state: TAPState) -> Result<()> {
+        if !state.is_end_state() {
+            bail!(
+                "JTAG shifts can only end in {}, {} or {}, given: {}",
+                TAPState::Idle,
+                TAPState::PauseDR,
+                TAPState::PauseIR,
+                state
+            );
+        }
+        Ok(())
+    }
+
+    /// Shifts the given transaction through the IR or DR of the targeted TAP, on TDI if write is
+    /// set, otherwise it will be verified (or captured) on TDO.
+    /// The TAP will be left in the given end state, or the default end state if not given.
+    fn shift(
+        &self,
+        dut: &Dut,
+        transaction: &Transaction,
+        ir: bool,
+        write: bool,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        if write {
+            self.shift_write_verify(dut, Some(transaction), None, ir, end_state)
+        } else {
+            self.shift_write_verify(dut, None, Some(transaction), ir, end_state)
+        }
+    }
+
+    /// Shifts the write transaction in on TDI while verifying the verify transaction on TDO,
+    /// padding both for the other TAPs in the scan chain
+    fn shift_write_verify(
+        &self,
+        dut: &Dut,
+        write: Option<&Transaction>,
+        verify: Option<&Transaction>,
+        ir: bool,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        let chain = self.taps();
+        if chain.is_empty() {
+            return self.shift_chain(dut, write, verify, ir, end_state);
+        }
+        let target = self.target(&chain)?;
+        let w = match write {
+            Some(t) => Some(chain::chain_transaction(&chain, target, t, ir)?),
+            None => None,
+        };
+        let v = match verify {
+            Some(t) => Some(chain::chain_transaction(&chain, target, t, ir)?),
+            None => None,
+        };
+        self.shift_chain(dut, w.as_ref(), v.as_ref(), ir, end_state)?;
+        let mut c = self.chain.write().unwrap();
+        if ir {
+            // Writing an instruction to the target means all others are now in BYPASS
+            if write.is_some() {
+                for (i, tap) in c.iter_mut().enumerate() {
+                    if i != target {
+                        tap.dr_size = 1;
+                    }
+                }
+            }
+        } else {
+            c[target].dr_size = write.or(verify).unwrap().width;
+        }
+        Ok(())
+    }
+
+    /// Shifts the given transactions through the IR or DR of the whole scan chain
+    fn shift_chain(
+        &self,
+        dut: &Dut,
+        write: Option<&Transaction>,
+        verify: Option<&Transaction>,
+        ir: bool,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        let end_state = end_state.unwrap_or(self.end_state());
+        Self::check_end_state(end_state)?;
+        let (reg, shift_state, exit_state) = if ir {
+            ("IR", TAPState::ShiftIR, TAPState::Exit1IR)
+        } else {
+            ("DR", TAPState::ShiftDR, TAPState::Exit1DR)
+        };
+        let trans = match (ir, write, verify) {
+            (false, Some(w), Some(v)) => {
+                node!(PAT::JTAGWriteVerifyDR, self.id, w.clone(), v.clone(), None)
+            }
+            (true, Some(w), _) => node!(PAT::JTAGWriteIR, self.id, w.clone(), None),
+            (true, None, Some(v)) => node!(PAT::JTAGVerifyIR, self.id, v.clone(), None),
+            (false, Some(w), None) => node!(PAT::JTAGWriteDR, self.id, w.clone(), None),
+            (false, None, Some(v)) => node!(PAT::JTAGVerifyDR, self.id, v.clone(), None),
+            (_, None, None) => bail!("No transaction given to shift through the JTAG {}", reg),
+        };
+        if ir && write.is_some() && verify.is_some() {
+            bail!("Simultaneous write and verify is only supported for DR shifts");
+        }
+        if let (Some(w), Some(v)) = (write, verify) {
+            if w.width != v.width {
+                bail!(
+                    "The write and verify transactions must be the same width, given {} and {}",
+                    w.width,
+                    v.width
+                );
+            }
+        }
+        let n_id = TEST.push_and_open(trans);
+
+        // A shift from Run-Test/Idle always spends one more cycle there before leaving, as the
+        // original driver did, so that existing patterns' vectors are unchanged
+        if self.state() == Some(TAPState::Idle) {
+            self.clock_tms(dut, false, 1)?;
+        }
+        self._move_to_state(shift_state, dut)?;
+        if let Some(w) = write {
+            self.comment(&format!("Write {} {:?}", reg, w.data));
+        }
+        if let Some(v) = verify {
+            self.comment(&format!("Verify {} {:?}", reg, v.data));
+        }
+        match self.scan_format() {
+            ScanFormat::Standard => {
+                let tms = PinCollection::from_group(dut, &self.tms.0, self.tms.1)?;
+                let tdi = PinCollection::from_group(dut, &self.tdi.0, self.tdi.1)?;
+                let tdo = PinCollection::from_group(dut, &self.tdo.0, self.tdo.1)?;
+                let mut nodes = match (write, verify) {
+                    (Some(w), Some(v)) => merge_cycles(
+                        tdi.push_transaction_nodes(w)?,
+                        tdo.push_transaction_nodes(v)?,
+                    ),
+                    (Some(w), None) => tdi.push_transaction_nodes(w)?,
+                    (None, Some(v)) => tdo.push_transaction_nodes(v)?,
+                    (None, None) => unreachable!(),
+                };
+                // TMS goes high with the last bit to exit the shift state
+                nodes.insert(
+                    nodes.len() - 2,
+                    tms.drive_high_nodes().first().unwrap().clone(),
+                );
+                TEST.append(&mut nodes);
+                *self.state.write().unwrap() = Some(exit_state);
+                self.comment(&format!("Completed {} Shift", reg));
+                if write.is_some() {
+                    tdi.highz();
+                }
+                if verify.is_some() {
+                    tdo.highz();
+                }
+            }
+            ScanFormat::OScan1 => {
+                let tmsc = PinCollection::from_group(dut, &self.tmsc.0, self.tmsc.1)?;
+                let tcks = cjtag::oscan1_symbols(write, verify)?;
+                TEST.append(&mut cjtag::oscan1_nodes(&tmsc, &tcks));
+                *self.state.write().unwrap() = Some(exit_state);
+                self.comment(&format!("Completed {} Shift", reg));
+            }
+        }
+        self._move_to_state(end_state, dut)?;
 
         TEST.close(n_id)?;
         Ok(())
     }
+
+    pub fn write_ir(
+        &self,
+        dut: &Dut,
+        transaction: &Transaction,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        self.shift(dut, transaction, true, true, end_state)
+    }
+
+    pub fn verify_ir(
+        &self,
+        dut: &Dut,
+        transaction: &Transaction,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        self.shift(dut, transaction, true, false, end_state)
+    }
+
+    pub fn write_dr(
+        &self,
+        dut: &Dut,
+        transaction: &Transaction,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        self.shift(dut, transaction, false, true, end_state)
+    }
+
+    pub fn verify_dr(
+        &self,
+        dut: &Dut,
+        transaction: &Transaction,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        self.shift(dut, transaction, false, false, end_state)
+    }
+
+    /// Shifts the write transaction into the DR while verifying the DR's captured contents
+    /// against the verify transaction
+    pub fn write_verify_dr(
+        &self,
+        dut: &Dut,
+        write: &Transaction,
+        verify: &Transaction,
+        end_state: Option<TAPState>,
+    ) -> Result<()> {
+        self.shift_write_verify(dut, Some(write), Some(verify), false, end_state)
+    }
+
+    /// Resets the TAPs and verifies the IDCODE of each one in the scan chain by shifting out
+    /// the whole DR chain. TAPs which don't have an IDCODE are expected to select BYPASS on reset.
+    pub fn verify_idcodes(&self, dut: &Dut) -> Result<()> {
+        let t = chain::idcodes_transaction(&self.taps())?;
+        self.reset(dut)?;
+        self.comment(&format!(
+            "Verify IDCODEs: {}",
+            self.taps()
+                .iter()
+                .map(|t| match t.idcode {
+                    Some(id) => format!("{}: 0x{:08X}", t.name, id),
+                    None => format!("{}: BYPASS", t.name),
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+        self.shift_chain(dut, None, Some(&t), false, None)
+    }
+}
+
+/// Combines the per-cycle pin actions of two shifts of the same length into a single sequence
+fn merge_cycles(a: Vec<Node<PAT>>, b: Vec<Node<PAT>>) -> Vec<Node<PAT>> {
+    let mut merged = vec![];
+    let mut b = b.into_iter();
+    for n in a {
+        if let PAT::Cycle(_, _) = n.attrs {
+            for n2 in b.by_ref() {
+                if let PAT::Cycle(_, _) = n2.attrs {
+                    break;
+                }
+                merged.push(n2);
+            }
+        }
+        merged.push(n);
+    }
+    // Keep anything given after the last cycle
+    merged.extend(b);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::model::pins::PinCollection;
+    use crate::testers::vector_based::api::repeat2_node;
+    use std::sync::Mutex;
+
+    /// Records each pin's action at every cycle
+    fn cycles(node: &Node<PAT>, pins: &[usize], state: &mut Vec<String>, out: &mut Vec<String>) {
+        match &node.attrs {
+            PAT::PinAction(id, action, _) => {
+                if let Some(i) = pins.iter().position(|p| p == id) {
+                    state[i] = action.to_string();
+                }
+            }
+            PAT::Cycle(n, _) => {
+                for _ in 0..*n {
+                    for (i, s) in state.iter().enumerate() {
+                        out[i].push_str(s);
+                    }
+                }
+            }
+            _ => {}
+        }
+        for child in &node.children {
+            cycles(child, pins, state, out);
+        }
+    }
+
+    /// Runs the given function on a JTAG service and returns the vectors generated on each of
+    /// the given pins, one character per cycle
+    fn generate<F>(pins: &[&str], func: F) -> Vec<String>
+    where
+        F: Fn(&Dut, &Service),
+    {
+        let mut dut = Dut::new("jtag_test");
+        let top = dut.create_model(None, "dut", None).unwrap();
+        for pin in ["tclk", "tdi", "tdo", "tms", "trstn", "tmsc", "tckc"] {
+            dut.add_pin(top, pin, None, None, None, None).unwrap();
+        }
+        let jtag = Service::new(&dut, 0, None, None, None, None, None, None, None, None).unwrap();
+        let ids: Vec<usize> = pins
+            .iter()
+            .map(|p| PinCollection::from_group(&dut, p, top).unwrap().as_ids()[0])
+            .collect();
+        let dut = Mutex::new(dut);
+        let dut = dut.lock().unwrap();
+        let _lock = TEST.lock();
+        TEST.start("jtag");
+        func(&dut, &jtag);
+        let mut state = vec!["-".to_string(); pins.len()];
+        let mut out = vec![String::new(); pins.len()];
+        cycles(&TEST.to_node(), &ids, &mut state, &mut out);
+        out
+    }
+
+    fn write(data: u32, width: usize) -> Transaction {
+        Transaction::new_write(BigUint::from(data), width).unwrap()
+    }
+
+    fn verify(data: u32, width: usize) -> Transaction {
+        Transaction::new_verify(BigUint::from(data), width).unwrap()
+    }
+
+    #[test]
+    fn write_ir_walks_the_tap_through_shift_ir_and_back_to_idle() {
+        let v = generate(&["tms", "tdi"], |dut, jtag| {
+            jtag.write_ir(dut, &write(0b1101, 4), None).unwrap();
+        });
+        // Idle, Select-DR, Select-IR, Capture-IR, Shift-IR (exiting on the last bit), then
+        // Update-IR and back to Idle
+        assert_eq!(v[0], "01100".to_owned() + "0001" + "10");
+        assert_eq!(v[1], "-----".to_owned() + "1011" + "ZZ");
+    }
+
+    #[test]
+    fn write_dr_walks_the_tap_through_shift_dr_and_back_to_idle() {
+        let v = generate(&["tms", "tdi"], |dut, jtag| {
+            jtag.write_dr(dut, &write(0b0110, 4), None).unwrap();
+        });
+        // Idle, Select-DR, Capture-DR, Shift-DR (exiting on the last bit), then Update-DR
+        // and back to Idle
+        assert_eq!(v[0], "0100".to_owned() + "0001" + "10");
+        assert_eq!(v[1], "----".to_owned() + "0110" + "ZZ");
+    }
+
+    #[test]
+    fn shifts_can_end_in_a_pause_state() {
+        let v = generate(&["tms", "tdi"], |dut, jtag| {
+            jtag.write_dr(dut, &write(0b01, 2), Some(TAPState::PauseDR))
+                .unwrap();
+            // The next shift leaves from Pause-DR directly
+            jtag.write_dr(dut, &write(0b10, 2), None).unwrap();
+        });
+        assert_eq!(v[0], "0100".to_owned() + "01" + "0" + "10" + "01" + "10");
+        assert_eq!(v[1], "----".to_owned() + "10" + "Z" + "ZZ" + "01" + "ZZ");
+    }
+
+    #[test]
+    fn write_verify_dr_drives_tdi_while_comparing_tdo() {
+        let v = generate(&["tms", "tdi", "tdo"], |dut, jtag| {
+            jtag.write_verify_dr(dut, &write(0b0011, 4), &verify(0b1010, 4), None)
+                .unwrap();
+        });
+        assert_eq!(v[0], "0100".to_owned() + "0001" + "10");
+        assert_eq!(v[1], "----".to_owned() + "1100" + "ZZ");
+        assert_eq!(v[2], "----".to_owned() + "LHLH" + "ZZ");
+    }
+
+    /// The TMSC vectors of the given OScan1 TCKs, each given as its nTDI, TMS and TDO periods
+    fn oscan1(tcks: &[&str]) -> String {
+        tcks.concat()
+    }
+
+    #[test]
+    fn escapes_toggle_tmsc_while_tckc_is_held_high() {
+        let v = generate(&["tmsc", "tckc"], |dut, jtag| {
+            let tckc = PinCollection::from_group(dut, "tckc", 0).unwrap();
+            tckc.drive_low();
+            jtag.escape(dut, Escape::Deselection).unwrap();
+        });
+        assert_eq!(v[0], "0".to_owned() + "1010" + "0");
+        // TCKC's previous action is restored afterwards
+        assert_eq!(v[1], "0".to_owned() + "1111" + "0");
+    }
+
+    #[test]
+    fn activates_oscan1_with_escapes_and_the_stfmt_command() {
+        let v = generate(&["tmsc", "tckc", "tms"], |dut, jtag| {
+            let tckc = PinCollection::from_group(dut, "tckc", 0).unwrap();
+            tckc.drive_low();
+            jtag.set_state(Some(TAPState::PauseDR));
+            jtag.activate_oscan1(dut).unwrap();
+            assert_eq!(jtag.scan_format(), ScanFormat::OScan1);
+            assert_eq!(jtag.state(), Some(TAPState::Idle));
+        });
+        // Reset and selection escapes, each between TMSC low cycles
+        let escapes = "0".to_owned() + "10101010" + "0" + "0" + "101010" + "0";
+        let activation_code = "001100010000";
+        // Two zero-bit scans from Idle, then the STFMT command and its OScan1 part
+        let commands = "0".to_owned() + "10110" + "10110" + "10000110" + "10000000000110";
+        let expected = escapes + activation_code + &commands;
+        assert_eq!(v[0], expected);
+        assert_eq!(
+            v[1],
+            ["0", "11111111", "0", "0", "111111", "0"].concat() + &"0".repeat(45)
+        );
+        // The standard TMS pin is not used
+        assert_eq!(v[2], "-".repeat(expected.len()));
+    }
+
+    #[test]
+    fn oscan1_shifts_are_made_on_tmsc() {
+        let v = generate(&["tmsc", "tms", "tdi", "tdo"], |dut, jtag| {
+            jtag.activate_oscan1(dut).unwrap();
+            TEST.start("oscan1");
+            jtag.write_ir(dut, &write(0b10, 2), None).unwrap();
+            jtag.write_verify_dr(dut, &write(0b01, 2), &verify(0b10, 2), None)
+                .unwrap();
+        });
+        // Idle, then to Shift-IR, shifting with TDI inverted and exiting on the last bit, then
+        // Update-IR and back to Idle
+        let ir = oscan1(&["10Z", "11Z", "11Z", "10Z", "10Z"])
+            + &oscan1(&["10Z", "01Z"])
+            + &oscan1(&["11Z", "10Z"]);
+        // TDO is compared in the third period of each TCK
+        let dr = oscan1(&["10Z", "11Z", "10Z", "10Z"])
+            + &oscan1(&["00L", "11H"])
+            + &oscan1(&["11Z", "10Z"]);
+        assert_eq!(v[0], ir + &dr);
+        for pin in &v[1..] {
+            assert_eq!(*pin, "-".repeat(v[0].len()));
+        }
+    }
+
+    #[test]
+    fn merging_cycles_keeps_any_trailing_nodes() {
+        let a = vec![
+            node!(PAT::PinAction, 1, "1".to_string(), None),
+            repeat2_node(1, true),
+        ];
+        let b = vec![
+            node!(PAT::PinAction, 2, "H".to_string(), None),
+            repeat2_node(1, true),
+            node!(PAT::PinAction, 2, "Z".to_string(), None),
+        ];
+        let merged = merge_cycles(a, b);
+        assert_eq!(merged.len(), 4);
+        assert_eq!(merged[3].attrs, PAT::PinAction(2, "Z".to_string(), None));
+    }
+
+    #[test]
+    fn tap_starts_in_idle_until_marked_unknown() {
+        let dut = Dut::new("jtag_test");
+        let jtag = Service::new(&dut, 0, None, None, None, None, None, None, None, None).unwrap();
+        assert_eq!(jtag.state(), Some(TAPState::Idle));
+        jtag.set_state(None);
+        assert_eq!(jtag.state(), None);
+    }
 }
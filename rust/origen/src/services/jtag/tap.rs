@@ -0,0 +1,238 @@
+//! Models the 16-state JTAG TAP controller (IEEE 1149.1) so that the driver can track the
+//! current state and move between states by the shortest TMS sequence
+
+use crate::Result;
+use std::collections::VecDeque;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum TAPState {
+    Reset,
+    Idle,
+
+    // DR States
+    SelectDR,
+    CaptureDR,
+    ShiftDR,
+    Exit1DR,
+    PauseDR,
+    Exit2DR,
+    UpdateDR,
+
+    // IR States
+    SelectIR,
+    CaptureIR,
+    ShiftIR,
+    Exit1IR,
+    PauseIR,
+    Exit2IR,
+    UpdateIR,
+}
+
+impl TAPState {
+    pub const ALL: [TAPState; 16] = [
+        TAPState::Reset,
+        TAPState::Idle,
+        TAPState::SelectDR,
+        TAPState::CaptureDR,
+        TAPState::ShiftDR,
+        TAPState::Exit1DR,
+        TAPState::PauseDR,
+        TAPState::Exit2DR,
+        TAPState::UpdateDR,
+        TAPState::SelectIR,
+        TAPState::CaptureIR,
+        TAPState::ShiftIR,
+        TAPState::Exit1IR,
+        TAPState::PauseIR,
+        TAPState::Exit2IR,
+        TAPState::UpdateIR,
+    ];
+
+    /// Returns the state the TAP will move to on the next TCK with the given TMS value
+    pub fn next(&self, tms: bool) -> TAPState {
+        use TAPState::*;
+        match (self, tms) {
+            (Reset, true) => Reset,
+            (Reset, false) => Idle,
+            (Idle, true) => SelectDR,
+            (Idle, false) => Idle,
+
+            (SelectDR, true) => SelectIR,
+            (SelectDR, false) => CaptureDR,
+            (CaptureDR, true) => Exit1DR,
+            (CaptureDR, false) => ShiftDR,
+            (ShiftDR, true) => Exit1DR,
+            (ShiftDR, false) => ShiftDR,
+            (Exit1DR, true) => UpdateDR,
+            (Exit1DR, false) => PauseDR,
+            (PauseDR, true) => Exit2DR,
+            (PauseDR, false) => PauseDR,
+            (Exit2DR, true) => UpdateDR,
+            (Exit2DR, false) => ShiftDR,
+            (UpdateDR, true) => SelectDR,
+            (UpdateDR, false) => Idle,
+
+            (SelectIR, true) => Reset,
+            (SelectIR, false) => CaptureIR,
+            (CaptureIR, true) => Exit1IR,
+            (CaptureIR, false) => ShiftIR,
+            (ShiftIR, true) => Exit1IR,
+            (ShiftIR, false) => ShiftIR,
+            (Exit1IR, true) => UpdateIR,
+            (Exit1IR, false) => PauseIR,
+            (PauseIR, true) => Exit2IR,
+            (PauseIR, false) => PauseIR,
+            (Exit2IR, true) => UpdateIR,
+            (Exit2IR, false) => ShiftIR,
+            (UpdateIR, true) => SelectDR,
+            (UpdateIR, false) => Idle,
+        }
+    }
+
+    /// Returns the shortest sequence of TMS values which will move the TAP from this state to
+    /// the given one, this will be empty if the TAP is already in the target state
+    pub fn tms_path_to(&self, target: TAPState) -> Vec<bool> {
+        // Breadth-first search, there are only 16 states so no need to be clever
+        let mut visited: Vec<(TAPState, Vec<bool>)> = vec![(*self, vec![])];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        queue.push_back(0);
+        while let Some(i) = queue.pop_front() {
+            let (state, path) = visited[i].clone();
+            if state == target {
+                return path;
+            }
+            for tms in [false, true] {
+                let next = state.next(tms);
+                if !visited.iter().any(|(s, _)| *s == next) {
+                    let mut p = path.clone();
+                    p.push(tms);
+                    visited.push((next, p));
+                    queue.push_back(visited.len() - 1);
+                }
+            }
+        }
+        unreachable!("All TAP states are reachable from any other")
+    }
+
+    /// Returns true if the TAP can remain in this state for multiple TCK cycles
+    pub fn is_stable(&self) -> bool {
+        matches!(
+            self,
+            TAPState::Reset
+                | TAPState::Idle
+                | TAPState::ShiftDR
+                | TAPState::PauseDR
+                | TAPState::ShiftIR
+                | TAPState::PauseIR
+        )
+    }
+
+    /// Returns true if this is a valid state in which to end a shift operation
+    pub fn is_end_state(&self) -> bool {
+        matches!(self, TAPState::Idle | TAPState::PauseDR | TAPState::PauseIR)
+    }
+
+    /// Returns the name of the state as given by the IEEE 1149.1 standard
+    pub fn name(&self) -> &'static str {
+        match self {
+            TAPState::Reset => "Test-Logic-Reset",
+            TAPState::Idle => "Run-Test/Idle",
+            TAPState::SelectDR => "Select-DR-Scan",
+            TAPState::CaptureDR => "Capture-DR",
+            TAPState::ShiftDR => "Shift-DR",
+            TAPState::Exit1DR => "Exit1-DR",
+            TAPState::PauseDR => "Pause-DR",
+            TAPState::Exit2DR => "Exit2-DR",
+            TAPState::UpdateDR => "Update-DR",
+            TAPState::SelectIR => "Select-IR-Scan",
+            TAPState::CaptureIR => "Capture-IR",
+            TAPState::ShiftIR => "Shift-IR",
+            TAPState::Exit1IR => "Exit1-IR",
+            TAPState::PauseIR => "Pause-IR",
+            TAPState::Exit2IR => "Exit2-IR",
+            TAPState::UpdateIR => "Update-IR",
+        }
+    }
+}
+
+impl fmt::Display for TAPState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl std::str::FromStr for TAPState {
+    type Err = crate::Error;
+
+    /// Accepts the standard state names or their short forms, e.g. "Run-Test/Idle", "idle",
+    /// "pause_dr", "Shift-IR"
+    fn from_str(s: &str) -> Result<Self> {
+        let n: String = s
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect();
+        let n = n.trim_end_matches("scan");
+        for state in TAPState::ALL.iter() {
+            let full: String = state
+                .name()
+                .to_lowercase()
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .collect();
+            let short = format!("{:?}", state).to_lowercase();
+            if n == full.trim_end_matches("scan") || n == short {
+                return Ok(*state);
+            }
+        }
+        match n {
+            "testlogicreset" | "tlr" => Ok(TAPState::Reset),
+            "runtestidle" | "rti" => Ok(TAPState::Idle),
+            _ => bail!("Unknown JTAG TAP state '{}'", s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TAPState;
+
+    #[test]
+    fn finds_shortest_paths() {
+        assert_eq!(
+            TAPState::Idle.tms_path_to(TAPState::ShiftIR),
+            vec![true, true, false, false]
+        );
+        assert_eq!(
+            TAPState::Reset.tms_path_to(TAPState::ShiftDR),
+            vec![false, true, false, false]
+        );
+        assert_eq!(
+            TAPState::Exit1DR.tms_path_to(TAPState::Idle),
+            vec![true, false]
+        );
+        assert_eq!(
+            TAPState::PauseIR.tms_path_to(TAPState::ShiftIR),
+            vec![true, false]
+        );
+        assert!(TAPState::Idle.tms_path_to(TAPState::Idle).is_empty());
+        // Any state reaches reset with at most 5 TMS=1 cycles
+        for s in TAPState::ALL.iter() {
+            let p = s.tms_path_to(TAPState::Reset);
+            assert!(p.len() <= 5 && p.iter().all(|tms| *tms));
+        }
+    }
+
+    #[test]
+    fn parses_state_names() {
+        assert_eq!("idle".parse::<TAPState>().unwrap(), TAPState::Idle);
+        assert_eq!("Run-Test/Idle".parse::<TAPState>().unwrap(), TAPState::Idle);
+        assert_eq!("pause_dr".parse::<TAPState>().unwrap(), TAPState::PauseDR);
+        assert_eq!(
+            "Select-IR-Scan".parse::<TAPState>().unwrap(),
+            TAPState::SelectIR
+        );
+        assert!("blah".parse::<TAPState>().is_err());
+    }
+}
@@ -1,11 +1,16 @@
 pub mod arm_debug;
+pub mod i2c;
+pub mod ieee1500;
+pub mod ijtag;
 pub mod jtag;
 pub mod simple;
+pub mod spi;
 pub mod swd;
 
 pub use arm_debug::ArmDebug;
 
-use crate::Result;
+use crate::{Dut, Result, Transaction};
+use std::sync::MutexGuard;
 
 #[derive(Debug)]
 pub enum Service {
@@ -16,6 +21,10 @@ pub enum Service {
     ArmDebugDP(arm_debug::dp::DP),
     ArmDebugJtagDP(arm_debug::jtag_dp::JtagDP),
     ArmDebugMemAP(arm_debug::mem_ap::MemAP),
+    IJTAG(ijtag::Service),
+    SPI(spi::Service),
+    I2C(i2c::Service),
+    IEEE1500(ieee1500::Service),
 }
 
 impl Service {
@@ -33,6 +42,34 @@ impl Service {
         }
     }
 
+    pub fn as_ijtag(&self) -> Result<&ijtag::Service> {
+        match self {
+            Self::IJTAG(s) => Ok(s),
+            _ => Err(error!("Expected service IJTAG but received {:?}", self)),
+        }
+    }
+
+    pub fn as_ieee1500(&self) -> Result<&ieee1500::Service> {
+        match self {
+            Self::IEEE1500(s) => Ok(s),
+            _ => Err(error!("Expected service IEEE1500 but received {:?}", self)),
+        }
+    }
+
+    pub fn as_spi(&self) -> Result<&spi::Service> {
+        match self {
+            Self::SPI(s) => Ok(s),
+            _ => Err(error!("Expected service SPI but received {:?}", self)),
+        }
+    }
+
+    pub fn as_i2c(&self) -> Result<&i2c::Service> {
+        match self {
+            Self::I2C(s) => Ok(s),
+            _ => Err(error!("Expected service I2C but received {:?}", self)),
+        }
+    }
+
     pub fn as_simple(&self) -> Result<&simple::Service> {
         match self {
             Self::Simple(s) => Ok(s),
@@ -180,6 +217,26 @@ impl Services {
         s.as_jtag()
     }
 
+    pub fn get_as_ijtag(&self, id: usize) -> Result<&ijtag::Service> {
+        let s = self.get_service(id)?;
+        s.as_ijtag()
+    }
+
+    pub fn get_as_ieee1500(&self, id: usize) -> Result<&ieee1500::Service> {
+        let s = self.get_service(id)?;
+        s.as_ieee1500()
+    }
+
+    pub fn get_as_spi(&self, id: usize) -> Result<&spi::Service> {
+        let s = self.get_service(id)?;
+        s.as_spi()
+    }
+
+    pub fn get_as_i2c(&self, id: usize) -> Result<&i2c::Service> {
+        let s = self.get_service(id)?;
+        s.as_i2c()
+    }
+
     pub fn get_as_simple(&self, id: usize) -> Result<&simple::Service> {
         let s = self.get_service(id)?;
         s.as_simple()
@@ -222,4 +279,46 @@ impl Services {
             _ => Err(error!("Expected service ArmDebug but received {:?}", s)),
         }
     }
+
+    /// Writes the given address/data transaction through the given service, which must be one
+    /// that supports addressed accesses (a Simple protocol, an SPI, I2C or IEEE 1500 service, or an ArmDebug MemAP)
+    pub fn write_transaction(
+        &self,
+        id: usize,
+        dut: &MutexGuard<Dut>,
+        t: &Transaction,
+    ) -> Result<()> {
+        match self.get_service(id)? {
+            Service::Simple(s) => s.write(dut, t.clone()),
+            Service::SPI(s) => s.write(dut, t.clone()),
+            Service::I2C(s) => s.write(dut, t.clone()),
+            Service::IEEE1500(s) => s.write_register(dut, self, t),
+            Service::ArmDebugMemAP(s) => s.write(dut, self, t),
+            s => bail!(
+                "Service {:?} does not support address/data transactions, a Simple, SPI, I2C, IEEE 1500 or ArmDebug MemAP service is required",
+                s
+            ),
+        }
+    }
+
+    /// Verifies the given address/data transaction through the given service, see
+    /// write_transaction
+    pub fn verify_transaction(
+        &self,
+        id: usize,
+        dut: &MutexGuard<Dut>,
+        t: &Transaction,
+    ) -> Result<()> {
+        match self.get_service(id)? {
+            Service::Simple(s) => s.verify(dut, t.clone()),
+            Service::SPI(s) => s.verify(dut, t.clone()),
+            Service::I2C(s) => s.verify(dut, t.clone()),
+            Service::IEEE1500(s) => s.verify_register(dut, self, t),
+            Service::ArmDebugMemAP(s) => s.verify(dut, self, t),
+            s => bail!(
+                "Service {:?} does not support address/data transactions, a Simple, SPI, I2C, IEEE 1500 or ArmDebug MemAP service is required",
+                s
+            ),
+        }
+    }
 }
@@ -0,0 +1,187 @@
+use super::service::Service;
+use crate::core::model::pins::PinCollection;
+use crate::generator::PAT;
+use crate::testers::api::ControllerAPI;
+use crate::{Dut, Result, Transaction};
+use origen_metal::ast::Node;
+
+impl ControllerAPI for Service {
+    fn name(&self) -> String {
+        "SPI".to_string()
+    }
+}
+
+struct Pins<'a> {
+    sclk: PinCollection<'a>,
+    mosi: PinCollection<'a>,
+    miso: PinCollection<'a>,
+    cs: PinCollection<'a>,
+}
+
+impl Service {
+    fn pins<'a>(&self, dut: &'a Dut) -> Result<Pins<'a>> {
+        Ok(Pins {
+            sclk: PinCollection::from_group(dut, &self.sclk.0, self.sclk.1)?,
+            mosi: PinCollection::from_group(dut, &self.mosi.0, self.mosi.1)?,
+            miso: PinCollection::from_group(dut, &self.miso.0, self.miso.1)?,
+            cs: PinCollection::from_group(dut, &self.cs.0, self.cs.1)?,
+        })
+    }
+
+    pub(crate) fn idle(&self, dut: &Dut) -> Result<()> {
+        let pins = self.pins(dut)?;
+        self.comment("Return SPI to idle pin states");
+        self.release(&pins);
+        Ok(())
+    }
+
+    fn release(&self, pins: &Pins) {
+        pins.sclk.drive(self.config.cpol);
+        pins.mosi.drive_low();
+        pins.miso.highz();
+        pins.cs.drive(!self.config.cs_active_high).cycle();
+    }
+
+    /// Shifts a single bit over two cycles, the first launches the MOSI data and the second is
+    /// the one in which MISO is sampled
+    fn shift(
+        &self,
+        pins: &Pins,
+        mosi: Option<&Transaction>,
+        miso: Option<&Transaction>,
+    ) -> Result<()> {
+        let launch = self.config.cpol ^ self.config.cpha;
+        pins.sclk.drive(launch);
+        pins.miso.highz();
+        match mosi {
+            Some(t) => {
+                pins.mosi.push_transaction(t)?;
+            }
+            None => {
+                pins.mosi.drive_low().cycle();
+            }
+        }
+        pins.sclk.drive(!launch);
+        match miso {
+            Some(t) => {
+                pins.miso.push_transaction(t)?;
+            }
+            None => {
+                let overlay = mosi.is_some_and(|t| t.has_overlay());
+                pins.sclk.repeat2(1, !overlay);
+            }
+        }
+        Ok(())
+    }
+
+    fn shift_out(&self, pins: &Pins, t: &Transaction, order: &[usize]) -> Result<()> {
+        for i in order {
+            self.shift(pins, Some(&t.bit_slice(*i, 2)?), None)?;
+        }
+        Ok(())
+    }
+
+    fn shift_in(&self, pins: &Pins, t: &Transaction, order: &[usize]) -> Result<()> {
+        for i in order {
+            self.shift(pins, None, Some(&t.bit_slice(*i, 1)?))?;
+        }
+        Ok(())
+    }
+
+    pub fn process_transaction(&self, dut: &Dut, node: &mut Node<PAT>) -> Result<()> {
+        let pins = self.pins(dut)?;
+        let (t, read) = match &node.attrs {
+            PAT::SPIWrite(_spi_id, transaction, _metadata) => {
+                self.comment(&format!("Write - Data: 0x{:X}", transaction.data));
+                (transaction, false)
+            }
+            PAT::SPIVerify(_spi_id, transaction, _metadata) => {
+                self.comment(&format!("Verify - Data: 0x{:X}", transaction.data));
+                (transaction, true)
+            }
+            PAT::SPICapture(_spi_id, transaction, _metadata) => {
+                self.comment(&format!("Capture - Width: {}", transaction.width));
+                (transaction, true)
+            }
+            _ => {
+                bail!("Unexpected node in SPI driver: {:?}", node)
+            }
+        };
+        let c = &self.config;
+
+        self.comment("Assert chip select");
+        pins.sclk.drive(c.cpol);
+        pins.mosi.drive_low();
+        pins.miso.highz();
+        pins.cs.drive(c.cs_active_high).cycle();
+
+        let cmd = if read { &c.read_cmd } else { &c.write_cmd };
+        if let Some((cmd, width)) = cmd {
+            self.comment(&format!("Command: 0x{:X}", cmd));
+            let cmd_t = Transaction::new_write(cmd.clone(), *width)?;
+            self.shift_out(&pins, &cmd_t, &shift_order(*width, *width, c.msb_first))?;
+        }
+        if let Some(width) = c.addr_width {
+            let addr = match &t.address {
+                Some(a) => a.clone(),
+                None => bail!(
+                    "SPI service '{}' requires an address for each transaction, but none was given: {:?}",
+                    self.id,
+                    t
+                ),
+            };
+            self.comment(&format!("Address: 0x{:X}", addr));
+            let addr_t = Transaction::new_write(addr, width)?;
+            self.shift_out(&pins, &addr_t, &shift_order(width, width, c.msb_first))?;
+        }
+
+        let order = shift_order(t.width, c.word_size, c.msb_first);
+        if read {
+            if c.read_dummy_cycles > 0 {
+                self.comment(&format!("Dummy cycles: {}", c.read_dummy_cycles));
+                for _ in 0..c.read_dummy_cycles {
+                    self.shift(&pins, None, None)?;
+                }
+            }
+            self.comment("Read data: target -> host");
+            self.shift_in(&pins, t, &order)?;
+        } else {
+            self.comment("Write data: host -> target");
+            self.shift_out(&pins, t, &order)?;
+        }
+
+        self.comment("Release chip select");
+        self.release(&pins);
+        Ok(())
+    }
+}
+
+/// Returns the bit positions of a field in the order they are to be shifted. The field is padded
+/// to a whole number of words, MSB first order shifts the most significant word first and LSB
+/// first order the least significant word first.
+fn shift_order(width: usize, word_size: usize, msb_first: bool) -> Vec<usize> {
+    let bits = 0..(width.div_ceil(word_size) * word_size);
+    if msb_first {
+        bits.rev().collect()
+    } else {
+        bits.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_order_is_by_word() {
+        assert_eq!(shift_order(4, 4, true), vec![3, 2, 1, 0]);
+        assert_eq!(shift_order(4, 4, false), vec![0, 1, 2, 3]);
+        // Padded to whole words, with the word order following the bit order
+        assert_eq!(shift_order(6, 4, true), vec![7, 6, 5, 4, 3, 2, 1, 0]);
+        assert_eq!(shift_order(8, 4, false), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+        // e.g. 0x1234 in 8-bit words goes out as 0x12 then 0x34
+        let order = shift_order(16, 8, true);
+        assert_eq!(&order[..8], &[15, 14, 13, 12, 11, 10, 9, 8]);
+        assert_eq!(&order[8..], &[7, 6, 5, 4, 3, 2, 1, 0]);
+    }
+}
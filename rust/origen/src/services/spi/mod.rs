@@ -0,0 +1,3 @@
+mod driver;
+mod service;
+pub use service::{Config, Service};
@@ -0,0 +1,129 @@
+use crate::core::dut::Dut;
+use crate::generator::PAT;
+use crate::precludes::controller::*;
+use crate::{Result, TEST};
+use num_bigint::BigUint;
+
+/// The SPI mode and framing, the default is mode 0 (CPOL = 0, CPHA = 0), MSB first with an
+/// active low chip select, 8-bit words and no command or address phases
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// The idle level of SCLK
+    pub cpol: bool,
+    /// When false data is sampled on the leading edge of SCLK, otherwise on the trailing edge
+    pub cpha: bool,
+    pub msb_first: bool,
+    pub cs_active_high: bool,
+    /// Data is shifted in whole words, zero-extended as required. When MSB first the most
+    /// significant word is shifted first, starting from its MSB, otherwise the least significant
+    /// word is shifted first, starting from its LSB.
+    pub word_size: usize,
+    /// The value and width of a command to be shifted out at the start of write frames
+    pub write_cmd: Option<(BigUint, usize)>,
+    /// The value and width of a command to be shifted out at the start of read frames
+    pub read_cmd: Option<(BigUint, usize)>,
+    /// The width of the transaction address to be shifted out after the command, if any
+    pub addr_width: Option<usize>,
+    /// The number of SCLK periods between the address and the data of read frames
+    pub read_dummy_cycles: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cpol: false,
+            cpha: false,
+            msb_first: true,
+            cs_active_high: false,
+            word_size: 8,
+            write_cmd: None,
+            read_cmd: None,
+            addr_width: None,
+            read_dummy_cycles: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Service {
+    pub id: usize,
+    pub sclk: (String, usize),
+    pub mosi: (String, usize),
+    pub miso: (String, usize),
+    pub cs: (String, usize),
+    pub config: Config,
+}
+
+impl Service {
+    pub fn new(
+        _dut: &Dut,
+        id: usize,
+        sclk: Option<&PinGroup>,
+        mosi: Option<&PinGroup>,
+        miso: Option<&PinGroup>,
+        cs: Option<&PinGroup>,
+        config: Config,
+    ) -> Result<Self> {
+        if config.word_size == 0 {
+            bail!("The SPI word size must be at least 1 bit");
+        }
+        let pin = |grp: Option<&PinGroup>, default: &str| match grp {
+            Some(grp) => grp.to_identifier(),
+            None => (default.to_string(), 0),
+        };
+        Ok(Self {
+            id,
+            sclk: pin(sclk, "sclk"),
+            mosi: pin(mosi, "mosi"),
+            miso: pin(miso, "miso"),
+            cs: pin(cs, "cs"),
+            config,
+        })
+    }
+
+    /// Returns the pins to their idle states, with the chip select deasserted
+    pub fn reset(&self, dut: &Dut) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::SPIReset, self.id));
+        self.idle(dut)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Shifts out the given transaction's data on MOSI, preceded by the write command and
+    /// address, if configured
+    pub fn write(&self, dut: &Dut, transaction: Transaction) -> Result<()> {
+        let mosi = PinCollection::from_group(dut, &self.mosi.0, self.mosi.1)?;
+        let mut t = transaction.clone();
+        t.apply_overlay_pin_ids(&mosi.as_ids())?;
+        let mut trans = node!(PAT::SPIWrite, self.id, t, None);
+        let n_id = TEST.push_and_open(trans.clone());
+        self.process_transaction(dut, &mut trans)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Verifies the given transaction's data on MISO, following the read command and address,
+    /// if configured
+    pub fn verify(&self, dut: &Dut, transaction: Transaction) -> Result<()> {
+        let miso = PinCollection::from_group(dut, &self.miso.0, self.miso.1)?;
+        let mut t = transaction.clone();
+        t.apply_overlay_pin_ids(&miso.as_ids())?;
+        let mut trans = node!(PAT::SPIVerify, self.id, t, None);
+        let n_id = TEST.push_and_open(trans.clone());
+        self.process_transaction(dut, &mut trans)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
+    /// Captures MISO during the data phase of a read frame, see verify
+    pub fn capture(&self, dut: &Dut, transaction: Transaction) -> Result<()> {
+        let miso = PinCollection::from_group(dut, &self.miso.0, self.miso.1)?;
+        let mut t = transaction.clone();
+        t.apply_overlay_pin_ids(&miso.as_ids())?;
+        let mut trans = node!(PAT::SPICapture, self.id, t, None);
+        let n_id = TEST.push_and_open(trans.clone());
+        self.process_transaction(dut, &mut trans)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+}
@@ -165,6 +165,20 @@ impl Service {
         Ok(())
     }
 
+    /// Writes TARGETSEL to select a multi-drop target, this is never acknowledged so the ACK
+    /// phase is not compared. It must immediately follow a line reset.
+    pub fn write_targetsel(&self, dut: &crate::Dut, targetsel: u32) -> Result<()> {
+        let n_id = TEST.push_and_open(node!(PAT::SWDTargetSel, self.id, targetsel));
+        let swdclk = PinCollection::from_group(dut, &self.swdclk.0, self.swdclk.1)?;
+        let swdio = PinCollection::from_group(dut, &self.swdio.0, self.swdio.1)?;
+        self.comment(&format!("Write TARGETSEL: 0x{:08X}", targetsel));
+        self.drive_header(&swdclk, &swdio, 0xC, false, false)?;
+        self.verify_ack(&swdio, &Acknowledgements::None)?;
+        self.drive_data(&swdio, &Transaction::new_write(targetsel.into(), 32)?)?;
+        TEST.close(n_id)?;
+        Ok(())
+    }
+
     pub fn line_reset(&self, dut: &crate::Dut) -> Result<usize> {
         let n_id = TEST.push_and_open(node!(PAT::SWDLineReset));
         let swdclk = PinCollection::from_group(dut, &self.swdclk.0, self.swdclk.1)?;
@@ -0,0 +1,640 @@
+//! Parsers for memory image files (Intel HEX, Motorola S-record, ELF and raw binary), these are
+//! loaded into a MemImage which holds the image data as a set of contiguous segments
+//! of bytes, each with a start address.
+//!
+//! An image can be converted into a sequence of address/data transactions sized to a given bus
+//! width, and downloaded through any service which supports addressed accesses.
+
+use crate::generator::PAT;
+use crate::{Dut, Result, Services, Transaction, TEST};
+use num_bigint::BigUint;
+use std::path::Path;
+use std::sync::MutexGuard;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    IntelHex,
+    SRecord,
+    Elf,
+    Binary,
+}
+
+impl std::str::FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ihex" | "intel_hex" | "hex" => Ok(ImageFormat::IntelHex),
+            "srec" | "s_record" | "srecord" => Ok(ImageFormat::SRecord),
+            "elf" => Ok(ImageFormat::Elf),
+            "bin" | "binary" => Ok(ImageFormat::Binary),
+            _ => Err(format!("'{}' is not a valid memory image format", s)),
+        }
+    }
+}
+
+impl ImageFormat {
+    /// Works out the format of the given file from its extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let ext = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        match ext.as_str() {
+            "hex" | "ihex" | "ihx" => Ok(ImageFormat::IntelHex),
+            "srec" | "s19" | "s28" | "s37" | "mot" => Ok(ImageFormat::SRecord),
+            "elf" | "axf" | "out" => Ok(ImageFormat::Elf),
+            "bin" => Ok(ImageFormat::Binary),
+            _ => bail!(
+                "Could not determine the format of memory image '{}' from its extension, please specify it",
+                path.display()
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub address: u64,
+    pub data: Vec<u8>,
+}
+
+impl Segment {
+    /// Returns the address of the byte following the end of the segment
+    pub fn end(&self) -> u64 {
+        self.address + self.data.len() as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MemImage {
+    /// Non-overlapping segments of data, sorted by address
+    pub segments: Vec<Segment>,
+    /// The start/entry address, if given by the image
+    pub entry_point: Option<u64>,
+}
+
+impl MemImage {
+    /// Loads the given image file, if no format is given it will be worked out from the
+    /// file's extension.
+    /// Binary images have no address information, so their data is placed at the given
+    /// base_address (0 if not given), which is ignored for the other formats.
+    pub fn load(
+        path: &Path,
+        format: Option<ImageFormat>,
+        base_address: Option<u64>,
+    ) -> Result<Self> {
+        let format = match format {
+            Some(f) => f,
+            None => ImageFormat::from_path(path)?,
+        };
+        if !path.exists() {
+            bail!("Memory image '{}' does not exist", path.display());
+        }
+        let r = match format {
+            ImageFormat::Binary => Ok(Self::from_binary(
+                &std::fs::read(path)?,
+                base_address.unwrap_or(0),
+            )),
+            ImageFormat::IntelHex => Self::from_intel_hex(&std::fs::read_to_string(path)?),
+            ImageFormat::SRecord => Self::from_srec(&std::fs::read_to_string(path)?),
+            ImageFormat::Elf => Self::from_elf(&std::fs::read(path)?),
+        };
+        match r {
+            Ok(img) => Ok(img),
+            Err(e) => bail!("Error loading memory image '{}': {}", path.display(), e.msg),
+        }
+    }
+
+    pub fn from_binary(data: &[u8], base_address: u64) -> Self {
+        let mut img = Self::default();
+        img.add(base_address, data);
+        img
+    }
+
+    pub fn from_intel_hex(content: &str) -> Result<Self> {
+        let mut img = Self::default();
+        let mut base: u64 = 0;
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with(':') {
+                bail!("Line {}: Intel HEX records must start with ':'", i + 1);
+            }
+            let bytes = record_bytes(&line[1..], i + 1)?;
+            if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+                bail!("Line {}: Malformed Intel HEX record", i + 1);
+            }
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+                bail!("Line {}: Intel HEX record checksum mismatch", i + 1);
+            }
+            let offset = ((bytes[1] as u64) << 8) | bytes[2] as u64;
+            let data = &bytes[4..bytes.len() - 1];
+            // The address records have a fixed length
+            let expected = match bytes[3] {
+                0x02 | 0x04 => Some(2),
+                0x03 | 0x05 => Some(4),
+                _ => None,
+            };
+            if expected.is_some_and(|n| data.len() != n) {
+                bail!(
+                    "Line {}: Intel HEX record type {:02X} must have {} data bytes",
+                    i + 1,
+                    bytes[3],
+                    expected.unwrap()
+                );
+            }
+            match bytes[3] {
+                0x00 => img.add(base + offset, data),
+                0x01 => break,
+                0x02 => base = (be_value(data) as u64) << 4,
+                0x03 => {
+                    img.entry_point =
+                        Some(((be_value(&data[0..2]) << 4) + be_value(&data[2..])) as u64)
+                }
+                0x04 => base = (be_value(data) as u64) << 16,
+                0x05 => img.entry_point = Some(be_value(data) as u64),
+                t => bail!("Line {}: Unknown Intel HEX record type {:02X}", i + 1, t),
+            }
+        }
+        Ok(img)
+    }
+
+    pub fn from_srec(content: &str) -> Result<Self> {
+        let mut img = Self::default();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with('S') || line.len() < 2 {
+                bail!("Line {}: S-records must start with 'S'", i + 1);
+            }
+            let t = match line.get(1..2) {
+                Some(t) => t,
+                None => bail!("Line {}: Unknown S-record type", i + 1),
+            };
+            let bytes = record_bytes(&line[2..], i + 1)?;
+            if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+                bail!("Line {}: Malformed S-record", i + 1);
+            }
+            if bytes.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0xFF {
+                bail!("Line {}: S-record checksum mismatch", i + 1);
+            }
+            let addr_bytes = match t {
+                "0" | "1" | "5" | "9" => 2,
+                "2" | "6" | "8" => 3,
+                "3" | "7" => 4,
+                _ => bail!("Line {}: Unknown S-record type S{}", i + 1, t),
+            };
+            if bytes.len() < addr_bytes + 2 {
+                bail!("Line {}: Malformed S-record", i + 1);
+            }
+            let addr = be_value(&bytes[1..addr_bytes + 1]) as u64;
+            let data = &bytes[addr_bytes + 1..bytes.len() - 1];
+            match t {
+                "1" | "2" | "3" => img.add(addr, data),
+                "7" | "8" | "9" => img.entry_point = Some(addr),
+                // Header and record counts
+                _ => {}
+            }
+        }
+        Ok(img)
+    }
+
+    /// Loads the loadable (PT_LOAD) segments of the given ELF file (32 or 64-bit, either
+    /// endianness) at their physical addresses. Zero-initialized regions (e.g. .bss) are not
+    /// included since they are not held by the file.
+    pub fn from_elf(data: &[u8]) -> Result<Self> {
+        if data.len() < 0x34 || data[0..4] != [0x7F, b'E', b'L', b'F'] {
+            bail!("Not an ELF file");
+        }
+        let elf = Elf {
+            data,
+            is_64: match data[4] {
+                1 => false,
+                2 => true,
+                x => bail!("Unknown ELF class {}", x),
+            },
+            big_endian: match data[5] {
+                1 => false,
+                2 => true,
+                x => bail!("Unknown ELF data encoding {}", x),
+            },
+        };
+        let mut img = Self::default();
+        let (phoff, phentsize, phnum) = if elf.is_64 {
+            img.entry_point = Some(elf.read(0x18, 8)?);
+            (elf.read(0x20, 8)?, elf.read(0x36, 2)?, elf.read(0x38, 2)?)
+        } else {
+            img.entry_point = Some(elf.read(0x18, 4)?);
+            (elf.read(0x1C, 4)?, elf.read(0x2A, 2)?, elf.read(0x2C, 2)?)
+        };
+        for i in 0..phnum {
+            let ph = match i
+                .checked_mul(phentsize)
+                .and_then(|x| x.checked_add(phoff))
+            {
+                Some(x) => x as usize,
+                None => bail!("ELF program header {} is beyond the end of the file", i),
+            };
+            if elf.read(ph, 4)? != 1 {
+                // Not PT_LOAD
+                continue;
+            }
+            let (offset, paddr, filesz) = if elf.is_64 {
+                (
+                    elf.read(ph + 8, 8)?,
+                    elf.read(ph + 24, 8)?,
+                    elf.read(ph + 32, 8)?,
+                )
+            } else {
+                (
+                    elf.read(ph + 4, 4)?,
+                    elf.read(ph + 12, 4)?,
+                    elf.read(ph + 16, 4)?,
+                )
+            };
+            let start = offset as usize;
+            let end = match start.checked_add(filesz as usize) {
+                Some(x) if x <= data.len() => x,
+                _ => bail!(
+                    "ELF program header {} refers to data beyond the end of the file",
+                    i
+                ),
+            };
+            img.add(paddr, &data[start..end]);
+        }
+        Ok(img)
+    }
+
+    /// Adds the given data to the image, any existing data at the same addresses will be
+    /// overwritten
+    pub fn add(&mut self, address: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let mut new = Segment {
+            address,
+            data: data.to_vec(),
+        };
+        let end = new.end();
+        let mut segments: Vec<Segment> = vec![];
+        for s in self.segments.drain(..) {
+            if s.end() < new.address || s.address > end {
+                // Not touching the new data
+                segments.push(s);
+            } else {
+                // Overlapping or adjacent, merge it into the new segment with the new data
+                // taking priority
+                let start = std::cmp::min(s.address, new.address);
+                let stop = std::cmp::max(s.end(), new.end());
+                let mut merged = vec![0; (stop - start) as usize];
+                let o = (s.address - start) as usize;
+                merged[o..o + s.data.len()].copy_from_slice(&s.data);
+                let o = (new.address - start) as usize;
+                merged[o..o + new.data.len()].copy_from_slice(&new.data);
+                new = Segment {
+                    address: start,
+                    data: merged,
+                };
+            }
+        }
+        segments.push(new);
+        segments.sort_by_key(|s| s.address);
+        self.segments = segments;
+    }
+
+    /// Returns the byte at the given address, None if the image does not define it
+    pub fn byte_at(&self, address: u64) -> Option<u8> {
+        self.segments
+            .iter()
+            .find(|s| address >= s.address && address < s.end())
+            .map(|s| s.data[(address - s.address) as usize])
+    }
+
+    /// Returns the total number of bytes defined by the image
+    pub fn len(&self) -> usize {
+        self.segments.iter().map(|s| s.data.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// Returns an iterator over all (address, byte) pairs defined by the image, in address order
+    pub fn bytes(&self) -> impl Iterator<Item = (u64, u8)> + '_ {
+        self.segments.iter().flat_map(|s| {
+            s.data
+                .iter()
+                .enumerate()
+                .map(move |(i, b)| (s.address + i as u64, *b))
+        })
+    }
+}
+
+/// Controls how gaps between the image's segments are handled when converting to transactions
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GapHandling {
+    /// Gaps are skipped, only bus words containing image data are accessed
+    Skip,
+    /// All gaps are filled, giving a contiguous range of accesses from the first to the last byte
+    Fill,
+    /// Gaps of up to the given number of bytes are filled, larger ones are skipped
+    FillUpTo(u64),
+}
+
+/// Controls when downloaded data is verified
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VerifyMode {
+    Off,
+    /// Verify each bus word immediately after writing it
+    EachWrite,
+    /// Verify all bus words after the whole image has been written
+    AfterDownload,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionOptions {
+    /// The width of each transaction in bits, must be a multiple of 8
+    pub bus_width: usize,
+    /// The value given to bytes within a transaction which are not defined by the image, i.e.
+    /// partially covered bus words and filled gaps
+    pub fill: u8,
+    pub gaps: GapHandling,
+    /// Bytes are assembled into bus words in little-endian order unless this is set
+    pub big_endian: bool,
+}
+
+impl Default for TransactionOptions {
+    fn default() -> Self {
+        Self {
+            bus_width: 32,
+            fill: 0xFF,
+            gaps: GapHandling::Skip,
+            big_endian: false,
+        }
+    }
+}
+
+impl MemImage {
+    /// Returns the image as a list of (address, data) pairs, one for each bus word to be accessed,
+    /// with addresses aligned to the bus width
+    pub fn bus_words(&self, options: &TransactionOptions) -> Result<Vec<(u64, BigUint)>> {
+        if options.bus_width == 0 || !options.bus_width.is_multiple_of(8) {
+            bail!(
+                "The bus width must be a non-zero multiple of 8 bits, given: {}",
+                options.bus_width
+            );
+        }
+        let bytes_per_word = (options.bus_width / 8) as u64;
+        // Group the segments into the address ranges to be accessed, gaps which are to be filled
+        // are bridged here
+        let mut runs: Vec<(u64, u64)> = vec![];
+        for s in &self.segments {
+            if let Some(last) = runs.last_mut() {
+                let gap = s.address - last.1;
+                let fill = match options.gaps {
+                    GapHandling::Skip => false,
+                    GapHandling::Fill => true,
+                    GapHandling::FillUpTo(max) => gap <= max,
+                };
+                if fill {
+                    last.1 = s.end();
+                    continue;
+                }
+            }
+            runs.push((s.address, s.end()));
+        }
+        let mut words: Vec<(u64, BigUint)> = vec![];
+        for (start, end) in runs {
+            let mut addr = start - (start % bytes_per_word);
+            // Skip a word which has already been covered by the previous run
+            if let Some((last, _)) = words.last() {
+                if addr <= *last {
+                    addr = *last + bytes_per_word;
+                }
+            }
+            while addr < end {
+                let bytes: Vec<u8> = (0..bytes_per_word)
+                    .map(|i| self.byte_at(addr + i).unwrap_or(options.fill))
+                    .collect();
+                let data = if options.big_endian {
+                    BigUint::from_bytes_be(&bytes)
+                } else {
+                    BigUint::from_bytes_le(&bytes)
+                };
+                words.push((addr, data));
+                addr += bytes_per_word;
+            }
+        }
+        Ok(words)
+    }
+
+    /// Returns the image as a sequence of write transactions sized to the given bus width
+    pub fn to_write_transactions(&self, options: &TransactionOptions) -> Result<Vec<Transaction>> {
+        let mut transactions = vec![];
+        for (addr, data) in self.bus_words(options)? {
+            transactions.push(Transaction::new_write_with_addr(
+                data,
+                options.bus_width,
+                addr as u128,
+            )?);
+        }
+        Ok(transactions)
+    }
+
+    /// Returns the image as a sequence of verify transactions sized to the given bus width
+    pub fn to_verify_transactions(&self, options: &TransactionOptions) -> Result<Vec<Transaction>> {
+        let mut transactions = vec![];
+        for (addr, data) in self.bus_words(options)? {
+            let mut t = Transaction::new_verify(data, options.bus_width)?;
+            t.address = Some(BigUint::from(addr));
+            transactions.push(t);
+        }
+        Ok(transactions)
+    }
+
+    /// Writes the image through the given service (any service supporting address/data
+    /// transactions, e.g. an ArmDebug MemAP), optionally verifying it.
+    /// Returns the number of write transactions generated.
+    pub fn download(
+        &self,
+        options: &TransactionOptions,
+        verify: VerifyMode,
+        service_id: usize,
+        dut: &MutexGuard<Dut>,
+        services: &Services,
+    ) -> Result<usize> {
+        let writes = self.to_write_transactions(options)?;
+        let verifies = match verify {
+            VerifyMode::Off => vec![],
+            _ => self.to_verify_transactions(options)?,
+        };
+        TEST.push(node!(
+            PAT::Comment,
+            0,
+            format!(
+                "Downloading memory image: {} bytes in {} {}-bit transactions",
+                self.len(),
+                writes.len(),
+                options.bus_width
+            )
+        ));
+        for (i, t) in writes.iter().enumerate() {
+            services.write_transaction(service_id, dut, t)?;
+            if verify == VerifyMode::EachWrite {
+                services.verify_transaction(service_id, dut, &verifies[i])?;
+            }
+        }
+        if verify == VerifyMode::AfterDownload {
+            TEST.push(node!(PAT::Comment, 0, "Verifying memory image".to_string()));
+            for t in &verifies {
+                services.verify_transaction(service_id, dut, t)?;
+            }
+        }
+        Ok(writes.len())
+    }
+}
+
+struct Elf<'a> {
+    data: &'a [u8],
+    is_64: bool,
+    big_endian: bool,
+}
+
+impl Elf<'_> {
+    fn read(&self, offset: usize, size: usize) -> Result<u64> {
+        if offset.checked_add(size).is_none_or(|end| end > self.data.len()) {
+            bail!("Unexpected end of ELF file at offset 0x{:X}", offset);
+        }
+        let bytes = &self.data[offset..offset + size];
+        Ok(if self.big_endian {
+            bytes.iter().fold(0u64, |v, b| (v << 8) | *b as u64)
+        } else {
+            bytes.iter().rev().fold(0u64, |v, b| (v << 8) | *b as u64)
+        })
+    }
+}
+
+fn record_bytes(hex: &str, line: usize) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        bail!("Line {}: Record has an odd number of hex characters", line);
+    }
+    let mut bytes = vec![];
+    for i in (0..hex.len()).step_by(2) {
+        match hex.get(i..i + 2).map(|h| u8::from_str_radix(h, 16)) {
+            Some(Ok(b)) => bytes.push(b),
+            _ => bail!("Line {}: Invalid hex characters in '{}'", line, hex),
+        }
+    }
+    Ok(bytes)
+}
+
+fn be_value(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0u32, |v, b| (v << 8) | *b as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_intel_hex() {
+        let img = MemImage::from_intel_hex(
+            ":020000040001F9\n:0400100001020304E2\n:020014000506DF\n:0400000500000100F6\n:00000001FF\n",
+        )
+        .unwrap();
+        assert_eq!(img.segments.len(), 1);
+        assert_eq!(img.segments[0].address, 0x10010);
+        assert_eq!(img.segments[0].data, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(img.entry_point, Some(0x100));
+        assert!(MemImage::from_intel_hex(":0400100001020304E3\n").is_err());
+        // Start segment address record with too few bytes
+        assert!(MemImage::from_intel_hex(":0100000300FC\n").is_err());
+        assert!(MemImage::from_intel_hex(":0é0000\n").is_err());
+    }
+
+    #[test]
+    fn parses_srec() {
+        let img = MemImage::from_srec(
+            "S00600004844521B\nS1070010AABBCCDDDA\nS307000020001122A5\nS9030000FC\n",
+        )
+        .unwrap();
+        assert_eq!(img.segments.len(), 2);
+        assert_eq!(img.byte_at(0x11), Some(0xBB));
+        assert_eq!(img.byte_at(0x2001), Some(0x22));
+        assert_eq!(img.byte_at(0x2002), None);
+        assert_eq!(img.len(), 6);
+        assert_eq!(img.entry_point, Some(0));
+        assert!(MemImage::from_srec("Sé030000FC\n").is_err());
+    }
+
+    #[test]
+    fn parses_elf() {
+        // Minimal 32-bit little-endian ELF with one PT_LOAD segment (4 bytes at 0x8000) and one
+        // non-loadable segment
+        let mut elf = vec![0u8; 0x34 + 0x40 + 4];
+        elf[0..6].copy_from_slice(&[0x7F, b'E', b'L', b'F', 1, 1]);
+        elf[0x18..0x1C].copy_from_slice(&0x8001u32.to_le_bytes());
+        elf[0x1C..0x20].copy_from_slice(&0x34u32.to_le_bytes());
+        elf[0x2A..0x2C].copy_from_slice(&0x20u16.to_le_bytes());
+        elf[0x2C..0x2E].copy_from_slice(&2u16.to_le_bytes());
+        let ph = 0x34;
+        elf[ph..ph + 4].copy_from_slice(&1u32.to_le_bytes());
+        elf[ph + 4..ph + 8].copy_from_slice(&0x74u32.to_le_bytes());
+        elf[ph + 12..ph + 16].copy_from_slice(&0x8000u32.to_le_bytes());
+        elf[ph + 16..ph + 20].copy_from_slice(&4u32.to_le_bytes());
+        elf[ph + 0x20..ph + 0x24].copy_from_slice(&4u32.to_le_bytes());
+        elf[0x74..0x78].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let img = MemImage::from_elf(&elf).unwrap();
+        assert_eq!(img.entry_point, Some(0x8001));
+        assert_eq!(img.segments.len(), 1);
+        assert_eq!(img.segments[0].address, 0x8000);
+        assert_eq!(img.segments[0].data, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+        assert!(MemImage::from_elf(&elf[0..0x40]).is_err());
+        // A segment which extends beyond the end of the file
+        elf[ph + 16..ph + 20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(MemImage::from_elf(&elf).is_err());
+    }
+
+    #[test]
+    fn converts_to_transactions() {
+        let mut img = MemImage::from_binary(&[1, 2, 3, 4, 5, 6], 0x102);
+        img.add(0x110, &[7]);
+        let mut opts = TransactionOptions::default();
+        let t = img.to_write_transactions(&opts).unwrap();
+        assert_eq!(t.len(), 3);
+        assert_eq!(t[0].addr().unwrap(), 0x100);
+        assert_eq!(t[0].data, BigUint::from(0x0201_FFFFu32));
+        assert_eq!(t[1].data, BigUint::from(0x0605_0403u32));
+        assert_eq!(t[2].addr().unwrap(), 0x110);
+        assert_eq!(t[2].data, BigUint::from(0xFFFF_FF07u32));
+
+        opts.gaps = GapHandling::FillUpTo(8);
+        opts.fill = 0;
+        opts.big_endian = true;
+        let t = img.to_verify_transactions(&opts).unwrap();
+        assert_eq!(t.len(), 5);
+        assert_eq!(t[0].data, BigUint::from(0x0000_0102u32));
+        assert_eq!(t[2].data, BigUint::from(0u32));
+        assert_eq!(t[4].addr().unwrap(), 0x110);
+
+        opts.gaps = GapHandling::FillUpTo(4);
+        assert_eq!(img.bus_words(&opts).unwrap().len(), 3);
+        opts.bus_width = 12;
+        assert!(img.bus_words(&opts).is_err());
+    }
+
+    #[test]
+    fn merges_segments() {
+        let mut img = MemImage::from_binary(&[1, 2, 3], 0x10);
+        img.add(0x13, &[4]);
+        img.add(0x0, &[9]);
+        img.add(0x11, &[7]);
+        assert_eq!(img.segments.len(), 2);
+        assert_eq!(img.segments[1].data, vec![1, 7, 3, 4]);
+    }
+}
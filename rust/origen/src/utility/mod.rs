@@ -2,11 +2,14 @@ pub mod big_uint_helpers;
 pub mod file_actions;
 pub mod file_utils;
 pub mod location;
+pub mod mem_image;
 pub mod command_helpers;
 pub mod github;
 pub mod num_helpers;
+pub mod publisher;
 pub mod release_scribe;
 pub mod sessions;
+pub mod unit_tester;
 
 use crate::{Result, STATUS};
 use std::path::{Path, PathBuf};
@@ -0,0 +1,95 @@
+//! Rust implementation of the default (Poetry-based) app publisher, used to package and
+//! publish an app when no frontend is available to provide the publisher from the app's
+//! Python.
+
+use crate::core::frontend::Publisher;
+use crate::utility::command_helpers::exec;
+use crate::{Result, ORIGEN_CONFIG, STATUS};
+use origen_metal::Outcome;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const REPO_NAME: &str = "origen_pkg_repo";
+const USERNAME_ENV_VAR: &str = "POETRY_HTTP_BASIC_ORIGEN_PKG_REPO_USERNAME";
+const PASSWORD_ENV_VAR: &str = "POETRY_HTTP_BASIC_ORIGEN_PKG_REPO_PASSWORD";
+const PUSH_MOTIVE: &str = "pkg_server_push";
+
+pub struct Poetry {
+    root: PathBuf,
+}
+
+impl Poetry {
+    /// Creates a publisher from the app's publisher config, only the default 'poetry' system
+    /// is available without a frontend
+    pub fn new(config: Option<&HashMap<String, String>>) -> Result<Self> {
+        if let Some(system) = config.and_then(|c| c.get("system")) {
+            if system.to_lowercase() != "poetry" {
+                bail!(
+                    "Publisher system '{}' is not available outside of the Python frontend",
+                    system
+                );
+            }
+        }
+        match &STATUS.app {
+            Some(app) => Ok(Self {
+                root: app.root.clone(),
+            }),
+            None => bail!("The publisher currently requires an application! No application found."),
+        }
+    }
+
+    fn run(&self, cmd: Vec<&str>, env: Option<HashMap<String, String>>) -> Result<Outcome> {
+        exec(cmd, false, None, Some(self.root.clone()), env, None, false)
+    }
+
+    fn credentials(&self) -> Result<HashMap<String, String>> {
+        if origen_metal::get_current_user_id()?.is_none() {
+            origen_metal::try_lookup_and_set_current_user()?;
+        }
+        origen_metal::with_current_user(|u| {
+            let mut env = HashMap::new();
+            env.insert(USERNAME_ENV_VAR.to_string(), u.username()?);
+            env.insert(
+                PASSWORD_ENV_VAR.to_string(),
+                u.password(Some(PUSH_MOTIVE), true, Some(None))?,
+            );
+            Ok(env)
+        })
+    }
+}
+
+impl Publisher for Poetry {
+    fn build_package(&self) -> Result<Outcome> {
+        let r = self.run(vec!["poetry", "build", "--format", "wheel"], None)?;
+        let mut o = Outcome::new_success_or_fail(r.succeeded());
+        o.insert_keyword_result("format", "wheel");
+        Ok(o)
+    }
+
+    fn upload(&self, _build: &Outcome, dry_run: bool) -> Result<Outcome> {
+        let url = &ORIGEN_CONFIG.pkg_server_push;
+        let repo = format!("repositories.{}", REPO_NAME);
+        if !self
+            .run(vec!["poetry", "config", &repo, url], None)?
+            .succeeded()
+        {
+            let mut o = Outcome::new_fail();
+            o.set_msg("Failed to add poetry repository");
+            return Ok(o);
+        }
+        log_trace!("Added poetry repository {} ({})", REPO_NAME, url);
+
+        let mut cmd = vec!["poetry", "publish", "-r", REPO_NAME];
+        if dry_run {
+            cmd.push("--dry-run");
+        }
+        let r = self.run(cmd, Some(self.credentials()?))?;
+        let mut o = Outcome::new_success_or_fail(r.succeeded());
+        if r.succeeded() {
+            o.set_msg(format!("Successfully pushed package to {}", url));
+        } else {
+            o.set_msg(format!("Failed to push package to repository {}", url));
+        }
+        Ok(o)
+    }
+}
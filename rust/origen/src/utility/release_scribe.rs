@@ -12,6 +12,7 @@ const DATETIME_ID: &str = "timestamp";
 
 const HISTORY_FILE_NAME: &str = "history.generated.toml";
 
+#[derive(Clone)]
 pub struct ReleaseScribe {
     pub history_toml: PathBuf,
     pub release_file: PathBuf,
@@ -201,6 +202,46 @@ impl ReleaseScribe {
     // }
 }
 
+/// Allows the release scribe to be used directly when publishing an app without a frontend
+impl crate::core::frontend::ReleaseScribe for ReleaseScribe {
+    fn release_note_file(&self) -> Result<PathBuf> {
+        Ok(self.release_file.clone())
+    }
+
+    fn get_release_note(&self) -> Result<String> {
+        ReleaseScribe::get_release_note(self)
+    }
+
+    fn get_release_note_from_file(&self) -> Result<String> {
+        ReleaseScribe::get_release_note_from_file(self)
+    }
+
+    fn get_release_title(&self) -> Result<Option<String>> {
+        ReleaseScribe::get_release_title(self)
+    }
+
+    fn history_tracking_file(&self) -> Result<PathBuf> {
+        Ok(self.history_toml.clone())
+    }
+
+    fn append_history(
+        &self,
+        version: &Version,
+        title: Option<&str>,
+        text: &str,
+        dry_run: bool,
+    ) -> Result<()> {
+        // Appending may temporarily redirect the history file, so work on a copy
+        ReleaseScribe::append_history(
+            &mut self.clone(),
+            version,
+            title.map(|t| t.to_string()),
+            Some(text.to_string()),
+            dry_run,
+        )
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize)]
 pub struct ReleaseHistory {
     releases: indexmap::IndexMap<String, Release>,
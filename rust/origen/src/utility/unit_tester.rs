@@ -0,0 +1,53 @@
+//! Rust implementation of the default (pytest) app unit tester, used by the publish checks
+//! when no frontend is available to provide the unit tester from the app's Python.
+
+use crate::core::frontend::UnitTester;
+use crate::utility::command_helpers::exec;
+use crate::{Result, STATUS};
+use origen_metal::{Outcome, OutcomeSubtypes};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+pub struct PyTest {
+    root: PathBuf,
+}
+
+impl PyTest {
+    /// Creates a unit tester from the app's unit tester config, only the default 'pytest'
+    /// system is available without a frontend
+    pub fn new(config: Option<&HashMap<String, String>>) -> Result<Self> {
+        if let Some(system) = config.and_then(|c| c.get("system")) {
+            if system.to_lowercase() != "pytest" {
+                bail!(
+                    "Unit tester system '{}' is not available outside of the Python frontend",
+                    system
+                );
+            }
+        }
+        match &STATUS.app {
+            Some(app) => Ok(Self {
+                root: app.root.clone(),
+            }),
+            None => {
+                bail!("The unit tester currently requires an application! No application found.")
+            }
+        }
+    }
+}
+
+impl UnitTester for PyTest {
+    fn run(&self) -> Result<Outcome> {
+        let r = exec(
+            vec!["poetry", "run", "pytest"],
+            false,
+            None,
+            Some(self.root.clone()),
+            None,
+            None,
+            false,
+        )?;
+        let mut o = Outcome::new_pass_or_fail(r.succeeded());
+        o.subtype = Some(OutcomeSubtypes::UnitTestStatus);
+        Ok(o)
+    }
+}
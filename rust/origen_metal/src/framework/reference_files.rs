@@ -7,6 +7,7 @@
 //! multiple generation jobs are running in parallel on the LSF.
 
 use crate::Result;
+use indexmap::IndexMap;
 use std::fs::{self, create_dir_all};
 use std::path::{Path, PathBuf};
 use std::sync::RwLock;
@@ -16,7 +17,7 @@ lazy_static! {
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum SaveRefType {
+pub enum SaveRefType {
     New,
     Changed,
 }
@@ -158,7 +159,8 @@ pub fn create_new_ref(key: &Path, new_file: &Path, ref_file: &Path) -> Result<()
     Ok(())
 }
 
-/// Clear (delete) all existing changed and new file references that have not been applied yet.
+/// Clear (delete) all existing changed and new file references that have not been applied yet,
+/// along with any review session of them.
 pub fn clear_save_refs() -> Result<()> {
     let save_dir = save_ref_dir()?;
     // Remove all existing save references
@@ -166,5 +168,116 @@ pub fn clear_save_refs() -> Result<()> {
         std::fs::remove_dir_all(&save_dir)?;
     }
     std::fs::create_dir_all(&save_dir)?;
+    ReviewSession::clear()?;
     Ok(())
 }
+
+/// A changed or new file reference which has been created but not necessarily applied yet
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingRef {
+    /// The key which identifies the reference, as given to apply_ref()
+    pub key: PathBuf,
+    pub kind: SaveRefType,
+    /// The newly generated file
+    pub source: PathBuf,
+    /// The reference file
+    pub dest: PathBuf,
+}
+
+/// Returns all previously created changed and new file references, ordered by their keys
+pub fn pending_refs() -> Result<Vec<PendingRef>> {
+    let dir = save_ref_dir()?;
+    let mut refs = vec![];
+    collect_refs(&dir, &dir, &mut refs)?;
+    refs.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(refs)
+}
+
+fn collect_refs(root: &Path, dir: &Path, refs: &mut Vec<PendingRef>) -> Result<()> {
+    if dir.is_dir() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                collect_refs(root, &path, refs)?;
+            } else {
+                let save_ref = SaveRef::load_from_file(&path)?;
+                // The key is the path of the save ref relative to the save ref dir, without the
+                // .toml extension added by SaveRef::save()
+                let key = path.strip_prefix(root).unwrap().to_string_lossy();
+                refs.push(PendingRef {
+                    key: PathBuf::from(key.trim_end_matches(".toml")),
+                    kind: save_ref.kind,
+                    source: save_ref.source,
+                    dest: save_ref.dest,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ReviewDecision {
+    /// The new file was saved as the reference
+    Accepted,
+    Skipped,
+}
+
+/// Records the decisions made while reviewing the pending references so that a review can be
+/// resumed later. It is stored alongside the save refs and is cleared along with them when the
+/// next generation run starts.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReviewSession {
+    pub decisions: IndexMap<String, ReviewDecision>,
+}
+
+impl ReviewSession {
+    fn file() -> Result<PathBuf> {
+        let dir = save_ref_dir()?;
+        let name = match dir.file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => "save_refs".to_string(),
+        };
+        Ok(dir.with_file_name(format!("{}_review.toml", name)))
+    }
+
+    /// Loads the current review session, returning an empty one if none has been started
+    pub fn load() -> Result<Self> {
+        let file = ReviewSession::file()?;
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        match toml::from_str(&fs::read_to_string(&file)?) {
+            Ok(s) => Ok(s),
+            Err(e) => bail!("Malformed save reference review file: {}", e),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let file = ReviewSession::file()?;
+        if let Some(dir) = file.parent() {
+            create_dir_all(dir)?;
+        }
+        fs::write(file, toml::to_string(&self).unwrap())?;
+        Ok(())
+    }
+
+    /// Deletes the current review session
+    pub fn clear() -> Result<()> {
+        let file = ReviewSession::file()?;
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    pub fn decision(&self, key: &Path) -> Option<ReviewDecision> {
+        self.decisions.get(&key.display().to_string()).copied()
+    }
+
+    /// Records the decision for the given reference and saves the session
+    pub fn record(&mut self, key: &Path, decision: ReviewDecision) -> Result<()> {
+        self.decisions.insert(key.display().to_string(), decision);
+        self.save()
+    }
+}
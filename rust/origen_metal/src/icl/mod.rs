@@ -0,0 +1,234 @@
+//! IEEE 1687 (IJTAG) support.
+//!
+//! ICL files are parsed into a model of their modules, from which a Network can be built to
+//! represent the scan network of a top-level module. The network tracks the values held by the
+//! network's registers and retargets register reads and writes (iRead/iWrite/iApply) into the
+//! DR scans required to apply them, opening SIBs and setting ScanMux selects as necessary.
+//!
+//! Only the structural parts of ICL are modeled, attributes, parameters, aliases and enums
+//! are accepted but ignored.
+
+pub mod network;
+mod parser;
+pub mod pdl;
+
+pub use network::{Network, Shift};
+pub use pdl::PDL;
+
+use crate::Result;
+use indexmap::IndexMap;
+use num_bigint::BigUint;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ICL {
+    pub modules: IndexMap<String, Module>,
+}
+
+impl ICL {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        parser::parse_file(path)
+    }
+
+    pub fn from_str(icl: &str) -> Result<Self> {
+        parser::parse_str(icl, None)
+    }
+
+    /// Adds the modules from the given ICL, e.g. to combine the ICL for several instruments,
+    /// an error will be raised if a module is defined more than once
+    pub fn merge(&mut self, other: ICL) -> Result<()> {
+        for (name, module) in other.modules {
+            if self.modules.contains_key(&name) {
+                bail!("ICL module '{}' is defined more than once", name);
+            }
+            self.modules.insert(name, module);
+        }
+        Ok(())
+    }
+
+    pub fn module(&self, name: &str) -> Result<&Module> {
+        match self.modules.get(name) {
+            Some(m) => Ok(m),
+            None => bail!("No ICL module named '{}' has been defined", name),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortKind {
+    ScanIn,
+    ScanOut,
+    Select,
+    ToSelect,
+    DataIn,
+    DataOut,
+    /// Clock, enable and reset ports which play no part in retargeting
+    Control,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Port {
+    pub name: String,
+    pub kind: PortKind,
+    pub range: Option<(usize, usize)>,
+    pub source: Option<Signal>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    /// A reference to a register, mux or port, optionally an instance port given as "inst.port"
+    Ref(String, Option<(usize, usize)>),
+    /// A constant value and its width, if sized
+    Const(BigUint, Option<usize>),
+    Inverted(String, Option<(usize, usize)>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanRegister {
+    pub name: String,
+    pub msb: usize,
+    pub lsb: usize,
+    pub scan_in_source: Option<Signal>,
+    pub capture_source: Vec<Signal>,
+    pub reset_value: Option<BigUint>,
+    pub default_load_value: Option<BigUint>,
+}
+
+impl ScanRegister {
+    pub fn width(&self) -> usize {
+        self.msb.abs_diff(self.lsb) + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanMux {
+    pub name: String,
+    /// The select signals, concatenated with the first being the most significant
+    pub selected_by: Vec<Signal>,
+    /// The select value(s) for each input
+    pub inputs: Vec<(Vec<BigUint>, Signal)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataRegister {
+    pub name: String,
+    pub msb: usize,
+    pub lsb: usize,
+    pub write_data_source: Option<Signal>,
+    pub reset_value: Option<BigUint>,
+}
+
+impl DataRegister {
+    pub fn width(&self) -> usize {
+        self.msb.abs_diff(self.lsb) + 1
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub name: String,
+    pub module: String,
+    /// Input port connections
+    pub inputs: IndexMap<String, Signal>,
+    pub parameters: IndexMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessLink {
+    pub name: String,
+    /// The standard the link conforms to, e.g. STD_1149_1_2001
+    pub of: String,
+    pub bsdl_entity: Option<String>,
+    /// The scan interfaces, given as "inst" or "inst.interface", accessed by each instruction
+    pub instructions: IndexMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub ports: IndexMap<String, Port>,
+    /// The ports which make up each scan interface
+    pub scan_interfaces: IndexMap<String, Vec<String>>,
+    pub scan_registers: IndexMap<String, ScanRegister>,
+    pub scan_muxes: IndexMap<String, ScanMux>,
+    pub data_registers: IndexMap<String, DataRegister>,
+    pub instances: IndexMap<String, Instance>,
+    pub access_links: IndexMap<String, AccessLink>,
+}
+
+impl Module {
+    /// Returns the scan in and scan out ports of the given scan interface, or of the module's
+    /// only scan interface if not given
+    pub fn scan_ports(&self, interface: Option<&str>) -> Result<(String, String)> {
+        let ports: Vec<&Port> = match interface {
+            Some(i) => match self.scan_interfaces.get(i) {
+                Some(ports) => ports.iter().filter_map(|p| self.ports.get(p)).collect(),
+                None => bail!("Module '{}' has no scan interface named '{}'", self.name, i),
+            },
+            None => self.ports.values().collect(),
+        };
+        let si: Vec<&&Port> = ports
+            .iter()
+            .filter(|p| p.kind == PortKind::ScanIn)
+            .collect();
+        let so: Vec<&&Port> = ports
+            .iter()
+            .filter(|p| p.kind == PortKind::ScanOut)
+            .collect();
+        if si.len() != 1 || so.len() != 1 {
+            bail!(
+                "Expected a single ScanInPort and ScanOutPort in module '{}'{}, found {} and {}",
+                self.name,
+                match interface {
+                    Some(i) => format!(" scan interface '{}'", i),
+                    None => "".to_string(),
+                },
+                si.len(),
+                so.len()
+            );
+        }
+        Ok((si[0].name.to_string(), so[0].name.to_string()))
+    }
+}
+
+/// Parses an ICL or PDL number, e.g. "8'hFF", "'b0101", "0x1F", "0b11", "12", returning its
+/// value and width (if sized)
+pub fn parse_number(num: &str) -> Result<(BigUint, Option<usize>)> {
+    let n = num.replace('_', "");
+    let (width, radix, digits) = if let Some(i) = n.find('\'') {
+        let width = match &n[..i] {
+            "" => None,
+            w => Some(
+                w.parse::<usize>()
+                    .map_err(|_| error!("Invalid number '{}'", num))?,
+            ),
+        };
+        let radix = match n[i + 1..].chars().next() {
+            Some('b') | Some('B') => 2,
+            Some('h') | Some('H') => 16,
+            Some('d') | Some('D') => 10,
+            _ => bail!("Invalid number '{}'", num),
+        };
+        (width, radix, n[i + 2..].to_string())
+    } else if let Some(d) = n.strip_prefix("0x").or(n.strip_prefix("0X")) {
+        (None, 16, d.to_string())
+    } else if let Some(d) = n.strip_prefix("0b").or(n.strip_prefix("0B")) {
+        (None, 2, d.to_string())
+    } else {
+        (None, 10, n.to_string())
+    };
+    if digits.contains(['x', 'X']) {
+        bail!("Don't care values are not supported, given '{}'", num);
+    }
+    match BigUint::parse_bytes(digits.as_bytes(), radix) {
+        Some(v) => {
+            if let Some(w) = width {
+                if v.bits() as usize > w {
+                    bail!("The value of '{}' does not fit in {} bits", num, w);
+                }
+            }
+            Ok((v, width))
+        }
+        None => bail!("Invalid number '{}'", num),
+    }
+}
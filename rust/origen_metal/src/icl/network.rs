@@ -0,0 +1,1032 @@
+//! A flattened model of the scan network below a top-level ICL module, along with the
+//! retargeting engine which works out the DR scans required to apply register accesses.
+//!
+//! All register, mux and instance paths are hierarchical paths relative to the top module,
+//! e.g. "sib1.SR" or "core.tdr".
+
+use super::{Module, PortKind, Signal, ICL};
+use crate::Result;
+use indexmap::IndexMap;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use std::collections::{HashMap, HashSet};
+
+/// A point in the scan network which feeds a scan input
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Node {
+    /// The network's scan input, i.e. TDI
+    ScanIn,
+    Register(String),
+    Mux(String),
+    Unconnected,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Register {
+    pub path: String,
+    pub width: usize,
+    /// The index of the register's least significant bit as declared in the ICL
+    pub lsb: usize,
+    pub scan_in: Node,
+    pub reset_value: BigUint,
+    /// The data register whose value is captured by this register, if any
+    pub captures: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectBit {
+    Register(String, usize),
+    Const(bool),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mux {
+    pub path: String,
+    /// The bits which make up the select value, most significant first
+    pub select: Vec<SelectBit>,
+    pub inputs: Vec<(Vec<BigUint>, Node)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataRegister {
+    pub path: String,
+    pub width: usize,
+    pub lsb: usize,
+    /// The scan register (and bit offset within it) which is the source of the write data
+    pub source: Option<(String, usize)>,
+}
+
+/// A DR scan of the active scan path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shift {
+    pub width: usize,
+    pub data: BigUint,
+    /// The expected data and the enables of the bits to be compared, if anything is to be read
+    pub expect: Option<(BigUint, BigUint)>,
+    /// The registers on the scan path, ordered from TDO to TDI
+    pub registers: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Network {
+    pub top: String,
+    pub registers: IndexMap<String, Register>,
+    pub muxes: IndexMap<String, Mux>,
+    pub data_registers: IndexMap<String, DataRegister>,
+    /// The module of each instance, keyed by its path
+    pub instances: IndexMap<String, String>,
+    pub scan_out: Node,
+    values: IndexMap<String, BigUint>,
+    writes: IndexMap<String, (BigUint, BigUint)>,
+    reads: IndexMap<String, (BigUint, BigUint)>,
+}
+
+/// A reference resolved to a network element, registers carry the bit offset and width being
+/// referenced
+#[derive(Debug, Clone, PartialEq)]
+enum Resolved {
+    ScanIn,
+    Register(String, usize, usize),
+    Mux(String),
+    DataRegister(String, usize, usize),
+    Const(BigUint, usize),
+    Unconnected,
+}
+
+impl Resolved {
+    fn width(&self) -> usize {
+        match self {
+            Resolved::Register(_, _, w)
+            | Resolved::DataRegister(_, _, w)
+            | Resolved::Const(_, w) => *w,
+            _ => 1,
+        }
+    }
+}
+
+struct Scope<'a> {
+    module: &'a Module,
+    parent: Option<String>,
+    instance: String,
+    /// The name of the scan in port which is connected to TDI
+    scan_in: Option<String>,
+}
+
+struct Builder<'a> {
+    icl: &'a ICL,
+    scopes: HashMap<String, Scope<'a>>,
+}
+
+fn join(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", scope, name)
+    }
+}
+
+fn ones(width: usize) -> BigUint {
+    (BigUint::one() << width) - BigUint::one()
+}
+
+fn bit(value: &BigUint, i: usize) -> bool {
+    value.bit(i as u64)
+}
+
+/// Returns the current value with the masked bits replaced by those from the given value
+fn merge(current: &BigUint, value: &BigUint, mask: &BigUint) -> BigUint {
+    (current ^ (current & mask)) | (value & mask)
+}
+
+impl<'a> Builder<'a> {
+    fn add_scope(
+        &mut self,
+        path: &str,
+        module: &'a Module,
+        parent: Option<String>,
+        instance: &str,
+        instances: &mut IndexMap<String, String>,
+    ) -> Result<()> {
+        self.scopes.insert(
+            path.to_string(),
+            Scope {
+                module,
+                parent,
+                instance: instance.to_string(),
+                scan_in: None,
+            },
+        );
+        for inst in module.instances.values() {
+            let p = join(path, &inst.name);
+            instances.insert(p.clone(), inst.module.to_string());
+            let m = self.icl.module(&inst.module)?;
+            self.add_scope(&p, m, Some(path.to_string()), &inst.name, instances)?;
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, scope: &str, signal: &Signal) -> Result<Resolved> {
+        match signal {
+            Signal::Const(v, w) => Ok(Resolved::Const(
+                v.clone(),
+                w.unwrap_or(std::cmp::max(1, v.bits() as usize)),
+            )),
+            Signal::Inverted(name, _) => bail!(
+                "Inverted signals are not supported by the scan network model, given '~{}' in '{}'",
+                name,
+                scope
+            ),
+            Signal::Ref(name, range) => {
+                let (r, lsb) = self.resolve_name(scope, name)?;
+                match range {
+                    None => Ok(r),
+                    Some((m, l)) => {
+                        let offset = std::cmp::min(*m, *l).saturating_sub(lsb);
+                        let width = m.abs_diff(*l) + 1;
+                        if offset + width > r.width() && r.width() > 1 {
+                            bail!(
+                                "Bit range [{}:{}] is out of range for '{}' in '{}'",
+                                m,
+                                l,
+                                name,
+                                scope
+                            );
+                        }
+                        Ok(match r {
+                            Resolved::Register(p, o, _) => Resolved::Register(p, o + offset, width),
+                            Resolved::DataRegister(p, o, _) => {
+                                Resolved::DataRegister(p, o + offset, width)
+                            }
+                            Resolved::Const(v, _) => {
+                                Resolved::Const((v >> offset) & ones(width), width)
+                            }
+                            r => r,
+                        })
+                    }
+                }
+            }
+        }
+    }
+
+    /// Resolves the given name within the given scope, returning what it refers to and the
+    /// index of its least significant bit as declared
+    fn resolve_name(&self, scope: &str, name: &str) -> Result<(Resolved, usize)> {
+        let s = &self.scopes[scope];
+        let module = s.module;
+        if let Some((inst, port)) = name.split_once('.') {
+            if module.instances.contains_key(inst) {
+                let child = join(scope, inst);
+                let m = self.scopes[&child].module;
+                return match m.ports.get(port) {
+                    Some(p) => match &p.source {
+                        Some(src) => {
+                            let r = self.resolve(&child, src)?;
+                            Ok((r, p.range.map(|(m, l)| std::cmp::min(m, l)).unwrap_or(0)))
+                        }
+                        None => bail!("Port '{}' of instance '{}' has no Source", port, child),
+                    },
+                    None => bail!("Module '{}' has no port named '{}'", m.name, port),
+                };
+            }
+        }
+        let path = join(scope, name);
+        if let Some(r) = module.scan_registers.get(name) {
+            return Ok((
+                Resolved::Register(path, 0, r.width()),
+                std::cmp::min(r.msb, r.lsb),
+            ));
+        }
+        if module.scan_muxes.contains_key(name) {
+            return Ok((Resolved::Mux(path), 0));
+        }
+        if let Some(r) = module.data_registers.get(name) {
+            return Ok((
+                Resolved::DataRegister(path, 0, r.width()),
+                std::cmp::min(r.msb, r.lsb),
+            ));
+        }
+        if let Some(p) = module.ports.get(name) {
+            let lsb = p.range.map(|(m, l)| std::cmp::min(m, l)).unwrap_or(0);
+            match p.kind {
+                PortKind::ScanOut | PortKind::DataOut | PortKind::ToSelect => {
+                    return match &p.source {
+                        Some(src) => Ok((self.resolve(scope, src)?, lsb)),
+                        None => Ok((Resolved::Unconnected, lsb)),
+                    }
+                }
+                _ => {
+                    if p.kind == PortKind::ScanIn && s.scan_in.as_deref() == Some(name) {
+                        return Ok((Resolved::ScanIn, lsb));
+                    }
+                    return match &s.parent {
+                        Some(parent) => {
+                            let inst = &self.scopes[parent].module.instances[&s.instance];
+                            match inst.inputs.get(name) {
+                                Some(src) => Ok((self.resolve(parent, src)?, lsb)),
+                                None => Ok((Resolved::Unconnected, lsb)),
+                            }
+                        }
+                        None => {
+                            if p.kind == PortKind::ScanIn {
+                                Ok((Resolved::ScanIn, lsb))
+                            } else {
+                                Ok((Resolved::Unconnected, lsb))
+                            }
+                        }
+                    };
+                }
+            }
+        }
+        bail!(
+            "Unknown signal '{}' referenced in module '{}'",
+            name,
+            module.name
+        )
+    }
+
+    fn node(&self, scope: &str, signal: &Option<Signal>, element: &str) -> Result<Node> {
+        match signal {
+            None => Ok(Node::Unconnected),
+            Some(sig) => match self.resolve(scope, sig)? {
+                Resolved::ScanIn => Ok(Node::ScanIn),
+                Resolved::Register(p, _, _) => Ok(Node::Register(p)),
+                Resolved::Mux(p) => Ok(Node::Mux(p)),
+                Resolved::Unconnected => Ok(Node::Unconnected),
+                r => bail!(
+                    "The scan input of '{}' must be driven by a scan register, mux or scan port, found: {:?}",
+                    join(scope, element),
+                    r
+                ),
+            },
+        }
+    }
+}
+
+impl Network {
+    /// Builds the scan network of the given top-level module, or the module containing an
+    /// AccessLink if not given.
+    /// If the module has an AccessLink, the network accessed by the given instruction (or the
+    /// first one) will be modeled, otherwise the module's own scan ports are the network's
+    /// scan in and out.
+    pub fn new(icl: &ICL, top: Option<&str>, instruction: Option<&str>) -> Result<Self> {
+        let top = match top {
+            Some(t) => icl.module(t)?,
+            None => Self::find_top(icl)?,
+        };
+        let mut b = Builder {
+            icl,
+            scopes: HashMap::new(),
+        };
+        let mut instances = IndexMap::new();
+        b.add_scope("", top, None, "", &mut instances)?;
+
+        // Work out the scope and ports of the scan interface
+        let (client, si, so) = match top.access_links.values().next() {
+            Some(link) => {
+                let interfaces = match instruction {
+                    Some(i) => match link.instructions.get(i) {
+                        Some(x) => x,
+                        None => bail!(
+                            "AccessLink '{}' has no instruction named '{}'",
+                            link.name,
+                            i
+                        ),
+                    },
+                    None => match link.instructions.values().next() {
+                        Some(x) => x,
+                        None => bail!("AccessLink '{}' has no instructions", link.name),
+                    },
+                };
+                if interfaces.len() != 1 {
+                    bail!(
+                        "Only AccessLink instructions with a single ScanInterface are supported, found {}",
+                        interfaces.len()
+                    );
+                }
+                let (inst, iface) = match interfaces[0].split_once('.') {
+                    Some((i, f)) => (i.to_string(), Some(f)),
+                    None => (interfaces[0].to_string(), None),
+                };
+                let scope = match b.scopes.get(&inst) {
+                    Some(s) => s,
+                    None => bail!(
+                        "The AccessLink refers to unknown instance '{}' in '{}'",
+                        inst,
+                        top.name
+                    ),
+                };
+                let (si, so) = scope.module.scan_ports(iface)?;
+                (inst, si, so)
+            }
+            None => {
+                if instruction.is_some() {
+                    bail!(
+                        "An instruction was given, but module '{}' has no AccessLink",
+                        top.name
+                    );
+                }
+                let (si, so) = top.scan_ports(None)?;
+                ("".to_string(), si, so)
+            }
+        };
+        b.scopes.get_mut(&client).unwrap().scan_in = Some(si);
+
+        let mut registers = IndexMap::new();
+        let mut muxes = IndexMap::new();
+        let mut data_registers = IndexMap::new();
+        let mut scope_paths: Vec<&String> = b.scopes.keys().collect();
+        scope_paths.sort();
+        for scope in scope_paths {
+            let module = b.scopes[scope].module;
+            for r in module.data_registers.values() {
+                let source = match &r.write_data_source {
+                    Some(sig) => match b.resolve(scope, sig)? {
+                        Resolved::Register(p, o, _) => Some((p, o)),
+                        _ => None,
+                    },
+                    None => None,
+                };
+                let path = join(scope, &r.name);
+                data_registers.insert(
+                    path.clone(),
+                    DataRegister {
+                        path,
+                        width: r.width(),
+                        lsb: std::cmp::min(r.msb, r.lsb),
+                        source,
+                    },
+                );
+            }
+            for r in module.scan_registers.values() {
+                let captures = match r.capture_source.as_slice() {
+                    [sig] => match b.resolve(scope, sig)? {
+                        Resolved::DataRegister(p, 0, w) if w == r.width() => Some(p),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let path = join(scope, &r.name);
+                registers.insert(
+                    path.clone(),
+                    Register {
+                        path,
+                        width: r.width(),
+                        lsb: std::cmp::min(r.msb, r.lsb),
+                        scan_in: b.node(scope, &r.scan_in_source, &r.name)?,
+                        reset_value: r.reset_value.clone().unwrap_or_default(),
+                        captures,
+                    },
+                );
+            }
+            for m in module.scan_muxes.values() {
+                let path = join(scope, &m.name);
+                let mut select = vec![];
+                for sig in &m.selected_by {
+                    let r = match b.resolve(scope, sig)? {
+                        Resolved::DataRegister(p, o, w) => {
+                            match &data_registers.get(&p).and_then(|d| d.source.clone()) {
+                                Some((src, offset)) => Resolved::Register(src.clone(), o + offset, w),
+                                None => bail!(
+                                    "The select of ScanMux '{}' is driven by data register '{}' which has no scan register source",
+                                    path,
+                                    p
+                                ),
+                            }
+                        }
+                        r => r,
+                    };
+                    match r {
+                        Resolved::Register(p, o, w) => {
+                            for i in (0..w).rev() {
+                                select.push(SelectBit::Register(p.clone(), o + i));
+                            }
+                        }
+                        Resolved::Const(v, w) => {
+                            for i in (0..w).rev() {
+                                select.push(SelectBit::Const(bit(&v, i)));
+                            }
+                        }
+                        r => bail!(
+                            "The select of ScanMux '{}' must be driven by scan registers or constants, found: {:?}",
+                            path,
+                            r
+                        ),
+                    }
+                }
+                let mut inputs = vec![];
+                for (values, sig) in &m.inputs {
+                    inputs.push((values.clone(), b.node(scope, &Some(sig.clone()), &m.name)?));
+                }
+                muxes.insert(
+                    path.clone(),
+                    Mux {
+                        path,
+                        select,
+                        inputs,
+                    },
+                );
+            }
+        }
+        let scan_out = b.node(&client, &Some(Signal::Ref(so.clone(), None)), &so)?;
+        let mut n = Network {
+            top: top.name.to_string(),
+            registers,
+            muxes,
+            data_registers,
+            instances,
+            scan_out,
+            values: IndexMap::new(),
+            writes: IndexMap::new(),
+            reads: IndexMap::new(),
+        };
+        n.reset();
+        Ok(n)
+    }
+
+    fn find_top(icl: &ICL) -> Result<&Module> {
+        let linked: Vec<&Module> = icl
+            .modules
+            .values()
+            .filter(|m| !m.access_links.is_empty())
+            .collect();
+        if linked.len() == 1 {
+            return Ok(linked[0]);
+        }
+        let instantiated: HashSet<&str> = icl
+            .modules
+            .values()
+            .flat_map(|m| m.instances.values().map(|i| i.module.as_str()))
+            .collect();
+        let tops: Vec<&Module> = icl
+            .modules
+            .values()
+            .filter(|m| !instantiated.contains(m.name.as_str()))
+            .collect();
+        if tops.len() == 1 {
+            Ok(tops[0])
+        } else {
+            bail!(
+                "Unable to work out the top-level ICL module, found candidates: {}",
+                tops.iter()
+                    .map(|m| m.name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(", ")
+            )
+        }
+    }
+
+    /// Returns all registers to their reset values and discards any pending accesses, this
+    /// should be called whenever the network is reset (e.g. by a TAP reset)
+    pub fn reset(&mut self) {
+        self.values = self
+            .registers
+            .values()
+            .map(|r| (r.path.to_string(), r.reset_value.clone()))
+            .collect();
+        self.writes.clear();
+        self.reads.clear();
+    }
+
+    /// Returns the current value of the given scan register
+    pub fn value(&self, register: &str) -> Result<&BigUint> {
+        match self.values.get(register) {
+            Some(v) => Ok(v),
+            None => bail!(
+                "No scan register named '{}' exists in the network",
+                register
+            ),
+        }
+    }
+
+    fn select_value(&self, mux: &Mux) -> BigUint {
+        let mut v = BigUint::zero();
+        for b in &mux.select {
+            v <<= 1;
+            let set = match b {
+                SelectBit::Const(c) => *c,
+                SelectBit::Register(r, i) => bit(&self.values[r], *i),
+            };
+            if set {
+                v |= BigUint::one();
+            }
+        }
+        v
+    }
+
+    fn selected_input(&self, mux: &Mux) -> Option<usize> {
+        let v = self.select_value(mux);
+        mux.inputs.iter().position(|(vals, _)| vals.contains(&v))
+    }
+
+    /// Returns the registers currently on the scan path, ordered from TDO to TDI
+    pub fn active_path(&self) -> Result<Vec<String>> {
+        let mut path = vec![];
+        let mut node = &self.scan_out;
+        for _ in 0..=(self.registers.len() + self.muxes.len()) {
+            match node {
+                Node::ScanIn => return Ok(path),
+                Node::Register(r) => {
+                    path.push(r.to_string());
+                    node = &self.registers[r].scan_in;
+                }
+                Node::Mux(m) => {
+                    let mux = &self.muxes[m];
+                    match self.selected_input(mux) {
+                        Some(i) => node = &mux.inputs[i].1,
+                        None => bail!(
+                            "ScanMux '{}' has no input for its current select value {}",
+                            m,
+                            self.select_value(mux)
+                        ),
+                    }
+                }
+                Node::Unconnected => bail!(
+                    "The active scan path is broken, it reaches an unconnected scan input after: {}",
+                    path.join(", ")
+                ),
+            }
+        }
+        bail!("The active scan path contains a loop")
+    }
+
+    /// Returns the 1-bit scan registers which act as the control bit of a Segment Insertion Bit,
+    /// i.e. which are the only select of a 2-input ScanMux
+    pub fn sibs(&self) -> Vec<String> {
+        self.muxes
+            .values()
+            .filter_map(|m| match m.select.as_slice() {
+                [SelectBit::Register(r, 0)]
+                    if m.inputs.len() == 2 && self.registers[r].width == 1 =>
+                {
+                    Some(r.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Resolves an access target, which may be a scan register or data register optionally with
+    /// a bit range, e.g. "core.tdr[3:0]", to the scan register bits which are to be accessed
+    fn target(&self, path: &str, read: bool) -> Result<(String, usize, usize)> {
+        let (name, range) = match path.find('[') {
+            Some(i) if path.ends_with(']') => {
+                let r = &path[i + 1..path.len() - 1];
+                let (m, l) = match r.split_once(':') {
+                    Some((m, l)) => (m.trim(), l.trim()),
+                    None => (r.trim(), r.trim()),
+                };
+                match (m.parse::<usize>(), l.parse::<usize>()) {
+                    (Ok(m), Ok(l)) => (&path[..i], Some((m, l))),
+                    _ => bail!("Invalid bit range in '{}'", path),
+                }
+            }
+            _ => (path, None),
+        };
+        let (reg, offset, width, lsb) = if let Some(r) = self.registers.get(name) {
+            (r.path.to_string(), 0, r.width, r.lsb)
+        } else if let Some(d) = self.data_registers.get(name) {
+            if read {
+                match self
+                    .registers
+                    .values()
+                    .find(|r| r.captures.as_deref() == Some(name))
+                {
+                    Some(r) => (r.path.to_string(), 0, d.width, d.lsb),
+                    None => bail!(
+                        "Data register '{}' can't be read, it is not captured by a scan register",
+                        name
+                    ),
+                }
+            } else {
+                match &d.source {
+                    Some((r, o)) => (r.to_string(), *o, d.width, d.lsb),
+                    None => bail!(
+                        "Data register '{}' can't be written, its write data does not come from a scan register",
+                        name
+                    ),
+                }
+            }
+        } else {
+            bail!("No register named '{}' exists in the scan network", name);
+        };
+        match range {
+            None => Ok((reg, offset, width)),
+            Some((m, l)) => {
+                let lo = std::cmp::min(m, l);
+                let w = m.abs_diff(l) + 1;
+                if lo < lsb || lo - lsb + w > width {
+                    bail!("Bit range is out of range in '{}'", path);
+                }
+                Ok((reg, offset + lo - lsb, w))
+            }
+        }
+    }
+
+    fn queue(
+        queue: &mut IndexMap<String, (BigUint, BigUint)>,
+        target: (String, usize, usize),
+        value: &BigUint,
+        path: &str,
+    ) -> Result<()> {
+        let (reg, offset, width) = target;
+        if value.bits() as usize > width {
+            bail!(
+                "The value {} does not fit in the {} bits of '{}'",
+                value,
+                width,
+                path
+            );
+        }
+        let mask = ones(width) << offset;
+        let e = queue
+            .entry(reg)
+            .or_insert((BigUint::zero(), BigUint::zero()));
+        e.0 = merge(&e.0, &(value << offset), &mask);
+        e.1 |= mask;
+        Ok(())
+    }
+
+    /// Queues a write of the given value to the given register, applied by the next iapply
+    pub fn iwrite(&mut self, path: &str, value: &BigUint) -> Result<()> {
+        let target = self.target(path, false)?;
+        Self::queue(&mut self.writes, target, value, path)
+    }
+
+    /// Queues a read of the given register, expecting the given value, which will be verified by
+    /// the next iapply
+    pub fn iread(&mut self, path: &str, value: &BigUint) -> Result<()> {
+        let target = self.target(path, true)?;
+        Self::queue(&mut self.reads, target, value, path)
+    }
+
+    /// Returns true if there are any writes or reads waiting to be applied
+    pub fn is_pending(&self) -> bool {
+        !self.writes.is_empty() || !self.reads.is_empty()
+    }
+
+    /// Returns the DR scans required to apply all pending writes and reads, updating the modeled
+    /// register values accordingly.
+    /// Any SIBs or ScanMuxes which need to be configured to bring the target registers onto the
+    /// scan path are set by preceding scans, others are left in their current state.
+    pub fn iapply(&mut self) -> Result<Vec<Shift>> {
+        let mut targets: HashSet<String> = self.writes.keys().cloned().collect();
+        targets.extend(self.reads.keys().cloned());
+        let mut shifts = vec![];
+        if targets.is_empty() {
+            return Ok(shifts);
+        }
+        for _ in 0..=(self.muxes.len() + 1) {
+            let path = self.active_path()?;
+            let missing: Vec<&String> = targets.iter().filter(|t| !path.contains(t)).collect();
+            if missing.is_empty() {
+                let mut values = self.values.clone();
+                for (reg, (v, mask)) in &self.writes {
+                    let cur = &values[reg];
+                    let new = merge(cur, v, mask);
+                    values.insert(reg.to_string(), new);
+                }
+                let mut shift = self.shift(&path, &values);
+                if !self.reads.is_empty() {
+                    let mut expect = BigUint::zero();
+                    let mut enables = BigUint::zero();
+                    let mut offset = 0;
+                    for reg in &path {
+                        if let Some((v, mask)) = self.reads.get(reg) {
+                            expect |= v << offset;
+                            enables |= mask << offset;
+                        }
+                        offset += self.registers[reg].width;
+                    }
+                    shift.expect = Some((expect, enables));
+                }
+                shifts.push(shift);
+                self.values = values;
+                self.writes.clear();
+                self.reads.clear();
+                return Ok(shifts);
+            }
+
+            // Configure the muxes which are on the current path to move closer to the targets
+            let required = self.plan(&targets)?;
+            let mut values = self.values.clone();
+            let mut changed = false;
+            for (reg, (v, mask)) in &required {
+                if path.contains(reg) {
+                    let cur = &values[reg];
+                    let new = merge(cur, v, mask);
+                    if &new != cur {
+                        changed = true;
+                        values.insert(reg.to_string(), new);
+                    }
+                }
+            }
+            if !changed {
+                let mut missing: Vec<&str> = missing.iter().map(|s| s.as_str()).collect();
+                missing.sort();
+                bail!(
+                    "Unable to retarget the scan network to access: {}",
+                    missing.join(", ")
+                );
+            }
+            shifts.push(self.shift(&path, &values));
+            self.values = values;
+        }
+        bail!("Unable to retarget the scan network, the required configuration did not converge")
+    }
+
+    /// Writes the control bit of all SIBs on the active scan path back to its reset value (i.e.
+    /// closes them), returning the required DR scan if any of them were open.
+    /// Any pending accesses will be applied at the same time.
+    pub fn close_sibs(&mut self) -> Result<Vec<Shift>> {
+        let path = self.active_path()?;
+        for sib in self.sibs() {
+            if path.contains(&sib) {
+                let reset = self.registers[&sib].reset_value.clone();
+                if self.values[&sib] != reset {
+                    self.iwrite(&sib, &reset)?;
+                }
+            }
+        }
+        self.iapply()
+    }
+
+    fn shift(&self, path: &[String], values: &IndexMap<String, BigUint>) -> Shift {
+        let mut data = BigUint::zero();
+        let mut offset = 0;
+        for reg in path {
+            let w = self.registers[reg].width;
+            data |= (&values[reg] & ones(w)) << offset;
+            offset += w;
+        }
+        Shift {
+            width: offset,
+            data,
+            expect: None,
+            registers: path.to_vec(),
+        }
+    }
+
+    /// Returns all scan registers which could be on the scan path upstream of the given node,
+    /// for any mux configuration
+    fn upstream(
+        &self,
+        node: &Node,
+        cache: &mut HashMap<Node, HashSet<String>>,
+        visiting: &mut HashSet<Node>,
+    ) -> HashSet<String> {
+        if let Some(u) = cache.get(node) {
+            return u.clone();
+        }
+        if !visiting.insert(node.clone()) {
+            return HashSet::new();
+        }
+        let mut u = HashSet::new();
+        match node {
+            Node::Register(r) => {
+                u.insert(r.to_string());
+                u.extend(self.upstream(&self.registers[r].scan_in, cache, visiting));
+            }
+            Node::Mux(m) => {
+                for (_, input) in &self.muxes[m].inputs {
+                    u.extend(self.upstream(input, cache, visiting));
+                }
+            }
+            _ => {}
+        }
+        visiting.remove(node);
+        cache.insert(node.clone(), u.clone());
+        u
+    }
+
+    /// Works out a scan path containing the targets, returning the required values of the
+    /// mux select bits along it, as (value, mask) per register.
+    /// Where there is a choice, the input leading to the most remaining targets is chosen,
+    /// favoring the currently selected one.
+    fn plan(&self, targets: &HashSet<String>) -> Result<IndexMap<String, (BigUint, BigUint)>> {
+        let mut remaining = targets.clone();
+        let mut required: IndexMap<String, (BigUint, BigUint)> = IndexMap::new();
+        let mut cache = HashMap::new();
+        let mut node = self.scan_out.clone();
+        for _ in 0..=(self.registers.len() + self.muxes.len()) {
+            match &node {
+                Node::Register(r) => {
+                    remaining.remove(r);
+                    node = self.registers[r].scan_in.clone();
+                }
+                Node::Mux(m) => {
+                    let mux = &self.muxes[m];
+                    let current = self.selected_input(mux);
+                    let mut best: Option<(usize, usize)> = None;
+                    for (i, (vals, input)) in mux.inputs.iter().enumerate() {
+                        if vals.is_empty() || !self.selectable(mux, &vals[0]) {
+                            continue;
+                        }
+                        let up = self.upstream(input, &mut cache, &mut HashSet::new());
+                        let score = up.intersection(&remaining).count();
+                        best = match best {
+                            None => Some((i, score)),
+                            Some((bi, bs)) => {
+                                if score > bs || (score == bs && Some(i) == current) {
+                                    Some((i, score))
+                                } else {
+                                    Some((bi, bs))
+                                }
+                            }
+                        };
+                    }
+                    let i = match best {
+                        Some((i, _)) => i,
+                        None => bail!("ScanMux '{}' has no selectable inputs", m),
+                    };
+                    let value = &mux.inputs[i].0[0];
+                    let n = mux.select.len();
+                    for (j, b) in mux.select.iter().enumerate() {
+                        if let SelectBit::Register(r, k) = b {
+                            let e = required
+                                .entry(r.to_string())
+                                .or_insert((BigUint::zero(), BigUint::zero()));
+                            e.0.set_bit(*k as u64, bit(value, n - 1 - j));
+                            e.1.set_bit(*k as u64, true);
+                        }
+                    }
+                    node = mux.inputs[i].1.clone();
+                }
+                _ => return Ok(required),
+            }
+        }
+        bail!("The scan network contains a loop")
+    }
+
+    /// Returns true if the given select value is possible given any constant select bits
+    fn selectable(&self, mux: &Mux, value: &BigUint) -> bool {
+        let n = mux.select.len();
+        mux.select.iter().enumerate().all(|(j, b)| match b {
+            SelectBit::Const(c) => *c == bit(value, n - 1 - j),
+            _ => true,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ICL_SRC: &str = r#"
+        Module SIB {
+            ScanInPort SI; ScanOutPort SO { Source SR; }
+            ScanInPort fromSO; ScanOutPort toSI { Source SI; }
+            SelectPort SEL; ToSelectPort toSEL { Source SR; }
+            ScanRegister SR { ScanInSource SIBmux; CaptureSource SR; ResetValue 1'b0; }
+            ScanMux SIBmux SelectedBy SR { 1'b0 : SI; 1'b1 : fromSO; }
+        }
+        Module Instrument {
+            ScanInPort si; ScanOutPort so { Source tdr[0]; }
+            SelectPort en;
+            ScanRegister tdr[7:0] { ScanInSource si; CaptureSource status; ResetValue 8'h00; }
+            DataRegister status[7:0];
+            DataRegister ctrl[3:0] { WriteDataSource tdr[7:4]; }
+        }
+        Module Client {
+            ScanInPort si; ScanOutPort so { Source sib2.SO; }
+            ScanInterface scan { Port si; Port so; }
+            Instance sib1 Of SIB { InputPort SI = si; InputPort fromSO = sib1_1.SO; }
+            Instance sib1_1 Of SIB { InputPort SI = sib1.toSI; InputPort fromSO = inst1.so; }
+            Instance inst1 Of Instrument { InputPort si = sib1_1.toSI; }
+            Instance sib2 Of SIB { InputPort SI = sib1.SO; InputPort fromSO = inst2.so; }
+            Instance inst2 Of Instrument { InputPort si = sib2.toSI; }
+        }
+        Module Chip {
+            Instance ijtag Of Client;
+            AccessLink Tap Of STD_1149_1_2001 {
+                BSDLEntity Chip;
+                ijtag_en { ScanInterface { ijtag.scan; } }
+            }
+        }
+    "#;
+
+    fn network() -> Network {
+        Network::new(&ICL::from_str(ICL_SRC).unwrap(), None, None).unwrap()
+    }
+
+    #[test]
+    fn builds_the_network() {
+        let n = network();
+        assert_eq!(n.top, "Chip");
+        assert_eq!(n.registers.len(), 5);
+        assert_eq!(n.instances["ijtag.inst1"], "Instrument");
+        assert_eq!(
+            n.active_path().unwrap(),
+            vec!["ijtag.sib2.SR", "ijtag.sib1.SR"]
+        );
+        let mut sibs = n.sibs();
+        sibs.sort();
+        assert_eq!(
+            sibs,
+            vec!["ijtag.sib1.SR", "ijtag.sib1_1.SR", "ijtag.sib2.SR"]
+        );
+        assert_eq!(
+            n.data_registers["ijtag.inst1.ctrl"].source,
+            Some(("ijtag.inst1.tdr".to_string(), 4))
+        );
+    }
+
+    #[test]
+    fn retargets_accesses() {
+        let mut n = network();
+        n.iwrite("ijtag.inst1.tdr", &BigUint::from(0xA5u32))
+            .unwrap();
+        let shifts = n.iapply().unwrap();
+        // Open sib1, then sib1_1, then write the TDR
+        assert_eq!(shifts.len(), 3);
+        assert_eq!(shifts[0].width, 2);
+        assert_eq!(shifts[0].data, BigUint::from(0b10u32));
+        assert_eq!(
+            shifts[1].registers,
+            vec!["ijtag.sib2.SR", "ijtag.sib1.SR", "ijtag.sib1_1.SR"]
+        );
+        assert_eq!(shifts[1].data, BigUint::from(0b110u32));
+        assert_eq!(shifts[2].width, 11);
+        assert_eq!(shifts[2].data, BigUint::from((0xA5u32 << 3) | 0b110));
+        assert_eq!(n.value("ijtag.inst1.tdr").unwrap(), &BigUint::from(0xA5u32));
+        assert!(!n.is_pending());
+
+        // The path is already open, and SIB2 is left closed
+        n.iwrite("ijtag.inst1.ctrl", &BigUint::from(0x3u32))
+            .unwrap();
+        n.iread("ijtag.inst1.status[3:0]", &BigUint::from(0x5u32))
+            .unwrap();
+        let shifts = n.iapply().unwrap();
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(shifts[0].data, BigUint::from((0x35u32 << 3) | 0b110));
+        assert_eq!(
+            shifts[0].expect,
+            Some((BigUint::from(0x5u32 << 3), BigUint::from(0xFu32 << 3)))
+        );
+
+        // Now something on the other branch
+        n.iwrite("ijtag.inst2.tdr", &BigUint::from(1u32)).unwrap();
+        let shifts = n.iapply().unwrap();
+        assert_eq!(shifts.len(), 2);
+        assert_eq!(
+            shifts[1].registers,
+            vec![
+                "ijtag.sib2.SR",
+                "ijtag.inst2.tdr",
+                "ijtag.sib1.SR",
+                "ijtag.sib1_1.SR",
+                "ijtag.inst1.tdr",
+            ]
+        );
+
+        let shifts = n.close_sibs().unwrap();
+        assert_eq!(shifts.len(), 1);
+        assert_eq!(
+            n.active_path().unwrap(),
+            vec!["ijtag.sib2.SR", "ijtag.sib1.SR"]
+        );
+        assert!(n
+            .iwrite("ijtag.inst1.tdr", &BigUint::from(0x100u32))
+            .is_err());
+        assert!(n.iwrite("ijtag.blah", &BigUint::from(1u32)).is_err());
+    }
+}
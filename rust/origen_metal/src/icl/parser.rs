@@ -0,0 +1,414 @@
+use super::*;
+use crate::{Error, Result};
+use pest::iterators::Pair;
+use pest::Parser;
+use std::fs;
+
+#[derive(Parser)]
+#[grammar = "icl/icl.pest"]
+pub struct ICLParser;
+
+pub(crate) fn parse_file(path: &Path) -> Result<ICL> {
+    if path.exists() {
+        let contents = fs::read_to_string(path)?;
+        match parse_str(&contents, Some(&path.display().to_string())) {
+            Ok(icl) => Ok(icl),
+            Err(e) => Err(Error::new(&format!(
+                "Error parsing file {}:\n{}",
+                path.display(),
+                e.msg
+            ))),
+        }
+    } else {
+        Err(Error::new(&format!(
+            "File does not exist: {}",
+            path.display()
+        )))
+    }
+}
+
+pub(crate) fn parse_str(icl: &str, source_file: Option<&str>) -> Result<ICL> {
+    let mut pairs = match ICLParser::parse(Rule::icl_source, icl) {
+        Err(e) => match source_file {
+            Some(f) => return Err(Error::new(&format!("{}", e.with_path(f)))),
+            None => return Err(Error::new(&format!("{}", e))),
+        },
+        Ok(pairs) => pairs,
+    };
+    let mut icl = ICL::default();
+    for pair in pairs.next().unwrap().into_inner() {
+        if pair.as_rule() == Rule::module_def {
+            let m = build_module(pair)?;
+            if icl.modules.contains_key(&m.name) {
+                bail!("ICL module '{}' is defined more than once", m.name);
+            }
+            icl.modules.insert(m.name.to_string(), m);
+        }
+    }
+    Ok(icl)
+}
+
+fn build_module(pair: Pair<Rule>) -> Result<Module> {
+    let mut inner = pair.into_inner();
+    let mut m = Module {
+        name: inner.next().unwrap().as_str().to_string(),
+        ..Default::default()
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::port_def => {
+                let p = build_port(item)?;
+                m.ports.insert(p.name.to_string(), p);
+            }
+            Rule::scan_interface_def => {
+                let mut inner = item.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                let ports = inner
+                    .filter(|p| p.as_rule() == Rule::interface_port)
+                    .map(|p| p.into_inner().next().unwrap().as_str().to_string())
+                    .collect();
+                m.scan_interfaces.insert(name, ports);
+            }
+            Rule::scan_register_def => {
+                let r = build_scan_register(item)?;
+                m.scan_registers.insert(r.name.to_string(), r);
+            }
+            Rule::scan_mux_def => {
+                let mux = build_scan_mux(item)?;
+                m.scan_muxes.insert(mux.name.to_string(), mux);
+            }
+            Rule::data_register_def => {
+                let r = build_data_register(item)?;
+                m.data_registers.insert(r.name.to_string(), r);
+            }
+            Rule::instance_def => {
+                let i = build_instance(item)?;
+                m.instances.insert(i.name.to_string(), i);
+            }
+            Rule::access_link_def => {
+                let a = build_access_link(item);
+                m.access_links.insert(a.name.to_string(), a);
+            }
+            _ => {}
+        }
+    }
+    Ok(m)
+}
+
+fn build_port(pair: Pair<Rule>) -> Result<Port> {
+    let mut inner = pair.into_inner();
+    let kind = match inner.next().unwrap().as_str() {
+        "ScanInPort" => PortKind::ScanIn,
+        "ScanOutPort" => PortKind::ScanOut,
+        "SelectPort" => PortKind::Select,
+        "ToSelectPort" => PortKind::ToSelect,
+        "DataInPort" => PortKind::DataIn,
+        "DataOutPort" => PortKind::DataOut,
+        _ => PortKind::Control,
+    };
+    let mut port = Port {
+        name: inner.next().unwrap().as_str().to_string(),
+        kind,
+        range: None,
+        source: None,
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::range => port.range = Some(build_range(item)?),
+            Rule::source_stmt => {
+                port.source = Some(build_signal(item.into_inner().next().unwrap())?)
+            }
+            _ => {}
+        }
+    }
+    Ok(port)
+}
+
+fn build_scan_register(pair: Pair<Rule>) -> Result<ScanRegister> {
+    let mut inner = pair.into_inner();
+    let mut r = ScanRegister {
+        name: inner.next().unwrap().as_str().to_string(),
+        msb: 0,
+        lsb: 0,
+        scan_in_source: None,
+        capture_source: vec![],
+        reset_value: None,
+        default_load_value: None,
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::range => (r.msb, r.lsb) = build_range(item)?,
+            Rule::scan_in_source => {
+                r.scan_in_source = Some(build_signal(item.into_inner().next().unwrap())?)
+            }
+            Rule::capture_source => {
+                r.capture_source = build_signal_list(item.into_inner().next().unwrap())?
+            }
+            Rule::reset_value => r.reset_value = Some(build_number(item)?),
+            Rule::default_load_value => r.default_load_value = Some(build_number(item)?),
+            _ => {}
+        }
+    }
+    Ok(r)
+}
+
+fn build_scan_mux(pair: Pair<Rule>) -> Result<ScanMux> {
+    let mut inner = pair.into_inner();
+    let mut mux = ScanMux {
+        name: inner.next().unwrap().as_str().to_string(),
+        selected_by: vec![],
+        inputs: vec![],
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::signal_list => mux.selected_by = build_signal_list(item)?,
+            Rule::mux_input => {
+                let mut values = vec![];
+                let mut source = None;
+                for p in item.into_inner() {
+                    match p.as_rule() {
+                        Rule::number => values.push(parse_number(p.as_str())?.0),
+                        Rule::signal => source = Some(build_signal(p)?),
+                        _ => {}
+                    }
+                }
+                mux.inputs.push((values, source.unwrap()));
+            }
+            _ => {}
+        }
+    }
+    Ok(mux)
+}
+
+fn build_data_register(pair: Pair<Rule>) -> Result<DataRegister> {
+    let mut inner = pair.into_inner();
+    let mut r = DataRegister {
+        name: inner.next().unwrap().as_str().to_string(),
+        msb: 0,
+        lsb: 0,
+        write_data_source: None,
+        reset_value: None,
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::range => (r.msb, r.lsb) = build_range(item)?,
+            Rule::write_data_source => {
+                r.write_data_source = Some(build_signal(item.into_inner().next().unwrap())?)
+            }
+            Rule::reset_value => r.reset_value = Some(build_number(item)?),
+            _ => {}
+        }
+    }
+    Ok(r)
+}
+
+fn build_instance(pair: Pair<Rule>) -> Result<Instance> {
+    let mut inner = pair.into_inner();
+    let name = inner.next().unwrap().as_str().to_string();
+    let module = inner.next().unwrap().as_str();
+    // Namespaces are not modeled, so drop any qualifier
+    let module = module.rsplit("::").next().unwrap().to_string();
+    let mut i = Instance {
+        name,
+        module,
+        inputs: IndexMap::new(),
+        parameters: IndexMap::new(),
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::input_port_stmt => {
+                let mut inner = item.into_inner();
+                let port = inner.next().unwrap().as_str().to_string();
+                let mut next = inner.next().unwrap();
+                if next.as_rule() == Rule::range {
+                    next = inner.next().unwrap();
+                }
+                i.inputs.insert(port, build_signal(next)?);
+            }
+            Rule::parameter_stmt => {
+                let mut inner = item.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                let value = inner.next().unwrap().as_str().trim().to_string();
+                i.parameters.insert(name, value);
+            }
+            _ => {}
+        }
+    }
+    Ok(i)
+}
+
+fn build_access_link(pair: Pair<Rule>) -> AccessLink {
+    let mut inner = pair.into_inner();
+    let mut a = AccessLink {
+        name: inner.next().unwrap().as_str().to_string(),
+        of: inner.next().unwrap().as_str().to_string(),
+        bsdl_entity: None,
+        instructions: IndexMap::new(),
+    };
+    for item in inner {
+        match item.as_rule() {
+            Rule::bsdl_entity => {
+                a.bsdl_entity = Some(item.into_inner().next().unwrap().as_str().to_string())
+            }
+            Rule::access_instruction => {
+                let mut inner = item.into_inner();
+                let name = inner.next().unwrap().as_str().to_string();
+                let mut interfaces = vec![];
+                for p in inner {
+                    if p.as_rule() == Rule::access_scan_interface {
+                        for n in p.into_inner() {
+                            interfaces.push(n.as_str().to_string());
+                        }
+                    }
+                }
+                a.instructions.insert(name, interfaces);
+            }
+            _ => {}
+        }
+    }
+    a
+}
+
+fn build_number(pair: Pair<Rule>) -> Result<BigUint> {
+    Ok(parse_number(pair.into_inner().next().unwrap().as_str())?.0)
+}
+
+fn build_range(pair: Pair<Rule>) -> Result<(usize, usize)> {
+    let vals: Vec<usize> = pair
+        .into_inner()
+        .map(|p| p.as_str().parse::<usize>().unwrap())
+        .collect();
+    if vals.len() == 1 {
+        Ok((vals[0], vals[0]))
+    } else {
+        Ok((vals[0], vals[1]))
+    }
+}
+
+fn build_signal_list(pair: Pair<Rule>) -> Result<Vec<Signal>> {
+    pair.into_inner().map(|p| build_signal(p)).collect()
+}
+
+fn build_signal(pair: Pair<Rule>) -> Result<Signal> {
+    let mut inner = pair.into_inner();
+    let first = inner.next().unwrap();
+    match first.as_rule() {
+        Rule::number => {
+            let (v, w) = parse_number(first.as_str())?;
+            Ok(Signal::Const(v, w))
+        }
+        Rule::inverted_signal => {
+            let mut inner = first.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let range = match inner.next() {
+                Some(r) => Some(build_range(r)?),
+                None => None,
+            };
+            Ok(Signal::Inverted(name, range))
+        }
+        _ => {
+            let range = match inner.next() {
+                Some(r) => Some(build_range(r)?),
+                None => None,
+            };
+            Ok(Signal::Ref(first.as_str().to_string(), range))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modules() {
+        let icl = parse_str(
+            r#"
+            // A segment insertion bit
+            Module SIB {
+                ScanInPort SI;
+                ScanOutPort SO { Source SR; }
+                ScanInPort fromSO;
+                ScanOutPort toSI { Source SI; }
+                SelectPort SEL;
+                ToSelectPort toSEL { Source SR; }
+                ScanRegister SR { ScanInSource SIBmux; CaptureSource SR; ResetValue 1'b0; }
+                ScanMux SIBmux SelectedBy SR {
+                    1'b0 : SI;
+                    1'b1 : fromSO;
+                }
+                Attribute lib_type = "sib";
+            }
+            /* An instrument */
+            Module Inst {
+                ScanInPort si; ScanOutPort so { Source tdr[0]; }
+                SelectPort en;
+                DataOutPort ctrl[7:0] { Source tdr; }
+                ScanRegister tdr[7:0] { ScanInSource si; ResetValue 8'h00; }
+                Parameter SIZE = 8;
+                Alias mode[1:0] = tdr[1:0] { RefEnum Modes; }
+                Enum Modes { off = 2'b00; on = 2'b11; }
+            }
+            Module Top {
+                ScanInPort TDI; ScanOutPort TDO { Source sib.SO; }
+                Instance sib Of SIB { InputPort SI = TDI; InputPort fromSO = inst.so; }
+                Instance inst Of ns::Inst { InputPort si = sib.toSI; InputPort en = sib.toSEL; Parameter SIZE = 8; }
+                AccessLink Tap Of STD_1149_1_2001 {
+                    BSDLEntity Chip;
+                    ijtag_en {
+                        ScanInterface { sib.scan; }
+                    }
+                }
+            }
+            "#,
+            None,
+        )
+        .unwrap();
+        assert_eq!(icl.modules.len(), 3);
+        let sib = icl.module("SIB").unwrap();
+        assert_eq!(sib.ports.len(), 6);
+        assert_eq!(
+            sib.ports["SO"].source,
+            Some(Signal::Ref("SR".to_string(), None))
+        );
+        assert_eq!(sib.scan_registers["SR"].width(), 1);
+        assert_eq!(sib.scan_muxes["SIBmux"].inputs.len(), 2);
+        assert_eq!(
+            sib.scan_muxes["SIBmux"].inputs[1],
+            (
+                vec![BigUint::from(1u32)],
+                Signal::Ref("fromSO".to_string(), None)
+            )
+        );
+        let inst = icl.module("Inst").unwrap();
+        assert_eq!(inst.scan_registers["tdr"].width(), 8);
+        assert_eq!(
+            inst.ports["so"].source,
+            Some(Signal::Ref("tdr".to_string(), Some((0, 0))))
+        );
+        let top = icl.module("Top").unwrap();
+        assert_eq!(top.instances["inst"].module, "Inst");
+        assert_eq!(
+            top.instances["inst"].inputs["si"],
+            Signal::Ref("sib.toSI".to_string(), None)
+        );
+        assert_eq!(
+            top.access_links["Tap"].instructions["ijtag_en"],
+            vec!["sib.scan".to_string()]
+        );
+        assert!(parse_str("Module A { ScanRegister R[3:0] { ScanInSource si } }", None).is_err());
+    }
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(
+            parse_number("8'hF_F").unwrap(),
+            (BigUint::from(255u32), Some(8))
+        );
+        assert_eq!(parse_number("'b101").unwrap(), (BigUint::from(5u32), None));
+        assert_eq!(parse_number("0x10").unwrap(), (BigUint::from(16u32), None));
+        assert_eq!(parse_number("12").unwrap(), (BigUint::from(12u32), None));
+        assert!(parse_number("2'b111").is_err());
+        assert!(parse_number("4'b1x00").is_err());
+    }
+}
@@ -0,0 +1,285 @@
+//! A reader for IEEE 1687 Procedural Description Language (PDL) level-0 files.
+//!
+//! PDL is based on Tcl, only its command structure is supported here, i.e. commands made up of
+//! words, braced and quoted words, comments and variable substitution of iProc arguments.
+//! Control flow and expressions are not supported.
+
+use crate::Result;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Proc {
+    pub name: String,
+    /// The module the proc was defined for via iProcsForModule, if any
+    pub module: Option<String>,
+    /// The argument names and their default values
+    pub args: Vec<(String, Option<String>)>,
+    pub body: Vec<Command>,
+}
+
+impl Proc {
+    /// Returns the body of the proc with the given argument values substituted
+    pub fn expand(&self, args: &[String]) -> Result<Vec<Command>> {
+        if args.len() > self.args.len() {
+            bail!(
+                "iProc '{}' takes {} arguments, but {} were given",
+                self.name,
+                self.args.len(),
+                args.len()
+            );
+        }
+        let mut vars = vec![];
+        for (i, (name, default)) in self.args.iter().enumerate() {
+            match args.get(i).or(default.as_ref()) {
+                Some(v) => vars.push((name.as_str(), v.as_str())),
+                None => bail!(
+                    "No value given for argument '{}' of iProc '{}'",
+                    name,
+                    self.name
+                ),
+            }
+        }
+        // Longest names first so that e.g. $addr doesn't match within $address
+        vars.sort_by(|a, b| b.0.len().cmp(&a.0.len()));
+        let sub = |word: &String| {
+            let mut w = word.to_string();
+            for (name, value) in &vars {
+                w = w
+                    .replace(&format!("${{{}}}", name), value)
+                    .replace(&format!("${}", name), value);
+            }
+            w
+        };
+        Ok(self
+            .body
+            .iter()
+            .map(|c| Command {
+                name: sub(&c.name),
+                args: c.args.iter().map(sub).collect(),
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PDL {
+    pub procs: Vec<Proc>,
+    /// Any commands given outside of an iProc
+    pub commands: Vec<Command>,
+}
+
+impl PDL {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            bail!("File does not exist: {}", path.display());
+        }
+        match Self::from_str(&std::fs::read_to_string(path)?) {
+            Ok(pdl) => Ok(pdl),
+            Err(e) => bail!("Error parsing file {}:\n{}", path.display(), e.msg),
+        }
+    }
+
+    pub fn from_str(pdl: &str) -> Result<Self> {
+        let mut p = PDL::default();
+        let mut module = None;
+        for c in parse_commands(pdl)? {
+            match c.name.as_str() {
+                "iProcsForModule" => {
+                    module = match c.args.first() {
+                        Some(m) => Some(m.rsplit("::").next().unwrap().to_string()),
+                        None => bail!("iProcsForModule requires a module name"),
+                    };
+                }
+                "iProc" => {
+                    if c.args.len() != 3 {
+                        bail!(
+                            "Expected iProc <name> {{<args>}} {{<body>}}, got: iProc {}",
+                            c.args.join(" ")
+                        );
+                    }
+                    let mut args = vec![];
+                    for a in split_words(&c.args[1])? {
+                        let w = split_words(&a)?;
+                        match w.as_slice() {
+                            [name] => args.push((name.to_string(), None)),
+                            [name, default] => {
+                                args.push((name.to_string(), Some(default.to_string())))
+                            }
+                            _ => bail!("Invalid argument '{}' in iProc '{}'", a, c.args[0]),
+                        }
+                    }
+                    p.procs.push(Proc {
+                        name: c.args[0].to_string(),
+                        module: module.clone(),
+                        args,
+                        body: parse_commands(&c.args[2])?,
+                    });
+                }
+                _ => p.commands.push(c),
+            }
+        }
+        Ok(p)
+    }
+
+    /// Adds the procs and commands from the given PDL
+    pub fn merge(&mut self, other: PDL) {
+        self.procs.extend(other.procs);
+        self.commands.extend(other.commands);
+    }
+
+    /// Returns the proc with the given name, defined for the given module or globally.
+    /// Procs defined for the module take precedence over global ones.
+    pub fn find_proc(&self, name: &str, module: Option<&str>) -> Option<&Proc> {
+        self.procs
+            .iter()
+            .rev()
+            .find(|p| p.name == name && p.module.is_some() && p.module.as_deref() == module)
+            .or_else(|| {
+                self.procs
+                    .iter()
+                    .rev()
+                    .find(|p| p.name == name && p.module.is_none())
+            })
+    }
+}
+
+/// Splits the given text into commands, which are separated by newlines or semicolons
+fn parse_commands(text: &str) -> Result<Vec<Command>> {
+    let mut commands = vec![];
+    let mut words: Vec<String> = vec![];
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' || c == ';' {
+            if !words.is_empty() {
+                commands.push(Command {
+                    name: words.remove(0),
+                    args: std::mem::take(&mut words),
+                });
+            }
+            i += 1;
+        } else if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == '#' && words.is_empty() {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else {
+            let (word, next) = read_word(&chars, i)?;
+            words.push(word);
+            i = next;
+        }
+    }
+    if !words.is_empty() {
+        commands.push(Command {
+            name: words.remove(0),
+            args: words,
+        });
+    }
+    Ok(commands)
+}
+
+/// Splits the given text into words, e.g. to read a Tcl list
+fn split_words(text: &str) -> Result<Vec<String>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut words = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].is_whitespace() {
+            i += 1;
+        } else {
+            let (word, next) = read_word(&chars, i)?;
+            words.push(word);
+            i = next;
+        }
+    }
+    Ok(words)
+}
+
+/// Reads the word starting at the given position, returning it and the position after it
+fn read_word(chars: &[char], start: usize) -> Result<(String, usize)> {
+    let mut i = start;
+    match chars[i] {
+        '{' => {
+            let mut depth = 0;
+            while i < chars.len() {
+                match chars[i] {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok((chars[start + 1..i].iter().collect(), i + 1));
+                        }
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            bail!("Missing closing brace in PDL");
+        }
+        '"' => {
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '"' && chars[i - 1] != '\\' {
+                    return Ok((chars[start + 1..i].iter().collect(), i + 1));
+                }
+                i += 1;
+            }
+            bail!("Missing closing quote in PDL");
+        }
+        _ => {
+            while i < chars.len() && !chars[i].is_whitespace() && chars[i] != ';' {
+                i += 1;
+            }
+            Ok((chars[start..i].iter().collect(), i))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_procs() {
+        let pdl = PDL::from_str(
+            r#"
+            # Procs for the instrument
+            iProcsForModule ns::Instrument
+            iProc write_ctrl {value {check 1}} {
+                iWrite tdr[7:4] $value
+                iApply
+                iRead status ${check}; iApply
+            }
+            iProc init {} { iNote "Initializing the instrument" }
+            iCall inst1.write_ctrl 0x3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(pdl.procs.len(), 2);
+        assert_eq!(pdl.commands.len(), 1);
+        assert_eq!(pdl.commands[0].args, vec!["inst1.write_ctrl", "0x3"]);
+        let p = pdl.find_proc("write_ctrl", Some("Instrument")).unwrap();
+        assert!(pdl.find_proc("write_ctrl", None).is_none());
+        let body = p.expand(&["0x5".to_string()]).unwrap();
+        assert_eq!(body.len(), 4);
+        assert_eq!(body[0].args, vec!["tdr[7:4]", "0x5"]);
+        assert_eq!(body[2].args, vec!["status", "1"]);
+        assert!(p.expand(&[]).is_err());
+        let body = pdl
+            .find_proc("init", Some("Instrument"))
+            .unwrap()
+            .expand(&[])
+            .unwrap();
+        assert_eq!(body[0].args, vec!["Initializing the instrument"]);
+    }
+}
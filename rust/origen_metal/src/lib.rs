@@ -31,6 +31,7 @@ pub mod ast;
 mod error;
 pub mod framework;
 pub mod frontend;
+pub mod icl;
 pub mod stil;
 pub mod utils;
 pub mod prog_gen;
@@ -10,6 +10,118 @@ pub trait Differ {
     /// An error will be returned if any of the files doesn't exist or if there is some
     /// other problem with reading them.
     fn has_diffs(&mut self) -> Result<bool>;
+
+    /// Returns a unified diff of the contained files, showing the given number of lines of
+    /// context around each change, or None if the differ does not support it.
+    /// Only the parts of the files which are compared by the differ are included, e.g. ignored
+    /// comments are not shown.
+    fn unified_diff(&mut self, context: usize) -> Result<Option<String>> {
+        let _ = context;
+        Ok(None)
+    }
+}
+
+/// Returns a unified diff between the given lines, where the names identify each side in the
+/// diff header. Each line is given with its (1-based) line number in the original file, which is
+/// what the hunk headers refer to, so lines may be omitted from either side without throwing
+/// the reported positions out.
+pub fn unified_diff(
+    a_name: &str,
+    b_name: &str,
+    a_lines: &[(usize, String)],
+    b_lines: &[(usize, String)],
+    context: usize,
+) -> String {
+    // Each edit is a line from A (-), a line from B (+) or a line common to both
+    let mut edits: Vec<(char, &str)> = vec![];
+    let a: Vec<&str> = a_lines.iter().map(|l| l.1.as_str()).collect();
+    let b: Vec<&str> = b_lines.iter().map(|l| l.1.as_str()).collect();
+    let prefix = a.iter().zip(b.iter()).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (am, bm) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    for l in &a[..prefix] {
+        edits.push((' ', l));
+    }
+    // Find the longest common subsequence of the differing middle section, unless it is too
+    // big to do so cheaply, in which case it is all shown as changed
+    if am.len().saturating_mul(bm.len()) <= 16_000_000 {
+        let mut lcs = vec![vec![0u32; bm.len() + 1]; am.len() + 1];
+        for i in (0..am.len()).rev() {
+            for j in (0..bm.len()).rev() {
+                lcs[i][j] = if am[i] == bm[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < am.len() || j < bm.len() {
+            if i < am.len() && j < bm.len() && am[i] == bm[j] {
+                edits.push((' ', am[i]));
+                i += 1;
+                j += 1;
+            } else if i < am.len() && (j == bm.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                edits.push(('-', am[i]));
+                i += 1;
+            } else {
+                edits.push(('+', bm[j]));
+                j += 1;
+            }
+        }
+    } else {
+        edits.extend(am.iter().map(|l| ('-', *l)));
+        edits.extend(bm.iter().map(|l| ('+', *l)));
+    }
+    for l in &a[a.len() - suffix..] {
+        edits.push((' ', l));
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", a_name, b_name);
+    let changes: Vec<usize> = (0..edits.len()).filter(|i| edits[*i].0 != ' ').collect();
+    let mut k = 0;
+    while k < changes.len() {
+        // Group changes which are close enough for their context to overlap into one hunk
+        let start = changes[k].saturating_sub(context);
+        let mut end = changes[k];
+        while k < changes.len() && changes[k] <= end + 2 * context + 1 {
+            end = changes[k];
+            k += 1;
+        }
+        let end = (end + context + 1).min(edits.len());
+        let a_start = edits[..start].iter().filter(|e| e.0 != '+').count();
+        let b_start = edits[..start].iter().filter(|e| e.0 != '-').count();
+        let a_len = edits[start..end].iter().filter(|e| e.0 != '+').count();
+        let b_len = edits[start..end].iter().filter(|e| e.0 != '-').count();
+        out += &format!(
+            "@@ -{},{} +{},{} @@\n",
+            hunk_start(a_lines, a_start, a_len),
+            a_len,
+            hunk_start(b_lines, b_start, b_len),
+            b_len
+        );
+        for (c, l) in &edits[start..end] {
+            out += &format!("{}{}\n", c, l);
+        }
+    }
+    out
+}
+
+/// Returns the original line number to report as the start of a hunk which covers len lines
+/// from the given index, an empty hunk being reported as starting at the line before it
+fn hunk_start(lines: &[(usize, String)], index: usize, len: usize) -> usize {
+    if len > 0 {
+        lines[index].0
+    } else if index > 0 {
+        lines[index - 1].0
+    } else {
+        0
+    }
 }
 
 /// A utility for diffing two different files, with the ability to ignore code in comments,
@@ -56,6 +168,18 @@ impl Differ for ASCIIDiffer {
     fn has_diffs(&mut self) -> Result<bool> {
         self.run()
     }
+
+    fn unified_diff(&mut self, context: usize) -> Result<Option<String>> {
+        let a = self.compared_lines(&self.file_a)?;
+        let b = self.compared_lines(&self.file_b)?;
+        Ok(Some(unified_diff(
+            &self.file_a.display().to_string(),
+            &self.file_b.display().to_string(),
+            &a,
+            &b,
+            context,
+        )))
+    }
 }
 
 impl ASCIIDiffer {
@@ -130,9 +254,32 @@ impl ASCIIDiffer {
         }
     }
 
+    /// Returns the lines of the given file which are compared, i.e. with the ignored portions
+    /// removed, each with its line number within the file
+    fn compared_lines(&self, file: &Path) -> Result<Vec<(usize, String)>> {
+        let f = File::open(file).context(format!("When opening '{}'", file.display()))?;
+        let mut lines = BufReader::new(f).lines();
+        let mut suspended = false;
+        let mut suspend_index: usize = 0;
+        let mut compared = vec![];
+        // Each compared line is the last one consumed from the file, but consecutive calls
+        // consume the lines in between which were ignored
+        let mut line_number = 0;
+        loop {
+            let mut counted = (&mut lines).inspect(|_| line_number += 1);
+            let l = self.get_next_line(&mut counted, suspended, suspend_index)?;
+            suspended = l.1;
+            suspend_index = l.2;
+            match l.0 {
+                Some(l) => compared.push((line_number, l)),
+                None => return Ok(compared),
+            }
+        }
+    }
+
     fn get_next_line(
         &self,
-        lines: &mut io::Lines<BufReader<File>>,
+        lines: &mut impl Iterator<Item = io::Result<String>>,
         mut suspended: bool,
         mut suspend_index: usize,
     ) -> Result<(Option<String>, bool, usize)> {
@@ -474,4 +621,53 @@ mod tests {
 
         assert_eq!(differ.has_diffs().unwrap(), false);
     }
+
+    #[test]
+    fn unified_diff_skips_ignored_lines() {
+        let mut file_a = NamedTempFile::new().unwrap();
+        let mut file_b = NamedTempFile::new().unwrap();
+        let _ = writeln!(
+            file_a,
+            "// Generated at 10:00\nline 1\nline 2\nline 3\nline 4\nline 5"
+        );
+        let _ = writeln!(
+            file_b,
+            "// Generated at 11:00\nline 1\nline 2\nline three\nline 4\nline 5"
+        );
+
+        let mut differ = ASCIIDiffer::new(file_a.path(), file_b.path());
+        let _ = differ.ignore_comments("//");
+        let diff = differ.unified_diff(1).unwrap().unwrap();
+        let hunks: Vec<&str> = diff.lines().skip(2).collect();
+        assert_eq!(
+            hunks,
+            vec![
+                "@@ -3,3 +3,3 @@",
+                " line 2",
+                "-line 3",
+                "+line three",
+                " line 4"
+            ]
+        );
+    }
+
+    #[test]
+    fn unified_diff_reports_original_line_numbers() {
+        let mut file_a = NamedTempFile::new().unwrap();
+        let mut file_b = NamedTempFile::new().unwrap();
+        let _ = writeln!(
+            file_a,
+            "line 1\nline 2\nline 3\n\nline 4\nline 5\nline 6\nline 7"
+        );
+        let _ = writeln!(
+            file_b,
+            "// Header\n\nline 1\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7\nline 8"
+        );
+
+        let mut differ = ASCIIDiffer::new(file_a.path(), file_b.path());
+        let _ = differ.ignore_comments("//");
+        let diff = differ.unified_diff(0).unwrap().unwrap();
+        let hunks: Vec<&str> = diff.lines().skip(2).collect();
+        assert_eq!(hunks, vec!["@@ -8,0 +10,1 @@", "+line 8"]);
+    }
 }
@@ -214,6 +214,11 @@ pub trait RevisionControlAPI: std::fmt::Debug {
     /// Supplying force: true will replace any existing tag with the same name.
     fn tag(&self, tagname: &str, force: bool, message: Option<&str>) -> Result<()>;
 
+    /// Returns the revision that is currently checked out in the local workspace, in a form that can
+    /// be given back to populate/checkout to reproduce the same view, e.g. the commit SHA for Git or
+    /// the workspace selector for DesignSync.
+    fn revision(&self) -> Result<String>;
+
     /// Initialize a new local workspace at path, pointing to the given location
     /// Returns true if a new workspace was created, false if the workspace already
     /// existed (no action), or an error.
@@ -253,6 +258,10 @@ impl RevisionControlAPI for RevisionControl {
         self.driver.tag(tagname, force, message)
     }
 
+    fn revision(&self) -> Result<String> {
+        self.driver.revision()
+    }
+
     fn init(&self) -> Result<Outcome> {
         self.driver.init()
     }
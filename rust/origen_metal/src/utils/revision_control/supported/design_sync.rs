@@ -114,6 +114,10 @@ impl RevisionControlAPI for Designsync {
         self._tag(tagname, force, _message, true)
     }
 
+    fn revision(&self) -> Result<String> {
+        self.get_selector()
+    }
+
     fn is_initialized(&self) -> Result<bool> {
         todo!("Initializing with DesignSync is not supported yet!");
     }
@@ -207,6 +207,11 @@ impl RevisionControlAPI for Git {
         Ok(())
     }
 
+    fn revision(&self) -> Result<String> {
+        let repo = Repository::open(&self.local)?;
+        self.current_commit_id(&repo)
+    }
+
     fn is_initialized(&self) -> Result<bool> {
         match Repository::open(&self.local) {
             Ok(_) => Ok(true),
@@ -105,6 +105,15 @@ where S: Into<String>
     Ok(dialogue.interact()? == 0)
 }
 
+/// Asks the user to select one of the given items, returning its index
+pub fn select_with_user<S>(prompt: S, items: &[&str], default: usize) -> Result<usize>
+where S: Into<String>
+{
+    let mut dialogue = Select::new();
+    dialogue.items(items).default(default).with_prompt(prompt);
+    Ok(dialogue.interact()?)
+}
+
 pub fn confirm_or_exit<S, D>(prompt: Option<S>, exit_dialogue: Option<D>, exit_code: Option<i32>) -> Result<()>
 where
     S: Into<String>,
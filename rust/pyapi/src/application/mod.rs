@@ -68,7 +68,7 @@ impl PyApplication {
                 }
             }
             if let Some(v) = kw.get_item("version")? {
-                ver = Some(OVersion::new_pep440(&v.extract::<String>()?)?);
+                ver = Some(origen::app().unwrap().next_version(Some(&v.extract::<String>()?))?);
             }
         }
         Ok(PyOutcome::from_origen(
@@ -119,6 +119,7 @@ impl PyApplication {
     #[pyo3(signature=(*_args))]
     fn __run_publish_checks__(&self, _args: &PyTuple) -> PyResult<bool> {
         let r = origen::app().unwrap().run_publish_checks(false)?;
+        r.summarize();
         Ok(r.passed())
     }
 }
@@ -16,6 +16,7 @@ pub fn get_pydut(py: Python<'_>) -> PyResult<&PyAny> {
 pub fn define(py: Python, m: &PyModule) -> PyResult<()> {
     let subm = PyModule::new(py, "dut")?;
     subm.add_class::<PyDUT>()?;
+    subm.add_class::<crate::memories::Memory>()?;
     crate::pins::define(py, subm)?;
     crate::registers::define(py, subm)?;
     crate::timesets::define(py, subm)?;
@@ -57,6 +58,38 @@ impl PyDUT {
         Ok(model.console_display(&dut)?)
     }
 
+    /// Returns the register model of the given model and its sub-blocks as a SystemVerilog
+    /// package of UVM register classes
+    #[pyo3(signature=(model_id, package=None))]
+    fn uvm_ral(&self, model_id: usize, package: Option<&str>) -> PyResult<String> {
+        let dut = origen::dut();
+        Ok(origen::core::model::registers::uvm_ral::generate(
+            &dut, model_id, package,
+        )?)
+    }
+
+    /// Checks the register model of the given model and its sub-blocks for consistency, returning
+    /// a list of (is_error, message) tuples, one for each problem found
+    fn validate_regs(&self, model_id: usize) -> PyResult<Vec<(bool, String)>> {
+        let dut = origen::dut();
+        Ok(
+            origen::core::model::registers::validation::validate(&dut, model_id)?
+                .iter()
+                .map(|i| (i.is_error(), i.to_string()))
+                .collect(),
+        )
+    }
+
+    /// Returns a reStructuredText page documenting the registers of the given model and its
+    /// sub-blocks
+    #[pyo3(signature=(model_id, title=None))]
+    fn register_rst(&self, model_id: usize, title: Option<&str>) -> PyResult<String> {
+        let dut = origen::dut();
+        Ok(origen::core::model::registers::rst::generate(
+            &dut, model_id, title,
+        )?)
+    }
+
     /// push_metadata(self, item)
     /// Pushes metadata object onto the current DUT
     pub fn push_metadata(&mut self, py: Python, item: &PyAny) -> usize {
@@ -14,6 +14,7 @@ mod dut;
 mod extensions;
 mod file_handler;
 mod infrastructure;
+mod memories;
 mod meta;
 mod model;
 #[macro_use]
@@ -571,6 +572,24 @@ fn app_config(py: Python) -> PyResult<Option<PyObject>> {
         let _ = app.with_config(|config| {
             let _ = ret.set_item("name", &config.name);
             let _ = ret.set_item("target", &config.target);
+            let _ = ret.set_item(
+                "target_presets",
+                match &config.target_presets {
+                    Some(presets) => {
+                        let d = PyDict::new(py);
+                        for (name, preset) in presets.iter() {
+                            let p = PyDict::new(py);
+                            let _ = p.set_item("targets", &preset.targets);
+                            let _ = p.set_item("duts", &preset.duts);
+                            let _ = p.set_item("testers", &preset.testers);
+                            let _ = p.set_item("modes", &preset.modes);
+                            let _ = d.set_item(name, p);
+                        }
+                        d.to_object(py)
+                    }
+                    None => py.None(),
+                },
+            );
             let _ = ret.set_item("mode", &config.mode);
             let _ = ret.set_item("__output_directory__", &config.output_directory);
             let _ = ret.set_item(
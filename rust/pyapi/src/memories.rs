@@ -0,0 +1,223 @@
+use num_bigint::BigUint;
+use origen::core::model::memories::MARCH_C_MINUS;
+use origen::utility::mem_image::{ImageFormat, MemImage};
+use origen::Dut;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use std::path::PathBuf;
+use std::sync::MutexGuard;
+
+/// A memory (array) within a block, accesses are made through the given service or, if not
+/// given, the memory's default service. The service can be given as the name of a service
+/// available to the block, or as a service or MemAP object.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct Memory {
+    pub id: usize,
+}
+
+#[pymethods]
+impl Memory {
+    #[getter]
+    fn id(&self) -> PyResult<usize> {
+        Ok(self.id)
+    }
+
+    #[getter]
+    fn name(&self) -> PyResult<String> {
+        Ok(origen::dut().get_memory(self.id)?.name.to_string())
+    }
+
+    #[getter]
+    fn width(&self) -> PyResult<usize> {
+        Ok(origen::dut().get_memory(self.id)?.width)
+    }
+
+    #[getter]
+    fn depth(&self) -> PyResult<usize> {
+        Ok(origen::dut().get_memory(self.id)?.depth)
+    }
+
+    /// Returns the fully resolved address of the given word (the first word by default)
+    #[pyo3(signature=(index=0))]
+    fn address(&self, index: usize) -> PyResult<u128> {
+        let dut = origen::dut();
+        Ok(dut.get_memory(self.id)?.word_address(index, &dut)?)
+    }
+
+    /// Returns the current value of the given word, None if it has not been given a value
+    fn word(&self, index: usize) -> PyResult<Option<BigUint>> {
+        Ok(origen::dut().get_memory(self.id)?.word(index)?)
+    }
+
+    /// Sets the value of the given word without generating any pattern activity
+    fn set_word(&self, index: usize, data: BigUint) -> PyResult<()> {
+        Ok(origen::dut().get_memory(self.id)?.set_word(index, data)?)
+    }
+
+    /// Returns the indexes of all words which have been given a value
+    fn defined_words(&self) -> PyResult<Vec<usize>> {
+        Ok(origen::dut().get_memory(self.id)?.defined_words())
+    }
+
+    fn clear(&self) -> PyResult<()> {
+        origen::dut().get_memory(self.id)?.clear();
+        Ok(())
+    }
+
+    /// Loads the given Intel HEX, S-record or binary image into the memory, returning the number
+    /// of words updated. The format is worked out from the file extension unless given.
+    /// Binary images are placed at the given base_address, or at the memory's address by default.
+    #[pyo3(signature=(path, format=None, base_address=None))]
+    fn load_image(
+        &self,
+        path: PathBuf,
+        format: Option<&str>,
+        base_address: Option<u64>,
+    ) -> PyResult<usize> {
+        let format = match format {
+            Some(f) => Some(f.parse::<ImageFormat>().map_err(PyValueError::new_err)?),
+            None => None,
+        };
+        let dut = origen::dut();
+        let mem = dut.get_memory(self.id)?;
+        let base_address = match base_address {
+            Some(a) => a,
+            None => mem.address(&dut)? as u64,
+        };
+        let img = MemImage::load(&path, format, Some(base_address))?;
+        Ok(mem.load_image(&img, &dut)?)
+    }
+
+    #[pyo3(signature=(index, data=None, service=None))]
+    fn write_word(
+        &self,
+        index: usize,
+        data: Option<BigUint>,
+        service: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .write_word(index, data, sid, &dut, &services)?)
+    }
+
+    /// Verifies the given word, expecting the given data or its current value by default
+    #[pyo3(signature=(index, data=None, service=None))]
+    fn verify_word(
+        &self,
+        index: usize,
+        data: Option<BigUint>,
+        service: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .verify_word(index, data, sid, &dut, &services)?)
+    }
+
+    #[pyo3(signature=(start, data, service=None))]
+    fn write_words(
+        &self,
+        start: usize,
+        data: Vec<BigUint>,
+        service: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .write_words(start, &data, sid, &dut, &services)?)
+    }
+
+    #[pyo3(signature=(start, count, service=None))]
+    fn verify_words(&self, start: usize, count: usize, service: Option<&PyAny>) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .verify_words(start, count, sid, &dut, &services)?)
+    }
+
+    /// Writes all words which have been given a value, e.g. to program a loaded image
+    #[pyo3(signature=(service=None))]
+    fn write_contents(&self, service: Option<&PyAny>) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .write_contents(sid, &dut, &services)?)
+    }
+
+    #[pyo3(signature=(service=None))]
+    fn verify_contents(&self, service: Option<&PyAny>) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .verify_contents(sid, &dut, &services)?)
+    }
+
+    #[pyo3(signature=(start=0, count=None, inverse=false, service=None))]
+    fn checkerboard(
+        &self,
+        start: usize,
+        count: Option<usize>,
+        inverse: bool,
+        service: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut
+            .get_memory(self.id)?
+            .checkerboard(start, count, inverse, sid, &dut, &services)?)
+    }
+
+    /// Applies the given march algorithm (March C- by default), e.g. "any(w0); up(r0,w1); down(r1,w0)"
+    #[pyo3(signature=(algorithm=None, start=0, count=None, service=None))]
+    fn march(
+        &self,
+        algorithm: Option<&str>,
+        start: usize,
+        count: Option<usize>,
+        service: Option<&PyAny>,
+    ) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let sid = self.service_id(&dut, service)?;
+        Ok(dut.get_memory(self.id)?.march(
+            algorithm.unwrap_or(MARCH_C_MINUS),
+            start,
+            count,
+            sid,
+            &dut,
+            &services,
+        )?)
+    }
+}
+
+impl Memory {
+    pub fn new(id: usize) -> Self {
+        Self { id: id }
+    }
+
+    fn service_id(&self, dut: &MutexGuard<Dut>, service: Option<&PyAny>) -> PyResult<usize> {
+        let mem = dut.get_memory(self.id)?;
+        match service {
+            None => Ok(mem.service_id(None, dut)?),
+            Some(s) => match s.extract::<&str>() {
+                Ok(name) => Ok(mem.service_id(Some(name), dut)?),
+                Err(_) => crate::services::service_id(s),
+            },
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::memories::Memory;
 use origen::core::model::Model as RichModel;
 use origen::Dut;
 use origen::Result;
@@ -51,4 +52,82 @@ impl Model {
     fn id(&self) -> PyResult<usize> {
         Ok(self.id)
     }
+
+    /// Defines a new reset domain on this block which will apply the given reset type
+    /// ("hard" by default) to all registers within it and its sub-blocks.
+    /// If a parent domain is given then this domain will also be reset whenever the parent is.
+    #[pyo3(signature=(name, reset=None, parent=None))]
+    fn add_reset_domain(
+        &self,
+        name: &str,
+        reset: Option<&str>,
+        parent: Option<&str>,
+    ) -> PyResult<()> {
+        origen::dut().create_reset_domain(self.id, name, reset, parent)?;
+        Ok(())
+    }
+
+    /// Returns the names of the reset domains defined by this block
+    #[getter]
+    fn reset_domains(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .materialize(&origen::dut())?
+            .reset_domains
+            .keys()
+            .map(|k| k.to_string())
+            .collect())
+    }
+
+    fn apply_reset_domain(&self, name: &str) -> PyResult<()> {
+        let dut = origen::dut();
+        Ok(self.materialize(&dut)?.reset_domain(name, &dut)?)
+    }
+
+    fn is_in_reset_state_for_domain(&self, name: &str) -> PyResult<bool> {
+        let dut = origen::dut();
+        Ok(self
+            .materialize(&dut)?
+            .is_in_reset_state_for_domain(name, &dut)?)
+    }
+
+    fn is_modified_since_reset_for_domain(&self, name: &str) -> PyResult<bool> {
+        let dut = origen::dut();
+        Ok(self
+            .materialize(&dut)?
+            .is_modified_since_reset_for_domain(name, &dut)?)
+    }
+
+    /// Defines a new memory (array) on this block with the given word width (in bits) and depth
+    /// (number of words), starting at the given address offset within the block.
+    /// The service is the name of the default service used to access the memory.
+    #[pyo3(signature=(name, width, depth, base_address=None, service=None))]
+    fn add_memory(
+        &self,
+        name: &str,
+        width: usize,
+        depth: usize,
+        base_address: Option<u128>,
+        service: Option<&str>,
+    ) -> PyResult<Memory> {
+        let id = origen::dut().create_memory(self.id, name, width, depth, base_address, service)?;
+        Ok(Memory::new(id))
+    }
+
+    /// Returns the names of the memories defined by this block
+    #[getter]
+    fn memories(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .materialize(&origen::dut())?
+            .memories
+            .keys()
+            .map(|k| k.to_string())
+            .collect())
+    }
+
+    fn memory(&self, name: &str) -> PyResult<Memory> {
+        match self.materialize(&origen::dut())?.get_memory_id(name) {
+            Some(id) => Ok(Memory::new(id)),
+            None => crate::runtime_error!(format!("No memory named '{}' exists", name)),
+        }
+    }
 }
@@ -41,13 +41,22 @@ impl PyDUT {
             .create_memory_map(model_id, name, address_unit_bits)?)
     }
 
-    fn get_or_create_memory_map(&self, model_id: usize, name: &str) -> PyResult<MemoryMap> {
+    #[pyo3(signature=(model_id, name, range=None))]
+    fn get_or_create_memory_map(
+        &self,
+        model_id: usize,
+        name: &str,
+        range: Option<u64>,
+    ) -> PyResult<MemoryMap> {
         let mut dut = DUT.lock().unwrap();
         let model = dut.get_model(model_id)?;
         let id = match model.get_memory_map_id(name) {
             Ok(v) => v,
             Err(_) => dut.create_memory_map(model_id, name, None)?,
         };
+        if let Some(r) = range {
+            dut.get_mut_memory_map(id)?.range = r;
+        }
         Ok(MemoryMap {
             id: id,
             name: name.to_string(),
@@ -1,15 +1,116 @@
+pub mod i2c;
+pub mod ieee1500;
+pub mod ijtag;
 pub mod jtag;
 pub mod simple;
+pub mod spi;
 pub mod swd;
 // pub mod ahb;
 
+use crate::standard_sub_blocks::arm_debug::MemAP;
+use origen::utility::mem_image::{
+    GapHandling, ImageFormat, MemImage, TransactionOptions, VerifyMode,
+};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
+use std::path::PathBuf;
 
 pub fn define(py: Python, m: &PyModule) -> PyResult<()> {
     let subm = PyModule::new(py, "services")?;
+    subm.add_wrapped(wrap_pyfunction!(download_image))?;
     subm.add_class::<jtag::JTAG>()?;
+    subm.add_class::<ijtag::IJTAG>()?;
     subm.add_class::<swd::SWD>()?;
     subm.add_class::<simple::Simple>()?;
+    subm.add_class::<spi::SPI>()?;
+    subm.add_class::<i2c::I2C>()?;
+    subm.add_class::<ieee1500::IEEE1500>()?;
     m.add_submodule(subm)?;
     Ok(())
 }
+
+/// Returns the ID of the given service object, which can be a Simple service, an ArmDebug MemAP
+/// or a service ID
+pub(crate) fn service_id(service: &PyAny) -> PyResult<usize> {
+    if let Ok(id) = service.extract::<usize>() {
+        Ok(id)
+    } else if let Ok(s) = service.extract::<PyRef<simple::Simple>>() {
+        s.id()
+    } else if let Ok(ap) = service.extract::<PyRef<MemAP>>() {
+        match ap.mem_ap_id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("The given MemAP has not been initialized"),
+        }
+    } else {
+        Err(PyValueError::new_err(format!(
+            "Expected a Simple service, a MemAP or a service ID, given: {}",
+            service
+        )))
+    }
+}
+
+/// Writes the given Intel HEX, S-record, ELF or binary image through the given service (a Simple
+/// service or an ArmDebug MemAP), returning the number of write transactions generated.
+///
+/// Gaps between the image's segments are skipped by default, "fill" will fill all of them or an
+/// integer will fill gaps of up to that many bytes. Undefined bytes within a transaction are given
+/// the fill value.
+/// The data can be verified after each write ("each") or after the whole image has been written
+/// ("after").
+#[pyfunction]
+#[pyo3(signature=(service, path, format=None, base_address=None, bus_width=32, fill=0xFF, gaps=None, big_endian=false, verify=None))]
+fn download_image(
+    service: &PyAny,
+    path: PathBuf,
+    format: Option<&str>,
+    base_address: Option<u64>,
+    bus_width: usize,
+    fill: u8,
+    gaps: Option<&PyAny>,
+    big_endian: bool,
+    verify: Option<&str>,
+) -> PyResult<usize> {
+    let format = match format {
+        Some(f) => Some(f.parse::<ImageFormat>().map_err(PyValueError::new_err)?),
+        None => None,
+    };
+    let gaps = match gaps {
+        None => GapHandling::Skip,
+        Some(g) => {
+            if let Ok(n) = g.extract::<u64>() {
+                GapHandling::FillUpTo(n)
+            } else {
+                match g.extract::<&str>()? {
+                    "skip" => GapHandling::Skip,
+                    "fill" => GapHandling::Fill,
+                    x => return Err(PyValueError::new_err(format!(
+                        "Unknown gap handling '{}', expected 'skip', 'fill' or a number of bytes",
+                        x
+                    ))),
+                }
+            }
+        }
+    };
+    let verify = match verify {
+        None => VerifyMode::Off,
+        Some("each") => VerifyMode::EachWrite,
+        Some("after") => VerifyMode::AfterDownload,
+        Some(x) => {
+            return Err(PyValueError::new_err(format!(
+                "Unknown verify mode '{}', expected 'each' or 'after'",
+                x
+            )))
+        }
+    };
+    let options = TransactionOptions {
+        bus_width,
+        fill,
+        gaps,
+        big_endian,
+    };
+    let id = service_id(service)?;
+    let img = MemImage::load(&path, format, base_address)?;
+    let dut = origen::dut();
+    let services = origen::services();
+    Ok(img.download(&options, verify, id, &dut, &services)?)
+}
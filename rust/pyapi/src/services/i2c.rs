@@ -0,0 +1,135 @@
+use crate::model::Model;
+use crate::resolve_transaction;
+use origen::services::{i2c, Service};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// I2C protocol for register accesses to the target at the given address, the pins default to
+/// "scl" and "sda" if not given.
+///
+/// Set i3c to generate I3C SDR private transfers instead, in which case the address is the
+/// target's dynamic address.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct I2C {
+    id: Option<usize>,
+
+    // Temporarily store the arguments, then clean up after initialized
+    args: Option<(Option<String>, Option<String>, i2c::Config)>,
+}
+
+#[pymethods]
+impl I2C {
+    #[new]
+    #[pyo3(signature=(
+        address, scl=None, sda=None, ten_bit=false, reg_addr_width=8, stretch_cycles=0, i3c=false
+    ))]
+    fn new(
+        address: u16,
+        scl: Option<String>,
+        sda: Option<String>,
+        ten_bit: bool,
+        reg_addr_width: usize,
+        stretch_cycles: u32,
+        i3c: bool,
+    ) -> Self {
+        Self {
+            id: None,
+            args: Some((
+                scl,
+                sda,
+                i2c::Config {
+                    mode: if i3c {
+                        i2c::Mode::I3CSDR
+                    } else {
+                        i2c::Mode::I2C
+                    },
+                    address,
+                    ten_bit,
+                    reg_addr_width,
+                    stretch_cycles,
+                },
+            )),
+        }
+    }
+
+    pub fn set_model(&mut self, name: &str, model: &Model) -> PyResult<Self> {
+        let mut dut = origen::dut();
+        let mut services = origen::services();
+        let id = services.next_id();
+        let service;
+        match self.args.as_ref() {
+            Some(args) => {
+                let scl = match &args.0 {
+                    Some(p) => Some(dut._get_pin_group(0, p)?),
+                    None => None,
+                };
+                let sda = match &args.1 {
+                    Some(p) => Some(dut._get_pin_group(0, p)?),
+                    None => None,
+                };
+                service = Service::I2C(i2c::Service::new(&dut, id, scl, sda, args.2.clone())?);
+            }
+            None => return crate::runtime_error!(
+                "Protocol I2C has not been properly initialized - missing initialization arguments"
+            ),
+        }
+        services.add_service(service);
+        model.materialize_mut(&mut dut)?.add_service(name, id)?;
+        self.id = Some(id);
+        self.args = None;
+        Ok(self.clone())
+    }
+
+    fn reset(slf: PyRef<Self>) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_i2c(slf.id()?)?.reset(&dut)?;
+        Ok(slf.into())
+    }
+
+    #[pyo3(signature=(bits_or_val, **write_opts))]
+    fn write_register(
+        slf: PyRef<Self>,
+        bits_or_val: &PyAny,
+        write_opts: Option<&PyDict>,
+    ) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Write),
+            write_opts,
+        )?;
+        services.get_as_i2c(slf.id()?)?.write(&dut, trans)?;
+        Ok(slf.into())
+    }
+
+    #[pyo3(signature=(bits_or_val, **verify_opts))]
+    fn verify_register(
+        slf: PyRef<Self>,
+        bits_or_val: &PyAny,
+        verify_opts: Option<&PyDict>,
+    ) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Verify),
+            verify_opts,
+        )?;
+        services.get_as_i2c(slf.id()?)?.verify(&dut, trans)?;
+        Ok(slf.into())
+    }
+}
+
+impl I2C {
+    pub(crate) fn id(&self) -> PyResult<usize> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("Protocol 'I2C' has not been properly initialized"),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use super::jtag::JTAG;
+use crate::model::Model;
+use crate::resolve_transaction;
+use num_bigint::BigUint;
+use origen::services::{ieee1500, Service};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// IEEE 1500 wrapper access to the cores daisy-chained behind the given JTAG service, the TAP
+/// instructions connecting the wrappers' WIRs and WDRs should be given along with the IR size.
+///
+/// The cores are given as a dict, ordered from TDI to TDO, of their names to their options:
+/// "wir_size" (required), "bypass" (the WS_BYPASS opcode, defaults to 0) and "instructions",
+/// a dict of instruction names to opcodes.
+/// Registers within a core's model should have the opcode of the WIR instruction which selects
+/// them as their address.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct IEEE1500 {
+    id: Option<usize>,
+
+    // Temporarily store the arguments, then clean up after initialized
+    args: Option<Args>,
+}
+
+#[derive(Debug, Clone)]
+struct Args {
+    jtag_id: usize,
+    wir_instruction: (BigUint, usize),
+    wdr_instruction: (BigUint, usize),
+    cores: Vec<(String, usize, Option<BigUint>, Vec<(String, BigUint)>)>,
+}
+
+#[pymethods]
+impl IEEE1500 {
+    #[new]
+    #[pyo3(signature=(jtag, wir_instruction, wdr_instruction, ir_size, cores=None))]
+    fn new(
+        jtag: &PyAny,
+        wir_instruction: BigUint,
+        wdr_instruction: BigUint,
+        ir_size: usize,
+        cores: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let jtag_id = match jtag.extract::<PyRef<JTAG>>() {
+            Ok(j) => j.id()?,
+            Err(_) => jtag.extract::<usize>()?,
+        };
+        let mut c = vec![];
+        if let Some(cores) = cores {
+            for (name, opts) in cores.iter() {
+                let name = name.extract::<String>()?;
+                let opts = opts.downcast::<PyDict>()?;
+                let wir_size = match opts.get_item("wir_size")? {
+                    Some(s) => s.extract::<usize>()?,
+                    None => {
+                        return crate::runtime_error!(format!(
+                            "No wir_size was given for IEEE 1500 core '{}'",
+                            name
+                        ))
+                    }
+                };
+                let bypass = match opts.get_item("bypass")? {
+                    Some(b) => Some(b.extract::<BigUint>()?),
+                    None => None,
+                };
+                let mut instructions = vec![];
+                if let Some(i) = opts.get_item("instructions")? {
+                    for (n, op) in i.downcast::<PyDict>()?.iter() {
+                        instructions.push((n.extract::<String>()?, op.extract::<BigUint>()?));
+                    }
+                }
+                c.push((name, wir_size, bypass, instructions));
+            }
+        }
+        Ok(Self {
+            id: None,
+            args: Some(Args {
+                jtag_id,
+                wir_instruction: (wir_instruction, ir_size),
+                wdr_instruction: (wdr_instruction, ir_size),
+                cores: c,
+            }),
+        })
+    }
+
+    pub fn set_model(&mut self, name: &str, model: &Model) -> PyResult<Self> {
+        let mut dut = origen::dut();
+        let mut services = origen::services();
+        let id = services.next_id();
+        let args = match self.args.as_ref() {
+            Some(args) => args,
+            None => {
+                return crate::runtime_error!(
+                    "IEEE1500 has not been properly initialized - missing initialization arguments"
+                )
+            }
+        };
+        let service = ieee1500::Service::new(
+            id,
+            args.jtag_id,
+            args.wir_instruction.clone(),
+            args.wdr_instruction.clone(),
+        );
+        for (core, wir_size, bypass, instructions) in &args.cores {
+            service.add_core(core, *wir_size, bypass.clone())?;
+            for (n, op) in instructions {
+                service.add_instruction(core, n, op.clone())?;
+            }
+        }
+        services.add_service(Service::IEEE1500(service));
+        model.materialize_mut(&mut dut)?.add_service(name, id)?;
+        self.id = Some(id);
+        self.args = None;
+        Ok(self.clone())
+    }
+
+    /// Adds a core to the TDO end of the chain
+    #[pyo3(signature=(name, wir_size, bypass=None, instructions=None))]
+    fn add_core(
+        &self,
+        name: &str,
+        wir_size: usize,
+        bypass: Option<BigUint>,
+        instructions: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let services = origen::services();
+        let s = services.get_as_ieee1500(self.id()?)?;
+        s.add_core(name, wir_size, bypass)?;
+        if let Some(i) = instructions {
+            for (n, op) in i.iter() {
+                s.add_instruction(name, &n.extract::<String>()?, op.extract::<BigUint>()?)?;
+            }
+        }
+        Ok(self.clone())
+    }
+
+    /// Selects the core targeted by subsequent accesses, the others are kept in WS_BYPASS
+    fn select_core(&self, name: &str) -> PyResult<Self> {
+        let services = origen::services();
+        services.get_as_ieee1500(self.id()?)?.select_core(name)?;
+        Ok(self.clone())
+    }
+
+    #[getter]
+    fn selected_core(&self) -> PyResult<Option<String>> {
+        let services = origen::services();
+        Ok(services.get_as_ieee1500(self.id()?)?.selected_core())
+    }
+
+    /// Loads the given instruction, either an opcode or the name of one of the selected core's
+    /// instructions, into the selected core's WIR
+    fn write_wir(&self, instruction: &PyAny) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let s = services.get_as_ieee1500(self.id()?)?;
+        let opcode = match instruction.extract::<String>() {
+            Ok(n) => s.instruction(&n)?,
+            Err(_) => instruction.extract::<BigUint>()?,
+        };
+        s.write_wir(&dut, &services, &opcode)?;
+        Ok(self.clone())
+    }
+
+    /// Writes the WDR currently selected by the selected core's WIR
+    #[pyo3(signature=(bits_or_val, **write_opts))]
+    fn write_wdr(&self, bits_or_val: &PyAny, write_opts: Option<&PyDict>) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Write),
+            write_opts,
+        )?;
+        services
+            .get_as_ieee1500(self.id()?)?
+            .write_wdr(&dut, &services, &trans)?;
+        Ok(self.clone())
+    }
+
+    /// Verifies the WDR currently selected by the selected core's WIR
+    #[pyo3(signature=(bits_or_val, **verify_opts))]
+    fn verify_wdr(&self, bits_or_val: &PyAny, verify_opts: Option<&PyDict>) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Verify),
+            verify_opts,
+        )?;
+        services
+            .get_as_ieee1500(self.id()?)?
+            .verify_wdr(&dut, &services, &trans)?;
+        Ok(self.clone())
+    }
+
+    #[pyo3(signature=(bits_or_val, **write_opts))]
+    fn write_register(&self, bits_or_val: &PyAny, write_opts: Option<&PyDict>) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Write),
+            write_opts,
+        )?;
+        services.write_transaction(self.id()?, &dut, &trans)?;
+        Ok(self.clone())
+    }
+
+    #[pyo3(signature=(bits_or_val, **verify_opts))]
+    fn verify_register(
+        &self,
+        bits_or_val: &PyAny,
+        verify_opts: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Verify),
+            verify_opts,
+        )?;
+        services.verify_transaction(self.id()?, &dut, &trans)?;
+        Ok(self.clone())
+    }
+
+    /// Resets the TAP, the WIRs are re-loaded by the next register access
+    fn reset(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services
+            .get_as_ieee1500(self.id()?)?
+            .reset(&dut, &services)?;
+        Ok(self.clone())
+    }
+}
+
+impl IEEE1500 {
+    pub(crate) fn id(&self) -> PyResult<usize> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("Service 'IEEE1500' has not been properly initialized"),
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use super::jtag::JTAG;
+use crate::model::Model;
+use num_bigint::BigUint;
+use origen::om::icl::{ICL, PDL};
+use origen::services::{ijtag, Service};
+use pyo3::prelude::*;
+use pyo3::types::PyTuple;
+use std::path::PathBuf;
+
+/// IEEE 1687 (IJTAG) instrument access through the scan network described by the given ICL
+/// file(s), with the resulting DR scans applied through the given JTAG service.
+///
+/// If the network is connected to the TAP by a dedicated instruction, its value and IR size
+/// should be given so that it can be loaded before the first access.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct IJTAG {
+    id: Option<usize>,
+
+    // Temporarily store the arguments, then clean up after initialized
+    args: Option<Args>,
+}
+
+#[derive(Debug, Clone)]
+struct Args {
+    icl: Vec<PathBuf>,
+    pdl: Vec<PathBuf>,
+    jtag_id: usize,
+    top: Option<String>,
+    access: Option<String>,
+    instruction: Option<(BigUint, usize)>,
+}
+
+#[pymethods]
+impl IJTAG {
+    #[new]
+    #[pyo3(signature=(icl, jtag, pdl=None, top=None, access=None, instruction=None, ir_size=None))]
+    fn new(
+        icl: &PyAny,
+        jtag: &PyAny,
+        pdl: Option<&PyAny>,
+        top: Option<String>,
+        access: Option<String>,
+        instruction: Option<BigUint>,
+        ir_size: Option<usize>,
+    ) -> PyResult<Self> {
+        let jtag_id = match jtag.extract::<PyRef<JTAG>>() {
+            Ok(j) => j.id()?,
+            Err(_) => jtag.extract::<usize>()?,
+        };
+        let instruction = match (instruction, ir_size) {
+            (Some(i), Some(s)) => Some((i, s)),
+            (None, None) => None,
+            _ => {
+                return crate::runtime_error!(
+                    "Both the instruction and ir_size must be given to load an IJTAG instruction"
+                )
+            }
+        };
+        Ok(Self {
+            id: None,
+            args: Some(Args {
+                icl: paths(icl)?,
+                pdl: match pdl {
+                    Some(p) => paths(p)?,
+                    None => vec![],
+                },
+                jtag_id,
+                top,
+                access,
+                instruction,
+            }),
+        })
+    }
+
+    pub fn set_model(&mut self, name: &str, model: &Model) -> PyResult<Self> {
+        let mut dut = origen::dut();
+        let mut services = origen::services();
+        let id = services.next_id();
+        let args = match self.args.as_ref() {
+            Some(args) => args,
+            None => {
+                return crate::runtime_error!(
+                    "IJTAG has not been properly initialized - missing initialization arguments"
+                )
+            }
+        };
+        let mut icl = ICL::default();
+        for f in &args.icl {
+            icl.merge(ICL::from_file(f)?)?;
+        }
+        let service = ijtag::Service::new(
+            id,
+            args.jtag_id,
+            &icl,
+            args.top.as_deref(),
+            args.access.as_deref(),
+            args.instruction.clone(),
+        )?;
+        for f in &args.pdl {
+            service.load_pdl(PDL::from_file(f)?);
+        }
+        services.add_service(Service::IJTAG(service));
+        model.materialize_mut(&mut dut)?.add_service(name, id)?;
+        self.id = Some(id);
+        self.args = None;
+        Ok(self.clone())
+    }
+
+    /// Makes the iProcs from the given PDL file available to icall
+    fn load_pdl(&self, path: PathBuf) -> PyResult<()> {
+        let services = origen::services();
+        services
+            .get_as_ijtag(self.id()?)?
+            .load_pdl(PDL::from_file(&path)?);
+        Ok(())
+    }
+
+    /// Queues a write to the given register (e.g. "core.sib1.tdr[3:0]"), applied by iapply
+    fn iwrite(&self, register: &str, value: BigUint) -> PyResult<Self> {
+        let services = origen::services();
+        services
+            .get_as_ijtag(self.id()?)?
+            .iwrite(register, &value)?;
+        Ok(self.clone())
+    }
+
+    /// Queues a read of the given register expecting the given value, verified by iapply
+    fn iread(&self, register: &str, value: BigUint) -> PyResult<Self> {
+        let services = origen::services();
+        services.get_as_ijtag(self.id()?)?.iread(register, &value)?;
+        Ok(self.clone())
+    }
+
+    fn iapply(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_ijtag(self.id()?)?.iapply(&dut, &services)?;
+        Ok(self.clone())
+    }
+
+    fn ireset(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_ijtag(self.id()?)?.ireset(&dut, &services)?;
+        Ok(self.clone())
+    }
+
+    /// Closes all open SIBs on the active scan path
+    fn close_sibs(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services
+            .get_as_ijtag(self.id()?)?
+            .close_sibs(&dut, &services)?;
+        Ok(self.clone())
+    }
+
+    /// Calls the given iProc, e.g. icall("core.inst1.write_ctrl", 0x3)
+    #[pyo3(signature=(proc, *args))]
+    fn icall(&self, proc: &str, args: &PyTuple) -> PyResult<Self> {
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| Ok(a.str()?.to_string()))
+            .collect::<PyResult<Vec<String>>>()?;
+        let dut = origen::dut();
+        let services = origen::services();
+        services
+            .get_as_ijtag(self.id()?)?
+            .icall(&dut, &services, proc, &args)?;
+        Ok(self.clone())
+    }
+
+    /// Returns the current value of the given scan register as held by the model
+    fn value(&self, register: &str) -> PyResult<BigUint> {
+        let services = origen::services();
+        Ok(services.get_as_ijtag(self.id()?)?.value(register)?)
+    }
+
+    /// The registers currently on the scan path, ordered from TDO to TDI
+    #[getter]
+    fn active_path(&self) -> PyResult<Vec<String>> {
+        let services = origen::services();
+        Ok(services.get_as_ijtag(self.id()?)?.active_path()?)
+    }
+}
+
+impl IJTAG {
+    pub(crate) fn id(&self) -> PyResult<usize> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("Service 'IJTAG' has not been properly initialized"),
+        }
+    }
+}
+
+/// Accepts a single path or a list of them
+fn paths(p: &PyAny) -> PyResult<Vec<PathBuf>> {
+    match p.extract::<PathBuf>() {
+        Ok(path) => Ok(vec![path]),
+        Err(_) => p.extract::<Vec<PathBuf>>(),
+    }
+}
@@ -1,21 +1,28 @@
 use crate::extract_value;
 use crate::model::Model;
 use crate::unpack_transaction_options;
+use num_bigint::BigUint;
+use origen::services::jtag::{Escape, TAPState};
 use origen::services::{jtag, Service};
 use pyo3::prelude::*;
 use pyo3::types::{PyAny, PyDict};
 
+/// JTAG protocol. The pins used by the cJTAG (IEEE 1149.7) two-pin formats default to "tmsc" and
+/// "tckc" if not given.
 #[pyclass]
 #[derive(Debug, Clone)]
 pub struct JTAG {
     id: usize,
+    tmsc: Option<String>,
+    tckc: Option<String>,
 }
 
 #[pymethods]
 impl JTAG {
     #[new]
-    fn new() -> Self {
-        JTAG { id: 0 }
+    #[pyo3(signature=(tmsc=None, tckc=None))]
+    fn new(tmsc: Option<String>, tckc: Option<String>) -> Self {
+        JTAG { id: 0, tmsc, tckc }
     }
 
     pub fn id(&self) -> PyResult<usize> {
@@ -29,13 +36,22 @@ impl JTAG {
         let mut dut = origen::dut();
         let mut services = origen::services();
         let id = services.next_id();
+        let tmsc = match &self.tmsc {
+            Some(p) => Some(dut._get_pin_group(0, p)?),
+            None => None,
+        };
+        let tckc = match &self.tckc {
+            Some(p) => Some(dut._get_pin_group(0, p)?),
+            None => None,
+        };
         let service = Service::JTAG(jtag::Service::new(
             &dut, id, None, // default IR size
+            None, // tclk
             None, // tdi
             None, // tdo
-            None, // toms
-            None, // tclk
+            None, // tms
             None, // trstn
+            tmsc, tckc,
         )?);
         services.add_service(service);
         model.materialize_mut(&mut dut)?.add_service(name, id)?;
@@ -51,6 +67,160 @@ impl JTAG {
         Ok(())
     }
 
+    /// Resets the TAP by asserting TRST for the given number of cycles
+    #[pyo3(signature=(cycles=None))]
+    fn trst_reset(&self, cycles: Option<usize>) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.trst_reset(&dut, cycles)?;
+        Ok(())
+    }
+
+    /// Moves to Run-Test/Idle and stays there for the given number of cycles
+    #[pyo3(signature=(cycles=1))]
+    fn idle(&self, cycles: usize) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.idle(&dut, cycles)?;
+        Ok(self.clone())
+    }
+
+    /// Moves the TAP to the given state, e.g. "pause_dr", by the shortest TMS sequence
+    fn move_to_state(&self, state: &str) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services
+            .get_as_jtag(self.id)?
+            .move_to_state(&dut, state.parse::<TAPState>()?)?;
+        Ok(self.clone())
+    }
+
+    /// Returns the name of the TAP's current state, None if it is not known
+    #[getter]
+    fn state(&self) -> PyResult<Option<String>> {
+        let services = origen::services();
+        Ok(services
+            .get_as_jtag(self.id)?
+            .state()
+            .map(|s| s.to_string()))
+    }
+
+    /// Tells the driver which state the TAP is in, e.g. after it has been driven by other means.
+    /// Setting it to None marks the state as unknown, the TAP will then be reset before the next
+    /// operation.
+    #[setter]
+    fn set_state(&self, state: Option<&str>) -> PyResult<()> {
+        let state = match state {
+            Some(s) => Some(s.parse::<TAPState>()?),
+            None => None,
+        };
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.set_state(state);
+        Ok(())
+    }
+
+    /// The state the TAP is left in after a shift when an end_state option is not given
+    #[getter]
+    fn end_state(&self) -> PyResult<String> {
+        let services = origen::services();
+        Ok(services.get_as_jtag(self.id)?.end_state().to_string())
+    }
+
+    #[setter]
+    fn set_end_state(&self, state: &str) -> PyResult<()> {
+        let services = origen::services();
+        services
+            .get_as_jtag(self.id)?
+            .set_end_state(state.parse::<TAPState>()?)?;
+        Ok(())
+    }
+
+    /// Adds a TAP to the scan chain, TAPs must be added in order starting from the one connected
+    /// to TDI. Once the chain has more than one TAP, shifts target the TAP given by select_tap or
+    /// the "tap" option, with the others padded with BYPASS instructions and bits.
+    #[pyo3(signature=(name, ir_size, bypass=None, idcode=None))]
+    fn add_tap(
+        &self,
+        name: &str,
+        ir_size: usize,
+        bypass: Option<BigUint>,
+        idcode: Option<u32>,
+    ) -> PyResult<Self> {
+        let services = origen::services();
+        services
+            .get_as_jtag(self.id)?
+            .add_tap(name, ir_size, bypass, idcode)?;
+        Ok(self.clone())
+    }
+
+    fn select_tap(&self, name: &str) -> PyResult<Self> {
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.select_tap(name)?;
+        Ok(self.clone())
+    }
+
+    #[getter]
+    fn selected_tap(&self) -> PyResult<Option<String>> {
+        let services = origen::services();
+        Ok(services.get_as_jtag(self.id)?.selected_tap())
+    }
+
+    /// Returns the names of the TAPs in the scan chain, ordered from TDI to TDO
+    #[getter]
+    fn taps(&self) -> PyResult<Vec<String>> {
+        let services = origen::services();
+        Ok(services
+            .get_as_jtag(self.id)?
+            .taps()
+            .into_iter()
+            .map(|t| t.name)
+            .collect())
+    }
+
+    /// Resets the TAPs and verifies the IDCODE of each one in the scan chain
+    fn verify_idcodes(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.verify_idcodes(&dut)?;
+        Ok(self.clone())
+    }
+
+    /// Applies a cJTAG escape sequence on TMSC/TCKC, one of "deselection", "selection" or
+    /// "reset"
+    fn escape(&self, escape: &str) -> PyResult<Self> {
+        let escape = match escape.to_lowercase().as_str() {
+            "deselection" => Escape::Deselection,
+            "selection" => Escape::Selection,
+            "reset" => Escape::Reset,
+            _ => {
+                return crate::runtime_error!(format!(
+                    "Unknown cJTAG escape '{}', expected 'deselection', 'selection' or 'reset'",
+                    escape
+                ))
+            }
+        };
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.escape(&dut, escape)?;
+        Ok(self.clone())
+    }
+
+    /// Activates the cJTAG OScan1 format, all subsequent operations are then applied through
+    /// the two pins TMSC and TCKC
+    fn activate_oscan1(&self) -> PyResult<Self> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_jtag(self.id)?.activate_oscan1(&dut)?;
+        Ok(self.clone())
+    }
+
+    /// The scan format currently in use, "Standard" or "OScan1"
+    #[getter]
+    fn scan_format(&self) -> PyResult<String> {
+        let services = origen::services();
+        Ok(services.get_as_jtag(self.id)?.scan_format().to_string())
+    }
+
     #[pyo3(signature=(bits_or_val, width=None, **kwargs))]
     fn write_dr(
         &self,
@@ -65,8 +235,9 @@ impl JTAG {
 
         let services = origen::services();
         let jtag = services.get_as_jtag(self.id)?;
+        select_tap(jtag, kwargs)?;
 
-        jtag.write_dr(&dut, &trans)?;
+        jtag.write_dr(&dut, &trans, end_state(kwargs)?)?;
         Ok(self.clone())
     }
 
@@ -84,8 +255,9 @@ impl JTAG {
 
         let services = origen::services();
         let jtag = services.get_as_jtag(self.id)?;
+        select_tap(jtag, kwargs)?;
 
-        jtag.verify_dr(&dut, &trans)?;
+        jtag.verify_dr(&dut, &trans, end_state(kwargs)?)?;
         Ok(self.clone())
     }
 
@@ -103,8 +275,9 @@ impl JTAG {
 
         let services = origen::services();
         let jtag = services.get_as_jtag(self.id)?;
+        select_tap(jtag, kwargs)?;
 
-        jtag.write_ir(&dut, &trans)?;
+        jtag.write_ir(&dut, &trans, end_state(kwargs)?)?;
         Ok(self.clone())
     }
 
@@ -122,8 +295,29 @@ impl JTAG {
 
         let services = origen::services();
         let jtag = services.get_as_jtag(self.id)?;
+        select_tap(jtag, kwargs)?;
 
-        jtag.verify_ir(&dut, &trans)?;
+        jtag.verify_ir(&dut, &trans, end_state(kwargs)?)?;
         Ok(self.clone())
     }
 }
+
+/// Selects the TAP given by the "tap" option, if present
+fn select_tap(jtag: &jtag::Service, kwargs: Option<&PyDict>) -> PyResult<()> {
+    if let Some(opts) = kwargs {
+        if let Some(t) = opts.get_item("tap")? {
+            jtag.select_tap(t.extract::<&str>()?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the end state given by the "end_state" option, if present
+fn end_state(kwargs: Option<&PyDict>) -> PyResult<Option<TAPState>> {
+    if let Some(opts) = kwargs {
+        if let Some(s) = opts.get_item("end_state")? {
+            return Ok(Some(s.extract::<&str>()?.parse::<TAPState>()?));
+        }
+    }
+    Ok(None)
+}
@@ -109,7 +109,7 @@ impl Simple {
 }
 
 impl Simple {
-    fn id(&self) -> PyResult<usize> {
+    pub(crate) fn id(&self) -> PyResult<usize> {
         match self.id {
             Some(id) => Ok(id),
             None => crate::runtime_error!("Protocol 'Simple' has not been properly initialized"),
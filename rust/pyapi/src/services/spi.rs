@@ -0,0 +1,190 @@
+use crate::model::Model;
+use crate::resolve_transaction;
+use num_bigint::BigUint;
+use origen::services::{spi, Service};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// SPI protocol, the pins default to "sclk", "mosi", "miso" and "cs" if not given.
+///
+/// Commands are given as a (value, width) tuple and are shifted out at the start of each
+/// frame, followed by the transaction's address if an address width is given.
+#[pyclass]
+#[derive(Debug, Clone)]
+pub struct SPI {
+    id: Option<usize>,
+
+    // Temporarily store the arguments, then clean up after initialized
+    args: Option<Args>,
+}
+
+#[derive(Debug, Clone)]
+struct Args {
+    pins: [Option<String>; 4],
+    config: spi::Config,
+}
+
+#[pymethods]
+impl SPI {
+    #[new]
+    #[pyo3(signature=(
+        sclk=None, mosi=None, miso=None, cs=None, cpol=false, cpha=false, msb_first=true,
+        cs_active_high=false, word_size=8, write_cmd=None, read_cmd=None, addr_width=None,
+        read_dummy_cycles=0
+    ))]
+    fn new(
+        sclk: Option<String>,
+        mosi: Option<String>,
+        miso: Option<String>,
+        cs: Option<String>,
+        cpol: bool,
+        cpha: bool,
+        msb_first: bool,
+        cs_active_high: bool,
+        word_size: usize,
+        write_cmd: Option<(BigUint, usize)>,
+        read_cmd: Option<(BigUint, usize)>,
+        addr_width: Option<usize>,
+        read_dummy_cycles: usize,
+    ) -> Self {
+        Self {
+            id: None,
+            args: Some(Args {
+                pins: [sclk, mosi, miso, cs],
+                config: spi::Config {
+                    cpol,
+                    cpha,
+                    msb_first,
+                    cs_active_high,
+                    word_size,
+                    write_cmd,
+                    read_cmd,
+                    addr_width,
+                    read_dummy_cycles,
+                },
+            }),
+        }
+    }
+
+    pub fn set_model(&mut self, name: &str, model: &Model) -> PyResult<Self> {
+        let mut dut = origen::dut();
+        let mut services = origen::services();
+        let id = services.next_id();
+        let args = match self.args.as_ref() {
+            Some(args) => args,
+            None => return crate::runtime_error!(
+                "Protocol SPI has not been properly initialized - missing initialization arguments"
+            ),
+        };
+        let mut grps = vec![];
+        for p in args.pins.iter() {
+            grps.push(match p {
+                Some(p) => Some(dut._get_pin_group(0, p)?),
+                None => None,
+            });
+        }
+        let service = Service::SPI(spi::Service::new(
+            &dut,
+            id,
+            grps[0],
+            grps[1],
+            grps[2],
+            grps[3],
+            args.config.clone(),
+        )?);
+        services.add_service(service);
+        model.materialize_mut(&mut dut)?.add_service(name, id)?;
+        self.id = Some(id);
+        self.args = None;
+        Ok(self.clone())
+    }
+
+    fn reset(slf: PyRef<Self>) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        services.get_as_spi(slf.id()?)?.reset(&dut)?;
+        Ok(slf.into())
+    }
+
+    #[pyo3(signature=(bits_or_val, **write_opts))]
+    fn write_register(
+        slf: PyRef<Self>,
+        bits_or_val: &PyAny,
+        write_opts: Option<&PyDict>,
+    ) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let spi = services.get_as_spi(slf.id()?)?;
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Write),
+            with_width(slf.py(), spi, write_opts)?,
+        )?;
+        spi.write(&dut, trans)?;
+        Ok(slf.into())
+    }
+
+    #[pyo3(signature=(bits_or_val, **verify_opts))]
+    fn verify_register(
+        slf: PyRef<Self>,
+        bits_or_val: &PyAny,
+        verify_opts: Option<&PyDict>,
+    ) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let spi = services.get_as_spi(slf.id()?)?;
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Verify),
+            with_width(slf.py(), spi, verify_opts)?,
+        )?;
+        spi.verify(&dut, trans)?;
+        Ok(slf.into())
+    }
+
+    #[pyo3(signature=(bits_or_val, **capture_opts))]
+    fn capture_register(
+        slf: PyRef<Self>,
+        bits_or_val: &PyAny,
+        capture_opts: Option<&PyDict>,
+    ) -> PyResult<Py<Self>> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let spi = services.get_as_spi(slf.id()?)?;
+        let trans = resolve_transaction(
+            &dut,
+            bits_or_val,
+            Some(origen::TransactionAction::Capture),
+            with_width(slf.py(), spi, capture_opts)?,
+        )?;
+        spi.capture(&dut, trans)?;
+        Ok(slf.into())
+    }
+}
+
+impl SPI {
+    pub(crate) fn id(&self) -> PyResult<usize> {
+        match self.id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("Protocol 'SPI' has not been properly initialized"),
+        }
+    }
+}
+
+/// Plain values are sized to a single word unless a width is given
+fn with_width<'py>(
+    py: Python<'py>,
+    spi: &spi::Service,
+    opts: Option<&'py PyDict>,
+) -> PyResult<Option<&'py PyDict>> {
+    let opts = match opts {
+        Some(o) => o.copy()?,
+        None => PyDict::new(py),
+    };
+    if !opts.contains("width")? {
+        opts.set_item("width", spi.config.word_size)?;
+    }
+    Ok(Some(opts))
+}
@@ -128,6 +128,16 @@ impl SWD {
         Ok(self.clone())
     }
 
+    fn write_targetsel(&self, targetsel: u32) -> PyResult<Self> {
+        let dut = origen::dut();
+        let mut services = origen::services();
+        let service = services.get_mut_service(self.id)?;
+        if let Service::SWD(swd) = service {
+            swd.write_targetsel(&dut, targetsel)?;
+        }
+        Ok(self.clone())
+    }
+
     pub fn id(&self) -> PyResult<usize> {
         Ok(self.id)
     }
@@ -1,7 +1,9 @@
 // use origen::standard_sub_blocks::ArmDebug as OrigenArmDebug;
 use origen::services::arm_debug::ArmDebug as OrigenArmDebug;
+use origen::services::arm_debug::{ApTopology, Component, Topology};
 // use origen::standard_sub_blocks::arm_debug::DP as OrigenDP;
 use origen::services::arm_debug::JtagDP as OrigenJtagDP;
+use origen::services::arm_debug::mem_ap::TransferSize;
 use origen::services::arm_debug::MemAP as OrigenMemAP;
 use origen::services::arm_debug::DP as OrigenDP;
 // use origen::standard_sub_blocks::arm_debug::mem_ap::MemAP as OrigenMemAP;
@@ -154,6 +156,38 @@ impl ArmDebug {
             slf.arm_debug_id = Some(arm_debug_id);
         }
         if let Some(opts) = block_options {
+            // Each target of a multi-drop SWD bus gets its own DP
+            if let Some(targets) = opts.get_item("targets")? {
+                for (name, target_opts) in targets.downcast::<PyDict>()?.iter() {
+                    let target_opts = target_opts.downcast::<PyDict>()?;
+                    let targetsel = match target_opts.get_item("targetsel")? {
+                        Some(t) => t.extract::<u32>()?,
+                        None => {
+                            return crate::runtime_error!(format!(
+                                "ArmDebug target '{}' requires a 'targetsel' value",
+                                name
+                            ))
+                        }
+                    };
+                    let args = PyTuple::new(
+                        py,
+                        &[name.to_object(py), "origen.arm_debug.dp".to_object(py)],
+                    );
+                    let kwargs = PyDict::new(py);
+                    let sb_options = PyDict::new(py);
+                    sb_options.set_item("arm_debug_id", arm_debug_id)?;
+                    sb_options.set_item("targetsel", targetsel)?;
+                    kwargs.set_item("sb_options", sb_options.to_object(py))?;
+                    let py_dp = instance
+                        .downcast::<PyCell<Self>>()?
+                        .call_method("add_sub_block", args, Some(kwargs))?
+                        .extract::<DP>()?;
+                    let mut services = origen::services();
+                    services
+                        .get_as_mut_arm_debug(arm_debug_id)?
+                        .add_target_dp_id(py_dp.dp_id.unwrap())?;
+                }
+            }
             if let Some(mem_aps) = opts.get_item("mem_aps")? {
                 let aps = mem_aps.downcast::<PyDict>()?;
                 for (ap_name, ap_opts) in aps.iter() {
@@ -176,9 +210,56 @@ impl ArmDebug {
                                 None
                             }
                         },
+                        {
+                            if let Some(dp) = ap_opts_dict.get_item("dp")? {
+                                Some(dp.extract::<String>()?)
+                            } else {
+                                None
+                            }
+                        },
                     )?;
                 }
             }
+            if let Some(topology) = opts.get_item("topology")? {
+                Self::set_topology(instance.downcast::<PyCell<Self>>()?, topology.downcast()?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Defines the expected debug topology, as a dict of the form:
+    ///
+    ///   {"dpidr": 0x6BA0_2477, "power_up": True, "aps": {
+    ///     "<mem_ap sub-block name>": {"idr": ..., "base": ..., "rom_table": {
+    ///       "cidr": ..., "pidr": ..., "entries": {
+    ///         "<component name>": {"offset": ..., "cidr": ..., "pidr": ..., "entries": ...}
+    ///   }}}}}
+    ///
+    /// All values are optional, "power_up" defaults to True.
+    fn set_topology(slf: &PyCell<Self>, topology: &PyDict) -> PyResult<()> {
+        let t = extract_topology(slf, topology)?;
+        let mut services = origen::services();
+        services.get_as_mut_arm_debug(slf.borrow().id()?)?.topology = Some(t);
+        Ok(())
+    }
+
+    /// Generates the pattern verifying the given topology, or the one previously defined
+    #[pyo3(signature=(topology=None))]
+    fn verify_topology(slf: &PyCell<Self>, topology: Option<&PyDict>) -> PyResult<()> {
+        let t = match topology {
+            Some(t) => Some(extract_topology(slf, t)?),
+            None => None,
+        };
+        let mut dut = origen::dut();
+        let services = origen::services();
+        let arm_debug = services.get_as_arm_debug(slf.borrow().id()?)?;
+        match t.as_ref().or(arm_debug.topology.as_ref()) {
+            Some(t) => arm_debug.verify_topology(&mut dut, &services, t)?,
+            None => {
+                return crate::runtime_error!(
+                    "No topology was given and none has been defined for this ArmDebug instance"
+                )
+            }
         }
         Ok(())
     }
@@ -189,6 +270,7 @@ impl ArmDebug {
         name: &str,
         ap: Option<u32>,
         csw_reset: Option<u32>,
+        dp: Option<String>,
     ) -> PyResult<()> {
         let args = PyTuple::new(
             py,
@@ -202,6 +284,11 @@ impl ArmDebug {
         if let Some(_csw_reset) = csw_reset {
             sb_options.set_item("csw_reset", _csw_reset)?;
         }
+        if let Some(_dp) = dp {
+            // The DP of a multi-drop target, rather than the default one
+            let target = slf.getattr(_dp.as_str())?.extract::<DP>()?;
+            sb_options.set_item("dp_id", target.dp_id)?;
+        }
         let arm_debug = slf.extract::<PyRefMut<Self>>()?;
         sb_options.set_item("arm_debug_id", arm_debug.arm_debug_id)?;
         kwargs.set_item("sb_options", sb_options.to_object(py))?;
@@ -217,6 +304,112 @@ impl ArmDebug {
         arm_debug.switch_to_swd(&dut, &services)?;
         Ok(slf.into())
     }
+
+    fn switch_to_dormant(slf: PyRefMut<Self>) -> PyResult<Py<Self>> {
+        let services = origen::services();
+        let arm_debug = services.get_as_arm_debug(slf.id()?)?;
+        let dut = origen::dut();
+        arm_debug.switch_to_dormant(&dut, &services)?;
+        Ok(slf.into())
+    }
+
+    fn switch_dormant_to_swd(slf: PyRefMut<Self>) -> PyResult<Py<Self>> {
+        let services = origen::services();
+        let arm_debug = services.get_as_arm_debug(slf.id()?)?;
+        let dut = origen::dut();
+        arm_debug.switch_dormant_to_swd(&dut, &services)?;
+        Ok(slf.into())
+    }
+
+    /// Selects the given multi-drop target (its DP sub-block name), verifying its DPIDR if given
+    /// and that its TARGETID matches
+    #[pyo3(signature=(target, dpidr=None))]
+    fn select_target(slf: &PyCell<Self>, target: &str, dpidr: Option<u32>) -> PyResult<()> {
+        let dp_id = match slf.getattr(target)?.extract::<DP>()?.dp_id {
+            Some(id) => id,
+            None => {
+                return crate::runtime_error!(format!(
+                    "Target '{}' has not been properly initialized",
+                    target
+                ))
+            }
+        };
+        let services = origen::services();
+        let arm_debug = services.get_as_arm_debug(slf.borrow().id()?)?;
+        let dut = origen::dut();
+        arm_debug.select_target(&dut, &services, dp_id, dpidr)?;
+        Ok(())
+    }
+}
+
+impl ArmDebug {
+    fn id(&self) -> PyResult<usize> {
+        match self.arm_debug_id {
+            Some(id) => Ok(id),
+            None => crate::runtime_error!("ArmDebug has not been properly initialized"),
+        }
+    }
+}
+
+fn extract_topology(slf: &PyCell<ArmDebug>, topology: &PyDict) -> PyResult<Topology> {
+    let mut aps = vec![];
+    if let Some(ap_opts) = topology.get_item("aps")? {
+        for (name, opts) in ap_opts.downcast::<PyDict>()?.iter() {
+            let name = name.extract::<String>()?;
+            let opts = opts.downcast::<PyDict>()?;
+            let mem_ap = slf.getattr(name.as_str())?.extract::<PyRef<MemAP>>()?;
+            let mem_ap_id = match mem_ap.mem_ap_id {
+                Some(id) => id,
+                None => {
+                    return crate::runtime_error!(format!(
+                        "MemAP '{}' has not been properly initialized",
+                        name
+                    ))
+                }
+            };
+            aps.push(ApTopology {
+                mem_ap_id,
+                idr: extract_opt(opts, "idr")?,
+                base: extract_opt(opts, "base")?,
+                rom_table: match opts.get_item("rom_table")? {
+                    Some(t) => Some(extract_component("rom_table".to_string(), t.downcast()?)?),
+                    None => None,
+                },
+            });
+        }
+    }
+    Ok(Topology {
+        dpidr: extract_opt(topology, "dpidr")?,
+        power_up: extract_opt(topology, "power_up")?.unwrap_or(true),
+        aps,
+    })
+}
+
+fn extract_component(name: String, opts: &PyDict) -> PyResult<Component> {
+    let entries = match opts.get_item("entries")? {
+        Some(e) => {
+            let mut entries = vec![];
+            for (n, o) in e.downcast::<PyDict>()?.iter() {
+                entries.push(extract_component(n.extract::<String>()?, o.downcast()?)?);
+            }
+            Some(entries)
+        }
+        None => None,
+    };
+    Ok(Component {
+        name,
+        offset: extract_opt(opts, "offset")?.unwrap_or(0),
+        cidr: extract_opt(opts, "cidr")?,
+        pidr: extract_opt(opts, "pidr")?,
+        entries,
+    })
+}
+
+fn extract_opt<'a, T: FromPyObject<'a>>(opts: &'a PyDict, key: &str) -> PyResult<Option<T>> {
+    match opts.get_item(key)? {
+        Some(v) if !v.is_none() => Ok(Some(v.extract::<T>()?)),
+        _ => Ok(None),
+    }
 }
 
 #[pyclass(subclass)]
@@ -275,7 +468,20 @@ impl DP {
             let mut dut = origen::dut();
             let model_id = obj.getattr(py, "model_id")?.extract::<usize>(py)?;
             let mut services = origen::services();
-            dp_id = OrigenDP::model_init(&mut dut, &mut services, model_id, arm_debug_id)?;
+            let targetsel = match block_options {
+                Some(opts) => match opts.get_item("targetsel")? {
+                    Some(t) => Some(t.extract::<u32>()?),
+                    None => None,
+                },
+                None => None,
+            };
+            dp_id = OrigenDP::model_init(
+                &mut dut,
+                &mut services,
+                model_id,
+                arm_debug_id,
+                targetsel,
+            )?;
         }
         let mut slf = instance.extract::<PyRefMut<Self>>()?;
         slf.dp_id = Some(dp_id);
@@ -318,6 +524,14 @@ impl DP {
         dp.verify_powered_up(&mut dut, &services)?;
         Ok(())
     }
+
+    fn verify_dpidr(&self, expected: u32) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let dp = services.get_as_dp(self.dp_id.unwrap())?;
+        dp.verify_dpidr(&dut, &services, expected)?;
+        Ok(())
+    }
 }
 
 #[pyclass(subclass)]
@@ -485,7 +699,7 @@ impl JtagDP {
 }
 
 #[pyclass(subclass)]
-struct MemAP {
+pub(crate) struct MemAP {
     pub mem_ap_id: Option<usize>,
 }
 
@@ -537,6 +751,14 @@ impl MemAP {
             addr = 0;
         }
 
+        let dp_id = match block_options {
+            Some(opts) => match opts.get_item("dp_id")? {
+                Some(id) => Some(id.extract::<usize>()?),
+                None => None,
+            },
+            None => None,
+        };
+
         let obj = instance.to_object(py);
         let args = PyTuple::new(py, &["default".to_object(py), "default".to_object(py)]);
         let mem_ap_id;
@@ -544,8 +766,14 @@ impl MemAP {
             let mut dut = origen::dut();
             let mut services = origen::services();
             let model_id = obj.getattr(py, "model_id")?.extract::<usize>(py)?;
-            mem_ap_id =
-                OrigenMemAP::model_init(&mut dut, &mut services, model_id, arm_debug_id, addr)?;
+            mem_ap_id = OrigenMemAP::model_init(
+                &mut dut,
+                &mut services,
+                model_id,
+                arm_debug_id,
+                addr,
+                dp_id,
+            )?;
         }
         let mut slf = instance.extract::<PyRefMut<Self>>()?;
         slf.mem_ap_id = Some(mem_ap_id);
@@ -601,4 +829,48 @@ impl MemAP {
         ap.verify_register(&dut, &services, &trans)?;
         Ok(())
     }
+
+    /// Writes the given data to consecutive addresses from addr as a block transfer, TAR is
+    /// auto-incremented rather than written for each element. Set packed to pack 8 or 16-bit
+    /// elements into each DRW access.
+    #[pyo3(signature=(addr, data, size=32, packed=false))]
+    fn write_block(&self, addr: u32, data: Vec<u32>, size: usize, packed: bool) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let ap = services.get_as_mem_ap(self.mem_ap_id.unwrap())?;
+        ap.write_block(
+            &dut,
+            &services,
+            addr,
+            &data,
+            TransferSize::from_bits(size)?,
+            packed,
+        )?;
+        Ok(())
+    }
+
+    /// Verifies the given data at consecutive addresses from addr as a block transfer
+    #[pyo3(signature=(addr, data, size=32, packed=false))]
+    fn verify_block(&self, addr: u32, data: Vec<u32>, size: usize, packed: bool) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let ap = services.get_as_mem_ap(self.mem_ap_id.unwrap())?;
+        ap.verify_block(
+            &dut,
+            &services,
+            addr,
+            &data,
+            TransferSize::from_bits(size)?,
+            packed,
+        )?;
+        Ok(())
+    }
+
+    fn verify_idr(&self, expected: u32) -> PyResult<()> {
+        let dut = origen::dut();
+        let services = origen::services();
+        let ap = services.get_as_mem_ap(self.mem_ap_id.unwrap())?;
+        ap.verify_idr(&dut, &services, expected)?;
+        Ok(())
+    }
 }
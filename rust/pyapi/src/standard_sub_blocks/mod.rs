@@ -1,4 +1,4 @@
-mod arm_debug;
+pub(crate) mod arm_debug;
 
 use pyo3::prelude::*;
 
@@ -117,6 +117,12 @@ impl PyTester {
         Ok(origen::tester().stats.to_pickle())
     }
 
+    /// Writes the JSON and JUnit reports of the generated files, or if running as a dispatched
+    /// job, writes its results for the dispatcher to report instead
+    fn _write_reports(&self) -> PyResult<()> {
+        Ok(origen::core::producer::report::write_generate_reports()?)
+    }
+
     #[getter]
     /// Property for the current :class:`_origen.dut.timesets.Timeset` or None, if no timeset has been set.
     /// Set to ``None`` to clear the current timeset.